@@ -1,7 +1,8 @@
 use crate::{
+    error::{Error, ParseError},
     namespaces::ParseNamespace,
     parse::Parse,
-    prolog::subset::entity::{entity_value::EntityValue, EntitySource},
+    prolog::subset::entity::{entity_value::EntityValue, expansion::ExpansionBudget, EntitySource},
     reference::{ParseReference, Reference},
     IResult, Name,
 };
@@ -10,6 +11,7 @@ use nom::{
     bytes::complete::{tag, take_till1},
     character::complete::char,
     combinator::{map, map_res, opt, value},
+    error::context,
     multi::{many0, separated_list1},
     sequence::{delimited, pair, tuple},
 };
@@ -53,16 +55,22 @@ impl<'a> Parse<'a> for Attribute {
     type Args = (
         Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         EntitySource,
+        ExpansionBudget,
     );
     type Output = IResult<&'a str, Self>;
 
     // [41] Attribute ::= Name Eq AttValue
     fn parse(input: &'a str, args: Self::Args) -> Self::Output {
-        let (entity_references, entity_source) = args;
+        let (entity_references, entity_source, budget) = args;
         {
             map(
                 tuple((Self::parse_name, Self::parse_eq, move |i| {
-                    Self::parse_attvalue(i, entity_references.clone(), entity_source.clone())
+                    Self::parse_attvalue(
+                        i,
+                        entity_references.clone(),
+                        entity_source.clone(),
+                        &budget,
+                    )
                 })),
                 |(name, _eq, value)| Attribute::Instance { name, value },
             )(input)
@@ -93,11 +101,12 @@ impl Attribute {
     }
 
     // [53] AttDef ::= S Name S AttType S DefaultDecl
-    pub fn parse_definition(
-        input: &str,
+    pub fn parse_definition<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, Attribute> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Attribute> {
         map(
             tuple((
                 Self::parse_multispace1,
@@ -105,7 +114,16 @@ impl Attribute {
                 Self::parse_multispace1,
                 |i| AttType::parse(i, ()),
                 Self::parse_multispace1,
-                |i| DefaultDecl::parse(i, (entity_references.clone(), entity_source.clone())),
+                |i| {
+                    DefaultDecl::parse(
+                        i,
+                        (
+                            entity_references.clone(),
+                            entity_source.clone(),
+                            budget.clone(),
+                        ),
+                    )
+                },
             )),
             |(_whitespace1, name, _whitespace2, att_type, _whitespace3, default_decl)| {
                 Attribute::Definition {
@@ -119,11 +137,12 @@ impl Attribute {
     }
 
     // Namespaces (Third Edition) [21] AttDef ::= S (QName | NSAttName) S AttType S DefaultDecl
-    pub fn parse_qualified_definition(
-        input: &str,
+    pub fn parse_qualified_definition<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, Attribute> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Attribute> {
         map(
             tuple((
                 Self::parse_multispace1,
@@ -134,7 +153,16 @@ impl Attribute {
                 Self::parse_multispace1,
                 |i| AttType::parse(i, ()),
                 Self::parse_multispace1,
-                |i| DefaultDecl::parse(i, (entity_references.clone(), entity_source.clone())),
+                |i| {
+                    DefaultDecl::parse(
+                        i,
+                        (
+                            entity_references.clone(),
+                            entity_source.clone(),
+                            budget.clone(),
+                        ),
+                    )
+                },
             )),
             |(_whitespace1, name, _whtiespace2, att_type, _whtiespace3, default_decl)| {
                 Attribute::Definition {
@@ -147,225 +175,223 @@ impl Attribute {
         )(input)
     }
 
+    // [67] Reference in an AttValue, expanded to its replacement text
+    fn parse_attvalue_reference<'a>(
+        input: &'a str,
+        entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        entity_source: EntitySource,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, AttributeValue> {
+        let (remaining, reference) = Reference::parse(input, entity_source.clone())?;
+        let value = reference
+            .normalize_attribute(entity_references, entity_source, budget)
+            .map_err(|e| Self::expansion_failure(input, e))?;
+        // White space in the replacement text of an entity is normalized, white space from a character
+        // reference is kept
+        let value = match (&reference, value) {
+            (Reference::EntityRef(_), AttributeValue::Value(value)) => {
+                AttributeValue::Value(value.replace(['\t', '\n', '\r'], " "))
+            }
+            (_, value) => value,
+        };
+        Ok((remaining, value))
+    }
+
+    // 3.3.3 Attribute-Value Normalization: literal white space, after end-of-line handling, becomes a space
+    fn parse_attvalue_text(input: &str) -> AttributeValue {
+        AttributeValue::Value(input.replace("\r\n", " ").replace(['\t', '\n', '\r'], " "))
+    }
+
+    // Concatenates the normalized text and references of an AttValue
+    fn concatenate_attvalue(contents: Vec<AttributeValue>) -> AttributeValue {
+        let mut buffer = String::new();
+        for content in contents {
+            if let AttributeValue::Value(value) = content {
+                buffer.push_str(&value);
+            }
+        }
+        AttributeValue::Value(buffer)
+    }
+
+    // Expansion errors, e.g. recursive entities, stop parsing at the reference
+    fn expansion_failure(input: &str, error: Error) -> nom::Err<Error> {
+        nom::Err::Failure(Error::ParseError(ParseError::with_cause(
+            nom::error::ErrorKind::Verify,
+            input,
+            error,
+        )))
+    }
+
     // [10] AttValue ::= '"' ([^<&"] | Reference)* '"'|  "'" ([^<&'] | Reference)* "'"
-    pub fn parse_attvalue(
-        input: &str,
+    pub fn parse_attvalue<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, AttributeValue> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, AttributeValue> {
         match entity_source {
-            EntitySource::Internal | EntitySource::None => {
-                map(
-                    alt((
-                        delimited(
-                            tag("\""),
+            EntitySource::Internal | EntitySource::None => map(
+                alt((
+                    delimited(
+                        tag("\""),
+                        many0(alt((
+                            map(
+                                take_till1(|c| c == '<' || c == '&' || c == '\"'),
+                                Self::parse_attvalue_text,
+                            ),
+                            |i| {
+                                Self::parse_attvalue_reference(
+                                    i,
+                                    entity_references.clone(),
+                                    entity_source.clone(),
+                                    budget,
+                                )
+                            },
+                        ))),
+                        tag("\""),
+                    ),
+                    delimited(
+                        tag("'"),
+                        many0(alt((
+                            map(
+                                take_till1(|c| c == '<' || c == '&' || c == '\''),
+                                Self::parse_attvalue_text,
+                            ),
+                            |i| {
+                                Self::parse_attvalue_reference(
+                                    i,
+                                    entity_references.clone(),
+                                    entity_source.clone(),
+                                    budget,
+                                )
+                            },
+                        ))),
+                        tag("'"),
+                    ),
+                )),
+                Self::concatenate_attvalue,
+            )(input),
+
+            EntitySource::External => map(
+                many0(alt((
+                    |i| {
+                        let (input, (reference, _whitespace)) = tuple((
+                            |input| Reference::parse_parameter_reference(input),
+                            Self::parse_multispace0,
+                        ))(i)?;
+                        let value = reference
+                            .normalize_attribute(
+                                entity_references.clone(),
+                                entity_source.clone(),
+                                budget,
+                            )
+                            .map_err(|e| Self::expansion_failure(i, e))?;
+                        Ok((input, value))
+                    },
+                    delimited(
+                        tag("\""),
+                        map(
                             many0(alt((
                                 map(
                                     take_till1(|c| c == '<' || c == '&' || c == '\"'),
-                                    |s: &str| AttributeValue::Value(s.into()),
-                                ),
-                                map(
-                                    |i| Reference::parse(i, entity_source.clone()),
-                                    |reference| {
-                                        reference.normalize_attribute(
-                                            entity_references.clone(),
-                                            entity_source.clone(),
-                                        )
-                                    },
+                                    Self::parse_attvalue_text,
                                 ),
+                                |i| {
+                                    Self::parse_attvalue_reference(
+                                        i,
+                                        entity_references.clone(),
+                                        entity_source.clone(),
+                                        budget,
+                                    )
+                                },
                             ))),
-                            tag("\""),
+                            Self::concatenate_attvalue,
                         ),
-                        delimited(
-                            tag("'"),
+                        tag("\""),
+                    ),
+                    delimited(
+                        tag("'"),
+                        map(
                             many0(alt((
                                 map(
                                     take_till1(|c| c == '<' || c == '&' || c == '\''),
-                                    |s: &str| AttributeValue::Value(s.into()),
+                                    Self::parse_attvalue_text,
                                 ),
-                                map(
-                                    |i| Reference::parse(i, entity_source.clone()),
-                                    |reference| {
-                                        reference.normalize_attribute(
-                                            entity_references.clone(),
-                                            entity_source.clone(),
-                                        )
-                                    },
-                                ),
-                            ))),
-                            tag("'"),
-                        ),
-                    )),
-                    |contents: Vec<AttributeValue>| {
-                        let mut buffer = String::new();
-                        for content in contents {
-                            if let AttributeValue::Value(mut value) = content {
-                                // End-of-Line Handling for each value
-                                let mut chars: Vec<char> = value.chars().collect();
-                                let mut i = 0;
-                                while i < chars.len() {
-                                    if chars[i] == '\r' {
-                                        if i + 1 < chars.len() && chars[i + 1] == '\n' {
-                                            chars.remove(i);
-                                        } else {
-                                            chars[i] = '\n';
-                                        }
-                                    }
-                                    i += 1;
-                                }
-                                value = chars.into_iter().collect();
-                                buffer.push_str(&value);
-                            }
-                        }
-
-                        AttributeValue::Value(buffer)
-                    },
-                )(input)
-            }
-
-            EntitySource::External => {
-                map(
-                    many0(alt((
-                        map(
-                            |i| {
-                                tuple((
-                                    |input| Reference::parse_parameter_reference(input),
-                                    Self::parse_multispace0,
-                                ))(i)
-                            },
-                            |(reference, _whitespace)| {
-                                reference.normalize_attribute(
-                                    entity_references.clone(),
-                                    entity_source.clone(),
-                                )
-                            },
-                        ),
-                        delimited(
-                            tag("\""),
-                            map(
-                                many0(alt((
-                                    map(
-                                        take_till1(|c| c == '<' || c == '&' || c == '\"'),
-                                        |s: &str| AttributeValue::Value(s.into()),
-                                    ),
-                                    map(
-                                        |i| Reference::parse(i, entity_source.clone()),
-                                        |reference| {
-                                            reference.normalize_attribute(
-                                                entity_references.clone(),
-                                                entity_source.clone(),
-                                            )
-                                        },
-                                    ),
-                                ))),
-                                |values| {
-                                    let mut buffer = String::new();
-                                    for value in values {
-                                        if let AttributeValue::Value(v) = value {
-                                            buffer.push_str(&v);
-                                        }
-                                    }
-                                    AttributeValue::Value(buffer)
+                                |i| {
+                                    Self::parse_attvalue_reference(
+                                        i,
+                                        entity_references.clone(),
+                                        entity_source.clone(),
+                                        budget,
+                                    )
                                 },
-                            ),
-                            tag("\""),
-                        ),
-                        delimited(
-                            tag("'"),
-                            map(
-                                many0(alt((
-                                    map(
-                                        take_till1(|c| c == '<' || c == '&' || c == '\''),
-                                        |s: &str| AttributeValue::Value(s.into()),
-                                    ),
-                                    map(
-                                        |i| Reference::parse(i, entity_source.clone()),
-                                        |reference| {
-                                            reference.normalize_attribute(
-                                                entity_references.clone(),
-                                                entity_source.clone(),
-                                            )
-                                        },
-                                    ),
-                                ))),
-                                |values| {
-                                    let mut buffer = String::new();
-                                    for value in values {
-                                        if let AttributeValue::Value(v) = value {
-                                            buffer.push_str(&v);
-                                        }
-                                    }
-                                    AttributeValue::Value(buffer)
-                                },
-                            ),
-                            tag("'"),
+                            ))),
+                            Self::concatenate_attvalue,
                         ),
-                    ))),
-                    |contents: Vec<AttributeValue>| {
-                        let mut buffer = String::new();
-                        for content in contents {
-                            if let AttributeValue::Value(mut value) = content {
-                                // End-of-Line Handling for each value
-                                let mut chars: Vec<char> = value.chars().collect();
-                                let mut i = 0;
-                                while i < chars.len() {
-                                    if chars[i] == '\r' {
-                                        if i + 1 < chars.len() && chars[i + 1] == '\n' {
-                                            chars.remove(i);
-                                        } else {
-                                            chars[i] = '\n';
-                                        }
-                                    }
-                                    i += 1;
-                                }
-                                value = chars.into_iter().collect();
-                                buffer.push_str(&value);
-                            }
-                        }
-
-                        AttributeValue::Value(buffer)
-                    },
-                )(input)
-            }
+                        tag("'"),
+                    ),
+                ))),
+                Self::concatenate_attvalue,
+            )(input),
         }
     }
 
     // Namespaces (Third Edition) [15] Attribute ::= NSAttName Eq AttValue | QName Eq AttValue
-    pub fn parse_attribute(
-        input: &str,
+    pub fn parse_attribute<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, Attribute> {
-        map(
-            alt((
-                tuple((Self::parse_namespace_attribute_name, Self::parse_eq, |i| {
-                    Attribute::parse_attvalue(i, entity_references.clone(), entity_source.clone())
-                })),
-                tuple((Self::parse_qualified_name, Self::parse_eq, |i| {
-                    Self::parse_attvalue(i, entity_references.clone(), entity_source.clone())
-                })),
-            )),
-            |result| match result {
-                (name, _eq, value) if name.prefix.is_some() => {
-                    let prefix = name.prefix.unwrap();
-
-                    if &prefix == "xmlns" {
-                        Attribute::Namespace {
-                            prefix: Prefix::Default,
-                            uri: value,
-                        }
-                    } else {
-                        Attribute::Namespace {
-                            prefix: Prefix::Prefix(prefix),
-                            uri: value,
-                        }
-                    }
-                }
-                (Name { prefix, local_part }, _eq, value) => Attribute::Instance {
-                    name: Name { prefix, local_part },
-                    value,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Attribute> {
+        context(
+            "[41] Attribute",
+            map(
+                alt((
+                    tuple((Self::parse_namespace_attribute_name, Self::parse_eq, |i| {
+                        Attribute::parse_attvalue(
+                            i,
+                            entity_references.clone(),
+                            entity_source.clone(),
+                            budget,
+                        )
+                    })),
+                    tuple((Self::parse_qualified_name, Self::parse_eq, |i| {
+                        Self::parse_attvalue(
+                            i,
+                            entity_references.clone(),
+                            entity_source.clone(),
+                            budget,
+                        )
+                    })),
+                )),
+                |result| match result {
+                    // Namespaces (Third Edition) [2] PrefixedAttName ::= 'xmlns:' NCName
+                    (
+                        Name {
+                            prefix: Some(prefix),
+                            local_part,
+                        },
+                        _eq,
+                        value,
+                    ) if prefix == "xmlns" => Attribute::Namespace {
+                        prefix: Prefix::Prefix(local_part),
+                        uri: value,
+                    },
+                    // Namespaces (Third Edition) [3] DefaultAttName ::= 'xmlns'
+                    (
+                        Name {
+                            prefix: None,
+                            local_part,
+                        },
+                        _eq,
+                        value,
+                    ) if local_part == "xmlns" => Attribute::Namespace {
+                        prefix: Prefix::Default,
+                        uri: value,
+                    },
+                    (name, _eq, value) => Attribute::Instance { name, value },
                 },
-            },
+            ),
         )(input)
     }
 }
@@ -426,6 +452,30 @@ impl<'a> Parse<'a> for AttType {
     }
 }
 impl AttType {
+    /// Normalizes an attribute value of this type as in [3.3.3 Attribute-Value Normalization](https://www.w3.org/TR/xml/#AVNormalize)
+    ///
+    /// `CDATA` values are returned as they are. Values of any other type have their leading and trailing spaces
+    /// removed and sequences of spaces replaced by a single space. Only spaces are collapsed, white space given by
+    /// character references such as `&#9;` is kept.
+    ///
+    /// ```rust
+    /// use nom_xml::attribute::{AttType, TokenizedType};
+    ///
+    /// let idrefs = AttType::Tokenized(TokenizedType::IDREFS);
+    /// assert_eq!(idrefs.normalize_value("  a   b "), "a b");
+    /// assert_eq!(AttType::CDATA.normalize_value("  a   b "), "  a   b ");
+    /// ```
+    pub fn normalize_value(&self, value: &str) -> String {
+        match self {
+            AttType::CDATA => value.to_string(),
+            _ => value
+                .split(' ')
+                .filter(|token| !token.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
     // [57] EnumeratedType ::= NotationType | Enumeration
     fn parse_enumerated_type(input: &str) -> IResult<&str, AttType> {
         alt((Self::parse_notation_type, Self::parse_enumeration))(input)
@@ -488,11 +538,12 @@ impl<'a> Parse<'a> for DefaultDecl {
     type Args = (
         Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         EntitySource,
+        ExpansionBudget,
     );
     type Output = IResult<&'a str, Self>;
     // [60] DefaultDecl ::= '#REQUIRED' | '#IMPLIED' | (('#FIXED' S)? AttValue)
     fn parse(input: &'a str, args: Self::Args) -> Self::Output {
-        let (entity_references, entity_source) = args;
+        let (entity_references, entity_source, budget) = args;
         let cloned_entity_references = entity_references.clone();
         let cloned_entity_source = entity_source.clone();
         alt((
@@ -506,6 +557,7 @@ impl<'a> Parse<'a> for DefaultDecl {
                             i,
                             cloned_entity_references.clone(),
                             cloned_entity_source.clone(),
+                            &budget,
                         )
                     },
                 ),
@@ -0,0 +1,703 @@
+// borrowed.rs
+
+//! Documents whose text borrows from the parsed input
+//!
+//! A [`BorrowedDocument`] has the shape of the [`Document`] that [`Document::parse`] returns for the same input, but its
+//! names, text, comments, CDATA sections and attribute values are [`Cow`]s that borrow from the input. Text is only
+//! allocated when entity or character references or attribute-value normalization change it. The prolog and
+//! processing instructions keep their owned types. [`BorrowedDocument::into_owned`] converts to a [`Document`].
+//!
+//! ```rust
+//! use nom_xml::{borrowed::BorrowedDocument, config::Config, parse::Parse, Document};
+//! use std::borrow::Cow;
+//!
+//! let xml = "<book isbn=\"978-0316129084\"><title>Leviathan Wakes</title>Corey &amp; Corey</book>";
+//! let (_, doc) = BorrowedDocument::parse(xml, &Config::default()).unwrap();
+//! let BorrowedDocument::Element(_, content, _) = &doc else { panic!() };
+//! let BorrowedDocument::Nested(children) = &**content else { panic!() };
+//! assert!(matches!(&children[1], BorrowedDocument::Content(Cow::Borrowed("Corey "))));
+//! assert!(matches!(&children[2], BorrowedDocument::Content(Cow::Owned(text)) if text == "&"));
+//! assert_eq!(doc.into_owned(), Document::parse(xml, &Config::default()).unwrap().1);
+//! ```
+
+use crate::{
+    attribute::{Attribute, AttributeValue, Prefix},
+    config::Config,
+    edit::{element_content, ElementContent},
+    error::Error,
+    misc::Misc,
+    namespaces::text,
+    parse::Parse,
+    processing_instruction::ProcessingInstruction,
+    prolog::{
+        doctype::DocType,
+        subset::{
+            entity::{entity_value::EntityValue, expansion::ExpansionBudget, EntitySource},
+            markup_declaration::MarkupDeclaration,
+            Subset,
+        },
+        xmldecl::XmlDecl,
+    },
+    tag::{Tag, TagState},
+    Document, IResult, Name,
+};
+use nom::{
+    bytes::complete::{tag, take_till, take_while},
+    character::complete::satisfy,
+    combinator::{opt, recognize},
+    error::ErrorKind,
+    sequence::pair,
+};
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, rc::Rc};
+
+type EntityReferences = Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>;
+
+/// A [`Name`] that borrows from the input
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BorrowedName<'a> {
+    pub prefix: Option<Cow<'a, str>>,
+    pub local_part: Cow<'a, str>,
+}
+
+impl BorrowedName<'_> {
+    pub fn into_owned(self) -> Name {
+        Name {
+            prefix: self.prefix.map(Cow::into_owned),
+            local_part: self.local_part.into_owned(),
+        }
+    }
+}
+
+impl From<Name> for BorrowedName<'static> {
+    fn from(name: Name) -> Self {
+        Self {
+            prefix: name.prefix.map(Cow::Owned),
+            local_part: Cow::Owned(name.local_part),
+        }
+    }
+}
+
+/// An attribute of a [`BorrowedTag`], the equivalent of [`Attribute::Instance`] and [`Attribute::Namespace`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BorrowedAttribute<'a> {
+    Instance {
+        name: BorrowedName<'a>,
+        value: Cow<'a, str>,
+    },
+    /// A namespace declaration, with no prefix for the default namespace
+    Namespace {
+        prefix: Option<Cow<'a, str>>,
+        uri: Cow<'a, str>,
+    },
+}
+
+impl BorrowedAttribute<'_> {
+    pub fn into_owned(self) -> Attribute {
+        match self {
+            BorrowedAttribute::Instance { name, value } => Attribute::Instance {
+                name: name.into_owned(),
+                value: AttributeValue::Value(value.into_owned()),
+            },
+            BorrowedAttribute::Namespace { prefix, uri } => Attribute::Namespace {
+                prefix: prefix.map_or(Prefix::Default, |prefix| {
+                    Prefix::Prefix(prefix.into_owned())
+                }),
+                uri: AttributeValue::Value(uri.into_owned()),
+            },
+        }
+    }
+
+    /// The attributes of tags, the other variants only occur in the DTD
+    fn from_owned(attribute: Attribute) -> Option<BorrowedAttribute<'static>> {
+        match attribute {
+            Attribute::Instance { name, value } => Some(BorrowedAttribute::Instance {
+                name: name.into(),
+                value: Cow::Owned(text(&value)),
+            }),
+            Attribute::Namespace { prefix, uri } => Some(BorrowedAttribute::Namespace {
+                prefix: match prefix {
+                    Prefix::Default => None,
+                    Prefix::Prefix(prefix) => Some(Cow::Owned(prefix)),
+                },
+                uri: Cow::Owned(text(&uri)),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A [`Tag`] that borrows from the input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BorrowedTag<'a> {
+    pub name: BorrowedName<'a>,
+    pub attributes: Option<Vec<BorrowedAttribute<'a>>>,
+    pub state: TagState,
+}
+
+impl BorrowedTag<'_> {
+    pub fn into_owned(self) -> Tag {
+        Tag::new(
+            self.name.into_owned(),
+            self.attributes.map(|attributes| {
+                attributes
+                    .into_iter()
+                    .map(BorrowedAttribute::into_owned)
+                    .collect()
+            }),
+            self.state,
+        )
+    }
+
+    /// Applies an edit of the owned tag, keeping the attributes it leaves unchanged borrowed
+    fn edit(&mut self, f: impl FnOnce(&mut Tag)) {
+        let mut tag = self.clone().into_owned();
+        f(&mut tag);
+        let existing = self.attributes.take().unwrap_or_default();
+        self.attributes = tag.attributes.map(|attributes| {
+            attributes
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, attribute)| match existing.get(index) {
+                    Some(borrowed) if borrowed.clone().into_owned() == attribute => {
+                        Some(borrowed.clone())
+                    }
+                    _ => BorrowedAttribute::from_owned(attribute),
+                })
+                .collect()
+        });
+    }
+}
+
+impl From<Tag> for BorrowedTag<'static> {
+    fn from(tag: Tag) -> Self {
+        Self {
+            name: tag.name.into(),
+            attributes: tag.attributes.map(|attributes| {
+                attributes
+                    .into_iter()
+                    .filter_map(BorrowedAttribute::from_owned)
+                    .collect()
+            }),
+            state: tag.state,
+        }
+    }
+}
+
+/// A [`Document`] whose text borrows from the input, see [`borrowed`](crate::borrowed)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BorrowedDocument<'a> {
+    Prolog {
+        xml_decl: Option<XmlDecl>,
+        misc: Option<Vec<Misc>>,
+        doc_type: Option<DocType>,
+    },
+    Element(BorrowedTag<'a>, Box<BorrowedDocument<'a>>, BorrowedTag<'a>),
+    Content(Cow<'a, str>),
+    Nested(Vec<BorrowedDocument<'a>>),
+    Empty,
+    EmptyTag(BorrowedTag<'a>),
+    ProcessingInstruction(ProcessingInstruction),
+    Comment(Cow<'a, str>),
+    CDATA(Cow<'a, str>),
+}
+
+impl<'a> BorrowedDocument<'a> {
+    /// Parses `input` into the [`Document`] that [`Document::parse`] returns, with its text borrowed from `input`
+    ///
+    /// Errors are those of [`Document::parse`], which parses the input again when it is not well-formed. It also
+    /// parses the markup that it accepts outside of the XML grammar, such as start tags written as `&#60;`, whose
+    /// text is then owned. [`Config::validate`] and [`Config::resolve_namespaces`] check an owned copy of the document.
+    pub fn parse(input: &'a str, config: &Config) -> IResult<&'a str, Self> {
+        let parsed = match Document::check_characters(input) {
+            Ok(_) => {
+                let budget = ExpansionBudget::new(config.entity_limits.clone());
+                Self::parse_document(input, config, &budget).ok()
+            }
+            Err(_) => None,
+        };
+        let Some((remaining, document)) = parsed else {
+            let (remaining, document) = Document::parse(input, config)?;
+            return Ok((Self::rest(input, remaining), document.into()));
+        };
+        if config.validate || config.resolve_namespaces {
+            let owned = document.clone().into_owned();
+            if config.validate {
+                owned.validate().map_err(nom::Err::Failure)?;
+            }
+            if config.resolve_namespaces {
+                owned.resolve_namespaces().map_err(nom::Err::Failure)?;
+            }
+        }
+        Ok((remaining, document))
+    }
+    /// Converts to a [`Document`], allocating the text that is borrowed
+    pub fn into_owned(self) -> Document {
+        match self {
+            BorrowedDocument::Prolog {
+                xml_decl,
+                misc,
+                doc_type,
+            } => Document::Prolog {
+                xml_decl,
+                misc,
+                doc_type,
+            },
+            BorrowedDocument::Element(start_tag, content, end_tag) => Document::Element(
+                start_tag.into_owned(),
+                Box::new(content.into_owned()),
+                end_tag.into_owned(),
+            ),
+            BorrowedDocument::Content(text) => Document::Content(Some(text.into_owned())),
+            BorrowedDocument::Nested(documents) => Document::Nested(
+                documents
+                    .into_iter()
+                    .map(BorrowedDocument::into_owned)
+                    .collect(),
+            ),
+            BorrowedDocument::Empty => Document::Empty,
+            BorrowedDocument::EmptyTag(tag) => Document::EmptyTag(tag.into_owned()),
+            BorrowedDocument::ProcessingInstruction(processing_instruction) => {
+                Document::ProcessingInstruction(processing_instruction)
+            }
+            BorrowedDocument::Comment(comment) => Document::Comment(comment.into_owned()),
+            BorrowedDocument::CDATA(cdata) => Document::CDATA(cdata.into_owned()),
+        }
+    }
+
+    // [1] document ::= prolog element Misc*, following `Document::parse_document`
+    fn parse_document(
+        input: &'a str,
+        config: &Config,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Self> {
+        let entity_references = Rc::new(RefCell::new(HashMap::new()));
+        let (remaining, prolog_and_references) =
+            opt(|i| Document::parse_prolog(i, entity_references.clone(), config, budget))(input)?;
+        let input = Self::rest(input, remaining);
+        let (prolog, entity_references) =
+            prolog_and_references.unwrap_or((None, entity_references));
+        if Document::entity_declarations_required(&prolog) {
+            budget.require_entity_declarations(true);
+        }
+
+        let source = Document::determine_source_from_references(&entity_references);
+        let (input, mut root) = Self::parse_element(input, &entity_references, source, budget)?;
+        let doc_type = match &prolog {
+            Some(Document::Prolog {
+                doc_type: Some(doc_type),
+                ..
+            }) => Some(doc_type),
+            _ => None,
+        };
+        if let Some(DocType {
+            subset: Some(subset),
+            ..
+        }) = doc_type
+        {
+            // The defaults are added to the root element, and to an empty-element tag that is its only content
+            let tags = match &mut root {
+                BorrowedDocument::Element(tag, content, _) => match &mut **content {
+                    BorrowedDocument::EmptyTag(empty_tag) => vec![tag, empty_tag],
+                    _ => vec![tag],
+                },
+                BorrowedDocument::EmptyTag(tag) => vec![tag],
+                _ => vec![],
+            };
+            for tag in tags {
+                let name = tag.name.clone().into_owned();
+                for subset in subset {
+                    if let Subset::MarkupDecl(MarkupDeclaration::AttList {
+                        name: element,
+                        att_defs: Some(att_defs),
+                    }) = subset
+                    {
+                        if *element == name {
+                            tag.edit(|tag| tag.merge_default_attributes(att_defs));
+                        }
+                    }
+                }
+            }
+        }
+        let mut documents = vec![root];
+
+        let mut input = input;
+        loop {
+            let (rest, _whitespace) = Document::parse_multispace0(input)?;
+            let (rest, misc) = if rest.starts_with("<!--") {
+                Self::parse_comment(rest)?
+            } else if rest.starts_with("<?") {
+                Self::parse_processing_instruction(rest)?
+            } else {
+                break;
+            };
+            documents.push(misc);
+            input = rest;
+        }
+        let (input, _whitespace) = Document::parse_multispace0(input)?;
+        if !input.is_empty() {
+            return Self::error(input);
+        }
+
+        if let Some(doc_type) = doc_type {
+            let att_defs = doc_type.attribute_definitions();
+            if !att_defs.is_empty() {
+                for document in &mut documents {
+                    document.normalize_attributes(&att_defs);
+                }
+            }
+        }
+
+        Ok((
+            input,
+            match prolog {
+                Some(Document::Prolog {
+                    xml_decl,
+                    misc,
+                    doc_type,
+                }) => {
+                    documents.insert(
+                        0,
+                        BorrowedDocument::Prolog {
+                            xml_decl,
+                            misc,
+                            doc_type,
+                        },
+                    );
+                    BorrowedDocument::Nested(documents)
+                }
+                _ if documents.len() == 1 => documents.remove(0),
+                _ => BorrowedDocument::Nested(documents),
+            },
+        ))
+    }
+
+    fn normalize_attributes(&mut self, att_defs: &HashMap<String, Vec<Attribute>>) {
+        match self {
+            BorrowedDocument::Element(tag, content, _) => {
+                if let Some(defs) = att_defs.get(&tag.name.clone().into_owned().to_string()) {
+                    tag.edit(|tag| tag.normalize_attributes(defs));
+                }
+                content.normalize_attributes(att_defs);
+            }
+            BorrowedDocument::EmptyTag(tag) => {
+                if let Some(defs) = att_defs.get(&tag.name.clone().into_owned().to_string()) {
+                    tag.edit(|tag| tag.normalize_attributes(defs));
+                }
+            }
+            BorrowedDocument::Nested(documents) => {
+                for document in documents {
+                    document.normalize_attributes(att_defs);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // [39] element ::= EmptyElemTag | STag content ETag
+    fn parse_element(
+        input: &'a str,
+        entity_references: &EntityReferences,
+        entity_source: EntitySource,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Self> {
+        let (input, _whitespace) = Document::parse_multispace0(input)?;
+        let (mut input, (_, name)) = pair(tag("<"), Self::parse_name)(input)?;
+        let mut attributes: Vec<(&str, Cow<str>)> = vec![];
+        let (input, empty) = loop {
+            let (rest, whitespace) = take_while(Document::is_whitespace)(input)?;
+            if let Some(rest) = rest.strip_prefix("/>") {
+                break (rest, true);
+            }
+            if let Some(rest) = rest.strip_prefix('>') {
+                break (rest, false);
+            }
+            if whitespace.is_empty() {
+                return Self::error(rest);
+            }
+            let (rest, attribute) = Self::parse_attribute(rest, entity_references, budget)?;
+            // [WFC: Unique Att Spec]
+            if attributes.iter().any(|(name, _)| *name == attribute.0) {
+                return Self::error(rest);
+            }
+            attributes.push(attribute);
+            input = rest;
+        };
+
+        if empty {
+            // The parser does not resolve the prefixes of attribute names in empty-element tags
+            let attributes = attributes
+                .into_iter()
+                .map(|(name, value)| BorrowedAttribute::Instance {
+                    name: BorrowedName {
+                        prefix: None,
+                        local_part: Cow::Borrowed(name),
+                    },
+                    value,
+                })
+                .collect::<Vec<_>>();
+            let tag = BorrowedTag {
+                name: Self::qualify(input, name)?,
+                attributes: (!attributes.is_empty()).then_some(attributes),
+                state: TagState::Empty,
+            };
+            return Ok((input, BorrowedDocument::EmptyTag(tag)));
+        }
+
+        let attributes = attributes
+            .into_iter()
+            .map(|(name, value)| {
+                Self::qualify(input, name).map(|name| match name {
+                    BorrowedName {
+                        prefix: None,
+                        local_part,
+                    } if local_part == "xmlns" => BorrowedAttribute::Namespace {
+                        prefix: None,
+                        uri: value,
+                    },
+                    BorrowedName {
+                        prefix: Some(prefix),
+                        local_part,
+                    } if prefix == "xmlns" => BorrowedAttribute::Namespace {
+                        prefix: Some(local_part),
+                        uri: value,
+                    },
+                    name => BorrowedAttribute::Instance { name, value },
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let start_tag = BorrowedTag {
+            name: Self::qualify(input, name)?,
+            attributes: (!attributes.is_empty()).then_some(attributes),
+            state: TagState::Start,
+        };
+        let (input, content) =
+            Self::parse_content(input, entity_references, entity_source, budget)?;
+
+        // [42] ETag ::= '</' Name S? '>'
+        let (input, _) = pair(tag("</"), Document::parse_multispace0)(input)?;
+        let (input, end_name) = Self::parse_name(input)?;
+        let (remaining, _) = pair(Document::parse_multispace0, tag(">"))(input)?;
+        let end_tag = BorrowedTag {
+            name: Self::qualify(input, end_name)?,
+            attributes: None,
+            state: TagState::End,
+        };
+        // [WFC: Element Type Match]
+        if start_tag.name != end_tag.name {
+            return Self::error(input);
+        }
+        let (remaining, _whitespace) = Document::parse_multispace0(remaining)?;
+        Ok((
+            remaining,
+            BorrowedDocument::Element(start_tag, Box::new(content), end_tag),
+        ))
+    }
+
+    // [41] Attribute ::= Name Eq AttValue
+    fn parse_attribute(
+        input: &'a str,
+        entity_references: &EntityReferences,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, (&'a str, Cow<'a, str>)> {
+        let (input, name) = Self::parse_name(input)?;
+        let (input, _eq) = Document::parse_eq(input)?;
+        let Some(quote) = input.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            return Self::error(input);
+        };
+        let Some(end) = input[1..].find(quote) else {
+            return Self::error(input);
+        };
+        let value = &input[1..=end];
+        if value.contains('<') {
+            return Self::error(input);
+        }
+        // 3.3.3 Attribute-Value Normalization replaces references and white space other than spaces
+        if value.contains(['&', '\t', '\n', '\r']) {
+            let (input, value) = Attribute::parse_attvalue(
+                input,
+                entity_references.clone(),
+                EntitySource::Internal,
+                budget,
+            )?;
+            return Ok((input, (name, Cow::Owned(text(&value)))));
+        }
+        Ok((&input[end + 2..], (name, Cow::Borrowed(value))))
+    }
+
+    // [43] content ::= CharData? ((element | Reference | CDSect | PI | Comment) CharData?)*
+    fn parse_content(
+        input: &'a str,
+        entity_references: &EntityReferences,
+        entity_source: EntitySource,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Self> {
+        let mut children = vec![];
+        let (mut input, text) = Self::parse_char_data(input)?;
+        children.extend(text);
+        loop {
+            let (rest, child) = if input.starts_with('&') {
+                let (rest, replacement) = Document::parse_references(
+                    input,
+                    entity_references,
+                    entity_source.clone(),
+                    budget,
+                )?;
+                (rest, replacement.into())
+            } else if input.starts_with("<![CDATA[") {
+                Self::parse_cdata_section(input)?
+            } else if input.starts_with("<!--") {
+                Self::parse_comment(input)?
+            } else if input.starts_with("<?") {
+                Self::parse_processing_instruction(input)?
+            } else if input.starts_with('<') && !input.starts_with("</") {
+                Self::parse_element(input, entity_references, EntitySource::Internal, budget)?
+            } else {
+                break;
+            };
+            children.push(child);
+            let (rest, text) = Self::parse_char_data(rest)?;
+            children.extend(text);
+            input = rest;
+        }
+        Ok((input, element_content(children)))
+    }
+
+    // [14] CharData ::= [^<&]* - ([^<&]* ']]>' [^<&]*), without the white space that the parser skips before it
+    fn parse_char_data(input: &'a str) -> IResult<&'a str, Option<Self>> {
+        let (input, _whitespace) = Document::parse_multispace0(input)?;
+        let (remaining, data) = take_till(|c| c == '<' || c == '&')(input)?;
+        if data.contains("]]>") {
+            return Self::error(input);
+        }
+        Ok((
+            remaining,
+            (!data.is_empty()).then_some(BorrowedDocument::Content(Cow::Borrowed(data))),
+        ))
+    }
+
+    // [18] CDSect ::= CDStart CData CDEnd
+    fn parse_cdata_section(input: &'a str) -> IResult<&'a str, Self> {
+        let cdata = &input["<![CDATA[".len()..];
+        match cdata.find("]]>") {
+            Some(end) => Ok((
+                &cdata[end + 3..],
+                BorrowedDocument::CDATA(Cow::Borrowed(&cdata[..end])),
+            )),
+            None => Self::error(input),
+        }
+    }
+
+    // [15] Comment ::= '<!--' ((Char - '-') | ('-' (Char - '-')))* '-->'
+    fn parse_comment(input: &'a str) -> IResult<&'a str, Self> {
+        let comment = &input["<!--".len()..];
+        match comment.find("-->") {
+            Some(end) if !comment[..end].contains("--") && !comment[..end].ends_with('-') => Ok((
+                &comment[end + 3..],
+                BorrowedDocument::Comment(Cow::Borrowed(&comment[..end])),
+            )),
+            _ => Self::error(input),
+        }
+    }
+
+    fn parse_processing_instruction(input: &'a str) -> IResult<&'a str, Self> {
+        let (input, processing_instruction) = ProcessingInstruction::parse(input, ())?;
+        Ok((
+            input,
+            BorrowedDocument::ProcessingInstruction(processing_instruction),
+        ))
+    }
+
+    // [5] Name ::= NameStartChar (NameChar)*
+    fn parse_name(input: &'a str) -> IResult<&'a str, &'a str> {
+        recognize(pair(
+            satisfy(Document::is_name_start_char),
+            take_while(Document::is_name_char),
+        ))(input)
+    }
+
+    // Namespaces (Third Edition) [7] QName ::= PrefixedName | UnprefixedName
+    fn qualify(input: &'a str, name: &'a str) -> Result<BorrowedName<'a>, nom::Err<Error>> {
+        match name.split_once(':') {
+            None => Ok(BorrowedName {
+                prefix: None,
+                local_part: Cow::Borrowed(name),
+            }),
+            Some((prefix, local_part))
+                if !prefix.is_empty()
+                    && local_part.starts_with(Document::is_name_start_char)
+                    && !local_part.contains(':') =>
+            {
+                Ok(BorrowedName {
+                    prefix: Some(Cow::Borrowed(prefix)),
+                    local_part: Cow::Borrowed(local_part),
+                })
+            }
+            // Left to `Document::parse`
+            _ => Self::error(input).map(|(_, name)| name),
+        }
+    }
+
+    // The parsers that take the `Config` return slices of `input` with its lifetime
+    fn rest(input: &'a str, remaining: &str) -> &'a str {
+        &input[input.len() - remaining.len()..]
+    }
+
+    // The errors are not reported, `Document::parse` parses the input again to explain them
+    fn error<T>(input: &'a str) -> IResult<&'a str, T> {
+        Err(nom::Err::Error(
+            nom::error::Error::new(input, ErrorKind::Verify).into(),
+        ))
+    }
+}
+
+impl ElementContent for BorrowedDocument<'_> {
+    fn empty() -> Self {
+        BorrowedDocument::Empty
+    }
+
+    fn nested(children: Vec<Self>) -> Self {
+        BorrowedDocument::Nested(children)
+    }
+
+    fn is_element(&self) -> bool {
+        matches!(self, BorrowedDocument::Element(..))
+    }
+}
+
+impl From<Document> for BorrowedDocument<'static> {
+    fn from(document: Document) -> Self {
+        match document {
+            Document::Prolog {
+                xml_decl,
+                misc,
+                doc_type,
+            } => BorrowedDocument::Prolog {
+                xml_decl,
+                misc,
+                doc_type,
+            },
+            Document::Element(start_tag, content, end_tag) => BorrowedDocument::Element(
+                start_tag.into(),
+                Box::new((*content).into()),
+                end_tag.into(),
+            ),
+            Document::Content(Some(text)) => BorrowedDocument::Content(Cow::Owned(text)),
+            Document::Content(None) | Document::Empty => BorrowedDocument::Empty,
+            Document::Nested(documents) => {
+                BorrowedDocument::Nested(documents.into_iter().map(Into::into).collect())
+            }
+            Document::EmptyTag(tag) => BorrowedDocument::EmptyTag(tag.into()),
+            Document::ProcessingInstruction(processing_instruction) => {
+                BorrowedDocument::ProcessingInstruction(processing_instruction)
+            }
+            Document::Comment(comment) => BorrowedDocument::Comment(Cow::Owned(comment)),
+            Document::CDATA(cdata) => BorrowedDocument::CDATA(Cow::Owned(cdata)),
+        }
+    }
+}
+
+impl From<BorrowedDocument<'_>> for Document {
+    fn from(document: BorrowedDocument<'_>) -> Self {
+        document.into_owned()
+    }
+}
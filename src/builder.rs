@@ -0,0 +1,195 @@
+// builder.rs
+
+//! Construction of documents with a fluent builder
+//!
+//! An [`Element`] builds the same [`Document`] as parsing its XML would: an [`Document::EmptyTag`] when it has no
+//! children, unless [`Element::with_end_tag`] is used, otherwise a [`Document::Element`] with matching start and end
+//! tags. Names are written as given, with the prefix before the first colon.
+//!
+//! ```rust
+//! use nom_xml::{builder::Element, config::Config, parse::Parse, Document};
+//!
+//! let book = Element::new("book")
+//!     .attr("isbn", "978-0316129084")
+//!     .child(Element::new("title").text("Leviathan Wakes"))
+//!     .child(Element::new("cover"))
+//!     .build();
+//! let xml = "<book isbn=\"978-0316129084\"><title>Leviathan Wakes</title><cover/></book>";
+//! assert_eq!(book.to_string(), xml);
+//! assert_eq!(book, Document::parse(xml, &Config::default()).unwrap().1);
+//! ```
+
+use crate::{
+    attribute::{Attribute, AttributeValue, Prefix},
+    edit::element_content,
+    namespaces::qualify,
+    processing_instruction::ProcessingInstruction,
+    prolog::xmldecl::XmlDecl,
+    tag::{Tag, TagState},
+    Document, Name,
+};
+
+/// A builder for an element, see [`builder`](crate::builder)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Document>,
+    end_tag: bool,
+}
+
+impl Element {
+    /// An element named `name` without attributes or children
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            attributes: vec![],
+            children: vec![],
+            end_tag: false,
+        }
+    }
+
+    /// Sets the attribute `name` to `value`, replacing the value of an attribute that is already set
+    pub fn attr(mut self, name: &str, value: impl Into<String>) -> Self {
+        let value = value.into();
+        match self
+            .attributes
+            .iter_mut()
+            .find(|(attribute, _)| attribute == name)
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.attributes.push((name.to_string(), value)),
+        }
+        self
+    }
+
+    /// Adds a child, either an [`Element`] or any [`Document`] that can be in the content of an element
+    pub fn child(mut self, child: impl Into<Document>) -> Self {
+        match child.into() {
+            Document::Content(Some(text)) => return self.text(text),
+            Document::Nested(children) => return self.children(children),
+            Document::Content(None) | Document::Empty => {}
+            child => self.children.push(child),
+        }
+        self
+    }
+
+    /// Adds each of `children`, see [`Element::child`]
+    pub fn children<T: Into<Document>>(self, children: impl IntoIterator<Item = T>) -> Self {
+        children.into_iter().fold(self, Element::child)
+    }
+
+    /// Adds text, which is joined with the text before it as it would be in a parsed document
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        let text = text.into();
+        if text.is_empty() {
+            return self;
+        }
+        match self.children.last_mut() {
+            Some(Document::Content(Some(previous))) => previous.push_str(&text),
+            _ => self.children.push(Document::Content(Some(text))),
+        }
+        self
+    }
+
+    /// Adds a CDATA section
+    pub fn cdata(mut self, text: impl Into<String>) -> Self {
+        self.children.push(Document::CDATA(text.into()));
+        self
+    }
+
+    /// Adds a comment
+    pub fn comment(mut self, text: impl Into<String>) -> Self {
+        self.children.push(Document::Comment(text.into()));
+        self
+    }
+
+    /// Adds a processing instruction
+    pub fn processing_instruction(mut self, target: &str, data: Option<&str>) -> Self {
+        self.children
+            .push(Document::ProcessingInstruction(ProcessingInstruction {
+                target: qualify(&Name::new(None, target)),
+                data: data.map(str::to_string),
+            }));
+        self
+    }
+
+    /// Builds the element with a start tag and an end tag even when it has no children, as in `<name></name>`
+    pub fn with_end_tag(mut self) -> Self {
+        self.end_tag = true;
+        self
+    }
+
+    /// The element as a [`Document::EmptyTag`], or a [`Document::Element`] when it has children or
+    /// [`Element::with_end_tag`] is used
+    pub fn build(self) -> Document {
+        let name = qualify(&Name::new(None, &self.name));
+        if self.children.is_empty() && !self.end_tag {
+            // The parser does not resolve the prefixes of attribute names in empty-element tags
+            let attributes = self
+                .attributes
+                .into_iter()
+                .map(|(name, value)| Attribute::Instance {
+                    name: Name::new(None, &name),
+                    value: AttributeValue::Value(value),
+                })
+                .collect::<Vec<_>>();
+            return Document::EmptyTag(Tag::new(
+                name,
+                (!attributes.is_empty()).then_some(attributes),
+                TagState::Empty,
+            ));
+        }
+        let attributes = self
+            .attributes
+            .into_iter()
+            .map(|(name, value)| {
+                let value = AttributeValue::Value(value);
+                match qualify(&Name::new(None, &name)) {
+                    Name {
+                        prefix: None,
+                        local_part,
+                    } if local_part == "xmlns" => Attribute::Namespace {
+                        prefix: Prefix::Default,
+                        uri: value,
+                    },
+                    Name {
+                        prefix: Some(prefix),
+                        local_part,
+                    } if prefix == "xmlns" => Attribute::Namespace {
+                        prefix: Prefix::Prefix(local_part),
+                        uri: value,
+                    },
+                    name => Attribute::Instance { name, value },
+                }
+            })
+            .collect::<Vec<_>>();
+        Document::Element(
+            Tag::new(
+                name.clone(),
+                (!attributes.is_empty()).then_some(attributes),
+                TagState::Start,
+            ),
+            Box::new(element_content(self.children)),
+            Tag::new(name, None, TagState::End),
+        )
+    }
+
+    /// A document with `xml_decl` in its prolog and this element as its root element
+    pub fn build_document(self, xml_decl: XmlDecl) -> Document {
+        Document::Nested(vec![
+            Document::Prolog {
+                xml_decl: Some(xml_decl),
+                misc: None,
+                doc_type: None,
+            },
+            self.build(),
+        ])
+    }
+}
+
+impl From<Element> for Document {
+    fn from(element: Element) -> Self {
+        element.build()
+    }
+}
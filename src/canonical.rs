@@ -0,0 +1,335 @@
+// canonical.rs
+
+use crate::{
+    attribute::{Attribute, Prefix},
+    config::{CanonicalConfig, CanonicalMethod},
+    namespaces::{qualify, text, XML_NAMESPACE},
+    processing_instruction::ProcessingInstruction,
+    tag::Tag,
+    transcode::Encode,
+    Document, Name,
+};
+use std::collections::HashMap;
+
+impl Document {
+    /// Writes the `Document` in the canonical form selected by the [`CanonicalConfig`]
+    ///
+    /// The XML declaration and the document type declaration are dropped, entity references and CDATA sections are
+    /// written as text, empty-element tags are written as start-tag/end-tag pairs and attributes are sorted.
+    /// Two documents that are structurally equivalent have byte-for-byte identical canonical forms,
+    /// which makes the output suitable for hashing and signatures.
+    ///
+    /// ```rust
+    /// use nom_xml::{parse::Parse, config::{CanonicalConfig, Config}, Document};
+    ///
+    /// let xml = "<?xml version=\"1.0\"?>\n<doc b='2' a=\"1\"><e/><![CDATA[x < y]]></doc>";
+    /// let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+    /// assert_eq!(
+    ///     doc.to_canonical_string(&CanonicalConfig::default()),
+    ///     "<doc a=\"1\" b=\"2\"><e></e>x &lt; y</doc>"
+    /// );
+    /// ```
+    pub fn to_canonical_string(&self, config: &CanonicalConfig) -> String {
+        let top_level = self.top_level();
+
+        let mut canonicalizer = Canonicalizer {
+            config,
+            output: String::new(),
+        };
+        let mut seen_root = false;
+        for document in top_level {
+            match document {
+                Document::Element(..) | Document::EmptyTag(_) => {
+                    canonicalizer.write_element(
+                        document,
+                        &NamespaceScope::default(),
+                        &HashMap::new(),
+                    );
+                    seen_root = true;
+                }
+                Document::ProcessingInstruction(_) | Document::Comment(_) => {
+                    canonicalizer.write_top_level(document, seen_root)
+                }
+                _ => {}
+            }
+        }
+        canonicalizer.output
+    }
+}
+
+/// The namespace bindings in scope, with the default namespace under the empty prefix
+type NamespaceScope = HashMap<String, String>;
+
+/// An attribute that is not a namespace declaration, with its value flattened
+type Attr = (Name, String);
+
+struct Canonicalizer<'a> {
+    config: &'a CanonicalConfig,
+    output: String,
+}
+
+impl Canonicalizer<'_> {
+    fn write_top_level(&mut self, document: &Document, after_root: bool) {
+        let separate = self.config.method != CanonicalMethod::XmlTest;
+        let mut node = String::new();
+        self.write_markup(document, &mut node);
+        if node.is_empty() {
+            return;
+        }
+        if separate && after_root {
+            self.output.push('\n');
+        }
+        self.output.push_str(&node);
+        if separate && !after_root {
+            self.output.push('\n');
+        }
+    }
+
+    /// Writes comments and PIs, comments are left out unless they are requested
+    fn write_markup(&self, document: &Document, f: &mut String) {
+        match document {
+            Document::Comment(comment)
+                if self.config.with_comments && self.config.method != CanonicalMethod::XmlTest =>
+            {
+                f.push_str("<!--");
+                f.push_str(comment);
+                f.push_str("-->");
+            }
+            Document::ProcessingInstruction(ProcessingInstruction { target, data }) => {
+                f.push_str("<?");
+                f.push_str(&target.to_string());
+                match data {
+                    Some(data) if !data.is_empty() => {
+                        f.push(' ');
+                        f.push_str(data);
+                    }
+                    // The xmltest form always separates the target from the data
+                    _ if self.config.method == CanonicalMethod::XmlTest => f.push(' '),
+                    _ => {}
+                }
+                f.push_str("?>");
+            }
+            _ => {}
+        }
+    }
+
+    fn write_content(
+        &mut self,
+        document: &Document,
+        scope: &NamespaceScope,
+        rendered: &NamespaceScope,
+    ) {
+        match document {
+            Document::Element(..) | Document::EmptyTag(_) => {
+                self.write_element(document, scope, rendered)
+            }
+            Document::Content(Some(text)) | Document::CDATA(text) => {
+                let text = self.encode_text(text);
+                self.output.push_str(&text);
+            }
+            Document::Nested(documents) => {
+                for document in documents {
+                    self.write_content(document, scope, rendered);
+                }
+            }
+            Document::Comment(_) | Document::ProcessingInstruction(_) => {
+                let mut node = String::new();
+                self.write_markup(document, &mut node);
+                self.output.push_str(&node);
+            }
+            _ => {}
+        }
+    }
+
+    fn write_element(
+        &mut self,
+        document: &Document,
+        parent_scope: &NamespaceScope,
+        parent_rendered: &NamespaceScope,
+    ) {
+        let (start_tag, content) = match document {
+            Document::Element(start_tag, content, _) => (start_tag, Some(content)),
+            Document::EmptyTag(tag) => (tag, None),
+            _ => return,
+        };
+        let (name, declarations, attributes) = split_attributes(start_tag);
+
+        let mut scope = parent_scope.clone();
+        for (prefix, uri) in &declarations {
+            scope.insert(prefix.clone(), uri.clone());
+        }
+        let mut rendered = parent_rendered.clone();
+
+        // (sort key, qualified name, value), namespace declarations are always written first
+        let mut namespace_declarations: Vec<(String, String)> = vec![];
+        let mut written_attributes: Vec<((String, String), String, String)> = vec![];
+        if self.config.method == CanonicalMethod::XmlTest {
+            // Namespace declarations are ordinary attributes in the xmltest form
+            for (prefix, uri) in declarations {
+                let qualified_name = xmlns_attribute_name(&prefix);
+                written_attributes.push((
+                    (String::new(), qualified_name.clone()),
+                    qualified_name,
+                    uri,
+                ));
+            }
+        } else {
+            for prefix in self.namespaces_to_render(&name, &attributes, &scope) {
+                let uri = scope.get(&prefix).cloned().unwrap_or_default();
+                if rendered
+                    .get(&prefix)
+                    .map_or(uri.is_empty(), |existing| *existing == uri)
+                {
+                    continue;
+                }
+                namespace_declarations.push((xmlns_attribute_name(&prefix), uri.clone()));
+                rendered.insert(prefix, uri);
+            }
+        }
+
+        for (attribute_name, value) in attributes {
+            let qualified_name = attribute_name.to_string();
+            // C14N sorts by namespace URI and then local name, the xmltest form by qualified name
+            let sort_key = match (&self.config.method, attribute_name.prefix) {
+                (CanonicalMethod::XmlTest, _) => (String::new(), qualified_name.clone()),
+                (_, None) => (String::new(), attribute_name.local_part),
+                (_, Some(prefix)) if prefix == "xml" => {
+                    (XML_NAMESPACE.to_string(), attribute_name.local_part)
+                }
+                (_, Some(prefix)) => (
+                    scope.get(&prefix).cloned().unwrap_or_default(),
+                    attribute_name.local_part,
+                ),
+            };
+            written_attributes.push((sort_key, qualified_name, value));
+        }
+        written_attributes.sort();
+
+        self.output.push('<');
+        self.output.push_str(&name.to_string());
+        let written_attributes = namespace_declarations.into_iter().chain(
+            written_attributes
+                .into_iter()
+                .map(|(_sort_key, qualified_name, value)| (qualified_name, value)),
+        );
+        for (qualified_name, value) in written_attributes {
+            let value = self.encode_attribute(&value);
+            self.output.push(' ');
+            self.output.push_str(&qualified_name);
+            self.output.push_str("=\"");
+            self.output.push_str(&value);
+            self.output.push('"');
+        }
+        self.output.push('>');
+        if let Some(content) = content {
+            self.write_content(content, &scope, &rendered);
+        }
+        self.output.push_str("</");
+        self.output.push_str(&name.to_string());
+        self.output.push('>');
+    }
+
+    /// The prefixes whose namespace declarations are candidates for output on an element
+    fn namespaces_to_render(
+        &self,
+        name: &Name,
+        attributes: &[Attr],
+        scope: &NamespaceScope,
+    ) -> Vec<String> {
+        let mut prefixes: Vec<String> = match &self.config.method {
+            CanonicalMethod::ExclusiveC14n { inclusive_prefixes } => {
+                // Exclusive C14N 1.0 §3 only renders namespaces that are visibly utilized
+                let mut prefixes = vec![name.prefix.clone().unwrap_or_default()];
+                prefixes.extend(
+                    attributes
+                        .iter()
+                        .filter_map(|(attribute_name, _)| attribute_name.prefix.clone()),
+                );
+                prefixes.extend(
+                    inclusive_prefixes
+                        .iter()
+                        .map(|prefix| match prefix.as_str() {
+                            "#default" => String::new(),
+                            prefix => prefix.to_string(),
+                        })
+                        .filter(|prefix| scope.contains_key(prefix)),
+                );
+                prefixes
+            }
+            _ => scope.keys().cloned().collect(),
+        };
+        prefixes.retain(|prefix| prefix != "xml");
+        prefixes.sort();
+        prefixes.dedup();
+        prefixes
+    }
+
+    fn encode_text(&self, text: &str) -> String {
+        let text = normalize_line_endings(text);
+        match self.config.method {
+            CanonicalMethod::XmlTest => encode_xml_test(&text),
+            _ => text.encode_char_data(),
+        }
+    }
+
+    fn encode_attribute(&self, value: &str) -> String {
+        let value = normalize_line_endings(value);
+        match self.config.method {
+            CanonicalMethod::XmlTest => encode_xml_test(&value),
+            _ => value.encode_attribute_value(),
+        }
+    }
+}
+
+/// Separates the namespace declarations of a tag from its other attributes, as (prefix, uri) and (name, value) pairs
+///
+/// Attributes of empty-element tags are parsed without namespaces, so prefixed names are split here as well.
+fn split_attributes(tag: &Tag) -> (Name, Vec<(String, String)>, Vec<Attr>) {
+    let mut declarations = vec![];
+    let mut attributes = vec![];
+    for attribute in tag.attributes.iter().flatten() {
+        match attribute {
+            Attribute::Namespace { prefix, uri } => {
+                let prefix = match prefix {
+                    Prefix::Default => String::new(),
+                    Prefix::Prefix(prefix) => prefix.clone(),
+                };
+                declarations.push((prefix, text(uri)));
+            }
+            Attribute::Instance { name, value } => {
+                let name = qualify(name);
+                match (name.prefix.as_deref(), name.local_part.as_str()) {
+                    (None, "xmlns") => declarations.push((String::new(), text(value))),
+                    (Some("xmlns"), prefix) => declarations.push((prefix.to_string(), text(value))),
+                    _ => attributes.push((name, text(value))),
+                }
+            }
+            _ => {}
+        }
+    }
+    (qualify(&tag.name), declarations, attributes)
+}
+
+fn xmlns_attribute_name(prefix: &str) -> String {
+    match prefix {
+        "" => "xmlns".to_string(),
+        prefix => format!("xmlns:{prefix}"),
+    }
+}
+
+fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
+
+/// Escapes data as `Datachar` from `tests/xmltest/canonxml.html`
+fn encode_xml_test(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' | '<' | '>' | '"' => encoded.push_str(&text.encode_reference(ch)),
+            '\t' | '\n' | '\r' => encoded.push_str(&text.encode_digit(ch)),
+            _ => encoded.push(ch),
+        }
+    }
+    encoded
+}
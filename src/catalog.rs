@@ -0,0 +1,465 @@
+//! [OASIS XML Catalogs](https://www.oasis-open.org/committees/download.php/14809/xml-catalogs.html) for mapping the
+//! public and system identifiers of external entities and DTDs to local files
+//!
+//! The `public`, `system`, `rewriteSystem`, `systemSuffix`, `delegatePublic`, `delegateSystem`, `nextCatalog` and
+//! `group` entries are supported, along with the `prefer` and `xml:base` attributes. URI references are resolved as
+//! file paths, relative to the directory of the catalog.
+//!
+//! ```rust
+//! use nom_xml::{
+//!     catalog::{Catalog, CatalogResolver},
+//!     config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+//!     parse::Parse,
+//!     Document,
+//! };
+//! use std::sync::Arc;
+//!
+//! let catalog = Catalog::parse_str(
+//!     r#"<catalog xmlns="urn:oasis:names:tc:entity:xmlns:xml:catalog">
+//!         <public publicId="-//Example//DTD Doc//EN" uri="dtd/doc.dtd"/>
+//!     </catalog>"#,
+//!     "schemas",
+//! )
+//! .unwrap();
+//! assert_eq!(
+//!     catalog.resolve_external_id(Some("-//Example//DTD Doc//EN"), Some("http://example.com/doc.dtd")),
+//!     Some("schemas/dtd/doc.dtd".into())
+//! );
+//!
+//! let config = Config {
+//!     external_parse_config: ExternalEntityParseConfig {
+//!         policy: ExternalEntityPolicy::AllowDirectories(vec!["schemas".into()]),
+//!         resolver: Some(Arc::new(CatalogResolver::new(catalog))),
+//!         ..Default::default()
+//!     },
+//!     ..Default::default()
+//! };
+//! # let _ = config;
+//! ```
+
+use crate::{
+    attribute::{Attribute, AttributeValue},
+    config::Config,
+    error::Error,
+    io::read_file,
+    parse::Parse,
+    resolver::{DenyAllResolver, EntityResolver, ResolvedEntity},
+    tag::Tag,
+    Document,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The entries of a catalog file and of the catalogs it refers to
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Catalog {
+    entries: Vec<CatalogEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CatalogEntry {
+    Public {
+        public_id: String,
+        uri: PathBuf,
+        prefer_public: bool,
+    },
+    System {
+        system_id: String,
+        uri: PathBuf,
+    },
+    RewriteSystem {
+        start: String,
+        prefix: String,
+    },
+    SystemSuffix {
+        suffix: String,
+        uri: PathBuf,
+    },
+    DelegatePublic {
+        start: String,
+        catalog: Catalog,
+        prefer_public: bool,
+    },
+    DelegateSystem {
+        start: String,
+        catalog: Catalog,
+    },
+    NextCatalog(Catalog),
+}
+
+// The `xml:base` and `prefer` attributes in scope of an entry
+#[derive(Clone)]
+struct EntryContext {
+    base: PathBuf,
+    prefer_public: bool,
+}
+
+// Catalogs are read once and reused wherever they are referred to again. A catalog referring back to one that is
+// still being loaded gets an empty catalog in its place, so catalogs referring to each other are not read forever.
+#[derive(Default)]
+struct CatalogLoader {
+    loading: HashSet<PathBuf>,
+    loaded: HashMap<PathBuf, Catalog>,
+}
+
+impl Catalog {
+    /// Loads a catalog file, along with the catalogs its `nextCatalog` and delegate entries refer to
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        CatalogLoader::default().load(path.as_ref())
+    }
+
+    /// Loads catalog files that are consulted in order, like the `XML_CATALOG_FILES` list of other tools
+    pub fn from_files<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<Self, Error> {
+        let mut loader = CatalogLoader::default();
+        let entries = paths
+            .into_iter()
+            .map(|path| loader.load(path.as_ref()).map(CatalogEntry::NextCatalog))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
+    }
+
+    /// Reads a catalog from a string, with relative URI references resolved against `base_directory`
+    pub fn parse_str(input: &str, base_directory: impl AsRef<Path>) -> Result<Self, Error> {
+        CatalogLoader::default().read(input, base_directory.as_ref())
+    }
+
+    /// The file that the public and system identifiers of an external identifier are mapped to
+    ///
+    /// System identifiers are matched first. Public identifiers are only matched when there is no system identifier,
+    /// or when the entry is in the scope of `prefer="public"`, which is the default.
+    pub fn resolve_external_id(
+        &self,
+        public_id: Option<&str>,
+        system_id: Option<&str>,
+    ) -> Option<PathBuf> {
+        let public_id = public_id.map(normalize_public_id);
+        self.resolve(public_id.as_deref(), system_id)
+    }
+
+    fn resolve(&self, public_id: Option<&str>, system_id: Option<&str>) -> Option<PathBuf> {
+        if let Some(system_id) = system_id {
+            if let Some(uri) = self.resolve_system(system_id) {
+                return uri;
+            }
+        }
+        if let Some(public_id) = public_id {
+            if let Some(uri) = self.resolve_public(public_id, system_id.is_some()) {
+                return uri;
+            }
+        }
+        self.entries.iter().find_map(|entry| match entry {
+            CatalogEntry::NextCatalog(catalog) => catalog.resolve(public_id, system_id),
+            _ => None,
+        })
+    }
+
+    // `Some` when an entry of this catalog decides the resolution, which is `None` when delegation finds no match
+    fn resolve_system(&self, system_id: &str) -> Option<Option<PathBuf>> {
+        if let Some(uri) = self.entries.iter().find_map(|entry| match entry {
+            CatalogEntry::System { system_id: id, uri } if id == system_id => Some(uri.clone()),
+            _ => None,
+        }) {
+            return Some(Some(uri));
+        }
+        if let Some((start, prefix)) = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                CatalogEntry::RewriteSystem { start, prefix } if system_id.starts_with(start) => {
+                    Some((start, prefix))
+                }
+                _ => None,
+            })
+            .max_by_key(|(start, _)| start.len())
+        {
+            return Some(Some(PathBuf::from(format!(
+                "{prefix}{}",
+                &system_id[start.len()..]
+            ))));
+        }
+        if let Some((_, uri)) = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                CatalogEntry::SystemSuffix { suffix, uri } if system_id.ends_with(suffix) => {
+                    Some((suffix, uri))
+                }
+                _ => None,
+            })
+            .max_by_key(|(suffix, _)| suffix.len())
+        {
+            return Some(Some(uri.clone()));
+        }
+        let mut delegates: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                CatalogEntry::DelegateSystem { start, catalog } if system_id.starts_with(start) => {
+                    Some((start, catalog))
+                }
+                _ => None,
+            })
+            .collect();
+        if delegates.is_empty() {
+            return None;
+        }
+        delegates.sort_by_key(|(start, _)| std::cmp::Reverse(start.len()));
+        Some(
+            delegates
+                .into_iter()
+                .find_map(|(_, catalog)| catalog.resolve(None, Some(system_id))),
+        )
+    }
+
+    fn resolve_public(&self, public_id: &str, has_system_id: bool) -> Option<Option<PathBuf>> {
+        if let Some(uri) = self.entries.iter().find_map(|entry| match entry {
+            CatalogEntry::Public {
+                public_id: id,
+                uri,
+                prefer_public,
+            } if id == public_id && (*prefer_public || !has_system_id) => Some(uri.clone()),
+            _ => None,
+        }) {
+            return Some(Some(uri));
+        }
+        let mut delegates: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                CatalogEntry::DelegatePublic {
+                    start,
+                    catalog,
+                    prefer_public,
+                } if public_id.starts_with(start) && (*prefer_public || !has_system_id) => {
+                    Some((start, catalog))
+                }
+                _ => None,
+            })
+            .collect();
+        if delegates.is_empty() {
+            return None;
+        }
+        delegates.sort_by_key(|(start, _)| std::cmp::Reverse(start.len()));
+        Some(
+            delegates
+                .into_iter()
+                .find_map(|(_, catalog)| catalog.resolve(Some(public_id), None)),
+        )
+    }
+}
+
+impl CatalogLoader {
+    fn load(&mut self, path: &Path) -> Result<Catalog, Error> {
+        let catalog_file_error = |source| Error::CatalogFile {
+            path: path.display().to_string(),
+            source: Box::new(source),
+        };
+        let canonical_path = path
+            .canonicalize()
+            .map_err(|e| catalog_file_error(e.into()))?;
+        if let Some(catalog) = self.loaded.get(&canonical_path) {
+            return Ok(catalog.clone());
+        }
+        if !self.loading.insert(canonical_path.clone()) {
+            return Ok(Catalog::default());
+        }
+        let data = read_file(&mut File::open(path).map_err(|e| catalog_file_error(e.into()))?)
+            .map_err(|e| catalog_file_error(e.into()))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        let catalog = self.read(&data, base).map_err(catalog_file_error)?;
+        self.loading.remove(&canonical_path);
+        self.loaded.insert(canonical_path, catalog.clone());
+        Ok(catalog)
+    }
+
+    fn read(&mut self, input: &str, base: &Path) -> Result<Catalog, Error> {
+        // The DTD of a catalog is not needed to read it, so it is never loaded
+        let (_, document) = Document::parse(input, &Config::default())?;
+        let context = EntryContext {
+            base: base.to_path_buf(),
+            prefer_public: true,
+        };
+        let mut entries = vec![];
+        self.read_entries(&document, &context, &mut entries)?;
+        Ok(Catalog { entries })
+    }
+
+    fn read_entries(
+        &mut self,
+        document: &Document,
+        context: &EntryContext,
+        entries: &mut Vec<CatalogEntry>,
+    ) -> Result<(), Error> {
+        match document {
+            Document::Nested(documents) => {
+                for document in documents {
+                    self.read_entries(document, context, entries)?;
+                }
+            }
+            Document::Element(tag, content, _) => {
+                let context = context.enter(tag);
+                match tag.name.local_part.as_str() {
+                    "catalog" | "group" => self.read_entries(content, &context, entries)?,
+                    _ => entries.extend(self.read_entry(tag, &context)?),
+                }
+            }
+            Document::EmptyTag(tag) => {
+                let context = context.enter(tag);
+                entries.extend(self.read_entry(tag, &context)?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Entries that are unknown or miss an attribute are ignored, as the specification requires
+    fn read_entry(
+        &mut self,
+        tag: &Tag,
+        context: &EntryContext,
+    ) -> Result<Option<CatalogEntry>, Error> {
+        let get = |name| attribute(tag, None, name);
+        let uri = || get("uri").map(|uri| context.resolve_uri(uri));
+        let entry = match tag.name.local_part.as_str() {
+            "public" => get("publicId")
+                .zip(uri())
+                .map(|(public_id, uri)| CatalogEntry::Public {
+                    public_id: normalize_public_id(public_id),
+                    uri,
+                    prefer_public: context.prefer_public,
+                }),
+            "system" => get("systemId")
+                .zip(uri())
+                .map(|(system_id, uri)| CatalogEntry::System {
+                    system_id: system_id.to_string(),
+                    uri,
+                }),
+            "rewriteSystem" => {
+                get("systemIdStartString")
+                    .zip(get("rewritePrefix"))
+                    .map(|(start, prefix)| CatalogEntry::RewriteSystem {
+                        start: start.to_string(),
+                        prefix: context.resolve_uri(prefix).display().to_string(),
+                    })
+            }
+            "systemSuffix" => {
+                get("systemIdSuffix")
+                    .zip(uri())
+                    .map(|(suffix, uri)| CatalogEntry::SystemSuffix {
+                        suffix: suffix.to_string(),
+                        uri,
+                    })
+            }
+            "delegatePublic" => match get("publicIdStartString").zip(get("catalog")) {
+                Some((start, catalog)) => Some(CatalogEntry::DelegatePublic {
+                    start: normalize_public_id(start),
+                    catalog: self.load(&context.resolve_uri(catalog))?,
+                    prefer_public: context.prefer_public,
+                }),
+                None => None,
+            },
+            "delegateSystem" => match get("systemIdStartString").zip(get("catalog")) {
+                Some((start, catalog)) => Some(CatalogEntry::DelegateSystem {
+                    start: start.to_string(),
+                    catalog: self.load(&context.resolve_uri(catalog))?,
+                }),
+                None => None,
+            },
+            "nextCatalog" => match get("catalog") {
+                Some(catalog) => Some(CatalogEntry::NextCatalog(
+                    self.load(&context.resolve_uri(catalog))?,
+                )),
+                None => None,
+            },
+            _ => None,
+        };
+        Ok(entry)
+    }
+}
+
+impl EntryContext {
+    fn enter(&self, tag: &Tag) -> Self {
+        let mut context = self.clone();
+        if let Some(base) = attribute(tag, Some("xml"), "base") {
+            context.base = self.resolve_uri(base);
+        }
+        if matches!(tag.name.local_part.as_str(), "catalog" | "group") {
+            match attribute(tag, None, "prefer") {
+                Some("public") => context.prefer_public = true,
+                Some("system") => context.prefer_public = false,
+                _ => {}
+            }
+        }
+        context
+    }
+
+    fn resolve_uri(&self, uri: &str) -> PathBuf {
+        let path = uri
+            .strip_prefix("file://")
+            .or_else(|| uri.strip_prefix("file:"))
+            .unwrap_or(uri);
+        self.base.join(path)
+    }
+}
+
+fn attribute<'a>(tag: &'a Tag, prefix: Option<&str>, local_part: &str) -> Option<&'a str> {
+    tag.attributes
+        .iter()
+        .flatten()
+        .find_map(|attribute| match attribute {
+            Attribute::Instance {
+                name,
+                value: AttributeValue::Value(value),
+            } if name.prefix.as_deref() == prefix && name.local_part == local_part => {
+                Some(value.as_str())
+            }
+            _ => None,
+        })
+}
+
+// Public identifiers are compared with their whitespace normalized, like a PubidLiteral
+fn normalize_public_id(public_id: &str) -> String {
+    public_id.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Maps identifiers to the files of a [`Catalog`], and passes the identifiers it has no entry for to the fallback
+///
+/// The fallback is a [`DenyAllResolver`], which leaves the entities that are not in the catalog unexpanded, unless
+/// another one is set with [`CatalogResolver::with_fallback`].
+#[derive(Clone)]
+pub struct CatalogResolver {
+    catalog: Catalog,
+    fallback: Arc<dyn EntityResolver>,
+}
+
+impl CatalogResolver {
+    pub fn new(catalog: Catalog) -> Self {
+        Self {
+            catalog,
+            fallback: Arc::new(DenyAllResolver),
+        }
+    }
+
+    pub fn with_fallback(mut self, fallback: impl EntityResolver + 'static) -> Self {
+        self.fallback = Arc::new(fallback);
+        self
+    }
+}
+
+impl EntityResolver for CatalogResolver {
+    fn resolve(
+        &self,
+        public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+    ) -> Result<ResolvedEntity, Error> {
+        match self.catalog.resolve_external_id(public_id, Some(system_id)) {
+            Some(path) => Ok(ResolvedEntity::File(path)),
+            None => self.fallback.resolve(public_id, system_id, base_uri),
+        }
+    }
+}
@@ -1,74 +1,248 @@
-use crate::warnln;
+use crate::{
+    error::Error,
+    resolver::{EntityResolver, FileSystemResolver, ResolvedEntity},
+};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use std::io::Write;
 /// Config is currently for setting up the parser to allow or prevent external entity parsing.
 /// It's important for preventing some security risks, but it's has not been thoroughly tested for all XML attacks.
-/// It prevents parsing of external entities by default, warns the user if it's enabled, and asks the user to confirm proceeding with external parsing in the terminal.
+/// It prevents parsing of external entities by default, see [`ExternalEntityPolicy`] for allowing them.
+/// Entity references are expanded within the [`EntityExpansionLimits`].
 
 #[derive(Clone, Default, Debug)]
 pub struct Config {
     pub external_parse_config: ExternalEntityParseConfig,
+    pub entity_limits: EntityExpansionLimits,
+    /// Checks the parsed document against its DTD, failing with [`Error::Validation`]
+    ///
+    /// See [`Document::validate`](crate::Document::validate) for what is checked.
+    pub validate: bool,
+    /// Resolves the namespaces of the parsed document, failing with the namespace errors of
+    /// [`Document::resolve_namespaces`](crate::Document::resolve_namespaces)
+    pub resolve_namespaces: bool,
 
     #[cfg(feature = "experimental")]
     pub targeted_parsing: Option<TargetedParsingConfig>,
 }
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default)]
 pub struct ExternalEntityParseConfig {
-    pub allow_ext_parse: bool,
-    pub ignore_ext_parse_warning: bool,
+    pub policy: ExternalEntityPolicy,
+    /// The directory that relative system identifiers are resolved against
     pub base_directory: Option<String>,
+    /// Maps the identifiers of external entities to their content, see [`resolver`](crate::resolver) for the ones
+    /// provided
+    ///
+    /// Without a resolver, system identifiers are read as file paths like [`FileSystemResolver`] does. The policy
+    /// applies to the files that the resolver returns.
+    pub resolver: Option<Arc<dyn EntityResolver>>,
 }
 
-#[cfg(feature = "experimental")]
-#[derive(Clone, Debug)]
-pub struct TargetedParsingConfig {
-    pub tag_name: String,
-    pub parse_multiple: bool, // True to parse multiple elements, false for a single element
+/// Decides which external entities are read from the file system
+///
+/// Allowing external entities might expose the system to an XML External Entity (XXE) attack.
+/// This crate makes no guarantees for security in this regard so make sure you trust your sources.
+#[derive(Clone, Default)]
+pub enum ExternalEntityPolicy {
+    /// External entities are not read and their references are left unexpanded
+    #[default]
+    Deny,
+    /// Only files inside one of these directories are read
+    ///
+    /// Reading any other file fails with [`Error::ExternalEntityNotAllowed`].
+    AllowDirectories(Vec<PathBuf>),
+    /// Every file is read
+    AllowAll,
+    /// The callback is given the system identifier and the path it resolves to, and returns whether the file may be read
+    ///
+    /// Refused files fail with [`Error::ExternalEntityNotAllowed`].
+    Callback(ExternalEntityCallback),
+}
+
+pub type ExternalEntityCallback = Arc<dyn Fn(&str, &Path) -> bool + Send + Sync>;
+
+impl fmt::Debug for ExternalEntityPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalEntityPolicy::Deny => write!(f, "Deny"),
+            ExternalEntityPolicy::AllowDirectories(directories) => f
+                .debug_tuple("AllowDirectories")
+                .field(directories)
+                .finish(),
+            ExternalEntityPolicy::AllowAll => write!(f, "AllowAll"),
+            ExternalEntityPolicy::Callback(_) => write!(f, "Callback(..)"),
+        }
+    }
 }
 
-pub(crate) fn check_config(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
-    match config {
-        Config {
-            external_parse_config:
-                ExternalEntityParseConfig {
-                    allow_ext_parse: true,
-                    ignore_ext_parse_warning: false,
-                    ..
-                },
-        } => {
-            warnln!("The configuration `{:?}` allows external entity parsing which might expose the system to an XML External Entity (XXE) attack.\nThis crate makes no guarantees for security in this regard so make sure you trust your sources.\nVerification of all `.ent` files is strongly recommended.", config);
-
-            loop {
-                print!("Do you wish to proceed? [y/n]: ");
-                std::io::stdout().flush().unwrap();
-
-                let mut decision = String::new();
-                std::io::stdin().read_line(&mut decision).unwrap();
-
-                match decision.trim().to_lowercase().as_str() {
-                    "y" | "Y" | "yes" => break,
-                    "n" | "N" | "no" => {
-                        return Err(nom::Err::Error(
-                            "User decided to stop due to potential XXE attack",
-                        )
-                        .into());
-                    }
-                    _ => eprintln!("Invalid input. Please type 'y' or 'n'"),
-                }
+impl fmt::Debug for ExternalEntityParseConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalEntityParseConfig")
+            .field("policy", &self.policy)
+            .field("base_directory", &self.base_directory)
+            .field("resolver", &self.resolver.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl ExternalEntityParseConfig {
+    /// Reads the content of an external entity, returning where it was read from for error messages and its bytes
+    ///
+    /// Fails with [`Error::ExternalEntityDisabled`] when the entity is not read, these entities are skipped,
+    /// and with [`Error::ExternalEntityNotAllowed`] when the policy refuses the file, which stops parsing.
+    pub(crate) fn load(
+        &self,
+        public_identifier: Option<&str>,
+        system_identifier: &str,
+    ) -> Result<(String, Vec<u8>), Error> {
+        if let ExternalEntityPolicy::Deny = self.policy {
+            return Err(Error::ExternalEntityDisabled {
+                system_id: system_identifier.to_string(),
+            });
+        }
+        // Where the entity is reported to be read from when it is not a file
+        let location = match &self.base_directory {
+            Some(base) => Path::new(base).join(system_identifier),
+            None => PathBuf::from(system_identifier),
+        };
+        let resolver = self
+            .resolver
+            .as_deref()
+            .unwrap_or(&FileSystemResolver as &dyn EntityResolver);
+        let resolved = resolver.resolve(
+            public_identifier,
+            system_identifier,
+            self.base_directory.as_deref(),
+        );
+        let path = match resolved {
+            Ok(ResolvedEntity::File(path)) => path,
+            // Content supplied by the resolver itself is not a file for the policy to check
+            Ok(ResolvedEntity::Content(bytes)) => return Ok((location.display().to_string(), bytes)),
+            Err(
+                e @ (Error::ExternalEntityDisabled { .. }
+                | Error::ExternalEntityNotAllowed { .. }
+                | Error::EntityFile { .. }),
+            ) => return Err(e),
+            Err(e) => {
+                return Err(Error::EntityFile {
+                    path: location.display().to_string(),
+                    source: Box::new(e),
+                })
             }
+        };
+        let entity_file_error = |source: std::io::Error| Error::EntityFile {
+            path: path.display().to_string(),
+            source: Box::new(source.into()),
+        };
+        let allowed_path = match &self.policy {
+            ExternalEntityPolicy::Deny => None,
+            ExternalEntityPolicy::AllowAll => Some(path.clone()),
+            ExternalEntityPolicy::AllowDirectories(directories) => {
+                // Comparing canonical paths keeps `..` components and symbolic links from escaping the directories
+                let canonical_path = path.canonicalize().map_err(entity_file_error)?;
+                // The checked path is read rather than `path`, whose links could be changed after the check
+                directories
+                    .iter()
+                    .any(|directory| {
+                        directory
+                            .canonicalize()
+                            .is_ok_and(|directory| canonical_path.starts_with(directory))
+                    })
+                    .then_some(canonical_path)
+            }
+            ExternalEntityPolicy::Callback(callback) => {
+                callback(system_identifier, &path).then(|| path.clone())
+            }
+        };
+        let Some(allowed_path) = allowed_path else {
+            return Err(Error::ExternalEntityNotAllowed {
+                system_id: system_identifier.to_string(),
+                path,
+            });
+        };
+        let bytes = fs::read(allowed_path).map_err(entity_file_error)?;
+        Ok((path.display().to_string(), bytes))
+    }
+}
+
+/// Caps on the expansion of entity references, which keep small documents from expanding into huge ones,
+/// e.g. the "billion laughs" attack
+///
+/// Exceeding a limit fails parsing with [`Error::EntityExpansionLimitExceeded`], the counts are kept per document.
+/// Entities that reference themselves fail with [`Error::RecursiveEntity`] regardless of the limits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntityExpansionLimits {
+    /// The number of entity references that may be nested inside the replacement text of another
+    pub max_depth: usize,
+    /// The total length in bytes of the replacement text of all the entity references in a document
+    pub max_expanded_size: usize,
+    /// The number of entity references that are expanded in a document, including the nested ones
+    pub max_expansions: usize,
+}
+
+impl Default for EntityExpansionLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 32,
+            max_expanded_size: 10 * 1024 * 1024,
+            max_expansions: 100_000,
         }
-        Config {
-            external_parse_config:
-                ExternalEntityParseConfig {
-                    allow_ext_parse: false,
-                    ignore_ext_parse_warning: true,
-                    ..
-                },
-        } => {
-            warnln!("The configuration `{:?}` may allow for unexpected parsing if `allow_ext_parse` is changed to true in the future", config);
+    }
+}
+
+/// FormatConfig controls the layout produced by [`Document::to_string_pretty`](crate::Document::to_string_pretty).
+///
+/// Elements whose content mixes text with markup, and elements in the scope of `xml:space="preserve"`, are always written as-is so their text is not altered.
+#[derive(Clone, Debug)]
+pub struct FormatConfig {
+    /// The string written once per nesting level
+    pub indent: String,
+    /// Elements that fit within this many characters, including indentation, are kept on a single line
+    pub line_width: usize,
+    /// Write elements without content as empty-element tags, i.e. `<a></a>` as `<a/>`
+    pub collapse_empty_elements: bool,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self {
+            indent: "    ".to_string(),
+            line_width: 80,
+            collapse_empty_elements: false,
         }
-        _ => (),
     }
-    Ok(())
+}
+
+/// CanonicalConfig selects the canonical form produced by [`Document::to_canonical_string`](crate::Document::to_canonical_string).
+#[derive(Clone, Debug, Default)]
+pub struct CanonicalConfig {
+    pub method: CanonicalMethod,
+    /// Keep comments in the output, i.e. the `#WithComments` variants of the C14N methods.
+    /// Comments are never part of the [`CanonicalMethod::XmlTest`] form.
+    pub with_comments: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CanonicalMethod {
+    /// [Canonical XML 1.0](https://www.w3.org/TR/xml-c14n)
+    #[default]
+    C14n,
+    /// [Exclusive XML Canonicalization 1.0](https://www.w3.org/TR/xml-exc-c14n/)
+    ///
+    /// Namespace declarations are only written where they are visibly utilized.
+    /// `inclusive_prefixes` is the InclusiveNamespaces PrefixList, with `#default` standing for the default namespace.
+    ExclusiveC14n { inclusive_prefixes: Vec<String> },
+    /// The canonical form described in `tests/xmltest/canonxml.html`, which is used by the `out/` files of the W3C test suite
+    XmlTest,
+}
+
+#[cfg(feature = "experimental")]
+#[derive(Clone, Debug)]
+pub struct TargetedParsingConfig {
+    pub tag_name: String,
+    pub parse_multiple: bool, // True to parse multiple elements, false for a single element
 }
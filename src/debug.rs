@@ -30,6 +30,15 @@ fn fmt_indented(f: &mut String, indent: usize, s: &str) {
     f.push_str(&" ".repeat(indent));
     f.push_str(s);
 }
+impl fmt::Debug for ProcessingInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcessingInstruction")
+            .field("target", &self.target)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
 impl fmt::Debug for Tag {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut s = String::new();
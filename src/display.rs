@@ -0,0 +1,800 @@
+// display.rs
+use crate::{
+    attribute::{AttType, Attribute, AttributeValue, DefaultDecl, Prefix},
+    config::FormatConfig,
+    misc::{Misc, MiscState},
+    namespaces::ExpandedName,
+    processing_instruction::ProcessingInstruction,
+    prolog::{
+        content_particle::ContentParticle,
+        declaration_content::{DeclarationContent, Mixed},
+        doctype::DocType,
+        external_id::ExternalID,
+        id::ID,
+        subset::{
+            entity::{
+                entity_declaration::{EntityDecl, EntityDeclaration},
+                entity_definition::EntityDefinition,
+                entity_value::EntityValue,
+            },
+            markup_declaration::MarkupDeclaration,
+            Subset,
+        },
+        xmldecl::{Standalone, XmlDecl},
+    },
+    reference::Reference,
+    tag::TagState,
+    transcode::Encode,
+    ConditionalState, Document, Name, Tag,
+};
+use std::fmt::{self, Formatter};
+
+/// Writes a literal using double quotes unless the literal itself contains them
+fn write_quoted(f: &mut String, literal: &str) {
+    if literal.contains('"') && !literal.contains('\'') {
+        f.push('\'');
+        f.push_str(literal);
+        f.push('\'');
+    } else {
+        f.push('"');
+        f.push_str(&literal.replace('"', "&#x22;"));
+        f.push('"');
+    }
+}
+
+/// Joins the names with the separator used by choice, seq, Mixed and Enumeration productions
+fn write_joined<T, F>(f: &mut String, items: &[T], separator: &str, mut write_item: F)
+where
+    F: FnMut(&mut String, &T),
+{
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.push_str(separator);
+        }
+        write_item(f, item);
+    }
+}
+
+impl fmt::Display for Document {
+    /// Writes the `Document` as XML
+    ///
+    /// ```rust
+    /// use nom_xml::{parse::Parse, config::Config, Document};
+    ///
+    /// let xml = "<root><child attr=\"a &amp; b\">Content</child></root>";
+    /// let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+    /// assert_eq!(doc.to_string(), xml);
+    /// ```
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        self.write_xml(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+impl Document {
+    pub(crate) fn write_xml(&self, f: &mut String) {
+        match self {
+            Document::Prolog {
+                xml_decl,
+                misc,
+                doc_type,
+            } => {
+                if let Some(xml_decl) = xml_decl {
+                    xml_decl.write_xml(f);
+                    f.push('\n');
+                }
+                if let Some(misc_vec) = misc {
+                    for misc in misc_vec
+                        .iter()
+                        .filter(|misc| misc.state == MiscState::BeforeDoctype)
+                    {
+                        misc.write_xml(f);
+                    }
+                }
+                if let Some(doc_type) = doc_type {
+                    doc_type.write_xml(f);
+                    f.push('\n');
+                }
+                if let Some(misc_vec) = misc {
+                    for misc in misc_vec
+                        .iter()
+                        .filter(|misc| misc.state == MiscState::AfterDoctype)
+                    {
+                        misc.write_xml(f);
+                    }
+                }
+            }
+            Document::Element(start_tag, content, end_tag) => {
+                start_tag.write_xml(f);
+                content.write_xml(f);
+                end_tag.write_xml(f);
+            }
+            // The parser treats leading whitespace in content as insignificant, so whitespace-only
+            // content can only have come from character references and is written back as such
+            Document::Content(Some(content))
+                if content
+                    .chars()
+                    .all(|c| matches!(c, ' ' | '\t' | '\r' | '\n')) =>
+            {
+                for ch in content.chars() {
+                    f.push_str(&content.encode_hex(ch));
+                }
+            }
+            Document::Content(Some(content)) => f.push_str(&content.encode_char_data()),
+            Document::Content(None) | Document::Empty => {}
+            Document::Nested(documents) => {
+                for document in documents {
+                    document.write_xml(f);
+                }
+            }
+            Document::EmptyTag(tag) => tag.write_xml(f),
+            Document::ProcessingInstruction(processing_instruction) => {
+                processing_instruction.write_xml(f)
+            }
+            Document::Comment(comment) => {
+                f.push_str("<!--");
+                f.push_str(comment);
+                f.push_str("-->");
+            }
+            Document::CDATA(cdata) => {
+                // `]]>` cannot appear inside of a CDATA section, so it is split across two sections
+                f.push_str("<![CDATA[");
+                f.push_str(&cdata.replace("]]>", "]]]]><![CDATA[>"));
+                f.push_str("]]>");
+            }
+        }
+    }
+}
+
+impl Document {
+    /// Writes the `Document` as XML that is re-indented according to the [`FormatConfig`]
+    ///
+    /// ```rust
+    /// use nom_xml::{parse::Parse, config::{Config, FormatConfig}, Document};
+    ///
+    /// let xml = "<root><child>Content</child><empty></empty></root>";
+    /// let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+    /// let format_config = FormatConfig {
+    ///     indent: "  ".to_string(),
+    ///     line_width: 20,
+    ///     collapse_empty_elements: true,
+    /// };
+    /// assert_eq!(
+    ///     doc.to_string_pretty(&format_config),
+    ///     "<root>\n  <child>Content</child>\n  <empty/>\n</root>\n"
+    /// );
+    /// ```
+    pub fn to_string_pretty(&self, config: &FormatConfig) -> String {
+        let mut s = String::new();
+        match self {
+            Document::Nested(documents) => {
+                for document in documents {
+                    document.write_pretty(&mut s, config, 0, false, false);
+                    if !s.ends_with('\n') {
+                        s.push('\n');
+                    }
+                }
+            }
+            document => {
+                document.write_pretty(&mut s, config, 0, false, false);
+                if !s.ends_with('\n') {
+                    s.push('\n');
+                }
+            }
+        }
+        s
+    }
+
+    fn write_pretty(
+        &self,
+        f: &mut String,
+        config: &FormatConfig,
+        depth: usize,
+        preserve_space: bool,
+        single_line: bool,
+    ) {
+        let Document::Element(start_tag, content, end_tag) = self else {
+            return self.write_xml(f);
+        };
+        let preserve_space = start_tag.preserves_space().unwrap_or(preserve_space);
+
+        if config.collapse_empty_elements && content.is_empty_content() {
+            let empty_tag = Tag::new(
+                start_tag.name.clone(),
+                start_tag.attributes.clone(),
+                TagState::Empty,
+            );
+            return empty_tag.write_pretty(f, config, depth, single_line);
+        }
+
+        if preserve_space || content.has_text() {
+            // Adding whitespace would change the text, so the element is written as-is
+            return self.write_xml(f);
+        }
+
+        if !single_line {
+            let mut line = String::new();
+            self.write_pretty(&mut line, config, depth, preserve_space, true);
+            if config.indent.chars().count() * depth + line.chars().count() <= config.line_width {
+                return f.push_str(&line);
+            }
+        }
+
+        start_tag.write_pretty(f, config, depth, single_line);
+        let children = match &**content {
+            Document::Nested(documents) => documents.iter().collect(),
+            Document::Empty => vec![],
+            document => vec![document],
+        };
+        for child in &children {
+            if !single_line {
+                f.push('\n');
+                f.push_str(&config.indent.repeat(depth + 1));
+            }
+            child.write_pretty(f, config, depth + 1, preserve_space, single_line);
+        }
+        if !single_line && !children.is_empty() {
+            f.push('\n');
+            f.push_str(&config.indent.repeat(depth));
+        }
+        end_tag.write_xml(f);
+    }
+
+    fn is_empty_content(&self) -> bool {
+        match self {
+            Document::Empty | Document::Content(None) => true,
+            Document::Content(Some(content)) => content.is_empty(),
+            Document::Nested(documents) => documents.iter().all(Document::is_empty_content),
+            _ => false,
+        }
+    }
+
+    /// Whether the content of an element contains character data, i.e. text only or mixed content
+    fn has_text(&self) -> bool {
+        match self {
+            Document::Content(Some(content)) => !content.is_empty(),
+            Document::CDATA(_) => true,
+            Document::Nested(documents) => documents.iter().any(Document::has_text),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        self.write_xml(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+impl Tag {
+    pub(crate) fn write_xml(&self, f: &mut String) {
+        match self.state {
+            TagState::End => {
+                f.push_str("</");
+                f.push_str(&self.name.to_string());
+                f.push('>');
+            }
+            TagState::Start | TagState::Empty => {
+                f.push('<');
+                f.push_str(&self.name.to_string());
+                if let Some(attributes) = &self.attributes {
+                    for attribute in attributes {
+                        f.push(' ');
+                        attribute.write_xml(f);
+                    }
+                }
+                if self.state == TagState::Empty {
+                    f.push_str("/>");
+                } else {
+                    f.push('>');
+                }
+            }
+        }
+    }
+}
+
+impl Tag {
+    /// The value of `xml:space` on this tag, if it is set to one of the values defined in XML 1.0 §2.10
+    fn preserves_space(&self) -> Option<bool> {
+        self.attributes
+            .iter()
+            .flatten()
+            .find_map(|attribute| match attribute {
+                Attribute::Instance {
+                    name,
+                    value: AttributeValue::Value(value),
+                } if name.prefix.as_deref() == Some("xml") && name.local_part == "space" => {
+                    match value.as_str() {
+                        "preserve" => Some(true),
+                        "default" => Some(false),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+    }
+
+    fn write_pretty(&self, f: &mut String, config: &FormatConfig, depth: usize, single_line: bool) {
+        let mut line = String::new();
+        self.write_xml(&mut line);
+        let attributes = self.attributes.as_deref().unwrap_or_default();
+        if single_line
+            || attributes.len() < 2
+            || config.indent.chars().count() * depth + line.chars().count() <= config.line_width
+        {
+            return f.push_str(&line);
+        }
+
+        // The tag is too long, so each attribute is written on its own line
+        f.push('<');
+        f.push_str(&self.name.to_string());
+        for attribute in attributes {
+            f.push('\n');
+            f.push_str(&config.indent.repeat(depth + 1));
+            attribute.write_xml(f);
+        }
+        f.push_str(if self.state == TagState::Empty {
+            "/>"
+        } else {
+            ">"
+        });
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.prefix {
+            Some(prefix) => write!(f, "{}:{}", prefix, self.local_part),
+            None => write!(f, "{}", self.local_part),
+        }
+    }
+}
+
+/// Written in Clark notation, `{namespace}local_part`
+impl fmt::Display for ExpandedName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{{{namespace}}}{}", self.local_part),
+            None => write!(f, "{}", self.local_part),
+        }
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        self.write_xml(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+impl Attribute {
+    pub(crate) fn write_xml(&self, f: &mut String) {
+        match self {
+            Attribute::Instance { name, value } => {
+                f.push_str(&name.to_string());
+                f.push_str("=\"");
+                value.write_xml(f);
+                f.push('"');
+            }
+            Attribute::Namespace { prefix, uri } => {
+                match prefix {
+                    Prefix::Default => f.push_str("xmlns"),
+                    Prefix::Prefix(prefix) => {
+                        f.push_str("xmlns:");
+                        f.push_str(prefix);
+                    }
+                }
+                f.push_str("=\"");
+                uri.write_xml(f);
+                f.push('"');
+            }
+            // [53] AttDef ::= S Name S AttType S DefaultDecl
+            Attribute::Definition {
+                name,
+                att_type,
+                default_decl,
+                ..
+            } => {
+                f.push_str(&name.to_string());
+                f.push(' ');
+                att_type.write_xml(f);
+                f.push(' ');
+                default_decl.write_xml(f);
+            }
+            Attribute::Reference(reference) => reference.write_xml(f),
+            Attribute::Required => f.push_str("#REQUIRED"),
+            Attribute::Implied => f.push_str("#IMPLIED"),
+        }
+    }
+}
+
+impl AttributeValue {
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            AttributeValue::Value(value) => f.push_str(&value.encode_attribute_value()),
+            AttributeValue::Values(values) => {
+                for value in values {
+                    value.write_xml(f);
+                }
+            }
+            AttributeValue::Reference(reference) => reference.write_xml(f),
+            AttributeValue::EmptyExternalReference => {}
+        }
+    }
+}
+
+impl fmt::Display for AttType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        self.write_xml(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+impl AttType {
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            AttType::CDATA => f.push_str("CDATA"),
+            AttType::Tokenized(tokenized_type) => f.push_str(&format!("{:?}", tokenized_type)),
+            AttType::Enumerated {
+                notation,
+                enumeration,
+            } => {
+                if let Some(notation) = notation {
+                    f.push_str("NOTATION (");
+                    write_joined(f, notation, " | ", |f, name| f.push_str(&name.to_string()));
+                    f.push(')');
+                } else if let Some(enumeration) = enumeration {
+                    f.push('(');
+                    write_joined(f, enumeration, " | ", |f, token| f.push_str(token));
+                    f.push(')');
+                }
+            }
+        }
+    }
+}
+
+impl DefaultDecl {
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            DefaultDecl::Required => f.push_str("#REQUIRED"),
+            DefaultDecl::Implied => f.push_str("#IMPLIED"),
+            DefaultDecl::Fixed(value) => {
+                f.push_str("#FIXED \"");
+                f.push_str(&value.encode_attribute_value());
+                f.push('"');
+            }
+            DefaultDecl::Value(value) => {
+                f.push('"');
+                f.push_str(&value.encode_attribute_value());
+                f.push('"');
+            }
+        }
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        self.write_xml(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+impl Reference {
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            Reference::EntityRef(name) => {
+                f.push('&');
+                f.push_str(&name.to_string());
+                f.push(';');
+            }
+            // Character references are stored decoded, so they are encoded again on the way out
+            Reference::CharRef(value) => {
+                for ch in value.chars() {
+                    f.push_str(&value.encode_hex(ch));
+                }
+            }
+        }
+    }
+
+    fn write_parameter_reference(&self, f: &mut String) {
+        match self {
+            Reference::EntityRef(name) => {
+                f.push('%');
+                f.push_str(&name.to_string());
+                f.push(';');
+            }
+            Reference::CharRef(_) => self.write_xml(f),
+        }
+    }
+}
+
+impl ProcessingInstruction {
+    fn write_xml(&self, f: &mut String) {
+        f.push_str("<?");
+        f.push_str(&self.target.to_string());
+        if let Some(data) = &self.data {
+            f.push(' ');
+            f.push_str(data);
+        }
+        f.push_str("?>");
+    }
+}
+
+impl Misc {
+    fn write_xml(&self, f: &mut String) {
+        match &*self.content {
+            Document::Nested(documents) => {
+                for document in documents {
+                    document.write_xml(f);
+                    f.push('\n');
+                }
+            }
+            document => {
+                document.write_xml(f);
+                f.push('\n');
+            }
+        }
+    }
+}
+
+impl XmlDecl {
+    fn write_xml(&self, f: &mut String) {
+        f.push_str("<?xml version=\"");
+        f.push_str(&self.version);
+        f.push('"');
+        if let Some(encoding) = &self.encoding {
+            f.push_str(" encoding=\"");
+            f.push_str(encoding);
+            f.push('"');
+        }
+        if let Some(standalone) = &self.standalone {
+            f.push_str(match standalone {
+                Standalone::Yes => " standalone=\"yes\"",
+                Standalone::No => " standalone=\"no\"",
+            });
+        }
+        f.push_str("?>");
+    }
+}
+
+impl DocType {
+    // [28] doctypedecl ::= '<!DOCTYPE' S Name (S ExternalID)? S? ('[' intSubset ']' S?)? '>'
+    fn write_xml(&self, f: &mut String) {
+        f.push_str("<!DOCTYPE ");
+        f.push_str(&self.name.to_string());
+        if let Some(external_id) = &self.external_id {
+            f.push(' ');
+            external_id.write_xml(f);
+        }
+        if let Some(subset) = &self.subset {
+            let mut declarations = String::new();
+            for subset in subset {
+                subset.write_xml(&mut declarations);
+            }
+            if !declarations.is_empty() {
+                f.push_str(" [\n");
+                f.push_str(&declarations);
+                f.push(']');
+            }
+        }
+        f.push('>');
+    }
+}
+
+impl ExternalID {
+    // [75] ExternalID ::= 'SYSTEM' S SystemLiteral | 'PUBLIC' S PubidLiteral S SystemLiteral
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            ExternalID::System(system_literal) => {
+                f.push_str("SYSTEM ");
+                write_quoted(f, system_literal);
+            }
+            ExternalID::Public {
+                pubid,
+                system_identifier,
+            } => {
+                f.push_str("PUBLIC ");
+                write_quoted(f, pubid);
+                if let ExternalID::System(system_literal) = &**system_identifier {
+                    f.push(' ');
+                    write_quoted(f, system_literal);
+                }
+            }
+        }
+    }
+}
+
+impl ID {
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            ID::ExternalID(external_id) => external_id.write_xml(f),
+            // [83] PublicID ::= 'PUBLIC' S PubidLiteral
+            ID::PublicID(pubid) => {
+                f.push_str("PUBLIC ");
+                write_quoted(f, pubid);
+            }
+        }
+    }
+}
+
+impl Subset {
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            Subset::MarkupDecl(markup_declaration) => {
+                markup_declaration.write_xml(f);
+                f.push('\n');
+            }
+            Subset::DeclSep { reference, .. } => {
+                reference.write_parameter_reference(f);
+                f.push('\n');
+            }
+            Subset::None => {}
+        }
+    }
+}
+
+impl fmt::Display for MarkupDeclaration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        self.write_xml(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+impl MarkupDeclaration {
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            // [45] elementdecl ::= '<!ELEMENT' S Name S contentspec S? '>'
+            MarkupDeclaration::Element { name, content_spec } => {
+                f.push_str("<!ELEMENT ");
+                f.push_str(&name.to_string());
+                if let Some(content_spec) = content_spec {
+                    f.push(' ');
+                    content_spec.write_xml(f);
+                }
+                f.push('>');
+            }
+            // [52] AttlistDecl ::= '<!ATTLIST' S Name AttDef* S? '>'
+            MarkupDeclaration::AttList { name, att_defs } => {
+                f.push_str("<!ATTLIST ");
+                f.push_str(&name.to_string());
+                for att_def in att_defs.iter().flatten() {
+                    f.push_str("\n    ");
+                    att_def.write_xml(f);
+                }
+                f.push('>');
+            }
+            MarkupDeclaration::Entity(entity_decl) => entity_decl.write_xml(f),
+            // [82] NotationDecl ::= '<!NOTATION' S Name S (ExternalID | PublicID) S? '>'
+            MarkupDeclaration::Notation { name, id } => {
+                f.push_str("<!NOTATION ");
+                f.push_str(&name.to_string());
+                f.push(' ');
+                id.write_xml(f);
+                f.push('>');
+            }
+            MarkupDeclaration::ProcessingInstruction(processing_instruction) => {
+                processing_instruction.write_xml(f)
+            }
+            MarkupDeclaration::Comment(comment) => comment.write_xml(f),
+        }
+    }
+}
+
+impl fmt::Display for DeclarationContent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        self.write_xml(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+impl DeclarationContent {
+    // [46] contentspec ::= 'EMPTY' | 'ANY' | Mixed | children
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            DeclarationContent::Empty => f.push_str("EMPTY"),
+            DeclarationContent::Any => f.push_str("ANY"),
+            DeclarationContent::Mixed(Mixed::PCDATA) => f.push_str("(#PCDATA)"),
+            DeclarationContent::Mixed(Mixed::Names(names)) => {
+                f.push_str("(#PCDATA");
+                for name in names {
+                    f.push_str(" | ");
+                    f.push_str(&name.to_string());
+                }
+                f.push_str(")*");
+            }
+            DeclarationContent::Children(content_particle) => content_particle.write_xml(f),
+        }
+    }
+}
+
+impl ContentParticle {
+    // [48] cp ::= (Name | choice | seq) ('?' | '*' | '+')?
+    fn write_xml(&self, f: &mut String) {
+        let conditional_state = match self {
+            ContentParticle::Name(name, conditional_state) => {
+                f.push_str(&name.to_string());
+                conditional_state
+            }
+            ContentParticle::Choice(particles, conditional_state) => {
+                f.push('(');
+                write_joined(f, particles, " | ", |f, particle| particle.write_xml(f));
+                f.push(')');
+                conditional_state
+            }
+            ContentParticle::Sequence(particles, conditional_state) => {
+                f.push('(');
+                write_joined(f, particles, ", ", |f, particle| particle.write_xml(f));
+                f.push(')');
+                conditional_state
+            }
+        };
+        f.push_str(match conditional_state {
+            ConditionalState::None => "",
+            ConditionalState::Optional => "?",
+            ConditionalState::ZeroOrMore => "*",
+            ConditionalState::OneOrMore => "+",
+        });
+    }
+}
+
+impl EntityDecl {
+    // [71] GEDecl ::= '<!ENTITY' S Name S EntityDef S? '>'
+    // [72] PEDecl ::= '<!ENTITY' S '%' S Name S PEDef S? '>'
+    fn write_xml(&self, f: &mut String) {
+        let EntityDeclaration { name, entity_def } = match self {
+            EntityDecl::General(declaration) => {
+                f.push_str("<!ENTITY ");
+                declaration
+            }
+            EntityDecl::Parameter(declaration) => {
+                f.push_str("<!ENTITY % ");
+                declaration
+            }
+        };
+        f.push_str(&name.to_string());
+        f.push(' ');
+        entity_def.write_xml(f);
+        f.push('>');
+    }
+}
+
+impl EntityDefinition {
+    // [73] EntityDef ::= EntityValue | (ExternalID NDataDecl?)
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            EntityDefinition::EntityValue(entity_value) => {
+                let mut literal = String::new();
+                entity_value.write_xml(&mut literal);
+                write_quoted(f, &literal);
+            }
+            // The TextDecl belongs to the external entity itself, so it isn't written here
+            EntityDefinition::External { id, n_data, .. } => {
+                id.write_xml(f);
+                if let Some(n_data) = n_data {
+                    f.push_str(" NDATA ");
+                    f.push_str(&n_data.to_string());
+                }
+            }
+        }
+    }
+}
+
+impl EntityValue {
+    fn write_xml(&self, f: &mut String) {
+        match self {
+            EntityValue::Document(document) => document.write_xml(f),
+            EntityValue::Value(value) => f.push_str(value),
+            EntityValue::Reference(reference) => reference.write_xml(f),
+            EntityValue::ParameterReference(reference) => reference.write_parameter_reference(f),
+            EntityValue::MarkupDecl(markup_declaration) => markup_declaration.write_xml(f),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+// edit.rs
+
+//! Editing of elements in place
+//!
+//! The children of an element are the documents in its content as they were parsed: elements, empty-element tags,
+//! text, CDATA sections, comments and processing instructions. The edits keep the representation used by the parser,
+//! an [`Document::EmptyTag`] becomes a [`Document::Element`] when it gets content, and an element without children
+//! has [`Document::Empty`] content, a single child other than an element directly and its other children in a
+//! [`Document::Nested`].
+//!
+//! ```rust
+//! use nom_xml::{config::Config, parse::Parse, Document, Name};
+//!
+//! let (_, mut doc) = Document::parse("<?xml version=\"1.0\"?><catalog><book/></catalog>", &Config::default()).unwrap();
+//! let catalog = doc.root_element_mut().unwrap();
+//! let book = &mut catalog.children_mut()[0];
+//! book.set_attribute(Name::new(None, "isbn"), "978-0316129084").unwrap();
+//! book.set_text("Leviathan Wakes").unwrap();
+//! catalog.rename(Name::new(None, "library")).unwrap();
+//! assert_eq!(
+//!     doc.root_element().unwrap().to_string(),
+//!     "<library><book isbn=\"978-0316129084\">Leviathan Wakes</book></library>"
+//! );
+//! ```
+
+use crate::{
+    attribute::{Attribute, AttributeValue, Prefix},
+    error::Error,
+    parse::Parse,
+    tag::{Tag, TagState},
+    Document, Name,
+};
+use std::mem;
+
+impl Document {
+    /// The root element of a parsed document, or the document itself when it is an element
+    pub fn root_element(&self) -> Option<&Document> {
+        match self {
+            Document::Element(..) | Document::EmptyTag(_) => Some(self),
+            Document::Nested(documents) => documents.iter().find_map(Document::root_element),
+            _ => None,
+        }
+    }
+
+    /// The root element of a parsed document for editing, or the document itself when it is an element
+    pub fn root_element_mut(&mut self) -> Option<&mut Document> {
+        match self {
+            Document::Element(..) | Document::EmptyTag(_) => Some(self),
+            Document::Nested(documents) => {
+                documents.iter_mut().find_map(Document::root_element_mut)
+            }
+            _ => None,
+        }
+    }
+
+    /// The children of an element, empty for empty-element tags and documents that are not elements
+    pub fn children(&self) -> &[Document] {
+        match self {
+            Document::Element(_, content, _) => match content.as_ref() {
+                Document::Nested(children) => children,
+                Document::Empty | Document::Content(None) => &[],
+                child => std::slice::from_ref(child),
+            },
+            _ => &[],
+        }
+    }
+
+    /// The children of an element for editing them in place, see [`Document::children`]
+    pub fn children_mut(&mut self) -> &mut [Document] {
+        match self {
+            Document::Element(_, content, _) => match content.as_mut() {
+                Document::Nested(children) => children,
+                Document::Empty | Document::Content(None) => &mut [],
+                child => std::slice::from_mut(child),
+            },
+            _ => &mut [],
+        }
+    }
+
+    /// Adds `child` after the children of this element, the documents of a [`Document::Nested`] are added one by one
+    pub fn append_child(&mut self, child: Document) -> Result<(), Error> {
+        let children = child_nodes(child)?;
+        self.edit_children("append a child to", |content| {
+            content.extend(children);
+            Ok(())
+        })
+    }
+
+    /// Inserts `child` at `index` in the children of this element, shifting the children after it
+    pub fn insert_child(&mut self, index: usize, child: Document) -> Result<(), Error> {
+        let children = child_nodes(child)?;
+        self.edit_children("insert a child into", |content| {
+            if index > content.len() {
+                return Err(Error::ChildIndexOutOfBounds {
+                    index,
+                    len: content.len(),
+                });
+            }
+            content.splice(index..index, children);
+            Ok(())
+        })
+    }
+
+    /// Removes the child at `index` from this element and returns it
+    ///
+    /// Removing the last child leaves an element with empty content, written as `<name></name>`.
+    pub fn remove_child(&mut self, index: usize) -> Result<Document, Error> {
+        self.edit_children("remove a child of", |content| {
+            check_index(index, content)?;
+            Ok(content.remove(index))
+        })
+    }
+
+    /// Replaces the child at `index` of this element with `child` and returns the replaced child
+    pub fn replace_child(&mut self, index: usize, child: Document) -> Result<Document, Error> {
+        let children = child_nodes(child)?;
+        self.edit_children("replace a child of", |content| {
+            check_index(index, content)?;
+            Ok(content.splice(index..=index, children).next().unwrap())
+        })
+    }
+
+    /// Replaces this document with an element named `name` that contains it
+    pub fn wrap(&mut self, name: Name) -> Result<(), Error> {
+        check_name(&name)?;
+        check_child(self)?;
+        let content = flatten(mem::replace(self, Document::Empty));
+        *self = Document::EmptyTag(Tag::new(name, None, TagState::Empty));
+        self.edit_children("wrap", |children| {
+            *children = content;
+            Ok(())
+        })
+    }
+
+    /// Sets the attribute `name` of this element to `value`, replacing its current value
+    ///
+    /// `xmlns` and `xmlns:prefix` declare namespaces, they are set as [`Attribute::Namespace`].
+    pub fn set_attribute(&mut self, name: Name, value: &str) -> Result<(), Error> {
+        check_name(&name)?;
+        check_characters(value)?;
+        let tag = self.start_tag_mut("set an attribute on")?;
+        let qualified_name = name.to_string();
+        let value = AttributeValue::Value(value.to_string());
+        let attribute = match (name.prefix.as_deref(), name.local_part.as_str()) {
+            (None, "xmlns") => Attribute::Namespace {
+                prefix: Prefix::Default,
+                uri: value,
+            },
+            (Some("xmlns"), prefix) => Attribute::Namespace {
+                prefix: Prefix::Prefix(prefix.to_string()),
+                uri: value,
+            },
+            _ => Attribute::Instance { name, value },
+        };
+        let attributes = tag.attributes.get_or_insert_with(Vec::new);
+        match attributes
+            .iter_mut()
+            .find(|attribute| attribute_name(attribute).as_deref() == Some(&qualified_name))
+        {
+            Some(existing) => *existing = attribute,
+            None => attributes.push(attribute),
+        }
+        Ok(())
+    }
+
+    /// Removes the attribute `name` from this element and returns it, `None` when the element does not have it
+    pub fn remove_attribute(&mut self, name: &Name) -> Result<Option<Attribute>, Error> {
+        let tag = self.start_tag_mut("remove an attribute from")?;
+        let qualified_name = name.to_string();
+        let Some(attributes) = &mut tag.attributes else {
+            return Ok(None);
+        };
+        let removed = attributes
+            .iter()
+            .position(|attribute| attribute_name(attribute).as_deref() == Some(&qualified_name))
+            .map(|index| attributes.remove(index));
+        if attributes.is_empty() {
+            tag.attributes = None;
+        }
+        Ok(removed)
+    }
+
+    /// Replaces the content of this element with `text`, or the text of a [`Document::Content`]
+    ///
+    /// An empty `text` leaves the element without children.
+    pub fn set_text(&mut self, text: &str) -> Result<(), Error> {
+        check_characters(text)?;
+        if let Document::Content(content) = self {
+            *content = Some(text.to_string());
+            return Ok(());
+        }
+        self.edit_children("set the text of", |children| {
+            children.clear();
+            if !text.is_empty() {
+                children.push(Document::Content(Some(text.to_string())));
+            }
+            Ok(())
+        })
+    }
+
+    /// Renames this element, in both its start and end tags
+    pub fn rename(&mut self, name: Name) -> Result<(), Error> {
+        check_name(&name)?;
+        match self {
+            Document::Element(start_tag, _, end_tag) => {
+                start_tag.name = name.clone();
+                end_tag.name = name;
+            }
+            Document::EmptyTag(tag) => tag.name = name,
+            _ => return Err(self.unsupported_edit("rename")),
+        }
+        Ok(())
+    }
+
+    fn start_tag_mut(&mut self, operation: &'static str) -> Result<&mut Tag, Error> {
+        match self {
+            Document::Element(start_tag, ..) => Ok(start_tag),
+            Document::EmptyTag(tag) => Ok(tag),
+            _ => Err(self.unsupported_edit(operation)),
+        }
+    }
+
+    /// Applies `edit` to the children of this element and stores them back as the parser would
+    fn edit_children<T>(
+        &mut self,
+        operation: &'static str,
+        edit: impl FnOnce(&mut Vec<Document>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut children = match self {
+            Document::Element(_, content, _) => {
+                match mem::replace(content.as_mut(), Document::Empty) {
+                    Document::Nested(children) => children,
+                    Document::Empty | Document::Content(None) => vec![],
+                    child => vec![child],
+                }
+            }
+            Document::EmptyTag(_) => vec![],
+            _ => return Err(self.unsupported_edit(operation)),
+        };
+        let result = edit(&mut children);
+        let content = element_content(children);
+        match self {
+            Document::Element(_, old_content, _) => **old_content = content,
+            Document::EmptyTag(_) if !matches!(content, Document::Empty) => {
+                if let Document::EmptyTag(tag) = mem::replace(self, Document::Empty) {
+                    let end_tag = Tag::new(tag.name.clone(), None, TagState::End);
+                    let start_tag = Tag {
+                        state: TagState::Start,
+                        ..tag
+                    };
+                    *self = Document::Element(start_tag, Box::new(content), end_tag);
+                }
+            }
+            _ => {}
+        }
+        result
+    }
+
+    fn unsupported_edit(&self, operation: &'static str) -> Error {
+        Error::UnsupportedEdit {
+            operation,
+            node: self.description(),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Document::Prolog { .. } => "the prolog",
+            Document::Element(..) | Document::EmptyTag(_) => "an element",
+            Document::Content(_) => "text",
+            Document::Nested(_) => "nested documents",
+            Document::Empty => "an empty document",
+            Document::ProcessingInstruction(_) => "a processing instruction",
+            Document::Comment(_) => "a comment",
+            Document::CDATA(_) => "a CDATA section",
+        }
+    }
+}
+
+/// The documents that the content of an element is made of, for [`Document`] and
+/// [`BorrowedDocument`](crate::borrowed::BorrowedDocument)
+pub(crate) trait ElementContent: Sized {
+    fn empty() -> Self;
+    fn nested(children: Vec<Self>) -> Self;
+    fn is_element(&self) -> bool;
+}
+
+impl ElementContent for Document {
+    fn empty() -> Self {
+        Document::Empty
+    }
+
+    fn nested(children: Vec<Self>) -> Self {
+        Document::Nested(children)
+    }
+
+    fn is_element(&self) -> bool {
+        matches!(self, Document::Element(..))
+    }
+}
+
+/// The content of an element with `children`, as the parser represents it
+pub(crate) fn element_content<D: ElementContent>(mut children: Vec<D>) -> D {
+    match children.len() {
+        0 => D::empty(),
+        1 if !children[0].is_element() => children.remove(0),
+        _ => D::nested(children),
+    }
+}
+
+/// The documents that `child` adds to the children of an element
+fn child_nodes(child: Document) -> Result<Vec<Document>, Error> {
+    check_child(&child)?;
+    Ok(flatten(child))
+}
+
+fn check_child(child: &Document) -> Result<(), Error> {
+    match child {
+        Document::Nested(documents) => documents.iter().try_for_each(check_child),
+        Document::Prolog { .. } | Document::Empty | Document::Content(None) => {
+            Err(Error::InvalidChild(child.description()))
+        }
+        Document::Content(Some(text)) | Document::Comment(text) | Document::CDATA(text) => {
+            check_characters(text)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn flatten(document: Document) -> Vec<Document> {
+    match document {
+        Document::Nested(documents) => documents.into_iter().flat_map(flatten).collect(),
+        document => vec![document],
+    }
+}
+
+fn check_index(index: usize, children: &[Document]) -> Result<(), Error> {
+    if index < children.len() {
+        Ok(())
+    } else {
+        Err(Error::ChildIndexOutOfBounds {
+            index,
+            len: children.len(),
+        })
+    }
+}
+
+/// Checks that `name` is a Name, with a prefix and local part that do not contain colons when it is prefixed
+fn check_name(name: &Name) -> Result<(), Error> {
+    let is_name = |part: &str, colons: bool| {
+        let mut chars = part.chars();
+        chars
+            .next()
+            .is_some_and(|c| Document::is_name_start_char(c) && (colons || c != ':'))
+            && chars.all(|c| Document::is_name_char(c) && (colons || c != ':'))
+    };
+    let valid = match &name.prefix {
+        Some(prefix) => is_name(prefix, false) && is_name(&name.local_part, false),
+        None => is_name(&name.local_part, true),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidName(name.clone()))
+    }
+}
+
+fn check_characters(text: &str) -> Result<(), Error> {
+    match text.chars().find(|&c| !Document::is_char(c)) {
+        Some(c) => Err(Error::IllegalCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+/// The qualified name of an attribute as written in its tag
+fn attribute_name(attribute: &Attribute) -> Option<String> {
+    match attribute {
+        Attribute::Instance { name, .. } => Some(name.to_string()),
+        Attribute::Namespace {
+            prefix: Prefix::Default,
+            ..
+        } => Some("xmlns".to_string()),
+        Attribute::Namespace {
+            prefix: Prefix::Prefix(prefix),
+            ..
+        } => Some(format!("xmlns:{prefix}")),
+        _ => None,
+    }
+}
@@ -1,4 +1,14 @@
-use std::fmt::{self, Debug, Display};
+use crate::{
+    attribute::AttType,
+    namespaces::ExpandedName,
+    prolog::{declaration_content::DeclarationContent, external_id::ExternalID},
+    Name,
+};
+use nom::{error::ErrorKind, InputLength};
+use std::{
+    fmt::{self, Debug, Display},
+    path::PathBuf,
+};
 
 #[macro_export]
 macro_rules! warnln {
@@ -13,6 +23,346 @@ pub enum Error {
     NomErrorFast(nom::error::ErrorKind),
     IoError(std::io::Error),
     UserAbort(String),
+    ParseError(ParseError),
+    /// A reference to an entity that has not been declared
+    UndeclaredEntity(Name),
+    /// An end tag whose name differs from the start tag of the element it closes
+    MismatchedEndTag {
+        start: Name,
+        end: Name,
+    },
+    /// An attribute that is specified more than once in the same tag
+    DuplicateAttribute {
+        tag: Name,
+        attribute: Name,
+    },
+    /// A character that is not allowed anywhere in an XML document by the [2] Char production
+    IllegalCharacter(char),
+    /// A character reference, such as `&#0;`, to a code point that is not allowed by the [2] Char production
+    IllegalCharacterReference(String),
+    /// A reference to an unparsed entity, which may only be named in attributes of type `ENTITY` or `ENTITIES`
+    UnparsedEntityReference(Name),
+    /// A reference to an external entity in an attribute value
+    ExternalEntityInAttributeValue(Name),
+    /// A reference to an entity whose replacement text is not well-formed where it is referenced, e.g. an entity
+    /// with an unclosed start tag in content or with `<` in an attribute value
+    MalformedEntity(Name),
+    /// A prefixed element or attribute name whose prefix is not bound to a namespace
+    UndeclaredNamespacePrefix(Name),
+    /// A namespace declaration that binds `xml` to another URI than [`XML_NAMESPACE`](crate::namespaces::XML_NAMESPACE),
+    /// declares `xmlns`, or binds one of their URIs to another prefix
+    ReservedNamespaceBinding {
+        prefix: Option<String>,
+        uri: String,
+    },
+    /// A namespace declaration such as `xmlns:p=""`, only the default namespace may be undeclared in XML 1.0
+    EmptyNamespaceUri {
+        prefix: String,
+    },
+    /// Two attributes of the same tag that have the same expanded name, e.g. because their prefixes are bound to the
+    /// same URI
+    DuplicateNamespacedAttribute {
+        tag: Name,
+        attribute: ExpandedName,
+    },
+    /// An external entity that is not parsed because the [`ExternalEntityPolicy`](crate::config::ExternalEntityPolicy) is `Deny`,
+    /// or because its [`EntityResolver`](crate::resolver::EntityResolver) does not supply it
+    ExternalEntityDisabled {
+        system_id: String,
+    },
+    /// An external entity whose file is refused by the [`ExternalEntityPolicy`](crate::config::ExternalEntityPolicy)
+    ExternalEntityNotAllowed {
+        system_id: String,
+        path: PathBuf,
+    },
+    /// An external identifier that cannot be resolved to a file
+    UnsupportedExternalId(ExternalID),
+    /// The file of an external entity could not be read or parsed, `source` is the [`Error::IoError`] or the
+    /// [`Error::ParseError`] located in the file
+    EntityFile {
+        path: String,
+        source: Box<Error>,
+    },
+    /// A catalog file could not be read or parsed, `source` is the [`Error::IoError`] or the [`Error::ParseError`]
+    /// located in the file
+    CatalogFile {
+        path: String,
+        source: Box<Error>,
+    },
+    /// An entity whose replacement text references itself, `chain` is the sequence of references from the
+    /// outermost one back to the entity being expanded
+    RecursiveEntity {
+        chain: Vec<Name>,
+    },
+    /// Expanding a reference to `entity` went over one of the [`EntityExpansionLimits`](crate::config::EntityExpansionLimits)
+    EntityExpansionLimitExceeded {
+        entity: Name,
+        limit: ExpansionLimit,
+    },
+    /// The document does not conform to its DTD, see [`Document::validate`](crate::Document::validate)
+    Validation(Vec<ValidationError>),
+    /// An XPath expression that cannot be parsed, or whose evaluation fails because of the type of a value, see
+    /// [`xpath`](crate::xpath)
+    InvalidXPath {
+        expression: String,
+        message: String,
+    },
+    /// A CSS selector that cannot be parsed, see [`selector`](crate::selector)
+    InvalidSelector {
+        selector: String,
+        message: String,
+    },
+    /// An edit that does not apply to the kind of document it is made on, e.g. adding a child to a comment, see
+    /// [`Document::append_child`](crate::Document::append_child)
+    UnsupportedEdit {
+        operation: &'static str,
+        node: &'static str,
+    },
+    /// A document that cannot be added to the content of an element, such as the prolog
+    InvalidChild(&'static str),
+    /// An index past the children of the element being edited
+    ChildIndexOutOfBounds {
+        index: usize,
+        len: usize,
+    },
+    /// A name given to an element or attribute that is not an XML name
+    InvalidName(Name),
+}
+
+/// The [`EntityExpansionLimits`](crate::config::EntityExpansionLimits) that was exceeded, with its configured value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpansionLimit {
+    Depth(usize),
+    ExpandedSize(usize),
+    Expansions(usize),
+}
+
+/// A violation of the DTD found by [`Document::validate`](crate::Document::validate)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The path of the element or attribute the error was found at, e.g. `/doc/item[2]` or `/doc/item[2]/@id`
+    ///
+    /// Elements are numbered among the siblings that have the same name when there is more than one of them.
+    pub path: String,
+    pub kind: ValidationErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    /// The document has no document type declaration to validate against
+    MissingDocType,
+    /// The root element is not the one named by the document type declaration
+    RootElementMismatch { expected: Name, found: Name },
+    /// An element whose type is not declared in the DTD
+    UndeclaredElement(Name),
+    /// The content of an element does not match the content model it is declared with
+    ///
+    /// `content` lists the child elements and the character data of the element, e.g. `(a, #PCDATA, b)`.
+    InvalidContent {
+        element: Name,
+        model: DeclarationContent,
+        content: String,
+    },
+    /// An attribute that is not declared in the attribute-list declaration of its element
+    UndeclaredAttribute(Name),
+    /// A `#REQUIRED` attribute that is not specified
+    MissingRequiredAttribute(Name),
+    /// A `#FIXED` attribute specified with a value other than its default
+    FixedAttributeMismatch {
+        attribute: Name,
+        expected: String,
+        found: String,
+    },
+    /// An attribute value that does not match the declared type, e.g. a value outside of an enumeration or a
+    /// `NMTOKEN` containing characters that are not name characters
+    ///
+    /// Values of every type other than `CDATA` are compared with their white space normalized.
+    InvalidAttributeValue {
+        attribute: Name,
+        att_type: AttType,
+        value: String,
+    },
+    /// An element type declared with more than one attribute of type `ID`, reported once for the DTD at the path `/`
+    MultipleIdAttributes {
+        element: Name,
+        attributes: Vec<Name>,
+    },
+    /// An ID value that is already given to another element, `first` is the path of the attribute that gives it first
+    DuplicateId { id: String, first: String },
+    /// An `IDREF` or `IDREFS` value naming an ID that no element has
+    UndefinedIdReference { attribute: Name, id: String },
+}
+
+/// A parse error and where it occurred
+///
+/// While parsing, only the length of the remaining input is known. The location is filled in before the error is
+/// returned from [`Document::parse`](crate::Document::parse), the [`io`](crate::io) functions and the
+/// [`EventReader`](crate::event::EventReader).
+#[derive(Debug)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    /// The production that failed to match, e.g. `[42] ETag`
+    pub expected: Option<&'static str>,
+    pub message: Option<String>,
+    /// The error found at this location when the input matched the grammar but is still not accepted,
+    /// e.g. [`Error::MismatchedEndTag`]
+    pub cause: Option<Box<Error>>,
+    pub location: Option<Location>,
+    pub(crate) remaining: usize,
+}
+
+/// A position in the parsed input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// Byte offset from the start of the input
+    pub offset: usize,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, counted in characters
+    pub column: usize,
+    /// The line of input containing the error
+    pub snippet: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ErrorKind, remaining: usize) -> Self {
+        Self {
+            kind,
+            expected: None,
+            message: None,
+            cause: None,
+            location: None,
+            remaining,
+        }
+    }
+
+    pub(crate) fn with_message(kind: ErrorKind, remaining: &str, message: String) -> Self {
+        Self {
+            message: Some(message),
+            ..Self::new(kind, remaining.len())
+        }
+    }
+
+    pub(crate) fn with_cause(kind: ErrorKind, remaining: &str, cause: Error) -> Self {
+        Self {
+            cause: Some(Box::new(cause)),
+            ..Self::new(kind, remaining.len())
+        }
+    }
+}
+
+impl Location {
+    /// The location of `offset` within `input`
+    pub fn new(input: &str, offset: usize) -> Self {
+        let mut offset = offset.min(input.len());
+        while !input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line_start = input[..offset].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |index| offset + index);
+        Self {
+            offset,
+            line: input[..offset].matches('\n').count() + 1,
+            column: input[line_start..offset].chars().count() + 1,
+            snippet: input[line_start..line_end].to_string(),
+        }
+    }
+}
+
+impl Error {
+    /// Fills in the location of a [`ParseError`] raised while parsing `input`
+    ///
+    /// Errors that have already been located, and errors that are not parse errors, are returned unchanged.
+    pub fn locate(self, input: &str) -> Self {
+        match self {
+            Error::ParseError(mut error) if error.location.is_none() => {
+                let offset = input.len().saturating_sub(error.remaining);
+                error.location = Some(Location::new(input, offset));
+                Error::ParseError(error)
+            }
+            Error::NomError(error) if !error.input.is_empty() && input.ends_with(&error.input) => {
+                let mut parse_error = ParseError::new(error.code, error.input.len());
+                parse_error.location = Some(Location::new(input, input.len() - error.input.len()));
+                Error::ParseError(parse_error)
+            }
+            error => error,
+        }
+    }
+
+    /// The error to match on to handle a specific failure, looking through the [`ParseError`]s that locate it
+    ///
+    /// ```rust
+    /// use nom_xml::{config::Config, error::Error, parse::Parse, Document};
+    ///
+    /// match Document::parse("<a>text</b>", &Config::default()) {
+    ///     Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+    ///         assert!(matches!(error.root_cause(), Error::MismatchedEndTag { .. }));
+    ///     }
+    ///     _ => unreachable!("The end tag does not match"),
+    /// }
+    /// ```
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::ParseError(ParseError {
+                cause: Some(cause), ..
+            }) => cause.root_cause(),
+            error => error,
+        }
+    }
+
+    /// Takes the root cause out of the error, see [`Error::root_cause`]
+    pub(crate) fn into_root_cause(self) -> Error {
+        match self {
+            Error::ParseError(ParseError {
+                cause: Some(cause), ..
+            }) => cause.into_root_cause(),
+            error => error,
+        }
+    }
+
+    /// Whether the error reports an external entity that was read but is not well-formed, as opposed to one that
+    /// could not be read
+    pub(crate) fn is_malformed_entity_file(&self) -> bool {
+        match self {
+            Error::EntityFile { source, .. } => match source.as_ref() {
+                Error::ParseError(_) | Error::NomError(_) => true,
+                Error::IoError(e) => e.kind() == std::io::ErrorKind::InvalidData,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// The length of the remaining input where the error was raised, if it is known
+    fn remaining(&self) -> Option<usize> {
+        match self {
+            Error::ParseError(error) => Some(error.remaining),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(Location { line, column, .. }) = &self.location {
+            write!(f, "{line}:{column}: ")?;
+        }
+        match (&self.message, &self.cause, self.expected) {
+            (Some(message), _, _) => write!(f, "{message}")?,
+            (None, Some(cause), _) => write!(f, "{cause}")?,
+            (None, None, Some(expected)) => write!(f, "expected {expected}")?,
+            (None, None, None) => write!(f, "failed to parse ({:?})", self.kind)?,
+        }
+        if let Some(Location {
+            column, snippet, ..
+        }) = &self.location
+        {
+            write!(f, "\n{snippet}\n{}^", " ".repeat(column - 1))?;
+        }
+        Ok(())
+    }
 }
 
 impl Display for Error {
@@ -22,6 +372,131 @@ impl Display for Error {
             Error::NomErrorFast(kind) => write!(f, "NomErrorFast: {:?}", kind),
             Error::IoError(e) => write!(f, "IoError: {}", e),
             Error::UserAbort(e) => write!(f, "UserAbort: {}", e),
+            Error::ParseError(e) => write!(f, "ParseError: {}", e),
+            Error::UndeclaredEntity(name) => write!(f, "Entity `{name}` is not declared"),
+            Error::MismatchedEndTag { start, end } => {
+                write!(f, "End tag `{end}` does not match start tag `{start}`")
+            }
+            Error::DuplicateAttribute { tag, attribute } => {
+                write!(
+                    f,
+                    "Attribute `{attribute}` is specified more than once in tag `{tag}`"
+                )
+            }
+            Error::IllegalCharacter(c) => write!(
+                f,
+                "Character U+{:04X} is not allowed in XML documents",
+                u32::from(*c)
+            ),
+            Error::IllegalCharacterReference(reference) => write!(
+                f,
+                "Character reference `{reference}` does not refer to a character allowed in XML documents"
+            ),
+            Error::UnparsedEntityReference(name) => {
+                write!(f, "Unparsed entity `{name}` may not be referenced")
+            }
+            Error::ExternalEntityInAttributeValue(name) => write!(
+                f,
+                "External entity `{name}` may not be referenced in an attribute value"
+            ),
+            Error::MalformedEntity(name) => write!(
+                f,
+                "The replacement text of entity `{name}` is not well-formed where it is referenced"
+            ),
+            Error::UndeclaredNamespacePrefix(name) => write!(
+                f,
+                "Prefix `{}` of `{name}` is not bound to a namespace",
+                name.prefix.as_deref().unwrap_or_default()
+            ),
+            Error::ReservedNamespaceBinding { prefix, uri } => {
+                let attribute = match prefix {
+                    Some(prefix) => format!("xmlns:{prefix}"),
+                    None => "xmlns".to_string(),
+                };
+                write!(
+                    f,
+                    "Namespace declaration `{attribute}=\"{uri}\"` violates the reserved bindings of `xml` and `xmlns`"
+                )
+            }
+            Error::EmptyNamespaceUri { prefix } => write!(
+                f,
+                "Prefix `{prefix}` cannot be undeclared, only the default namespace may be bound to an empty URI"
+            ),
+            Error::DuplicateNamespacedAttribute { tag, attribute } => write!(
+                f,
+                "Attribute `{attribute}` is specified more than once in tag `{tag}`"
+            ),
+            Error::ExternalEntityDisabled { system_id } => {
+                write!(
+                    f,
+                    "External entity `{system_id}` is not parsed because external parsing is disabled in the configuration"
+                )
+            }
+            Error::ExternalEntityNotAllowed { system_id, path } => write!(
+                f,
+                "External entity `{system_id}` resolves to `{}`, which the external entity policy does not allow",
+                path.display()
+            ),
+            Error::UnsupportedExternalId(id) => {
+                write!(
+                    f,
+                    "External identifier `{id:?}` cannot be resolved to a file"
+                )
+            }
+            Error::EntityFile { path, source } => {
+                write!(f, "External entity file `{path}`: {source}")
+            }
+            Error::CatalogFile { path, source } => {
+                write!(f, "Catalog file `{path}`: {source}")
+            }
+            Error::RecursiveEntity { chain } => {
+                let chain: Vec<String> = chain.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "Entity `{}` references itself: {}",
+                    chain.last().map(String::as_str).unwrap_or_default(),
+                    chain.join(" -> ")
+                )
+            }
+            Error::EntityExpansionLimitExceeded { entity, limit } => {
+                write!(f, "Expanding entity `{entity}` exceeds the limit of ")?;
+                match limit {
+                    ExpansionLimit::Depth(depth) => write!(f, "{depth} nested entity references"),
+                    ExpansionLimit::ExpandedSize(size) => {
+                        write!(f, "{size} bytes of expanded replacement text")
+                    }
+                    ExpansionLimit::Expansions(count) => {
+                        write!(f, "{count} entity references expanded")
+                    }
+                }
+            }
+            Error::Validation(errors) => {
+                write!(f, "Document is not valid:")?;
+                for error in errors {
+                    write!(f, "\n{error}")?;
+                }
+                Ok(())
+            }
+            Error::InvalidXPath {
+                expression,
+                message,
+            } => write!(f, "XPath expression `{expression}`: {message}"),
+            Error::InvalidSelector { selector, message } => {
+                write!(f, "Selector `{selector}`: {message}")
+            }
+            Error::UnsupportedEdit { operation, node } => {
+                write!(f, "Cannot {operation} {node}")
+            }
+            Error::InvalidChild(node) => {
+                write!(f, "Cannot add {node} to the content of an element")
+            }
+            Error::ChildIndexOutOfBounds { index, len } => {
+                write!(
+                    f,
+                    "Child index {index} is out of bounds for an element with {len} children"
+                )
+            }
+            Error::InvalidName(name) => write!(f, "`{name}` is not a valid XML name"),
         }
     }
 }
@@ -33,6 +508,80 @@ impl std::error::Error for Error {
             Error::NomErrorFast(_) => None,
             Error::IoError(e) => Some(e),
             Error::UserAbort(_) => None,
+            Error::ParseError(ParseError { cause, .. }) => cause
+                .as_deref()
+                .map(|cause| cause as &(dyn std::error::Error + 'static)),
+            Error::EntityFile { source, .. } | Error::CatalogFile { source, .. } => {
+                Some(source.as_ref())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.kind {
+            ValidationErrorKind::MissingDocType => {
+                write!(f, "The document has no document type declaration")
+            }
+            ValidationErrorKind::RootElementMismatch { expected, found } => write!(
+                f,
+                "Root element `{found}` does not match the document type `{expected}`"
+            ),
+            ValidationErrorKind::UndeclaredElement(name) => {
+                write!(f, "Element `{name}` is not declared")
+            }
+            ValidationErrorKind::InvalidContent {
+                element,
+                model,
+                content,
+            } => write!(
+                f,
+                "Content {content} of element `{element}` does not match {model}"
+            ),
+            ValidationErrorKind::UndeclaredAttribute(name) => {
+                write!(f, "Attribute `{name}` is not declared")
+            }
+            ValidationErrorKind::MissingRequiredAttribute(name) => {
+                write!(f, "Required attribute `{name}` is not specified")
+            }
+            ValidationErrorKind::FixedAttributeMismatch {
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Attribute `{attribute}` has the value \"{found}\" but is #FIXED to \"{expected}\""
+            ),
+            ValidationErrorKind::InvalidAttributeValue {
+                attribute,
+                att_type,
+                value,
+            } => write!(
+                f,
+                "Value \"{value}\" of attribute `{attribute}` is not a valid {att_type}"
+            ),
+            ValidationErrorKind::MultipleIdAttributes {
+                element,
+                attributes,
+            } => {
+                let attributes: Vec<String> =
+                    attributes.iter().map(|name| format!("`{name}`")).collect();
+                write!(
+                    f,
+                    "Element `{element}` is declared with more than one ID attribute: {}",
+                    attributes.join(", ")
+                )
+            }
+            ValidationErrorKind::DuplicateId { id, first } => {
+                write!(f, "ID \"{id}\" is already given by {first}")
+            }
+            ValidationErrorKind::UndefinedIdReference { attribute, id } => write!(
+                f,
+                "Attribute `{attribute}` references ID \"{id}\", which no element has"
+            ),
         }
     }
 }
@@ -55,6 +604,18 @@ impl From<nom::error::Error<String>> for Error {
     }
 }
 
+impl From<nom::Err<Error>> for Error {
+    fn from(error: nom::Err<Error>) -> Self {
+        match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => e,
+            nom::Err::Incomplete(_) => Error::NomError(nom::error::Error::new(
+                "Incomplete input".to_string(),
+                ErrorKind::Complete,
+            )),
+        }
+    }
+}
+
 impl From<Error> for nom::Err<Error> {
     fn from(error: Error) -> Self {
         nom::Err::Failure(error)
@@ -72,51 +633,63 @@ impl From<Box<dyn std::error::Error>> for Error {
 
 impl<I> nom::error::ParseError<I> for Error
 where
-    I: Debug + ToString,
+    I: Debug + ToString + InputLength,
 {
-    fn from_error_kind(input: I, kind: nom::error::ErrorKind) -> Self {
-        if cfg!(debug_assertions) {
-            Error::NomError(nom::error::Error::new(input.to_string(), kind))
-        } else {
-            Error::NomErrorFast(kind)
-        }
+    fn from_error_kind(input: I, kind: ErrorKind) -> Self {
+        Error::ParseError(ParseError::new(kind, input.input_len()))
     }
 
-    fn append(input: I, kind: nom::error::ErrorKind, _other: Self) -> Self {
-        if cfg!(debug_assertions) {
-            Error::NomError(nom::error::Error::new(input.to_string(), kind))
-        } else {
-            Error::NomErrorFast(kind)
+    // The error that is being appended to is closer to the cause, so it is kept
+    fn append(input: I, kind: ErrorKind, other: Self) -> Self {
+        match other {
+            Error::ParseError(_) => other,
+            _ => Self::from_error_kind(input, kind),
         }
     }
 
     fn from_char(input: I, _: char) -> Self {
-        if cfg!(debug_assertions) {
-            Error::NomError(nom::error::Error::new(
-                input.to_string(),
-                nom::error::ErrorKind::Char,
-            ))
-        } else {
-            Error::NomErrorFast(nom::error::ErrorKind::Char)
+        Self::from_error_kind(input, ErrorKind::Char)
+    }
+
+    // Of two failed alternatives, the one that got furthest into the input is reported
+    fn or(self, other: Self) -> Self {
+        match (self.remaining(), other.remaining()) {
+            (Some(remaining), Some(other_remaining)) if other_remaining < remaining => other,
+            (None, Some(_)) => other,
+            _ => self,
         }
     }
+}
 
-    fn or(self, _other: Self) -> Self {
-        self
+impl<I> nom::error::ContextError<I> for Error
+where
+    I: Debug + ToString + InputLength,
+{
+    // Contexts are added from the innermost production outwards, so the first one is kept
+    fn add_context(input: I, context: &'static str, other: Self) -> Self {
+        match other {
+            Error::ParseError(mut error) => {
+                error.expected.get_or_insert(context);
+                Error::ParseError(error)
+            }
+            _ => {
+                let mut error = ParseError::new(ErrorKind::Fail, input.input_len());
+                error.expected = Some(context);
+                Error::ParseError(error)
+            }
+        }
     }
 }
 
 impl<I, E> nom::error::FromExternalError<I, E> for Error
 where
-    I: Debug + ToString,
+    I: Debug + ToString + InputLength,
     E: Debug + Display,
 {
-    fn from_external_error(input: I, kind: nom::error::ErrorKind, _e: E) -> Self {
-        if cfg!(debug_assertions) {
-            Error::NomError(nom::error::Error::new(input.to_string(), kind))
-        } else {
-            Error::NomErrorFast(kind)
-        }
+    fn from_external_error(input: I, kind: ErrorKind, e: E) -> Self {
+        let mut error = ParseError::new(kind, input.input_len());
+        error.message = Some(e.to_string());
+        Error::ParseError(error)
     }
 }
 
@@ -126,27 +699,11 @@ pub trait ConvertNomError<E> {
 
 impl<E> ConvertNomError<nom::error::Error<E>> for nom::Err<nom::error::Error<E>>
 where
-    E: Debug + ToString,
+    E: InputLength,
 {
     fn convert_nom_error(self) -> nom::Err<Error> {
-        match self {
-            nom::Err::Error(e) => {
-                let nom::error::Error { input, code } = e;
-                nom::Err::Error(if cfg!(debug_assertions) {
-                    Error::NomError(nom::error::Error::new(input.to_string(), code))
-                } else {
-                    Error::NomErrorFast(code)
-                })
-            }
-            nom::Err::Failure(e) => {
-                let nom::error::Error { input, code } = e;
-                nom::Err::Failure(if cfg!(debug_assertions) {
-                    Error::NomError(nom::error::Error::new(input.to_string(), code))
-                } else {
-                    Error::NomErrorFast(code)
-                })
-            }
-            nom::Err::Incomplete(needed) => nom::Err::Incomplete(needed),
-        }
+        self.map(|nom::error::Error { input, code }| {
+            Error::ParseError(ParseError::new(code, input.input_len()))
+        })
     }
 }
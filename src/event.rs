@@ -0,0 +1,612 @@
+// event.rs
+
+use crate::{
+    attribute::Attribute,
+    config::Config,
+    error::{Error, Location, ParseError},
+    parse::Parse,
+    processing_instruction::ProcessingInstruction,
+    prolog::{
+        doctype::DocType,
+        subset::entity::{entity_value::EntityValue, expansion::ExpansionBudget, EntitySource},
+        xmldecl::XmlDecl,
+    },
+    reference::Reference,
+    tag::{Tag, TagState},
+    Document, IResult, Name,
+};
+use encoding_rs::{CoderResult, Decoder, UTF_8};
+use nom::branch::alt;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    io::BufRead,
+    rc::Rc,
+};
+
+/// The longest markup opening that is looked at before deciding what comes next, i.e. `<![CDATA[`
+const LOOKAHEAD: usize = 9;
+
+/// A single parse event produced by an [`EventReader`]
+///
+/// Empty-element tags produce a `StartTag` whose [`TagState`] is `Empty`, directly followed by its `EndTag`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    StartTag(Tag),
+    EndTag(Tag),
+    /// Character data with entity and character references expanded
+    Text(String),
+    CDATA(String),
+    Comment(String),
+    ProcessingInstruction(ProcessingInstruction),
+    Doctype(DocType),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReaderState {
+    Start,
+    Prolog,
+    Root,
+    Epilogue,
+    Done,
+}
+
+/// The outcome of trying to parse the next token from the buffered input
+enum Step {
+    Parsed(usize),
+    Incomplete,
+}
+
+/// A pull parser that reads a document one [`Event`] at a time
+///
+/// Only the markup that is currently being parsed is held in memory, so it can be used for documents that are too
+/// large for [`Document::parse`]. Input is decoded as UTF-8 unless it starts with a byte order mark, and line endings
+/// are normalized as it is read.
+///
+/// ```rust
+/// use nom_xml::{config::Config, event::{Event, EventReader}};
+///
+/// let xml = "<root><child>Content</child><empty/></root>";
+/// let config = Config::default();
+/// let mut names = vec![];
+/// for event in EventReader::from_str(xml, &config) {
+///     if let Event::StartTag(tag) = event.unwrap() {
+///         names.push(tag.name.local_part);
+///     }
+/// }
+/// assert_eq!(names, ["root", "child", "empty"]);
+/// ```
+pub struct EventReader<'a, R> {
+    reader: R,
+    config: &'a Config,
+    decoder: Decoder,
+    buffer: String,
+    position: usize,
+    /// The location of the start of `buffer` in the whole input
+    buffer_location: Location,
+    pending_carriage_return: bool,
+    eof: bool,
+    state: ReaderState,
+    open_tags: Vec<Name>,
+    entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+    attribute_definitions: HashMap<String, Vec<Attribute>>,
+    // Entity references are counted against the budget of the whole document
+    expansion_budget: ExpansionBudget,
+    events: VecDeque<Event>,
+}
+
+impl<'a> EventReader<'a, &'a [u8]> {
+    pub fn from_str(input: &'a str, config: &'a Config) -> Self {
+        Self::new(input.as_bytes(), config)
+    }
+}
+
+impl<'a, R: BufRead> EventReader<'a, R> {
+    pub fn new(reader: R, config: &'a Config) -> Self {
+        Self {
+            reader,
+            config,
+            decoder: UTF_8.new_decoder(),
+            buffer: String::new(),
+            position: 0,
+            buffer_location: Location::new("", 0),
+            pending_carriage_return: false,
+            eof: false,
+            state: ReaderState::Start,
+            open_tags: vec![],
+            entity_references: Rc::new(RefCell::new(HashMap::new())),
+            attribute_definitions: HashMap::new(),
+            expansion_budget: ExpansionBudget::new(config.entity_limits.clone()),
+            events: VecDeque::new(),
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        while self.events.is_empty() {
+            if self.state == ReaderState::Done {
+                return Ok(None);
+            }
+            match self.step()? {
+                Step::Parsed(consumed) => self.position += consumed,
+                Step::Incomplete if self.eof => {
+                    self.state = ReaderState::Done;
+                    return Err(self.error_at_position("Unexpected end of input"));
+                }
+                Step::Incomplete => self.fill_buffer()?,
+            }
+        }
+        Ok(self.events.pop_front())
+    }
+
+    /// Reads from the underlying reader until the unparsed input has at least doubled, or the input is exhausted
+    fn fill_buffer(&mut self) -> Result<(), Error> {
+        if self.position > 0 {
+            let end = Location::new(&self.buffer, self.position);
+            let mut buffer_location = self.absolute_location(end);
+            // Keep the part of the line that is drained, so snippets of later errors show the whole line
+            buffer_location.snippet = buffer_location
+                .snippet
+                .chars()
+                .take(buffer_location.column - 1)
+                .collect();
+            self.buffer_location = buffer_location;
+            self.buffer.drain(..self.position);
+            self.position = 0;
+        }
+        let target_length = (self.buffer.len() * 2).max(1);
+        while !self.eof && self.buffer.len() < target_length {
+            let bytes = self.reader.fill_buf()?;
+            let last = bytes.is_empty();
+            let mut decoded = String::with_capacity(
+                self.decoder
+                    .max_utf8_buffer_length(bytes.len())
+                    .unwrap_or(bytes.len() * 3),
+            );
+            let (result, read, had_errors) =
+                self.decoder.decode_to_string(bytes, &mut decoded, last);
+            debug_assert!(matches!(result, CoderResult::InputEmpty));
+            // Byte sequences that are malformed in the encoding fail like they do for `io::decode_bytes`
+            if had_errors {
+                let encoding = self.decoder.encoding().name();
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("The input is not valid {encoding}"),
+                )
+                .into());
+            }
+            self.reader.consume(read);
+            self.push_normalized(&decoded, last);
+            self.eof = last;
+        }
+        Ok(())
+    }
+
+    // 2.11 End-of-Line Handling, a `\r` at the end of a chunk is held back until it is known whether `\n` follows
+    fn push_normalized(&mut self, decoded: &str, last: bool) {
+        let mut chars = decoded.chars().peekable();
+        if self.pending_carriage_return {
+            self.pending_carriage_return = false;
+            self.buffer.push('\n');
+            if chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+        }
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\r' => match chars.peek() {
+                    Some('\n') => {}
+                    Some(_) => self.buffer.push('\n'),
+                    None if last => self.buffer.push('\n'),
+                    None => self.pending_carriage_return = true,
+                },
+                ch => self.buffer.push(ch),
+            }
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, Error> {
+        let input = &self.buffer[self.position..];
+        if input.len() < LOOKAHEAD && !self.eof {
+            return Ok(Step::Incomplete);
+        }
+        match self.state {
+            ReaderState::Start => {
+                // [23] XMLDecl is only allowed at the very start of the document
+                if !XmlDecl::starts_declaration(input) {
+                    self.state = ReaderState::Prolog;
+                    return Ok(Step::Parsed(0));
+                }
+                let step = self.parse_markup(|i, _, _| {
+                    XmlDecl::parse(i, ()).map(|(i, _xml_decl)| (i, None))
+                })?;
+                if let Step::Parsed(_) = step {
+                    self.state = ReaderState::Prolog;
+                }
+                Ok(step)
+            }
+            ReaderState::Prolog | ReaderState::Epilogue => {
+                let trimmed = input.trim_start_matches([' ', '\t', '\r', '\n']);
+                let whitespace = input.len() - trimmed.len();
+                if whitespace > 0 {
+                    return Ok(Step::Parsed(whitespace));
+                }
+                if input.is_empty() {
+                    return match self.state {
+                        ReaderState::Epilogue if self.eof => {
+                            self.state = ReaderState::Done;
+                            Ok(Step::Parsed(0))
+                        }
+                        _ => Ok(Step::Incomplete),
+                    };
+                }
+                if input.starts_with("<!--") {
+                    self.parse_markup(|i, _, _| {
+                        Document::parse_comment(i).map(|(i, comment)| match comment {
+                            Document::Comment(comment) => (i, Some(Event::Comment(comment))),
+                            _ => (i, None),
+                        })
+                    })
+                } else if input.starts_with("<?") {
+                    self.parse_markup(|i, _, _| {
+                        ProcessingInstruction::parse(i, ())
+                            .map(|(i, pi)| (i, Some(Event::ProcessingInstruction(pi))))
+                    })
+                } else if self.state == ReaderState::Prolog && input.starts_with("<!DOCTYPE") {
+                    self.parse_doctype()
+                } else if self.state == ReaderState::Prolog && input.starts_with('<') {
+                    self.state = ReaderState::Root;
+                    Ok(Step::Parsed(0))
+                } else if self.state == ReaderState::Prolog {
+                    Err(self.error_at_position("Expected the root element"))
+                } else {
+                    Err(self.error_at_position("Unexpected content after the root element"))
+                }
+            }
+            ReaderState::Root => self.step_content(),
+            ReaderState::Done => Ok(Step::Parsed(0)),
+        }
+    }
+
+    // [43] content ::= CharData? ((element | Reference | CDSect | PI | Comment) CharData?)*
+    fn step_content(&mut self) -> Result<Step, Error> {
+        let input = &self.buffer[self.position..];
+        if input.starts_with("</") {
+            self.parse_markup(|i, _, _| {
+                Tag::parse_end_tag(i).map(|(i, tag)| (i, Some(Event::EndTag(tag))))
+            })
+        } else if input.starts_with("<!--") {
+            self.parse_markup(|i, _, _| {
+                Document::parse_comment(i).map(|(i, comment)| match comment {
+                    Document::Comment(comment) => (i, Some(Event::Comment(comment))),
+                    _ => (i, None),
+                })
+            })
+        } else if input.starts_with("<![CDATA[") {
+            self.parse_markup(|i, _, _| {
+                Document::parse_cdata_section(i).map(|(i, cdata)| match cdata {
+                    Document::CDATA(cdata) => (i, Some(Event::CDATA(cdata))),
+                    _ => (i, None),
+                })
+            })
+        } else if input.starts_with("<?") {
+            self.parse_markup(|i, _, _| {
+                ProcessingInstruction::parse(i, ())
+                    .map(|(i, pi)| (i, Some(Event::ProcessingInstruction(pi))))
+            })
+        } else if input.starts_with('<') {
+            self.parse_markup(|i, entity_references, budget| {
+                // [39] element ::= EmptyElemTag | STag content ETag
+                alt((
+                    |i| {
+                        Tag::parse_empty_element_tag(
+                            i,
+                            entity_references.clone(),
+                            EntitySource::Internal,
+                            budget,
+                        )
+                    },
+                    |i| {
+                        Tag::parse_start_tag(
+                            i,
+                            entity_references.clone(),
+                            EntitySource::Internal,
+                            budget,
+                        )
+                    },
+                ))(i)
+                .map(|(i, tag)| (i, Some(Event::StartTag(tag))))
+            })
+        } else {
+            self.parse_text()
+        }
+    }
+
+    /// Runs a parser on the next piece of markup once its closing delimiter has been buffered
+    ///
+    /// Only markup that is still missing its delimiter is incomplete; a failure to parse complete markup is an error.
+    fn parse_markup<F>(&mut self, parser: F) -> Result<Step, Error>
+    where
+        F: for<'i> Fn(
+            &'i str,
+            &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+            &ExpansionBudget,
+        ) -> IResult<&'i str, Option<Event>>,
+    {
+        let input = &self.buffer[self.position..];
+        let end = match markup_end(input) {
+            Some(end) => end,
+            None if self.eof => input.len(),
+            None => return Ok(Step::Incomplete),
+        };
+        let markup = &input[..end];
+        match parser(markup, &self.entity_references, &self.expansion_budget) {
+            Ok((remaining, event)) => {
+                let consumed = markup.len() - remaining.len();
+                if let Some(event) = event {
+                    self.push_event(event)?;
+                }
+                Ok(Step::Parsed(consumed))
+            }
+            Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                Err(self.locate(e, self.position + end))
+            }
+            Err(nom::Err::Incomplete(_)) => Err(self.error_at_position("Unexpected end of input")),
+        }
+    }
+
+    // [28] doctypedecl ::= '<!DOCTYPE' S Name (S ExternalID)? S? ('[' intSubset ']' S?)? '>'
+    fn parse_doctype(&mut self) -> Result<Step, Error> {
+        let input = &self.buffer[self.position..];
+        // The external subset is read while parsing, so the declaration is only parsed once it is complete
+        let Some(end) = doctype_end(input) else {
+            return Ok(Step::Incomplete);
+        };
+        let (_, doc_type) = DocType::parse(
+            &input[..end],
+            (
+                self.entity_references.clone(),
+                self.config,
+                self.expansion_budget.clone(),
+            ),
+        )
+        .map_err(|e| self.locate(e.into(), self.position + end))?;
+        self.entity_references =
+            Document::collect_entity_references(&doc_type, self.entity_references.clone());
+        self.attribute_definitions = doc_type.attribute_definitions();
+        self.push_event(Event::Doctype(doc_type))?;
+        Ok(Step::Parsed(end))
+    }
+
+    // [14] CharData and [67] Reference, up to the next markup
+    fn parse_text(&mut self) -> Result<Step, Error> {
+        let input = &self.buffer[self.position..];
+        let Some(end) = input.find('<') else {
+            return Ok(Step::Incomplete);
+        };
+        let mut remaining = &input[..end];
+        let mut text = String::new();
+        let mut events = vec![];
+        while !remaining.is_empty() {
+            match remaining.find('&') {
+                Some(0) => {
+                    let (rest, reference) = Reference::parse(remaining, EntitySource::Internal)
+                        .map_err(|e| self.locate(e.into(), self.position + end))?;
+                    let entity_value = reference
+                        .normalize_entity(self.entity_references.clone(), &self.expansion_budget)
+                        .map_err(|e| {
+                            let error =
+                                ParseError::with_cause(nom::error::ErrorKind::Verify, remaining, e);
+                            self.locate(Error::ParseError(error), self.position + end)
+                        })?;
+                    match entity_value {
+                        EntityValue::Value(value) => text.push_str(&value),
+                        EntityValue::Document(document) => {
+                            if !text.is_empty() {
+                                events.push(Event::Text(std::mem::take(&mut text)));
+                            }
+                            document_events(&document, &mut events);
+                        }
+                        _ => {}
+                    }
+                    remaining = rest;
+                }
+                Some(index) => {
+                    text.push_str(&remaining[..index]);
+                    remaining = &remaining[index..];
+                }
+                None => {
+                    text.push_str(remaining);
+                    remaining = "";
+                }
+            }
+        }
+        if !text.is_empty() {
+            events.push(Event::Text(text));
+        }
+        for event in events {
+            self.push_event(event)?;
+        }
+        Ok(Step::Parsed(end))
+    }
+
+    /// Queues an event, checking that end tags match their start tags and adding defaulted attributes
+    fn push_event(&mut self, event: Event) -> Result<(), Error> {
+        match event {
+            Event::StartTag(mut tag) => {
+                if let Some(att_defs) = self.attribute_definitions.get(&tag.name.to_string()) {
+                    tag.merge_default_attributes(att_defs);
+                    tag.normalize_attributes(att_defs);
+                }
+                let end_tag = (tag.state == TagState::Empty)
+                    .then(|| Tag::new(tag.name.clone(), None, TagState::End));
+                self.open_tags.push(tag.name.clone());
+                self.events.push_back(Event::StartTag(tag));
+                if let Some(end_tag) = end_tag {
+                    self.push_event(Event::EndTag(end_tag))?;
+                }
+            }
+            Event::EndTag(tag) => {
+                // [GIMatch] The Name in an element's end-tag must match the element type in the start-tag
+                match self.open_tags.pop() {
+                    Some(name) if name == tag.name => {}
+                    Some(name) => {
+                        return Err(self.cause_at_position(Error::MismatchedEndTag {
+                            start: name,
+                            end: tag.name,
+                        }))
+                    }
+                    None => return Err(self.error_at_position("Unexpected end tag")),
+                }
+                if self.open_tags.is_empty() {
+                    self.state = ReaderState::Epilogue;
+                }
+                self.events.push_back(Event::EndTag(tag));
+            }
+            event => self.events.push_back(event),
+        }
+        Ok(())
+    }
+
+    fn cause_at_position(&self, cause: Error) -> Error {
+        let error = ParseError::with_cause(
+            nom::error::ErrorKind::Verify,
+            &self.buffer[self.position..],
+            cause,
+        );
+        self.locate(Error::ParseError(error), self.buffer.len())
+    }
+
+    fn error_at_position(&self, message: &str) -> Error {
+        let error = ParseError::with_message(
+            nom::error::ErrorKind::Verify,
+            &self.buffer[self.position..],
+            message.to_string(),
+        );
+        self.locate(Error::ParseError(error), self.buffer.len())
+    }
+
+    /// Locates an error from a parser that was given the buffered input up to `input_end`
+    fn locate(&self, error: Error, input_end: usize) -> Error {
+        match error {
+            Error::ParseError(mut error) if error.location.is_none() => {
+                let index = input_end.saturating_sub(error.remaining);
+                let location = Location::new(&self.buffer, index);
+                error.location = Some(self.absolute_location(location));
+                Error::ParseError(error)
+            }
+            error => error,
+        }
+    }
+
+    /// Converts a location in `buffer` into a location in the whole input
+    ///
+    /// `buffer_location` is where the buffer starts, and its snippet is the start of that line which has been drained
+    fn absolute_location(&self, location: Location) -> Location {
+        let Location {
+            offset,
+            line,
+            column,
+            snippet,
+        } = location;
+        let base = &self.buffer_location;
+        Location {
+            offset: base.offset + offset,
+            line: base.line + line - 1,
+            column: if line == 1 {
+                base.column + column - 1
+            } else {
+                column
+            },
+            snippet: if line == 1 {
+                format!("{}{snippet}", base.snippet)
+            } else {
+                snippet
+            },
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<'_, R> {
+    type Item = Result<Event, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_event() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => None,
+            Err(e) => {
+                self.state = ReaderState::Done;
+                self.events.clear();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// The length of the comment, CDATA section, processing instruction or tag at the start of `input`, if its closing
+/// delimiter has been read
+fn markup_end(input: &str) -> Option<usize> {
+    if input.starts_with("<!--") {
+        input.find("-->").map(|index| index + 3)
+    } else if input.starts_with("<![CDATA[") {
+        input.find("]]>").map(|index| index + 3)
+    } else if input.starts_with("<?") {
+        input.find("?>").map(|index| index + 2)
+    } else {
+        // Attribute values may contain `>`
+        let mut index = 0;
+        while let Some(ch) = input[index..].chars().next() {
+            match ch {
+                '"' | '\'' => index += input[index + 1..].find(ch)? + 1,
+                '>' => return Some(index + 1),
+                _ => {}
+            }
+            index += ch.len_utf8();
+        }
+        None
+    }
+}
+
+/// Finds the end of a document type declaration, skipping over literals, comments and processing instructions in
+/// the internal subset
+fn doctype_end(input: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut index = 0;
+    while let Some(ch) = input[index..].chars().next() {
+        match ch {
+            '"' | '\'' => index += input[index + 1..].find(ch)? + 1,
+            '<' if input[index..].starts_with("<!--") => index += input[index..].find("-->")? + 2,
+            '<' if input[index..].starts_with("<?") => index += input[index..].find("?>")? + 1,
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '>' if depth == 0 => return Some(index + 1),
+            _ => {}
+        }
+        index += ch.len_utf8();
+    }
+    None
+}
+
+/// The events of a parsed entity's replacement text
+fn document_events(document: &Document, events: &mut Vec<Event>) {
+    match document {
+        Document::Element(start_tag, content, end_tag) => {
+            events.push(Event::StartTag(start_tag.clone()));
+            document_events(content, events);
+            events.push(Event::EndTag(end_tag.clone()));
+        }
+        // The matching end tag is added when the event is queued
+        Document::EmptyTag(tag) => events.push(Event::StartTag(tag.clone())),
+        Document::Content(Some(text)) => events.push(Event::Text(text.clone())),
+        Document::CDATA(cdata) => events.push(Event::CDATA(cdata.clone())),
+        Document::Comment(comment) => events.push(Event::Comment(comment.clone())),
+        Document::ProcessingInstruction(pi) => {
+            events.push(Event::ProcessingInstruction(pi.clone()))
+        }
+        Document::Nested(documents) => {
+            for document in documents {
+                document_events(document, events);
+            }
+        }
+        Document::Prolog { .. } | Document::Content(None) | Document::Empty => {}
+    }
+}
@@ -1,14 +1,18 @@
-use crate::attribute::Attribute;
 use crate::parse::Parse;
 use crate::prolog::subset::entity::entity_value::EntityValue;
+use crate::prolog::subset::entity::expansion::ExpansionBudget;
 use crate::prolog::subset::Subset;
 use crate::Name;
 
 use crate::config::Config;
 use crate::prolog::subset::entity::EntitySource;
 use crate::prolog::textdecl::TextDecl;
+use crate::prolog::xmldecl::XmlDecl;
 use crate::reference::Reference;
-use crate::{error::Error, Document};
+use crate::{
+    error::{Error, ParseError},
+    Document,
+};
 use encoding_rs::*;
 use nom::branch::alt;
 use nom::combinator::{map, opt};
@@ -17,10 +21,13 @@ use nom::multi::many1;
 
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter};
 
 use std::rc::Rc;
-use std::{fs::File, io::Read};
+use std::{
+    fs::File,
+    io::{Read, Write},
+};
 
 /// Read the file and decode the contents into a String
 pub fn read_file(file: &mut File) -> std::io::Result<String> {
@@ -29,11 +36,24 @@ pub fn read_file(file: &mut File) -> std::io::Result<String> {
 
     reader.read_to_end(&mut bytes)?;
 
-    let (encoding, bom_length) = match Encoding::for_bom(&bytes) {
+    decode_bytes(&bytes)
+}
+
+// Decodes according to the byte order mark, or as UTF-8 without one, and normalizes line endings
+//
+// Byte sequences that are malformed in the encoding fail with `ErrorKind::InvalidData`
+pub(crate) fn decode_bytes(bytes: &[u8]) -> std::io::Result<String> {
+    let (encoding, bom_length) = match Encoding::for_bom(bytes) {
         Some((enc, len)) => (enc, len),
         None => (UTF_8, 0),
     };
-    let (decoded_str, _, _) = encoding.decode(&bytes[bom_length..]);
+    let (decoded_str, _, had_errors) = encoding.decode(&bytes[bom_length..]);
+    if had_errors {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("The input is not valid {}", encoding.name()),
+        ));
+    }
 
     let mut data = decoded_str.into_owned();
 
@@ -45,44 +65,57 @@ pub fn read_file(file: &mut File) -> std::io::Result<String> {
 /// Parse the entire file into a Document
 ///
 /// Note: Beware using for extremely large files as it will load the entire file into memory
-pub fn parse_entire_file(
-    file: &mut File,
-    config: &Config,
-) -> Result<Document, Box<dyn std::error::Error>> {
+pub fn parse_entire_file(file: &mut File, config: &Config) -> Result<Document, Error> {
     let data = read_file(file)?;
 
-    let parse_result = Document::parse(&data, config);
-    match parse_result {
+    match Document::parse(&data, config) {
         Ok((_, document)) => Ok(document),
-        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
-            // Handle Nom parsing errors
-            Err(Error::NomError(nom::error::Error::new(
-                e.to_string(),
-                nom::error::ErrorKind::Fail,
-            ))
-            .into())
-        }
+        // Parse errors are already located in `data` by `Document::parse`
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(e),
         Err(nom::Err::Incomplete(_)) => Err(Error::NomError(nom::error::Error::new(
             "parse_file: Incomplete parsing".to_string(),
             nom::error::ErrorKind::Fail,
-        ))
-        .into()),
+        ))),
     }
 }
 
-pub(crate) fn parse_external_entity_file(
-    file: &mut File,
+/// Write the Document to the file as XML
+///
+/// See the [`Display`](std::fmt::Display) implementation of [`Document`] for the output format
+pub fn write_file(file: &mut File, document: &Document) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(file);
+    writer.write_all(document.to_string().as_bytes())?;
+    writer.flush()
+}
+
+type ExternalEntityFileResult = Result<(Vec<EntityValue>, Option<Vec<Subset>>), Error>;
+
+pub(crate) fn parse_external_entity(
+    bytes: &[u8],
     config: &Config,
     external_entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
-) -> Result<(Vec<EntityValue>, Option<Vec<Subset>>), Box<dyn std::error::Error>> {
-    let mut data = read_file(file)?;
-    data = data.replace("\r\n", "\n").replace('\r', "\n");
-    let (input, _text_decl) = opt(|i| TextDecl::parse(i, ()))(data.as_str())?;
+    budget: &ExpansionBudget,
+) -> ExternalEntityFileResult {
+    let data = decode_bytes(bytes)?;
+    Document::check_characters(&data).map_err(|e| Error::from(e).locate(&data))?;
+    let (input, _text_decl) =
+        opt(|i| TextDecl::parse(i, ()))(data.as_str()).map_err(|e| Error::from(e).locate(&data))?;
+    // [77] TextDecl only occurs at the start of an external entity, and without a standalone declaration
+    if XmlDecl::starts_declaration(input) {
+        return Err(Error::ParseError(ParseError::with_message(
+            nom::error::ErrorKind::Verify,
+            input,
+            "A text declaration is only allowed at the start of an external entity, with an optional version and an encoding"
+                .to_string(),
+        ))
+        .locate(&data));
+    }
     //TODO: handle the text_decl such that if the encoding being used to parse the file is different, then the encoding is handled accordingly, i.e file being parsed again with the proper decoding
     let args = (
         external_entity_references.clone(),
         config,
         EntitySource::External,
+        budget.clone(),
     );
     let (input, subsets) = match Subset::parse(input, args) {
         Ok((input, subsets)) => {
@@ -92,27 +125,40 @@ pub(crate) fn parse_external_entity_file(
                 (input, Some(subsets))
             }
         }
-        _ => (input, None),
+        Err(nom::Err::Failure(e)) => return Err(e.locate(&data)),
+        Err(_) => (input, None),
     };
+    // Parameter entity references within markup declarations are not supported, the declarations of an entity with
+    // one are skipped
+    if input.starts_with("<!") && input[..input.find('>').unwrap_or(input.len())].contains('%') {
+        return Ok((vec![], None));
+    }
 
-    let (_, entity_values) = alt((
+    let (input, entity_values) = alt((
         many1(map(
             |i| Reference::parse(i, EntitySource::External),
             EntityValue::Reference,
         )),
         map(
-            |i| Document::parse_content(i, &external_entity_references, EntitySource::External),
+            |i| {
+                Document::parse_content(
+                    i,
+                    &external_entity_references,
+                    EntitySource::External,
+                    budget,
+                )
+            },
             |doc| vec![EntityValue::Document(doc)],
         ),
     ))(input)
-    .map_err(|err| match err {
-        nom::Err::Error(_e) | nom::Err::Failure(_e) => Box::new(Error::NomError(
-            nom::error::Error::new(input.to_string(), nom::error::ErrorKind::Fail),
-        )),
-        nom::Err::Incomplete(_) => Box::new(Error::NomError(nom::error::Error::new(
-            "parse_external_ent_file: Incomplete input.".to_string(),
-            nom::error::ErrorKind::Fail,
-        ))),
-    })?;
+    .map_err(|e| Error::from(e).locate(&data))?;
+    if !input.trim_start_matches([' ', '\t', '\r', '\n']).is_empty() {
+        return Err(Error::ParseError(ParseError::with_message(
+            nom::error::ErrorKind::Verify,
+            input,
+            "Expected markup declarations or content".to_string(),
+        ))
+        .locate(&data));
+    }
     Ok((entity_values, subsets))
 }
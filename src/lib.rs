@@ -2,9 +2,16 @@
 #![doc = include_str!("docs/crate_description.md")]
 //!
 pub mod attribute;
+pub mod borrowed;
+pub mod builder;
+mod canonical;
+pub mod catalog;
 pub mod config;
 mod debug;
+mod display;
+mod edit;
 pub mod error;
+pub mod event;
 pub mod io;
 pub mod misc;
 pub mod namespaces;
@@ -12,11 +19,17 @@ pub mod parse;
 pub mod processing_instruction;
 pub mod prolog;
 pub mod reference;
+pub mod resolver;
+pub mod sax;
+pub mod selector;
 pub mod tag;
 pub mod transcode;
+pub mod tree;
+pub mod validation;
+pub mod xpath;
 
 use crate::{
-    config::{check_config, Config, ExternalEntityParseConfig},
+    config::Config,
     misc::{Misc, MiscState},
     parse::Parse,
     processing_instruction::ProcessingInstruction,
@@ -25,33 +38,34 @@ use crate::{
         subset::{
             entity::{
                 entity_declaration::EntityDecl, entity_definition::EntityDefinition,
-                entity_value::EntityValue, EntitySource,
+                entity_value::EntityValue, expansion::ExpansionBudget, EntitySource,
             },
             markup_declaration::MarkupDeclaration,
             Subset,
         },
-        xmldecl::XmlDecl,
+        xmldecl::{Standalone, XmlDecl},
     },
     reference::Reference,
-    tag::Tag,
+    tag::{Tag, TagState},
 };
 
 use attribute::Attribute;
 
-use error::{ConvertNomError, Error};
-use io::parse_external_entity_file;
-use namespaces::ParseNamespace;
+use error::{Error, ParseError};
+use io::parse_external_entity;
+use namespaces::{ExpandedName, NamespaceScope, ParseNamespace};
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_till, take_until},
-    combinator::{cut, map, map_res, not, opt, value},
+    bytes::complete::{tag, take_till},
+    combinator::{cut, map, map_res, opt, value},
+    error::context,
     multi::{many0, many1, many_till},
     sequence::{pair, preceded, tuple},
 };
 
 use prolog::{external_id::ExternalID, subset::entity::entity_declaration::EntityDeclaration};
 
-use std::{cell::RefCell, collections::HashMap, fmt, fs::File, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
 
 // pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 pub type IResult<I, O> = nom::IResult<I, O, Error>;
@@ -119,95 +133,281 @@ impl<'a> Parse<'a> for Document {
     /// let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
     /// println!("{doc:?}");
     /// ```
+    ///
+    /// Parse errors are returned as [`Error::ParseError`] with their [`Location`](error::Location) in `input`, and
+    /// violations of the DTD as [`Error::Validation`] when [`Config::validate`] is set. Namespace errors are returned
+    /// when [`Config::resolve_namespaces`] is set, see [`Document::resolve_namespaces`].
     fn parse(input: &'a str, args: Self::Args) -> Self::Output {
-        match check_config(args) {
-            Ok(_) => {
-                let entity_references = Rc::new(RefCell::new(HashMap::new()));
-                let (input, prolog_and_references) =
-                    opt(|i| Self::parse_prolog(i, entity_references.clone(), args))(input)?;
-
-                let (prolog, new_entity_references) = match prolog_and_references {
-                    Some((prolog, entity_references)) => (prolog, entity_references),
-                    None => (None, entity_references.clone()),
-                };
-
-                let mut documents = Vec::new();
-
-                let mut current_input = input;
-                while !current_input.is_empty() {
-                    let (input, mut start_tag) = opt(|i| {
-                        Tag::parse_start_tag(
-                            i,
-                            new_entity_references.clone(),
-                            EntitySource::Internal,
-                        )
-                    })(current_input)?;
-
-                    let source = Self::determine_source_from_references(&new_entity_references); //THIS IS THE ISSUE
-
-                    let (input, content) = Self::parse_content(
-                        input,
-                        &new_entity_references,
-                        source, //TODO Investigate how to handle both internal and external
-                    )?;
+        Self::check_characters(input).map_err(|e| e.map(|e| e.locate(input)))?;
+        let budget = ExpansionBudget::new(args.entity_limits.clone());
+        let (remaining, document) =
+            Self::parse_document(input, args, &budget).map_err(|e| e.map(|e| e.locate(input)))?;
+        if args.validate {
+            document.validate().map_err(nom::Err::Failure)?;
+        }
+        if args.resolve_namespaces {
+            document.resolve_namespaces().map_err(nom::Err::Failure)?;
+        }
+        Ok((remaining, document))
+    }
+}
 
-                    let (input, end_tag) = opt(Tag::parse_end_tag)(input)?;
+impl Document {
+    // [WFC: Entity Declared] applies to all references of documents without a DTD and of standalone documents,
+    // the DTD requires them for an internal subset without parameter entity references
+    fn entity_declarations_required(prolog: &Option<Document>) -> bool {
+        match prolog {
+            Some(Document::Prolog {
+                xml_decl, doc_type, ..
+            }) => {
+                doc_type.is_none()
+                    || matches!(
+                        xml_decl,
+                        Some(XmlDecl {
+                            standalone: Some(Standalone::Yes),
+                            ..
+                        })
+                    )
+            }
+            _ => true,
+        }
+    }
 
-                    let mut empty_tag = if let Document::EmptyTag(empty_tag) = &content {
-                        Some(empty_tag.clone())
-                    } else {
-                        None
-                    };
-
-                    if let Some(Document::Prolog {
-                        doc_type:
-                            Some(DocType {
-                                subset: Some(ref subset),
-                                ..
-                            }),
-                        ..
-                    }) = prolog
-                    {
-                        for subset in subset {
-                            if let Subset::MarkupDecl(MarkupDeclaration::AttList {
-                                name,
-                                att_defs: Some(att_defs),
-                            }) = subset
-                            {
-                                if let Some(start_tag) = &mut start_tag {
-                                    if start_tag.name == *name {
-                                        start_tag.merge_default_attributes(&att_defs.clone());
-                                    }
-                                }
-                                if let Some(empty_tag) = &mut empty_tag {
-                                    if empty_tag.name == *name {
-                                        empty_tag.merge_default_attributes(&att_defs.clone());
-                                    }
-                                }
-                            }
+    fn parse_document<'a>(
+        input: &'a str,
+        args: &'a Config,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Document> {
+        let entity_references = Rc::new(RefCell::new(HashMap::new()));
+        let (input, prolog_and_references) =
+            opt(|i| Self::parse_prolog(i, entity_references.clone(), args, budget))(input)?;
+
+        let (prolog, new_entity_references) = match prolog_and_references {
+            Some((prolog, entity_references)) => (prolog, entity_references),
+            None => (None, entity_references.clone()),
+        };
+        if Self::entity_declarations_required(&prolog) {
+            budget.require_entity_declarations(true);
+        }
+
+        // [39] element, the root of the document
+        let (input, _whitespace) = Self::parse_multispace0(input)?;
+        let (input, mut start_tag) = opt(|i| {
+            Tag::parse_start_tag(
+                i,
+                new_entity_references.clone(),
+                EntitySource::Internal,
+                budget,
+            )
+        })(input)?;
+
+        let (input, content, end_tag) = if start_tag.is_some() {
+            let source = Self::determine_source_from_references(&new_entity_references); //THIS IS THE ISSUE
+
+            let (input, content) = Self::parse_content(
+                input,
+                &new_entity_references,
+                source, //TODO Investigate how to handle both internal and external
+                budget,
+            )?;
+
+            let (input, end_tag) = opt(Tag::parse_end_tag)(input)?;
+            (input, content, end_tag)
+        } else {
+            let (input, empty_tag) = opt(|i| {
+                Tag::parse_empty_element_tag(
+                    i,
+                    new_entity_references.clone(),
+                    EntitySource::Internal,
+                    budget,
+                )
+            })(input)?;
+            (
+                input,
+                empty_tag.map_or(Document::Empty, Document::EmptyTag),
+                None,
+            )
+        };
+
+        let mut empty_tag = if let Document::EmptyTag(empty_tag) = &content {
+            Some(empty_tag.clone())
+        } else {
+            None
+        };
+
+        if let Some(Document::Prolog {
+            doc_type:
+                Some(DocType {
+                    subset: Some(ref subset),
+                    ..
+                }),
+            ..
+        }) = prolog
+        {
+            for subset in subset {
+                if let Subset::MarkupDecl(MarkupDeclaration::AttList {
+                    name,
+                    att_defs: Some(att_defs),
+                }) = subset
+                {
+                    if let Some(start_tag) = &mut start_tag {
+                        if start_tag.name == *name {
+                            start_tag.merge_default_attributes(&att_defs.clone());
                         }
                     }
+                    if let Some(empty_tag) = &mut empty_tag {
+                        if empty_tag.name == *name {
+                            empty_tag.merge_default_attributes(&att_defs.clone());
+                        }
+                    }
+                }
+            }
+        }
 
-                    let (input, doc) = Self::construct_document_element(
-                        input, start_tag, content, end_tag, empty_tag,
-                    )?;
-                    if let Document::Empty = &doc {
-                        break;
+        let missing_end_tag = start_tag.is_some() && end_tag.is_none();
+        let (input, root) =
+            Self::construct_document_element(input, start_tag, content, end_tag, empty_tag)
+                .map_err(|e| {
+                    if missing_end_tag {
+                        Self::explain_missing_end_tag(input, &new_entity_references, e, budget)
+                    } else {
+                        e
                     }
+                })?;
+        if let Document::Empty = root {
+            return Err(Self::explain_unparsed_markup(
+                input,
+                &new_entity_references,
+                nom::Err::Error(Error::ParseError(ParseError::with_message(
+                    nom::error::ErrorKind::Verify,
+                    input,
+                    "Expected the root element of the document".to_string(),
+                ))),
+                budget,
+            ));
+        }
+        let mut documents = vec![root];
 
-                    documents.push(doc);
-                    current_input = input;
-                }
+        // [1] document ::= prolog element Misc*
+        let (input, misc) = many0(preceded(
+            Self::parse_multispace0,
+            alt((
+                Self::parse_comment,
+                map(
+                    |i| ProcessingInstruction::parse(i, ()),
+                    Document::ProcessingInstruction,
+                ),
+            )),
+        ))(input)?;
+        documents.extend(misc);
+        let (input, _whitespace) = Self::parse_multispace0(input)?;
+        if !input.is_empty() {
+            let error = nom::Err::Failure(Error::ParseError(ParseError::with_message(
+                nom::error::ErrorKind::Verify,
+                input,
+                "Only comments, processing instructions and white space may follow the root element"
+                    .to_string(),
+            )));
+            return Err(if input.starts_with("<!--") || input.starts_with("<?") {
+                Self::explain_unparsed_markup(input, &new_entity_references, error, budget)
+            } else {
+                error
+            });
+        }
 
-                let (input, documents) = Self::construct_document(input, prolog, documents)?;
-                Ok((input, documents))
+        if let Some(Document::Prolog {
+            doc_type: Some(doc_type),
+            ..
+        }) = &prolog
+        {
+            let att_defs = doc_type.attribute_definitions();
+            if !att_defs.is_empty() {
+                for document in &mut documents {
+                    document.normalize_attributes(&att_defs);
+                }
             }
-            Err(e) => Err(Error::from(e).into()),
         }
+
+        Self::construct_document(input, prolog, documents)
     }
 }
 
 impl Document {
+    // [2] Char: all of the input, including the parts the parsers skip over, must be legal characters
+    pub(crate) fn check_characters(input: &str) -> IResult<&str, ()> {
+        match input.char_indices().find(|(_, c)| !Self::is_char(*c)) {
+            Some((index, c)) => Err(nom::Err::Failure(Error::ParseError(
+                ParseError::with_cause(
+                    nom::error::ErrorKind::Verify,
+                    &input[index..],
+                    Error::IllegalCharacter(c),
+                ),
+            ))),
+            None => Ok((input, ())),
+        }
+    }
+
+    // 3.3.3 Attribute-Value Normalization of the attributes declared with a type other than CDATA, which needs
+    // the declarations of the whole DTD and is therefore done once the elements are parsed
+    fn normalize_attributes(&mut self, att_defs: &HashMap<String, Vec<Attribute>>) {
+        match self {
+            Document::Element(start_tag, content, _) => {
+                if let Some(defs) = att_defs.get(&start_tag.name.to_string()) {
+                    start_tag.normalize_attributes(defs);
+                }
+                content.normalize_attributes(att_defs);
+            }
+            Document::EmptyTag(tag) => {
+                if let Some(defs) = att_defs.get(&tag.name.to_string()) {
+                    tag.normalize_attributes(defs);
+                }
+            }
+            Document::Nested(documents) => {
+                for document in documents {
+                    document.normalize_attributes(att_defs);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The content parser stops at the first markup it cannot parse, so the reason the end tag is missing
+    /// is found by parsing that markup again as an element
+    fn explain_missing_end_tag(
+        input: &str,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        error: nom::Err<Error>,
+        budget: &ExpansionBudget,
+    ) -> nom::Err<Error> {
+        if input.is_empty() {
+            return nom::Err::Error(Error::ParseError(ParseError::with_message(
+                nom::error::ErrorKind::Eof,
+                input,
+                "Unexpected end of input, expected an end tag".to_string(),
+            )));
+        }
+        Self::explain_unparsed_markup(input, entity_references, error, budget)
+    }
+
+    /// Reports why the markup at the start of `input` does not parse, or returns `error` if it does
+    fn explain_unparsed_markup(
+        input: &str,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        error: nom::Err<Error>,
+        budget: &ExpansionBudget,
+    ) -> nom::Err<Error> {
+        let result = if input.starts_with("<!--") {
+            Self::parse_comment(input).map(|_| ())
+        } else if input.starts_with("<![CDATA[") {
+            Self::parse_cdata_section(input).map(|_| ())
+        } else if input.starts_with("<?") {
+            ProcessingInstruction::parse(input, ()).map(|_| ())
+        } else {
+            Self::parse_element(input, entity_references.clone(), budget).map(|_| ())
+        };
+        result.err().unwrap_or(error)
+    }
+
     fn determine_source_from_references(
         refs: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
     ) -> EntitySource {
@@ -232,13 +432,14 @@ impl Document {
         input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         config: &'a Config,
+        budget: &ExpansionBudget,
     ) -> PrologResult<'a> {
         let (input, xml_decl) = opt(|i| XmlDecl::parse(i, ()))(input)?;
         let (input, _) = Self::parse_multispace0(input)?;
         let (input, misc_before) =
             opt(|input| Misc::parse(input, MiscState::BeforeDoctype))(input)?;
         let (input, doc_type) =
-            opt(|i| DocType::parse(i, (entity_references.clone(), config)))(input)?;
+            opt(|i| DocType::parse(i, (entity_references.clone(), config, budget.clone())))(input)?;
         let (input, misc_after) = match &doc_type {
             Some(_) => opt(|input| Misc::parse(input, MiscState::AfterDoctype))(input)?,
             None => (input, None),
@@ -265,14 +466,17 @@ impl Document {
 
     // [14] CharData ::= [^<&]* - ([^<&]* ']]>' [^<&]*)
     fn parse_char_data(input: &str) -> IResult<&str, String> {
-        map(
-            tuple((
-                take_till(|c: char| c == '<' || c == '&'),
-                not(tag::<&str, &str, nom::error::Error<&str>>("]]>")),
-            )),
-            |(data, _)| data.to_string(),
-        )(input)
-        .map_err(|e| e.convert_nom_error())
+        let (remaining, data) = take_till(|c: char| c == '<' || c == '&')(input)?;
+        if let Some(index) = data.find("]]>") {
+            return Err(nom::Err::Failure(Error::ParseError(
+                ParseError::with_message(
+                    nom::error::ErrorKind::Verify,
+                    &input[index..],
+                    "`]]>` is not allowed in character data".to_string(),
+                ),
+            )));
+        }
+        Ok((remaining, data.to_string()))
     }
 
     // [20] CData ::= (Char* - (Char* ']]>' Char*))
@@ -293,44 +497,72 @@ impl Document {
     // [19] CDStart ::= '<![CDATA['
     //[21] CDEnd ::= ']]>'
     fn parse_cdata_section(input: &str) -> IResult<&str, Document> {
-        map(
-            preceded(tag("<![CDATA["), Self::parse_cdata),
-            Document::CDATA,
+        context(
+            "[18] CDSect",
+            map(
+                preceded(tag("<![CDATA["), Self::parse_cdata),
+                Document::CDATA,
+            ),
         )(input)
     }
 
     // [39] element	::= EmptyElemTag | STag content ETag
-    fn parse_element(
-        input: &str,
+    fn parse_element<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
-    ) -> IResult<&str, Document> {
-        let (input, doc) = alt((
-            preceded(
-                Self::parse_multispace0, // this is not adhering strictly to the spec, but handles the case where there is whitespace before the start tag for human readability
-                map(
-                    |i| {
-                        Tag::parse_empty_element_tag(
-                            i,
-                            entity_references.clone(),
-                            EntitySource::None,
-                        )
-                    },
-                    Document::EmptyTag,
-                ),
-            ),
-            map(
-                tuple((
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Document> {
+        let (input, doc) = context(
+            "[39] element",
+            alt((
+                preceded(
                     Self::parse_multispace0, // this is not adhering strictly to the spec, but handles the case where there is whitespace before the start tag for human readability
-                    |i| Tag::parse_start_tag(i, entity_references.clone(), EntitySource::Internal),
-                    |i| Self::parse_content(i, &entity_references, EntitySource::Internal),
-                    Tag::parse_end_tag,
-                    Self::parse_multispace0, // this is not adhering strictly to the spec, but handles the case where there is whitespace after the start tag for human readability
-                )),
-                |(_whitespace1, start_tag, content, end_tag, _whitespace2)| {
-                    Document::Element(start_tag, Box::new(content), end_tag)
+                    map(
+                        |i| {
+                            Tag::parse_empty_element_tag(
+                                i,
+                                entity_references.clone(),
+                                EntitySource::Internal,
+                                budget,
+                            )
+                        },
+                        Document::EmptyTag,
+                    ),
+                ),
+                |i| {
+                    let (i, _whitespace1) = Self::parse_multispace0(i)?; // this is not adhering strictly to the spec, but handles the case where there is whitespace before the start tag for human readability
+                    let (i, start_tag) = Tag::parse_start_tag(
+                        i,
+                        entity_references.clone(),
+                        EntitySource::Internal,
+                        budget,
+                    )?;
+                    let (i, content) =
+                        Self::parse_content(i, &entity_references, EntitySource::Internal, budget)?;
+                    let (remaining, end_tag) = Tag::parse_end_tag(i).map_err(|e| {
+                        Self::explain_missing_end_tag(i, &entity_references, e, budget)
+                    })?;
+                    // [WFC: Element Type Match] The Name in an element's end-tag must match the element type in the start-tag
+                    if start_tag.name != end_tag.name {
+                        return Err(nom::Err::Failure(Error::ParseError(
+                            ParseError::with_cause(
+                                nom::error::ErrorKind::Verify,
+                                i,
+                                Error::MismatchedEndTag {
+                                    start: start_tag.name,
+                                    end: end_tag.name,
+                                },
+                            ),
+                        )));
+                    }
+                    let (remaining, _whitespace2) = Self::parse_multispace0(remaining)?; // this is not adhering strictly to the spec, but handles the case where there is whitespace after the start tag for human readability
+                    Ok((
+                        remaining,
+                        Document::Element(start_tag, Box::new(content), end_tag),
+                    ))
                 },
-            ),
-        ))(input)?;
+            )),
+        )(input)?;
 
         Ok((input, doc))
     }
@@ -340,25 +572,26 @@ impl Document {
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
     ) -> Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>> {
         if let Some(entities) = doc_type.extract_entities() {
+            let mut references = entity_references.borrow_mut();
+            // The parameter entities recorded while parsing the DTD are not referenced in content, where a general
+            // entity may have the same name
             for boxed_entity in &entities {
-                if let Subset::MarkupDecl(MarkupDeclaration::Entity(entity_decl)) = &**boxed_entity
+                if let Subset::MarkupDecl(MarkupDeclaration::Entity(EntityDecl::Parameter(decl))) =
+                    &**boxed_entity
                 {
-                    match entity_decl {
-                        EntityDecl::General(decl) | EntityDecl::Parameter(decl) => {
-                            match &decl.entity_def {
-                                EntityDefinition::EntityValue(value) => {
-                                    let mut references = entity_references.borrow_mut();
-                                    references
-                                        .entry((decl.name.clone(), EntitySource::Internal))
-                                        .or_insert(value.clone());
-                                }
-                                EntityDefinition::External { .. } => {
-                                    let mut references = entity_references.borrow_mut();
-
-                                    references.entry((decl.name.clone(), EntitySource::External));
-                                }
-                            }
-                        }
+                    if let EntityDefinition::EntityValue(_) = decl.entity_def {
+                        references.remove(&(decl.name.clone(), EntitySource::Internal));
+                    }
+                }
+            }
+            for boxed_entity in &entities {
+                if let Subset::MarkupDecl(MarkupDeclaration::Entity(EntityDecl::General(decl))) =
+                    &**boxed_entity
+                {
+                    if let EntityDefinition::EntityValue(value) = &decl.entity_def {
+                        references
+                            .entry((decl.name.clone(), EntitySource::Internal))
+                            .or_insert(value.clone());
                     }
                 }
             }
@@ -371,21 +604,36 @@ impl Document {
         }
     }
 
-    fn process_references(
-        entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
-    ) -> impl Fn(Vec<Reference>) -> Document {
-        move |references| {
-            let mut contents: Vec<String> = Vec::new();
-            for reference in references.into_iter() {
-                match reference.normalize_entity(entity_references.clone()) {
-                    EntityValue::Document(doc) => return doc,
-                    EntityValue::Value(val) => contents.push(val),
-                    _ => {}
-                }
+    // [67] Reference+ in content, expanded to their replacement
+    fn parse_references<'a>(
+        input: &'a str,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        entity_source: EntitySource,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Document> {
+        let (input, entity_values) = many1(|i| {
+            let (remaining, reference) = Reference::parse(i, entity_source.clone())?;
+            let entity_value = reference
+                .normalize_entity(entity_references.clone(), budget)
+                .map_err(|e| {
+                    nom::Err::Failure(Error::ParseError(ParseError::with_cause(
+                        nom::error::ErrorKind::Verify,
+                        i,
+                        e,
+                    )))
+                })?;
+            Ok((remaining, entity_value))
+        })(input)?;
+        let mut contents: Vec<String> = Vec::new();
+        for entity_value in entity_values {
+            match entity_value {
+                EntityValue::Document(doc) => return Ok((input, doc)),
+                EntityValue::Value(val) => contents.push(val),
+                _ => {}
             }
-            let content = contents.concat();
-            Document::Content(Some(content))
         }
+        let content = contents.concat();
+        Ok((input, Document::Content(Some(content))))
     }
 
     // TODO: add validation for elements using the ConditionalState in the ContentParticle from the prolog
@@ -394,6 +642,7 @@ impl Document {
         input: &'a str,
         entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
+        budget: &ExpansionBudget,
     ) -> IResult<&'a str, Document> {
         let (input, ((_whitespace, maybe_chardata), elements)) = tuple((
             pair(
@@ -402,17 +651,14 @@ impl Document {
             ),
             many0(alt((
                 pair(
-                    map(
-                        many1(|i| Reference::parse(i, entity_source.clone())),
-                        Self::process_references(entity_references.clone()),
-                    ),
+                    |i| Self::parse_references(i, entity_references, entity_source.clone(), budget),
                     pair(
                         Self::parse_multispace0, // this is not strictly adhering to the standard; however, it prevents the first Nested element from being Nested([Content(" ")])
                         opt(Self::parse_char_data),
                     ),
                 ),
                 pair(
-                    |i| Self::parse_element(i, entity_references.clone()),
+                    |i| Self::parse_element(i, entity_references.clone(), budget),
                     pair(
                         Self::parse_multispace0, // this is not strictly adhering to the standard; however, it prevents the first Nested element from being Nested([Content(" ")])
                         opt(Self::parse_char_data),
@@ -500,19 +746,21 @@ impl Document {
 
     // [15] Comment ::= '<!--' ((Char - '-') | ('-' (Char - '-')))* '-->'
     fn parse_comment(input: &str) -> IResult<&str, Document> {
-        map_res(
-            pair(tag("<!--"), many_till(Self::parse_char, tag("-->"))),
-            |(_open_comment, (comment_content, _close_comment))| {
-                let comment_string: String = comment_content.into_iter().collect();
-                if comment_string.contains("--") {
-                    Err(nom::Err::Failure(nom::error::Error::new(
-                        format!("Failed to parse comment: {comment_string}. Content contains '--'"),
-                        nom::error::ErrorKind::Verify,
-                    )))
-                } else {
-                    Ok(Document::Comment(comment_string))
-                }
-            },
+        context(
+            "[15] Comment",
+            map_res(
+                pair(tag("<!--"), many_till(Self::parse_char, tag("-->"))),
+                |(_open_comment, (comment_content, _close_comment))| {
+                    let comment_string: String = comment_content.into_iter().collect();
+                    if comment_string.contains("--") {
+                        Err(format!("Comment `{comment_string}` contains '--'"))
+                    } else if comment_string.ends_with('-') {
+                        Err(format!("Comment `{comment_string}` ends with '-'"))
+                    } else {
+                        Ok(Document::Comment(comment_string))
+                    }
+                },
+            ),
         )(input)
     }
 
@@ -526,12 +774,13 @@ impl Document {
         match (start_tag, end_tag, content, empty_tag) {
             (Some(start), Some(end), content, None) => {
                 if start.name != end.name {
-                    return Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
-                        format!(
-                            "{start:?} != {end:?}\ninput:{input:#?}",
-                            input = input.to_string()
-                        ),
+                    return Err(nom::Err::Error(Error::ParseError(ParseError::with_cause(
                         nom::error::ErrorKind::Verify,
+                        input,
+                        Error::MismatchedEndTag {
+                            start: start.name.clone(),
+                            end: end.name.clone(),
+                        },
                     ))));
                 }
 
@@ -541,12 +790,13 @@ impl Document {
             }
             (Some(start), Some(end), _, Some(empty_tag)) => {
                 if start.name != end.name {
-                    return Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
-                        format!(
-                            "{start:?} != {end:?}\ninput:{input:#?}",
-                            input = input.to_string()
-                        ),
+                    return Err(nom::Err::Error(Error::ParseError(ParseError::with_cause(
                         nom::error::ErrorKind::Verify,
+                        input,
+                        Error::MismatchedEndTag {
+                            start: start.name.clone(),
+                            end: end.name.clone(),
+                        },
                     ))));
                 }
 
@@ -557,12 +807,13 @@ impl Document {
             }
             (Some(_), None, Document::Element(start, inner_content, end), None) => {
                 if start.name != end.name {
-                    return Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
-                        format!(
-                            "{start:?} != {end:?}\ninput:{input:#?}",
-                            input = input.to_string()
-                        ),
+                    return Err(nom::Err::Error(Error::ParseError(ParseError::with_cause(
                         nom::error::ErrorKind::Verify,
+                        input,
+                        Error::MismatchedEndTag {
+                            start: start.name.clone(),
+                            end: end.name.clone(),
+                        },
                     ))));
                 }
 
@@ -572,12 +823,13 @@ impl Document {
             }
             (None, None, Document::Element(start, inner_content, end), None) => {
                 if start.name != end.name {
-                    return Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
-                        format!(
-                            "{start:?} != {end:?}\ninput:{input:#?}",
-                            input = input.to_string()
-                        ),
+                    return Err(nom::Err::Error(Error::ParseError(ParseError::with_cause(
                         nom::error::ErrorKind::Verify,
+                        input,
+                        Error::MismatchedEndTag {
+                            start: start.name.clone(),
+                            end: end.name.clone(),
+                        },
                     ))));
                 }
 
@@ -601,13 +853,13 @@ impl Document {
 
                 Ok((input, document))
             }
-            _ => Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
-                format!(
-                    "Error Constructing Document element with input: {:#?}",
-                    input.to_string()
+            _ => Err(nom::Err::Error(Error::ParseError(
+                ParseError::with_message(
+                    nom::error::ErrorKind::Verify,
+                    input,
+                    "Error Constructing Document element".to_string(),
                 ),
-                nom::error::ErrorKind::Verify,
-            )))),
+            ))),
         }
     }
 
@@ -617,13 +869,13 @@ impl Document {
         documents: Vec<Document>,
     ) -> IResult<&str, Document> {
         match documents.len() {
-            0 => Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
-                format!(
-                    "Error Constructing the Document. Parsed length is 0 with input: {:#?}",
-                    input.to_string()
+            0 => Err(nom::Err::Error(Error::ParseError(
+                ParseError::with_message(
+                    nom::error::ErrorKind::Verify,
+                    input,
+                    "Error Constructing the Document. Parsed length is 0".to_string(),
                 ),
-                nom::error::ErrorKind::Verify,
-            )))),
+            ))),
             1 => match prolog {
                 Some(prolog) => Ok((
                     input,
@@ -642,118 +894,56 @@ impl Document {
         }
     }
 
-    pub(crate) fn process_external_entity_file(
-        file_path: String,
+    pub(crate) fn process_external_entity(
+        external_id: &ExternalID,
         name: &Name,
         config: &Config,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
-    ) -> Result<Option<Vec<Subset>>, Box<dyn std::error::Error>> {
-        match File::open(file_path) {
-            Ok(mut file) => {
-                match parse_external_entity_file(&mut file, config, entity_references.clone()) {
-                    Ok((entities, subsets)) => {
-                        entities.iter().for_each(|entity| {
-                            entity_references
-                                .borrow_mut()
-                                .insert((name.clone(), EntitySource::External), entity.clone());
-                        });
-                        Ok(subsets)
-                    }
-                    _ => Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
-                        "Failed to match [entity] from `parse_external_entity_file`".to_string(),
-                        nom::error::ErrorKind::Fail,
-                    )))
-                    .into()),
-                }
-            }
-            Err(e) => Err(Error::from(e).into()),
-        }
+        budget: &ExpansionBudget,
+    ) -> Result<Option<Vec<Subset>>, Error> {
+        let (location, bytes) = external_id.load(config)?;
+        let (entities, subsets) =
+            parse_external_entity(&bytes, config, entity_references.clone(), budget).map_err(
+                |e| Error::EntityFile {
+                    path: location,
+                    source: Box::new(e),
+                },
+            )?;
+        entities.iter().for_each(|entity| {
+            entity_references
+                .borrow_mut()
+                .insert((name.clone(), EntitySource::External), entity.clone());
+        });
+        Ok(subsets)
     }
 
     fn get_external_entity_from_declaration(
         entity_declaration: EntityDecl,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         config: &Config,
-    ) -> Result<Option<Vec<Subset>>, Box<dyn std::error::Error>> {
-        if let Config {
-            external_parse_config:
-                ExternalEntityParseConfig {
-                    allow_ext_parse: true,
-                    base_directory,
-                    ..
-                },
-        } = &config
-        {
-            if let EntityDecl::Parameter(EntityDeclaration {
-                name,
-                entity_def:
-                    EntityDefinition::External {
-                        id: ExternalID::System(ent_file),
-                        ..
-                    },
-            })
-            | EntityDecl::General(EntityDeclaration {
-                name,
-                entity_def:
-                    EntityDefinition::External {
-                        id: ExternalID::System(ent_file),
-                        ..
-                    },
-            }) = &entity_declaration
-            {
-                let file_path = match base_directory {
-                    Some(base) => format!("{}/{}", base, ent_file),
-                    None => ent_file.clone(),
-                };
-                Self::process_external_entity_file(file_path, name, config, entity_references)
-            } else if let EntityDecl::General(EntityDeclaration {
-                name,
-                entity_def:
-                    EntityDefinition::External {
-                        id:
-                            ExternalID::Public {
-                                system_identifier, ..
-                            },
-                        ..
-                    },
-            }) = entity_declaration
-            {
-                if let ExternalID::System(system_identifier) = *system_identifier {
-                    let file_path = match base_directory {
-                        Some(base) => format!("{}/{}", base, system_identifier),
-                        None => system_identifier.clone(),
-                    };
-                    Document::process_external_entity_file(
-                        file_path,
-                        &name,
-                        config,
-                        entity_references,
-                    )
-                } else {
-                    Err(nom::Err::Error(nom::error::Error::new(
-                        "Failed to match *system_identifier",
-                        nom::error::ErrorKind::Fail,
-                    ))
-                    .into())
-                }
-            } else {
-                Err(nom::Err::Error(nom::error::Error::new(
-                    "Failed to match ExternalID::Public",
-                    nom::error::ErrorKind::Fail,
-                ))
-                .into())
-            }
-        } else {
-            Err(nom::Err::Error(nom::error::Error::new(
-                "Failed to match &entity_declaration",
-                nom::error::ErrorKind::Fail,
-            ))
-            .into())
-        }
+        budget: &ExpansionBudget,
+    ) -> Result<Option<Vec<Subset>>, Error> {
+        let (EntityDecl::Parameter(EntityDeclaration {
+            name,
+            entity_def: EntityDefinition::External { id, .. },
+        })
+        | EntityDecl::General(EntityDeclaration {
+            name,
+            entity_def: EntityDefinition::External { id, .. },
+        })) = &entity_declaration
+        else {
+            return Ok(None);
+        };
+        Self::process_external_entity(id, name, config, entity_references, budget)
     }
 
     /// The main interface for parsing the first element that matches criteria
     ///
+    /// `tag_name` is matched against the local part of element names, so `entry` also selects `<atom:entry>`, or
+    /// against the whole qualified name when it has a prefix. Names in Clark notation such as
+    /// `{http://www.w3.org/2005/Atom}entry` select elements by their expanded name, see
+    /// [`parse_element_by_expanded_name`](Self::parse_element_by_expanded_name).
+    ///
     /// See the [`parse_first_matching_element`](https://github.com/RodogInfinite/NomExML/blob/main/examples/parse_first_matching_element.rs) example for more information
     ///
     /// Run with `cargo run --example parse_first_matching_element`
@@ -768,50 +958,15 @@ impl Document {
         tag_name: &'a str,
         attributes: &Option<Vec<Attribute>>,
     ) -> IResult<&'a str, Document> {
-        let (input, _) = take_until(format!("<{}", tag_name).as_str())(input)?;
-        let entity_references = &Rc::new(RefCell::new(HashMap::new()));
-        let (input, doc) = alt((
-            preceded(
-                Self::parse_multispace0, // this is not adhering strictly to the spec, but handles the case where there is whitespace before the start tag for human readability
-                map(
-                    |i| {
-                        Tag::parse_empty_element_tag_by_name(
-                            i,
-                            tag_name,
-                            attributes,
-                            entity_references,
-                            EntitySource::None,
-                        )
-                    },
-                    Document::EmptyTag,
-                ),
-            ),
-            map(
-                tuple((
-                    Self::parse_multispace0, // this is not adhering strictly to the spec, but handles the case where there is whitespace before the start tag for human readability
-                    |i| {
-                        Tag::parse_start_tag_by_name(
-                            i,
-                            tag_name,
-                            attributes,
-                            entity_references,
-                            EntitySource::Internal,
-                        )
-                    },
-                    |i| Self::parse_content(i, entity_references, EntitySource::Internal),
-                    |i| Tag::parse_end_tag_by_name(i, tag_name),
-                    Self::parse_multispace0, // this is not adhering strictly to the spec, but handles the case where there is whitespace after the start tag for human readability
-                )),
-                |(_whitespace1, start_tag, content, end_tag, _whitespace2)| {
-                    Document::Element(start_tag, Box::new(content), end_tag)
-                },
-            ),
-        ))(input)?;
-        Ok((input, doc))
+        let (input, mut elements) =
+            Self::search_elements(input, &ElementSelector::new(tag_name), attributes, true)?;
+        Ok((input, elements.remove(0)))
     }
 
     /// The main interface for parsing many elements with the same tag name
     ///
+    /// `tag_name` is matched as in [`parse_element_by_tag_name`](Self::parse_element_by_tag_name).
+    ///
     /// See the [`parse_all_of_specific_tag`](https://github.com/RodogInfinite/NomExML/blob/main/examples/parse_all_of_specific_tag.rs) example for more information
     ///
     /// Run with `cargo run --example parse_all_of_specific_tag`
@@ -823,11 +978,173 @@ impl Document {
         attributes: &Option<Vec<Attribute>>,
     ) -> IResult<&'a str, Vec<Document>> {
         warnln!("parse_elements_by_tag_name will parse all elements with the tag name `{tag_name}` no matter the nesting level", );
-        warnln!("parse_element_by_tag_name currently only parses start tags without attributes, in this case`<{tag_name}>`");
 
-        many1(|i| Self::parse_element_by_tag_name(i, tag_name, attributes))(input)
+        Self::search_elements(input, &ElementSelector::new(tag_name), attributes, false)
     }
 
+    /// Parses the first element whose expanded name is `name`, whatever prefix it is written with
+    ///
+    /// The namespace declarations of the enclosing elements are tracked while the input is searched. The
+    /// declarations in scope on the element are added to its start tag, so that it keeps its namespaces when it is
+    /// used on its own, e.g. with [`resolve_namespaces`](Self::resolve_namespaces). Fails with the namespace errors
+    /// of [`resolve_namespaces`](Self::resolve_namespaces) for declarations that violate the reserved bindings.
+    ///
+    /// ```rust
+    /// use nom_xml::{namespaces::ExpandedName, Document};
+    ///
+    /// let xml = "<a:feed xmlns:a=\"http://www.w3.org/2005/Atom\"><entry/><a:entry>Hello</a:entry></a:feed>";
+    /// let atom_entry = ExpandedName::new(Some("http://www.w3.org/2005/Atom"), "entry");
+    /// let (_, entry) = Document::parse_element_by_expanded_name(xml, &atom_entry, &None).unwrap();
+    /// assert_eq!(
+    ///     entry.to_string(),
+    ///     "<a:entry xmlns:a=\"http://www.w3.org/2005/Atom\">Hello</a:entry>"
+    /// );
+    /// ```
+    pub fn parse_element_by_expanded_name<'a>(
+        input: &'a str,
+        name: &ExpandedName,
+        attributes: &Option<Vec<Attribute>>,
+    ) -> IResult<&'a str, Document> {
+        let selector = ElementSelector::ExpandedName(name.clone());
+        let (input, mut elements) = Self::search_elements(input, &selector, attributes, true)?;
+        Ok((input, elements.remove(0)))
+    }
+
+    /// Parses all elements whose expanded name is `name`, whatever prefix they are written with
+    ///
+    /// See [`parse_element_by_expanded_name`](Self::parse_element_by_expanded_name).
+    pub fn parse_elements_by_expanded_name<'a>(
+        input: &'a str,
+        name: &ExpandedName,
+        attributes: &Option<Vec<Attribute>>,
+    ) -> IResult<&'a str, Vec<Document>> {
+        let selector = ElementSelector::ExpandedName(name.clone());
+        Self::search_elements(input, &selector, attributes, false)
+    }
+
+    /// Parses the elements selected by `selector`, the first one only when `first_only` is set
+    ///
+    /// The tags of `input` are visited in order, so elements nested in a selected element are not selected.
+    fn search_elements<'a>(
+        input: &'a str,
+        selector: &ElementSelector,
+        attributes: &Option<Vec<Attribute>>,
+        first_only: bool,
+    ) -> IResult<&'a str, Vec<Document>> {
+        let entity_references = Rc::new(RefCell::new(HashMap::new()));
+        let budget = &ExpansionBudget::default();
+        let namespace_aware = matches!(selector, ElementSelector::ExpandedName(_));
+        let mut scopes = vec![namespaces::document_scope()];
+        let mut elements = vec![];
+        let mut remaining = input;
+        while let Some(offset) = remaining.find('<') {
+            remaining = &remaining[offset..];
+            if let Some(after_markup) = Self::skip_markup(remaining) {
+                if remaining.starts_with("</") && scopes.len() > 1 {
+                    scopes.pop();
+                }
+                remaining = after_markup;
+                continue;
+            }
+            // A malformed start tag is an error, as skipping it would leave its end tag without a scope to close
+            let (after_tag, tag) = alt((
+                |i| {
+                    Tag::parse_empty_element_tag(
+                        i,
+                        entity_references.clone(),
+                        EntitySource::Internal,
+                        budget,
+                    )
+                },
+                |i| {
+                    Tag::parse_start_tag(
+                        i,
+                        entity_references.clone(),
+                        EntitySource::Internal,
+                        budget,
+                    )
+                },
+            ))(remaining)?;
+
+            let mut scope = scopes.last().cloned().unwrap_or_default();
+            if namespace_aware {
+                namespaces::declare_namespaces(&tag, &mut scope).map_err(|e| {
+                    nom::Err::Failure(Error::ParseError(ParseError::with_cause(
+                        nom::error::ErrorKind::Verify,
+                        remaining,
+                        e,
+                    )))
+                })?;
+            }
+            let attributes_match = attributes.as_ref().is_none_or(|expected| {
+                tag.attributes
+                    .as_ref()
+                    .map_or(expected.is_empty(), |found| found == expected)
+            });
+            if attributes_match && selector.matches(&tag.name, &scope) {
+                let (after_element, mut element) =
+                    Self::parse_element(remaining, entity_references.clone(), budget)?;
+                if namespace_aware {
+                    namespaces::declare_inherited_namespaces(&mut element, &scope);
+                }
+                elements.push(element);
+                remaining = after_element;
+                if first_only {
+                    break;
+                }
+                continue;
+            }
+            if tag.state == TagState::Start {
+                scopes.push(scope);
+            }
+            remaining = after_tag;
+        }
+
+        if elements.is_empty() {
+            return Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
+                input.to_string(),
+                nom::error::ErrorKind::TakeUntil,
+            ))));
+        }
+        Ok((remaining, elements))
+    }
+
+    /// The input after the end tag, comment, CDATA section, processing instruction or declaration at the start of
+    /// `input`, `None` for start tags and empty-element tags
+    fn skip_markup(input: &str) -> Option<&str> {
+        let end = if input.starts_with("<!--") {
+            "-->"
+        } else if input.starts_with("<![CDATA[") {
+            "]]>"
+        } else if input.starts_with("<?") {
+            "?>"
+        } else if input.starts_with("</") {
+            ">"
+        } else if input.starts_with("<!") {
+            // Declarations such as the DOCTYPE end at the first `>` outside of literals and the internal subset
+            let mut quote = None;
+            let mut depth = 0;
+            for (index, c) in input.char_indices() {
+                match (quote, c) {
+                    (Some(q), c) if c == q => quote = None,
+                    (Some(_), _) => {}
+                    (None, '"' | '\'') => quote = Some(c),
+                    (None, '[') => depth += 1,
+                    (None, ']') => depth -= 1,
+                    (None, '>') if depth <= 0 => return Some(&input[index + 1..]),
+                    _ => {}
+                }
+            }
+            return Some("");
+        } else {
+            return None;
+        };
+        Some(
+            input
+                .find(end)
+                .map_or("", |index| &input[index + end.len()..]),
+        )
+    }
     #[cfg(feature = "experimental")]
     pub fn parse_element_from_pattern<'a>(
         input: &'a str,
@@ -835,6 +1152,7 @@ impl Document {
         pattern: &'a Pattern,
         strict: bool,
         entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        budget: &ExpansionBudget,
     ) -> IResult<&'a str, Document> {
         let (_, _pattern_doc) = Self::parse_element(pattern.xml, entity_references.clone())?;
 
@@ -910,9 +1228,33 @@ impl Document {
     // }
     /// The main interface for exracting content from the Document tree
     /// See the  [`extract_information_manual`](https://github.com/RodogInfinite/NomExML/blob/main/examples/extract_information_manual.rs) example for more information
-    pub fn iter_with_depth(&self, max_level: usize) -> DocumentIterator {
+    pub fn iter_with_depth(&self, max_level: usize) -> DocumentIterator<'_> {
         DocumentIterator::new(self, Some(max_level))
     }
+
+    /// The nodes that are children of the document node, with the comments and PIs of the prolog after the prolog
+    pub(crate) fn top_level(&self) -> Vec<&Document> {
+        fn flatten<'a>(document: &'a Document, top_level: &mut Vec<&'a Document>) {
+            match document {
+                Document::Nested(documents) => {
+                    for document in documents {
+                        flatten(document, top_level);
+                    }
+                }
+                Document::Prolog { misc, .. } => {
+                    top_level.push(document);
+                    for misc in misc.iter().flatten() {
+                        flatten(&misc.content, top_level);
+                    }
+                }
+                document => top_level.push(document),
+            }
+        }
+
+        let mut top_level = vec![];
+        flatten(self, &mut top_level);
+        top_level
+    }
 }
 
 impl<'a> IntoIterator for &'a Document {
@@ -925,17 +1267,17 @@ impl<'a> IntoIterator for &'a Document {
 }
 
 pub trait DocumentIteratorExt {
-    fn iter_with_depth(&self, max_level: usize) -> DocumentIterator;
+    fn iter_with_depth(&self, max_level: usize) -> DocumentIterator<'_>;
 }
 
 impl DocumentIteratorExt for Vec<Document> {
-    fn iter_with_depth(&self, max_level: usize) -> DocumentIterator {
+    fn iter_with_depth(&self, max_level: usize) -> DocumentIterator<'_> {
         DocumentIterator::new_from_slice(self, Some(max_level))
     }
 }
 
-impl<'a> DocumentIteratorExt for &'a [Document] {
-    fn iter_with_depth(&self, max_level: usize) -> DocumentIterator {
+impl DocumentIteratorExt for &[Document] {
+    fn iter_with_depth(&self, max_level: usize) -> DocumentIterator<'_> {
         DocumentIterator::new_from_slice(self, Some(max_level))
     }
 }
@@ -963,7 +1305,7 @@ impl<'a> Iterator for DocumentIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         while let Some((doc, level)) = self.stack.pop() {
-            if self.max_depth.map_or(true, |max| level < max) {
+            if self.max_depth.is_none_or(|max| level < max) {
                 match doc {
                     Document::Nested(docs) => {
                         for d in docs.iter().rev() {
@@ -1007,6 +1349,33 @@ impl<'a> Parse<'a> for ConditionalState {
     }
 }
 
+/// How [`Document::parse_element_by_tag_name`] and [`Document::parse_element_by_expanded_name`] select elements
+enum ElementSelector {
+    /// A local part, or a qualified name when it has a prefix
+    TagName(String),
+    ExpandedName(ExpandedName),
+}
+
+impl ElementSelector {
+    fn new(tag_name: &str) -> Self {
+        match ExpandedName::from_clark_notation(tag_name) {
+            Some(name) if tag_name.starts_with('{') => Self::ExpandedName(name),
+            _ => Self::TagName(tag_name.to_string()),
+        }
+    }
+
+    fn matches(&self, name: &Name, scope: &NamespaceScope) -> bool {
+        let name = namespaces::qualify(name);
+        match self {
+            Self::TagName(tag_name) => {
+                name.local_part == *tag_name || name.to_string() == *tag_name
+            }
+            Self::ExpandedName(expanded_name) => namespaces::expand(&name, scope, true)
+                .is_ok_and(|expanded| expanded == *expanded_name),
+        }
+    }
+}
+
 // TODO: migrate this to error.rs possibly combine with CustomError
 #[derive(Debug)]
 pub enum DocumentError {
@@ -1040,8 +1409,9 @@ impl<'a> Pattern<'a> {
     pub fn parse(
         &self,
         entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
-    ) -> Result<Pattern, Box<dyn std::error::Error>> {
-        let (_, doc) = Document::parse_element(self.xml, entity_references.clone())?;
+        budget: &ExpansionBudget,
+    ) -> Result<Pattern<'_>, Error> {
+        let (_, doc) = Document::parse_element(self.xml, entity_references.clone(), budget)?;
 
         Ok(Self { xml: self.xml, doc })
     }
@@ -1135,6 +1505,11 @@ impl DynamicEquality for Document {
     }
 }
 
+/// Fills the fields of a struct from the elements of a document
+///
+/// Implementations are usually generated with `#[derive(ExtractFields)]` from `nom-xml-derive`, whose generated
+/// methods return `Box<dyn std::error::Error>`, so the methods of this trait do too. [`Error`] converts into it
+/// with `?`.
 pub trait UpdateFields {
     fn update_fields(&mut self, doc: &Document) -> Result<(), Box<dyn std::error::Error>>
     where
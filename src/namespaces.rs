@@ -1,11 +1,28 @@
-use crate::{error::Error, parse::Parse, IResult, Name};
+// namespaces.rs
+
+//! Parsing of qualified names and resolution of their prefixes to namespace URIs
+
+use crate::{
+    attribute::{Attribute, AttributeValue, Prefix},
+    error::Error,
+    parse::Parse,
+    tag::Tag,
+    Document, IResult, Name,
+};
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{anychar, char},
+    bytes::complete::{tag, take_while},
+    character::complete::char,
     combinator::{map, verify},
     sequence::{pair, preceded, tuple},
 };
+use std::collections::HashMap;
+
+/// The namespace URI that the `xml` prefix is bound to by definition
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// The namespace URI of namespace declarations, which may not be bound to any prefix
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
 
 pub trait ParseNamespace<'a>: Parse<'a> + Sized {
     // [1] NSAttName ::=   	PrefixedAttName | DefaultAttName
@@ -36,8 +53,8 @@ pub trait ParseNamespace<'a>: Parse<'a> + Sized {
     fn parse_non_colonized_name(input: &str) -> IResult<&str, String> {
         map(
             pair(
-                Self::parse_name_start_char,
-                nom::bytes::complete::take_while1(|c: char| Self::is_name_char(c) && c != ':'),
+                Self::parse_non_colonized_name_start_char,
+                take_while(|c: char| Self::is_name_char(c) && c != ':'),
             ),
             |(start_char, rest_chars)| {
                 let mut name = start_char.to_string();
@@ -54,7 +71,7 @@ pub trait ParseNamespace<'a>: Parse<'a> + Sized {
 
     // [6] NCNameStartChar ::= NCName - ( Char Char Char* ) /* The first letter of an NCName */
     fn parse_non_colonized_name_start_char(input: &str) -> IResult<&str, char> {
-        verify(anychar, |c| *c != ':')(input)
+        verify(Self::parse_name_start_char, |c| *c != ':')(input)
     }
 
     // [7] QName ::= PrefixedName | UnprefixedName
@@ -88,3 +105,387 @@ pub trait ParseNamespace<'a>: Parse<'a> + Sized {
     // [10] Prefix ::= NCName
     // [11] LocalPart ::= NCName
 }
+
+/// A name with its prefix replaced by the URI of the namespace it is bound to
+///
+/// Names written with different prefixes that are bound to the same URI have equal expanded names.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExpandedName {
+    /// The namespace URI, `None` for unprefixed attributes and for unprefixed elements outside of a default namespace
+    pub namespace: Option<String>,
+    pub local_part: String,
+}
+
+impl ExpandedName {
+    pub fn new(namespace: Option<&str>, local_part: &str) -> Self {
+        Self {
+            namespace: namespace.map(|namespace| namespace.to_string()),
+            local_part: local_part.to_string(),
+        }
+    }
+
+    /// Parses a name in Clark notation, `{namespace}local_part` or `local_part` for names in no namespace
+    ///
+    /// ```rust
+    /// use nom_xml::namespaces::ExpandedName;
+    ///
+    /// assert_eq!(
+    ///     ExpandedName::from_clark_notation("{http://www.w3.org/2005/Atom}entry"),
+    ///     Some(ExpandedName::new(Some("http://www.w3.org/2005/Atom"), "entry"))
+    /// );
+    /// assert_eq!(ExpandedName::from_clark_notation("{unclosed"), None);
+    /// ```
+    pub fn from_clark_notation(name: &str) -> Option<Self> {
+        let (namespace, local_part) = match name.strip_prefix('{') {
+            Some(name) => {
+                let (namespace, local_part) = name.split_once('}')?;
+                ((!namespace.is_empty()).then_some(namespace), local_part)
+            }
+            None => (None, name),
+        };
+        if local_part.is_empty() || local_part.contains(['{', '}', ':']) {
+            return None;
+        }
+        Some(Self::new(namespace, local_part))
+    }
+
+    /// Whether this is the name `local_part` in the namespace `namespace`
+    pub fn matches(&self, namespace: Option<&str>, local_part: &str) -> bool {
+        self.namespace.as_deref() == namespace && self.local_part == local_part
+    }
+}
+
+/// An attribute of a [`NamespacedElement`], namespace declarations are not included
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamespacedAttribute {
+    /// The qualified name as it is written in the document
+    pub name: Name,
+    pub expanded_name: ExpandedName,
+    pub value: String,
+}
+
+/// An element with the prefixes of its name and attributes resolved, see [`Document::resolve_namespaces`]
+#[derive(Clone, Debug)]
+pub struct NamespacedElement<'a> {
+    /// The [`Document::Element`] or [`Document::EmptyTag`] that was resolved
+    pub element: &'a Document,
+    /// The qualified name as it is written in the document
+    pub name: Name,
+    pub expanded_name: ExpandedName,
+    pub attributes: Vec<NamespacedAttribute>,
+    pub children: Vec<NamespacedElement<'a>>,
+    scope: NamespaceScope,
+}
+
+/// The namespace bindings in scope, with the default namespace under `None`
+pub(crate) type NamespaceScope = HashMap<Option<String>, String>;
+
+impl<'a> NamespacedElement<'a> {
+    /// The URI that `prefix` is bound to on this element, `None` stands for the default namespace
+    pub fn namespace_uri(&self, prefix: Option<&str>) -> Option<&str> {
+        self.scope
+            .get(&prefix.map(|prefix| prefix.to_string()))
+            .map(String::as_str)
+    }
+
+    /// The namespace bindings in scope on this element as (prefix, URI) pairs sorted by prefix, including the
+    /// binding of `xml`
+    pub fn in_scope_namespaces(&self) -> Vec<(Option<&str>, &str)> {
+        let mut bindings: Vec<_> = self
+            .scope
+            .iter()
+            .map(|(prefix, uri)| (prefix.as_deref(), uri.as_str()))
+            .collect();
+        bindings.sort();
+        bindings
+    }
+
+    /// The value of the attribute with the expanded name `{namespace}local_part`
+    pub fn attribute(&self, namespace: Option<&str>, local_part: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.expanded_name.matches(namespace, local_part))
+            .map(|attribute| attribute.value.as_str())
+    }
+
+    /// The descendant elements with the expanded name `{namespace}local_part` in document order, whatever prefix
+    /// they are written with
+    pub fn descendants_by_name(
+        &self,
+        namespace: Option<&str>,
+        local_part: &str,
+    ) -> Vec<&NamespacedElement<'a>> {
+        let mut descendants = vec![];
+        self.collect_by_name(namespace, local_part, &mut descendants);
+        descendants
+    }
+
+    fn collect_by_name<'b>(
+        &'b self,
+        namespace: Option<&str>,
+        local_part: &str,
+        descendants: &mut Vec<&'b NamespacedElement<'a>>,
+    ) {
+        for child in &self.children {
+            if child.expanded_name.matches(namespace, local_part) {
+                descendants.push(child);
+            }
+            child.collect_by_name(namespace, local_part, descendants);
+        }
+    }
+}
+
+impl Document {
+    /// Resolves the prefixes of the names of the root element and its descendants to the namespaces in scope
+    ///
+    /// `xmlns` and `xmlns:prefix` attributes declare namespaces for the element they are specified on and its
+    /// content. Unprefixed elements are in the default namespace, unprefixed attributes in no namespace. Fails with
+    /// [`Error::UndeclaredNamespacePrefix`] when a prefix is not bound, with [`Error::ReservedNamespaceBinding`]
+    /// when `xml` is bound to another URI or `xmlns` is declared, with [`Error::EmptyNamespaceUri`] when a prefix is
+    /// undeclared and with [`Error::DuplicateNamespacedAttribute`] when two attributes have the same expanded name.
+    /// Returns `None` for documents without an element.
+    ///
+    /// Set [`Config::resolve_namespaces`](crate::config::Config::resolve_namespaces) to check the namespaces of
+    /// documents as they are parsed.
+    ///
+    /// ```rust
+    /// use nom_xml::{config::Config, namespaces::ExpandedName, parse::Parse, Document};
+    ///
+    /// let xml = "<feed xmlns=\"http://www.w3.org/2005/Atom\" xmlns:a=\"http://www.w3.org/2005/Atom\"><a:title>News</a:title></feed>";
+    /// let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+    /// let feed = doc.resolve_namespaces().unwrap().unwrap();
+    /// assert_eq!(
+    ///     feed.expanded_name,
+    ///     ExpandedName::new(Some("http://www.w3.org/2005/Atom"), "feed")
+    /// );
+    /// let titles = feed.descendants_by_name(Some("http://www.w3.org/2005/Atom"), "title");
+    /// assert_eq!(titles[0].name.to_string(), "a:title");
+    /// ```
+    pub fn resolve_namespaces(&self) -> Result<Option<NamespacedElement<'_>>, Error> {
+        self.root_element()
+            .map(|root| resolve_element(root, &document_scope()))
+            .transpose()
+    }
+}
+
+/// The namespaces in scope outside of the root element, where only `xml` is bound
+pub(crate) fn document_scope() -> NamespaceScope {
+    NamespaceScope::from([(Some("xml".to_string()), XML_NAMESPACE.to_string())])
+}
+
+fn resolve_element<'a>(
+    document: &'a Document,
+    parent_scope: &NamespaceScope,
+) -> Result<NamespacedElement<'a>, Error> {
+    let (tag, content) = match document {
+        Document::Element(start_tag, content, _) => (start_tag, Some(content)),
+        Document::EmptyTag(tag) => (tag, None),
+        _ => unreachable!("only elements are resolved"),
+    };
+
+    let mut scope = parent_scope.clone();
+    declare_namespaces(tag, &mut scope)?;
+    let mut attributes = vec![];
+    for attribute in tag.attributes.iter().flatten() {
+        if let Attribute::Instance { name, value } = attribute {
+            if namespace_declaration(attribute).is_none() {
+                attributes.push((qualify(name), text(value)));
+            }
+        }
+    }
+
+    let name = qualify(&tag.name);
+    let expanded_name = expand(&name, &scope, true)?;
+    let attributes = attributes
+        .into_iter()
+        .map(|(name, value)| {
+            Ok(NamespacedAttribute {
+                expanded_name: expand(&name, &scope, false)?,
+                name,
+                value,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    // [NSC: Attributes Unique]
+    for (index, attribute) in attributes.iter().enumerate() {
+        if attributes[..index]
+            .iter()
+            .any(|other| other.expanded_name == attribute.expanded_name)
+        {
+            return Err(Error::DuplicateNamespacedAttribute {
+                tag: name,
+                attribute: attribute.expanded_name.clone(),
+            });
+        }
+    }
+
+    let mut children = vec![];
+    if let Some(content) = content {
+        resolve_children(content, &scope, &mut children)?;
+    }
+    Ok(NamespacedElement {
+        element: document,
+        name,
+        expanded_name,
+        attributes,
+        children,
+        scope,
+    })
+}
+
+fn resolve_children<'a>(
+    content: &'a Document,
+    scope: &NamespaceScope,
+    children: &mut Vec<NamespacedElement<'a>>,
+) -> Result<(), Error> {
+    match content {
+        Document::Element(..) | Document::EmptyTag(_) => {
+            children.push(resolve_element(content, scope)?)
+        }
+        Document::Nested(documents) => {
+            for document in documents {
+                resolve_children(document, scope, children)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Adds the namespace declarations of `tag` to `scope`
+pub(crate) fn declare_namespaces(tag: &Tag, scope: &mut NamespaceScope) -> Result<(), Error> {
+    for attribute in tag.attributes.iter().flatten() {
+        if let Some((prefix, uri)) = namespace_declaration(attribute) {
+            declare(scope, prefix, uri)?;
+        }
+    }
+    Ok(())
+}
+
+/// Adds declarations for the namespaces in `scope` that the start tag of `element` does not declare itself, so that
+/// an element taken out of its document keeps the namespaces of its ancestors
+pub(crate) fn declare_inherited_namespaces(element: &mut Document, scope: &NamespaceScope) {
+    let tag = match element {
+        Document::Element(start_tag, ..) => start_tag,
+        Document::EmptyTag(tag) => tag,
+        _ => return,
+    };
+    let declared: Vec<_> = tag
+        .attributes
+        .iter()
+        .flatten()
+        .filter_map(namespace_declaration)
+        .map(|(prefix, _uri)| prefix)
+        .collect();
+    let mut inherited: Vec<_> = scope
+        .iter()
+        .filter(|(prefix, _uri)| prefix.as_deref() != Some("xml") && !declared.contains(prefix))
+        .collect();
+    if inherited.is_empty() {
+        return;
+    }
+    inherited.sort();
+    tag.attributes
+        .get_or_insert_with(Vec::new)
+        .extend(
+            inherited
+                .into_iter()
+                .map(|(prefix, uri)| Attribute::Namespace {
+                    prefix: match prefix {
+                        Some(prefix) => Prefix::Prefix(prefix.clone()),
+                        None => Prefix::Default,
+                    },
+                    uri: AttributeValue::Value(uri.clone()),
+                }),
+        );
+}
+
+/// The prefix and URI declared by a namespace declaration, `None` for other attributes
+///
+/// Attributes of empty-element tags are parsed without namespaces, so their declarations are recognized by name.
+pub(crate) fn namespace_declaration(attribute: &Attribute) -> Option<(Option<String>, String)> {
+    match attribute {
+        Attribute::Namespace {
+            prefix: Prefix::Default,
+            uri,
+        } => Some((None, text(uri))),
+        Attribute::Namespace {
+            prefix: Prefix::Prefix(prefix),
+            uri,
+        } => Some((Some(prefix.clone()), text(uri))),
+        Attribute::Instance { name, value } => {
+            let name = qualify(name);
+            match (name.prefix.as_deref(), name.local_part.as_str()) {
+                (None, "xmlns") => Some((None, text(value))),
+                (Some("xmlns"), prefix) => Some((Some(prefix.to_string()), text(value))),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// Namespaces in XML 1.0 (Third Edition) §3 [NSC: Reserved Prefixes and Namespace Names], [NSC: No Prefix Undeclaring]
+fn declare(scope: &mut NamespaceScope, prefix: Option<String>, uri: String) -> Result<(), Error> {
+    let reserved = match (prefix.as_deref(), uri.as_str()) {
+        (Some("xml"), uri) => uri != XML_NAMESPACE,
+        (Some("xmlns"), _) => true,
+        (_, XML_NAMESPACE | XMLNS_NAMESPACE) => true,
+        _ => false,
+    };
+    if reserved {
+        return Err(Error::ReservedNamespaceBinding { prefix, uri });
+    }
+    match prefix {
+        Some(prefix) if uri.is_empty() => return Err(Error::EmptyNamespaceUri { prefix }),
+        None if uri.is_empty() => {
+            scope.remove(&None);
+        }
+        prefix => {
+            scope.insert(prefix, uri);
+        }
+    }
+    Ok(())
+}
+
+// [NSC: Prefix Declared]
+pub(crate) fn expand(
+    name: &Name,
+    scope: &NamespaceScope,
+    element: bool,
+) -> Result<ExpandedName, Error> {
+    let namespace = match &name.prefix {
+        Some(prefix) => Some(
+            scope
+                .get(&Some(prefix.clone()))
+                .cloned()
+                .ok_or_else(|| Error::UndeclaredNamespacePrefix(name.clone()))?,
+        ),
+        None if element => scope.get(&None).cloned(),
+        None => None,
+    };
+    Ok(ExpandedName {
+        namespace,
+        local_part: name.local_part.clone(),
+    })
+}
+
+/// Splits the prefix off names that were parsed without namespaces
+pub(crate) fn qualify(name: &Name) -> Name {
+    match (&name.prefix, name.local_part.split_once(':')) {
+        (None, Some((prefix, local_part))) if !prefix.is_empty() && !local_part.is_empty() => {
+            Name::new(Some(prefix), local_part)
+        }
+        _ => name.clone(),
+    }
+}
+
+/// The text of an attribute value, with its references as written
+pub(crate) fn text(value: &AttributeValue) -> String {
+    match value {
+        AttributeValue::Value(value) => value.clone(),
+        AttributeValue::Values(values) => values.iter().map(text).collect(),
+        AttributeValue::Reference(reference) => reference.to_string(),
+        AttributeValue::EmptyExternalReference => String::new(),
+    }
+}
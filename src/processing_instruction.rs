@@ -4,6 +4,7 @@ use crate::{parse::Parse, IResult, Name};
 use nom::{
     bytes::complete::tag,
     combinator::{map, map_res, opt, peek},
+    error::context,
     multi::many_till,
     sequence::{preceded, tuple},
 };
@@ -20,20 +21,24 @@ impl<'a> Parse<'a> for ProcessingInstruction {
 
     // [16] PI ::= '<?' PITarget (S (Char* - (Char* '?>' Char*)))? '?>'
     fn parse(input: &'a str, _args: Self::Args) -> Self::Output {
-        map(
-            tuple((
-                tag("<?"),
-                Self::parse_target,
-                opt(preceded(
-                    Self::parse_multispace1,
-                    many_till(Self::parse_char, peek(tag("?>"))),
+        context(
+            "[16] PI",
+            map(
+                tuple((
+                    tag("<?"),
+                    Self::parse_target,
+                    opt(preceded(
+                        Self::parse_multispace1,
+                        many_till(Self::parse_char, peek(tag("?>"))),
+                    )),
+                    tag("?>"),
                 )),
-                tag("?>"),
-            )),
-            |(_open_tag, target, data_chars_opt, _close_tag)| {
-                let data = data_chars_opt.map(|(chars, _)| chars.into_iter().collect::<String>());
-                ProcessingInstruction { target, data }
-            },
+                |(_open_tag, target, data_chars_opt, _close_tag)| {
+                    let data =
+                        data_chars_opt.map(|(chars, _)| chars.into_iter().collect::<String>());
+                    ProcessingInstruction { target, data }
+                },
+            ),
         )(input)
     }
 }
@@ -1,10 +1,12 @@
 use crate::{
+    attribute::Attribute,
+    error::{Error, ParseError},
     namespaces::ParseNamespace,
     parse::Parse,
     prolog::subset::{
         entity::{
             entity_declaration::EntityDecl, entity_definition::EntityDefinition,
-            entity_value::EntityValue,
+            entity_value::EntityValue, expansion::ExpansionBudget,
         },
         Subset,
     },
@@ -13,6 +15,7 @@ use crate::{
 use nom::{
     bytes::complete::tag,
     combinator::opt,
+    error::context,
     sequence::{delimited, pair, preceded, tuple},
 };
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
@@ -33,82 +36,84 @@ impl<'a> Parse<'a> for DocType {
     type Args = (
         Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         &'a Config,
+        ExpansionBudget,
     );
 
     type Output = IResult<&'a str, Self>;
 
     fn parse(input: &'a str, args: Self::Args) -> Self::Output {
-        let (entity_references, config) = args;
+        let (entity_references, config, budget) = args;
+        Self::parse_doctype(input, entity_references, config, &budget)
+    }
+}
+
+impl DocType {
+    // [28] doctypedecl ::= '<!DOCTYPE' S Name (S ExternalID)? S? ('[' intSubset ']' S?)? '>'
+    fn parse_doctype<'a>(
+        input: &'a str,
+        entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        config: &'a Config,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Self> {
         let mut merged_subsets = vec![];
-        let (input, (_open_tag, _whitespace1, name, external_id, _whitespace2)) = tuple((
-            tag("<!DOCTYPE"),
-            Self::parse_multispace1,
-            Self::parse_name,
-            opt(preceded(Self::parse_multispace1, |i| {
-                ExternalID::parse(i, ())
-            })),
-            Self::parse_multispace0,
-        ))(input)?;
+        let (input, (_open_tag, _whitespace1, name, external_id, _whitespace2)) = context(
+            "[28] doctypedecl",
+            tuple((
+                tag("<!DOCTYPE"),
+                Self::parse_multispace1,
+                Self::parse_name,
+                opt(preceded(Self::parse_multispace1, |i| {
+                    ExternalID::parse(i, ())
+                })),
+                Self::parse_multispace0,
+            )),
+        )(input)?;
+        // The declarations of a document with only an internal subset are all known, unless the subset references
+        // parameter entities
+        budget.require_entity_declarations(external_id.is_none());
         if let Some(external_id) = external_id {
-            let mut external_subsets = match external_id.get_external_entity_from_id(
+            // The internal subset is read first so that its declarations take precedence, e.g. parameter entities
+            // that switch the conditional sections of the external subset
+            let (input, (subset, _whitespace3)) = tuple((
+                opt(delimited(
+                    pair(tag("["), Self::parse_multispace0),
+                    |i| {
+                        Subset::parse(
+                            i,
+                            (
+                                entity_references.clone(),
+                                config,
+                                EntitySource::External,
+                                budget.clone(),
+                            ),
+                        )
+                    },
+                    pair(Self::parse_multispace0, tag("]")),
+                )),
+                Self::parse_multispace0,
+            ))(input)?;
+            let external_subsets = match external_id.get_external_entity_from_id(
                 input,
                 entity_references.clone(),
                 config,
+                budget,
             ) {
-                Ok(subsets) => subsets,
+                Ok(external_subsets) => external_subsets,
+                Err(e @ Error::ExternalEntityNotAllowed { .. }) => {
+                    return Err(nom::Err::Failure(e))
+                }
+                Err(e) if e.is_malformed_entity_file() => return Err(nom::Err::Failure(e)),
+                // External subsets that are denied or cannot be read are skipped
                 Err(_) => None,
             };
-            let (input, (mut subset, _whitespace3, _close_tag, _whitespace4)) =
-                tuple((
-                    opt(delimited(
-                        pair(tag("["), Self::parse_multispace0),
-                        |i| {
-                            Subset::parse(
-                                i,
-                                (entity_references.clone(), config, EntitySource::External),
-                            )
-                        },
-                        pair(Self::parse_multispace0, tag("]")),
-                    )),
-                    Self::parse_multispace0,
-                    tag(">"),
-                    Self::parse_multispace0,
-                ))(input)?;
-            if let Some(subset) = &mut subset {
-                subset.iter_mut().for_each(|subset| match subset {
-                    Subset::MarkupDecl(MarkupDeclaration::Entity(EntityDecl::General(
-                        entity_decl,
-                    )))
-                    | Subset::MarkupDecl(MarkupDeclaration::Entity(EntityDecl::Parameter(
-                        entity_decl,
-                    ))) => {
-                        if let EntityDefinition::EntityValue(EntityValue::Reference(ref_val)) =
-                            &mut entity_decl.entity_def
-                        {
-                            ref_val.normalize_entity(entity_references.clone());
-                        }
-                    }
-
-                    _ => {}
-                });
+            let (input, (_close_tag, _whitespace4)) =
+                tuple((tag(">"), Self::parse_multispace0))(input)?;
+            if let Some(subset) = &subset {
+                Self::expand_entity_references(input, subset, &entity_references, budget)?;
                 merged_subsets.extend(subset.clone());
             }
-            if let Some(subset) = &mut external_subsets {
-                subset.iter_mut().for_each(|subset| match subset {
-                    Subset::MarkupDecl(MarkupDeclaration::Entity(EntityDecl::General(
-                        entity_decl,
-                    )))
-                    | Subset::MarkupDecl(MarkupDeclaration::Entity(EntityDecl::Parameter(
-                        entity_decl,
-                    ))) => {
-                        if let EntityDefinition::EntityValue(EntityValue::Reference(ref_val)) =
-                            &mut entity_decl.entity_def
-                        {
-                            ref_val.normalize_entity(entity_references.clone());
-                        }
-                    }
-                    _ => {}
-                });
+            if let Some(subset) = &external_subsets {
+                Self::expand_entity_references(input, subset, &entity_references, budget)?;
                 merged_subsets.extend(subset.clone());
             }
             // we need to create a subsets that merges external subsets with subset
@@ -132,42 +137,28 @@ impl<'a> Parse<'a> for DocType {
                 ))
             }
         } else {
-            let (input, (mut subset, _whitespace3, _close_tag, _whitespace4)) =
-                tuple((
-                    opt(delimited(
-                        pair(tag("["), Self::parse_multispace0),
-                        |i| {
-                            Subset::parse(
-                                i,
-                                (entity_references.clone(), config, EntitySource::Internal),
-                            )
-                        },
-                        pair(Self::parse_multispace0, tag("]")),
-                    )),
-                    Self::parse_multispace0,
-                    tag(">"),
-                    Self::parse_multispace0,
-                ))(input)?;
-            if let Some(subset) = &mut subset {
-                subset.iter_mut().for_each(|subset| {
-                    match subset {
-                        //match internal_subset {
-                        Subset::MarkupDecl(MarkupDeclaration::Entity(EntityDecl::General(
-                            entity_decl,
-                        )))
-                        | Subset::MarkupDecl(MarkupDeclaration::Entity(EntityDecl::Parameter(
-                            entity_decl,
-                        ))) => {
-                            if let EntityDefinition::EntityValue(EntityValue::Reference(ref_val)) =
-                                &mut entity_decl.entity_def
-                            {
-                                ref_val.normalize_entity(entity_references.clone());
-                            }
-                        }
-
-                        _ => {}
-                    }
-                });
+            let (input, (subset, _whitespace3, _close_tag, _whitespace4)) = tuple((
+                opt(delimited(
+                    pair(tag("["), Self::parse_multispace0),
+                    |i| {
+                        Subset::parse(
+                            i,
+                            (
+                                entity_references.clone(),
+                                config,
+                                EntitySource::Internal,
+                                budget.clone(),
+                            ),
+                        )
+                    },
+                    pair(Self::parse_multispace0, tag("]")),
+                )),
+                Self::parse_multispace0,
+                tag(">"),
+                Self::parse_multispace0,
+            ))(input)?;
+            if let Some(subset) = &subset {
+                Self::expand_entity_references(input, subset, &entity_references, budget)?;
             }
             Ok((
                 input,
@@ -203,6 +194,67 @@ impl DocType {
             Some(entities)
         }
     }
+
+    /// The attribute definitions of the subsets by the qualified name of their element type, including those of
+    /// the external subset and of parameter entities
+    ///
+    /// Definitions from several attribute-list declarations of an element type are kept in the order they were
+    /// declared, so the first definition of an attribute, which is the binding one, comes first.
+    pub fn attribute_definitions(&self) -> HashMap<String, Vec<Attribute>> {
+        fn collect(subset: &Subset, definitions: &mut HashMap<String, Vec<Attribute>>) {
+            match subset {
+                Subset::MarkupDecl(MarkupDeclaration::AttList {
+                    name,
+                    att_defs: Some(att_defs),
+                }) => definitions
+                    .entry(name.to_string())
+                    .or_default()
+                    .extend(att_defs.iter().cloned()),
+                Subset::DeclSep {
+                    expansion: Some(expansion),
+                    ..
+                } => collect(expansion, definitions),
+                _ => {}
+            }
+        }
+
+        let mut definitions = HashMap::new();
+        for subset in self.subset.iter().flatten() {
+            collect(subset, &mut definitions);
+        }
+        definitions
+    }
+
+    // Entities whose value is a reference are expanded once the subset is parsed, so recursive entities are
+    // reported even if they are never referenced
+    fn expand_entity_references(
+        input: &str,
+        subsets: &[Subset],
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        budget: &ExpansionBudget,
+    ) -> Result<(), nom::Err<Error>> {
+        for subset in subsets {
+            if let Subset::MarkupDecl(MarkupDeclaration::Entity(
+                EntityDecl::General(entity_decl) | EntityDecl::Parameter(entity_decl),
+            )) = subset
+            {
+                if let EntityDefinition::EntityValue(EntityValue::Reference(ref_val)) =
+                    &entity_decl.entity_def
+                {
+                    ref_val
+                        .normalize_entity(entity_references.clone(), budget)
+                        .map_err(|e| {
+                            nom::Err::Failure(Error::ParseError(ParseError::with_cause(
+                                nom::error::ErrorKind::Verify,
+                                input,
+                                e,
+                            )))
+                        })?;
+                }
+            }
+        }
+        Ok(())
+    }
     //TODO: figure out how to integrate this or remove
     // fn _parse_qualified_doctype(
     //     input: &str,
@@ -1,8 +1,8 @@
-use std::{cell::RefCell, collections::HashMap, fs::File, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use crate::{
-    error::Error, io::parse_external_entity_file, parse::Parse, prolog::subset::Subset, Config,
-    ExternalEntityParseConfig, IResult, Name,
+    error::Error, io::parse_external_entity, parse::Parse, prolog::subset::Subset, Config, IResult,
+    Name,
 };
 use nom::{
     branch::alt,
@@ -13,7 +13,7 @@ use nom::{
 
 use super::{
     id::ID,
-    subset::entity::{entity_value::EntityValue, EntitySource},
+    subset::entity::{entity_value::EntityValue, expansion::ExpansionBudget, EntitySource},
 };
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -75,66 +75,51 @@ impl ExternalID {
         )(input)
     }
 
+    /// The public identifier of a `PUBLIC` external identifier
+    pub fn public_id(&self) -> Option<&str> {
+        match self {
+            ExternalID::System(_) => None,
+            ExternalID::Public { pubid, .. } => Some(pubid),
+        }
+    }
+
+    /// The system identifier, which `PUBLIC` external identifiers also have
+    pub fn system_id(&self) -> Option<&str> {
+        match self {
+            ExternalID::System(system_identifier) => Some(system_identifier),
+            ExternalID::Public {
+                system_identifier, ..
+            } => system_identifier.system_id(),
+        }
+    }
+
+    // Reads the entity through the `ExternalEntityPolicy`, returning where it was read from and its bytes
+    pub(crate) fn load(&self, config: &Config) -> Result<(String, Vec<u8>), Error> {
+        let Some(system_identifier) = self.system_id() else {
+            return Err(Error::UnsupportedExternalId(self.clone()));
+        };
+        config
+            .external_parse_config
+            .load(self.public_id(), system_identifier)
+    }
+
     pub fn get_external_entity_from_id(
         &self,
         input: &str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         config: &Config,
-    ) -> Result<Option<Vec<Subset>>, Box<dyn std::error::Error>> {
-        if let Config {
-            external_parse_config:
-                ExternalEntityParseConfig {
-                    allow_ext_parse: true,
-                    base_directory,
-                    ..
+        budget: &ExpansionBudget,
+    ) -> Result<Option<Vec<Subset>>, Error> {
+        let (location, bytes) = self.load(config)?;
+        let (_entities, subsets) =
+            parse_external_entity(&bytes, config, entity_references.clone(), budget).map_err(
+                |e| Error::EntityFile {
+                    path: location,
+                    source: Box::new(e),
                 },
-        } = &config
-        {
-            if let ExternalID::System(system_identifier) = self {
-                let file_path = base_directory.as_ref().map_or_else(
-                    || system_identifier.clone(),
-                    |base| format!("{}/{}", base, system_identifier),
-                );
-
-                match File::open(file_path) {
-                    Ok(mut file) => {
-                        match parse_external_entity_file(
-                            &mut file,
-                            config,
-                            entity_references.clone(),
-                        ) {
-                            Ok((_entities, subsets)) => {
-                                let (_input, (_whitespace1, _close_tag, _whitespace2)) =
-                                    tuple((
-                                        Self::parse_multispace0,
-                                        tag(">"),
-                                        Self::parse_multispace0,
-                                    ))(input)?;
-                                Ok(subsets)
-                            }
-                            _ => Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
-                                "Failed to match [entity] from `parse_external_entity_file`"
-                                    .to_string(),
-                                nom::error::ErrorKind::Fail,
-                            )))
-                            .into()),
-                        }
-                    }
-                    Err(e) => Err(Error::from(e).into()),
-                }
-            } else {
-                Err(nom::Err::Error(nom::error::Error::new(
-                    "Only ExternalID::System is supported for direct parsing",
-                    nom::error::ErrorKind::Fail,
-                ))
-                .into())
-            }
-        } else {
-            Err(nom::Err::Error(nom::error::Error::new(
-                "External parsing is disabled in the configuration",
-                nom::error::ErrorKind::Fail,
-            ))
-            .into())
-        }
+            )?;
+        let (_input, (_whitespace1, _close_tag, _whitespace2)) =
+            tuple((Self::parse_multispace0, tag(">"), Self::parse_multispace0))(input)?;
+        Ok(subsets)
     }
 }
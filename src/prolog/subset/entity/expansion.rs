@@ -0,0 +1,218 @@
+// expansion.rs
+
+use super::{
+    entity_declaration::EntityDeclaration, entity_definition::EntityDefinition,
+    entity_value::EntityValue,
+};
+use crate::{
+    config::EntityExpansionLimits,
+    error::{Error, ExpansionLimit},
+    Name,
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+/// The entity references expanded so far while parsing one document
+///
+/// A budget is created from the [`EntityExpansionLimits`] of each document and passed to the parsers along with the
+/// entity references, clones of it share the same counts.
+#[derive(Clone, Debug, Default)]
+pub struct ExpansionBudget(Rc<RefCell<Budget>>);
+
+#[derive(Debug, Default)]
+struct Budget {
+    limits: EntityExpansionLimits,
+    expansions: usize,
+    expanded_size: usize,
+    declarations: EntityDeclarations,
+    // The entities whose replacement text is being expanded, outermost first
+    expanding: Vec<Name>,
+}
+
+/// The general entities declared by the DTD of a document, for the well-formedness constraints on references
+#[derive(Clone, Debug, Default)]
+struct EntityDeclarations {
+    // Whether every entity referenced must be declared, see [WFC: Entity Declared]
+    required: bool,
+    declared: HashSet<String>,
+    external: HashSet<String>,
+    unparsed: HashSet<String>,
+    // The values of the internal entities, which attribute defaults reference before the DTD is complete
+    values: HashMap<String, EntityValue>,
+    // The entities whose replacement text is not well-formed in content, or in attribute values
+    malformed_in_content: HashSet<String>,
+    malformed_in_attributes: HashSet<String>,
+}
+
+impl ExpansionBudget {
+    pub fn new(limits: EntityExpansionLimits) -> Self {
+        Self(Rc::new(RefCell::new(Budget {
+            limits,
+            ..Budget::default()
+        })))
+    }
+
+    /// Whether a general entity `name` has been declared, later declarations of an entity are not binding
+    pub(crate) fn is_entity_declared(&self, name: &Name) -> bool {
+        self.0
+            .borrow()
+            .declarations
+            .declared
+            .contains(&name.to_string())
+    }
+
+    /// The value of the internal general entity `name` declared so far
+    pub(crate) fn entity_value(&self, name: &Name) -> Option<EntityValue> {
+        self.0
+            .borrow()
+            .declarations
+            .values
+            .get(&name.to_string())
+            .cloned()
+    }
+
+    /// Records that the replacement text of the entity `name` is not well-formed in content, or in attribute values
+    pub(crate) fn declare_malformed_entity(
+        &self,
+        name: &Name,
+        in_content: bool,
+        in_attributes: bool,
+    ) {
+        let declarations = &mut self.0.borrow_mut().declarations;
+        let name = name.to_string();
+        if in_content {
+            declarations.malformed_in_content.insert(name.clone());
+        }
+        if in_attributes {
+            declarations.malformed_in_attributes.insert(name);
+        }
+    }
+
+    /// Sets whether references to undeclared entities are errors for the rest of the document
+    ///
+    /// [WFC: Entity Declared] applies to documents without a DTD, with only an internal subset without
+    /// parameter entity references, or with `standalone="yes"`.
+    pub(crate) fn require_entity_declarations(&self, required: bool) {
+        self.0.borrow_mut().declarations.required = required;
+    }
+
+    /// Adds the general entity of `declaration` to the declarations the rest of the document is checked against
+    pub(crate) fn declare_entity(&self, declaration: &EntityDeclaration) {
+        let declarations = &mut self.0.borrow_mut().declarations;
+        let name = declaration.name.to_string();
+        if declarations.declared.contains(&name) {
+            return;
+        }
+        match &declaration.entity_def {
+            EntityDefinition::External {
+                n_data: Some(_), ..
+            } => declarations.unparsed.insert(name.clone()),
+            EntityDefinition::External { .. } => declarations.external.insert(name.clone()),
+            EntityDefinition::EntityValue(value) => {
+                declarations.values.insert(name.clone(), value.clone());
+                false
+            }
+        };
+        declarations.declared.insert(name);
+    }
+
+    /// Checks a reference to the entity `name`, in an attribute value when `in_attribute` is set
+    ///
+    /// Fails with [`Error::UnparsedEntityReference`] for [WFC: Parsed Entity], with
+    /// [`Error::ExternalEntityInAttributeValue`] for [WFC: No External Entity References], and with
+    /// [`Error::MalformedEntity`] when the replacement text of the entity is not well-formed there.
+    pub(crate) fn check_reference(&self, name: &Name, in_attribute: bool) -> Result<(), Error> {
+        let declarations = &self.0.borrow().declarations;
+        let name_string = name.to_string();
+        let malformed = if in_attribute {
+            &declarations.malformed_in_attributes
+        } else {
+            &declarations.malformed_in_content
+        };
+        if declarations.unparsed.contains(&name_string) {
+            Err(Error::UnparsedEntityReference(name.clone()))
+        } else if in_attribute && declarations.external.contains(&name_string) {
+            Err(Error::ExternalEntityInAttributeValue(name.clone()))
+        } else if malformed.contains(&name_string) {
+            Err(Error::MalformedEntity(name.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks a reference to the entity `name` that has no replacement text
+    ///
+    /// The predefined entities need not be declared, their names are already decoded by [`Parse::parse_name`](crate::parse::Parse::parse_name).
+    pub(crate) fn check_undeclared(&self, name: &Name) -> Result<(), Error> {
+        let undeclared = {
+            let declarations = &self.0.borrow().declarations;
+            declarations.required && !declarations.declared.contains(&name.to_string())
+        };
+        let predefined = name.prefix.is_none()
+            && matches!(name.local_part.as_str(), "&" | "<" | ">" | "'" | "\"");
+        if undeclared && !predefined {
+            Err(Error::UndeclaredEntity(name.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Starts expanding the replacement text of the entity `name`, until the matching [`ExpansionBudget::exit`]
+    ///
+    /// Fails with [`Error::RecursiveEntity`] when `name` is already being expanded, and with
+    /// [`Error::EntityExpansionLimitExceeded`] when the expansion is nested too deep or is one too many.
+    pub(crate) fn enter(&self, name: &Name) -> Result<(), Error> {
+        {
+            let budget = self.0.borrow();
+            if budget.expanding.contains(name) {
+                let mut chain = budget.expanding.clone();
+                chain.push(name.clone());
+                return Err(Error::RecursiveEntity { chain });
+            }
+            if budget.expanding.len() > budget.limits.max_depth {
+                return Err(Error::EntityExpansionLimitExceeded {
+                    entity: name.clone(),
+                    limit: ExpansionLimit::Depth(budget.limits.max_depth),
+                });
+            }
+        }
+        self.record_expansion(name)?;
+        self.0.borrow_mut().expanding.push(name.clone());
+        Ok(())
+    }
+
+    /// Finishes expanding the entity entered last
+    pub(crate) fn exit(&self) {
+        self.0.borrow_mut().expanding.pop();
+    }
+
+    /// Counts one expansion of the entity `name`
+    pub(crate) fn record_expansion(&self, name: &Name) -> Result<(), Error> {
+        self.charge(name, 1, 0)
+    }
+
+    /// Counts the size of replacement text produced for a reference to the entity `name`
+    pub(crate) fn record_expanded_size(&self, name: &Name, size: usize) -> Result<(), Error> {
+        self.charge(name, 0, size)
+    }
+
+    fn charge(&self, name: &Name, expansions: usize, size: usize) -> Result<(), Error> {
+        let mut budget = self.0.borrow_mut();
+        budget.expansions += expansions;
+        budget.expanded_size = budget.expanded_size.saturating_add(size);
+        let limit = if budget.expansions > budget.limits.max_expansions {
+            ExpansionLimit::Expansions(budget.limits.max_expansions)
+        } else if budget.expanded_size > budget.limits.max_expanded_size {
+            ExpansionLimit::ExpandedSize(budget.limits.max_expanded_size)
+        } else {
+            return Ok(());
+        };
+        Err(Error::EntityExpansionLimitExceeded {
+            entity: name.clone(),
+            limit,
+        })
+    }
+}
@@ -1,6 +1,7 @@
 pub mod entity_declaration;
 pub mod entity_definition;
 pub mod entity_value;
+pub mod expansion;
 
 #[derive(Clone, Hash, Eq, PartialEq, Debug)]
 pub enum EntitySource {
@@ -16,7 +16,7 @@ use crate::{
     parse::Parse,
     processing_instruction::ProcessingInstruction,
     prolog::{declaration_content::DeclarationContent, external_id::ExternalID, id::ID},
-    reference::Reference,
+    reference::{ParseReference, Reference},
     Document, IResult, Name,
 };
 
@@ -24,6 +24,7 @@ use super::entity::{
     entity_declaration::{EntityDecl, GeneralEntityDeclaration, ParameterEntityDeclaration},
     entity_definition::EntityDefinition,
     entity_value::EntityValue,
+    expansion::ExpansionBudget,
     EntitySource,
 };
 
@@ -51,18 +52,24 @@ impl<'a> Parse<'a> for MarkupDeclaration {
     type Args = (
         Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         EntitySource,
+        ExpansionBudget,
     );
     type Output = IResult<&'a str, Option<MarkupDeclaration>>;
     // [29] markupdecl ::= elementdecl | AttlistDecl | EntityDecl | NotationDecl | PI | Comment
     fn parse(input: &'a str, args: Self::Args) -> Self::Output {
-        let (entity_references, entity_source) = args;
+        let (entity_references, entity_source, budget) = args;
 
         let (input, res) = opt(alt((
             Self::parse_element_declaration,
             |i| {
-                Self::parse_attlist_declaration(i, entity_references.clone(), entity_source.clone())
+                Self::parse_attlist_declaration(
+                    i,
+                    entity_references.clone(),
+                    entity_source.clone(),
+                    &budget,
+                )
             },
-            |i| Self::parse_entity(i, entity_references.clone(), entity_source.clone()),
+            |i| Self::parse_entity(i, entity_references.clone(), entity_source.clone(), &budget),
             Self::parse_notation,
             Self::parse_processing_instruction,
             Self::parse_comment,
@@ -122,18 +129,24 @@ impl MarkupDeclaration {
     }
     // [52] AttlistDecl ::= '<!ATTLIST' S Name AttDef* S? '>'
     // Namespaces (Third Edition) [20] AttlistDecl ::= '<!ATTLIST' S QName AttDef* S? '>'
-    pub fn parse_attlist_declaration(
-        input: &str,
+    pub fn parse_attlist_declaration<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, MarkupDeclaration> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, MarkupDeclaration> {
         let (input, (_start, _whitespace1, name, att_defs, _whitespace2, _close)) =
             tuple((
                 tag("<!ATTLIST"),
                 Self::parse_multispace1,
                 alt((Self::parse_name, Self::parse_qualified_name)),
                 many0(|i| {
-                    Attribute::parse_definition(i, entity_references.clone(), entity_source.clone())
+                    Attribute::parse_definition(
+                        i,
+                        entity_references.clone(),
+                        entity_source.clone(),
+                        budget,
+                    )
                 }),
                 Self::parse_multispace0,
                 tag(">"),
@@ -148,17 +161,19 @@ impl MarkupDeclaration {
     }
 
     // [70] EntityDecl ::= GEDecl | PEDecl
-    fn parse_entity(
-        input: &str,
+    fn parse_entity<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, MarkupDeclaration> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, MarkupDeclaration> {
         alt((
             |i| {
                 Self::parse_general_entity_declaration(
                     i,
                     entity_references.clone(),
                     entity_source.clone(),
+                    budget,
                 )
             },
             |i| {
@@ -166,23 +181,26 @@ impl MarkupDeclaration {
                     i,
                     entity_references.clone(),
                     entity_source.clone(),
+                    budget,
                 )
             },
         ))(input)
     }
 
     // [71] GEDecl ::= '<!ENTITY' S Name S EntityDef S? '>'
-    fn parse_general_entity_declaration(
-        input: &str,
+    fn parse_general_entity_declaration<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, MarkupDeclaration> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, MarkupDeclaration> {
         let (input, (_start, _whitespace1, name, _whitespace2)) = tuple((
             tag("<!ENTITY"),
             Self::parse_multispace1,
             Self::parse_name,
             Self::parse_multispace1,
         ))(input)?;
+        Self::check_entity_literal(input, &name, true, budget)?;
 
         let (input, (entity_def, _whitespace3, _close)) = tuple((
             |i| {
@@ -191,6 +209,7 @@ impl MarkupDeclaration {
                     name.clone(),
                     entity_references.clone(),
                     entity_source.clone(),
+                    budget,
                 )
             },
             Self::parse_multispace0,
@@ -206,11 +225,12 @@ impl MarkupDeclaration {
     }
 
     // [72]    PEDecl ::=    '<!ENTITY' S '%' S Name S PEDef S? '>'
-    fn parse_parameter_entity_declaration(
-        input: &str,
+    fn parse_parameter_entity_declaration<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, MarkupDeclaration> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, MarkupDeclaration> {
         let (input, (_start, _whitespace1, _percent, _whitespace2, name, _whitespace3)) =
             tuple((
                 tag("<!ENTITY"),
@@ -220,6 +240,7 @@ impl MarkupDeclaration {
                 Self::parse_name,
                 Self::parse_multispace1,
             ))(input)?;
+        Self::check_entity_literal(input, &name, false, budget)?;
 
         let (input, (entity_def, _whitespace4, _close)) = tuple((
             |i| {
@@ -228,6 +249,7 @@ impl MarkupDeclaration {
                     name.clone(),
                     entity_references.clone(),
                     entity_source.clone(),
+                    budget,
                 )
             },
             Self::parse_multispace0,
@@ -244,12 +266,13 @@ impl MarkupDeclaration {
     }
 
     // [74] PEDef ::= EntityValue | ExternalID
-    fn parse_parameter_definition(
-        input: &str,
+    fn parse_parameter_definition<'a>(
+        input: &'a str,
         name: Name,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, EntityDefinition> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, EntityDefinition> {
         alt((
             map(
                 |i| {
@@ -258,6 +281,7 @@ impl MarkupDeclaration {
                         name.clone(),
                         entity_references.clone(),
                         entity_source.clone(),
+                        budget,
                     )
                 },
                 EntityDefinition::EntityValue,
@@ -274,12 +298,13 @@ impl MarkupDeclaration {
     }
 
     // [73] EntityDef ::= EntityValue | (ExternalID NDataDecl?)
-    fn parse_entity_definition(
-        input: &str,
+    fn parse_entity_definition<'a>(
+        input: &'a str,
         name: Name,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, EntityDefinition> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, EntityDefinition> {
         alt((
             map(
                 |i| {
@@ -288,6 +313,7 @@ impl MarkupDeclaration {
                         name.clone(),
                         entity_references.clone(),
                         entity_source.clone(),
+                        budget,
                     )
                 },
                 EntityDefinition::EntityValue,
@@ -306,6 +332,103 @@ impl MarkupDeclaration {
         ))(input)
     }
 
+    // [9] EntityValue: `&` and `%` only start references in a literal. The replacement text of a general entity, with
+    // the character references of the literal replaced, is checked for where the entity is referenced, it must be
+    // well-formed content, see [WFC: Parsed Entity], and may not contain `<` in attribute values, see
+    // [WFC: No < in Attribute Values]. References to other entities are bypassed, they are checked with their own
+    // declarations.
+    fn check_entity_literal(
+        input: &str,
+        name: &Name,
+        general: bool,
+        budget: &ExpansionBudget,
+    ) -> Result<(), nom::Err<error::Error>> {
+        let failure = |input: &str, message: String| {
+            nom::Err::Failure(error::Error::ParseError(error::ParseError::with_message(
+                nom::error::ErrorKind::Verify,
+                input,
+                message,
+            )))
+        };
+        let Some(quote) = input.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            return Ok(());
+        };
+        let Some(end) = input[1..].find(quote) else {
+            return Ok(());
+        };
+        let mut literal = &input[1..=end];
+        let mut replacement_text = String::new();
+        while let Some(position) = literal.find(['&', '%']) {
+            replacement_text.push_str(&literal[..position]);
+            literal = &literal[position..];
+            literal = if literal.starts_with('%') {
+                match Reference::parse_parameter_reference(literal) {
+                    Ok((remaining, _reference)) => remaining,
+                    Err(_) => {
+                        return Err(failure(
+                            literal,
+                            "`%` must start a parameter entity reference".to_string(),
+                        ))
+                    }
+                }
+            } else {
+                match Reference::parse(literal, EntitySource::Internal) {
+                    Ok((remaining, Reference::CharRef(value))) => {
+                        replacement_text.push_str(&value);
+                        remaining
+                    }
+                    Ok((remaining, Reference::EntityRef(_))) => {
+                        replacement_text.push_str(&literal[..literal.len() - remaining.len()]);
+                        remaining
+                    }
+                    Err(nom::Err::Failure(e)) => return Err(nom::Err::Failure(e)),
+                    Err(_) => {
+                        return Err(failure(
+                            literal,
+                            "`&` must start an entity or character reference".to_string(),
+                        ))
+                    }
+                }
+            };
+        }
+        // Only the first declaration of an entity is binding
+        if !general || budget.is_entity_declared(name) {
+            return Ok(());
+        }
+        replacement_text.push_str(literal);
+
+        let mut content = String::new();
+        let mut rest = replacement_text.as_str();
+        while let Some(position) = rest.find('&') {
+            content.push_str(&rest[..position]);
+            rest = &rest[position..];
+            rest = match Reference::parse(rest, EntitySource::Internal) {
+                Ok((remaining, Reference::EntityRef(_))) => remaining,
+                Ok((remaining, Reference::CharRef(_))) => {
+                    content.push_str(&rest[..rest.len() - remaining.len()]);
+                    remaining
+                }
+                Err(_) => {
+                    budget.declare_malformed_entity(name, true, true);
+                    return Ok(());
+                }
+            };
+        }
+        content.push_str(rest);
+        let entity_references = Rc::new(RefCell::new(HashMap::new()));
+        let in_content = !matches!(
+            Document::parse_content(
+                &content,
+                &entity_references,
+                EntitySource::Internal,
+                &ExpansionBudget::default()
+            ),
+            Ok(("", _))
+        );
+        budget.declare_malformed_entity(name, in_content, content.contains('<'));
+        Ok(())
+    }
+
     // [76] NDataDecl ::= S 'NDATA' S Name
     fn parse_ndata_declaration(input: &str) -> IResult<&str, Name> {
         let (input, _) = Self::parse_multispace1(input)?;
@@ -316,42 +439,33 @@ impl MarkupDeclaration {
         Ok((input, name))
     }
     // [9] EntityValue	::= '"' ([^%&"] | PEReference | Reference)* '"'|  "'" ([^%&'] | PEReference | Reference)* "'"
-    pub fn parse_entity_value(
-        input: &str,
+    pub fn parse_entity_value<'a>(
+        input: &'a str,
         name: Name,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, EntityValue> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, EntityValue> {
         //TODO: I hate this. Refactor is possible
         let cloned_references = entity_references.clone();
-        let cloned_references2 = entity_references.clone();
+        let cloned_budget = budget.clone();
 
         let cloned_entity_source = entity_source.clone();
         let cloned_entity_source2 = entity_source.clone();
         // TODO: removed alt((alt(()))) need to test if functionality is the same
         alt((
-            map(
-                tuple((
-                    alt((char('\"'), char('\''))),
-                    Self::capture_span(alt((
-                        move |i| Document::parse_element(i, cloned_references.clone()),
-                        Document::parse_cdata_section,
-                    ))),
-                    alt((char('\"'), char('\''))),
-                )),
-                |(_, (raw_entity_value, doc), _)| {
-                    entity_references.borrow_mut().insert(
-                        (name.clone(), EntitySource::Internal),
-                        EntityValue::Document(doc),
-                    );
-                    EntityValue::Value(raw_entity_value.to_string())
-                },
-            ),
             map_res(
                 tuple((
                     alt((char('\"'), char('\''))),
                     Self::capture_span(move |i| {
-                        Self::parse(i, (cloned_references2.clone(), entity_source.clone()))
+                        Self::parse(
+                            i,
+                            (
+                                cloned_references.clone(),
+                                entity_source.clone(),
+                                cloned_budget.clone(),
+                            ),
+                        )
                     }),
                     alt((char('\"'), char('\''))),
                 )),
@@ -484,9 +598,18 @@ impl MarkupDeclaration {
             }
         }
     }
+    // General entity references are expanded where the entity is referenced, so they are kept in the replacement text
     fn get_reference_value(reference: Reference) -> String {
         match reference {
-            Reference::EntityRef(value) => value.local_part,
+            Reference::EntityRef(name) => match name.local_part.as_str() {
+                // The predefined entities, whose names are decoded by `parse_name`
+                "&" if name.prefix.is_none() => "&amp;".to_string(),
+                "<" if name.prefix.is_none() => "&lt;".to_string(),
+                ">" if name.prefix.is_none() => "&gt;".to_string(),
+                "'" if name.prefix.is_none() => "&apos;".to_string(),
+                "\"" if name.prefix.is_none() => "&quot;".to_string(),
+                _ => format!("&{name};"),
+            },
             Reference::CharRef(value) => value,
         }
     }
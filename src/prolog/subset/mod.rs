@@ -1,13 +1,15 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use crate::{
-    config::ExternalEntityParseConfig, prolog::external_id::ExternalID, reference::Reference,
+    error::{Error, ParseError},
+    reference::Reference,
     Document, IResult, Name,
 };
 
 use self::{
     entity::entity_declaration::{EntityDecl, EntityDeclaration},
     entity::entity_value::EntityValue,
+    entity::expansion::ExpansionBudget,
     entity::EntitySource,
 };
 
@@ -16,14 +18,19 @@ pub mod entity;
 pub mod markup_declaration;
 
 use entity::entity_definition::EntityDefinition;
-use nom::{branch::alt, combinator::map, multi::many0};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{cut, map, value},
+    multi::many0,
+    sequence::{pair, terminated, tuple},
+};
 
 use crate::{
     attribute::Attribute, namespaces::ParseNamespace, parse::Parse,
     prolog::subset::markup_declaration::MarkupDeclaration, reference::ParseReference, Config,
 };
 
-//TODO handle circular references in all entity replacements
 #[derive(Clone, PartialEq, Eq)]
 pub enum Subset {
     MarkupDecl(MarkupDeclaration),
@@ -53,97 +60,56 @@ impl<'a> Parse<'a> for Subset {
         Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         &'a Config,
         EntitySource,
+        ExpansionBudget,
     );
     type Output = IResult<&'a str, Vec<Subset>>;
 
     //[28b]	intSubset ::= (markupdecl | DeclSep)*
     fn parse(input: &'a str, args: Self::Args) -> Self::Output {
-        let (entity_references, config, entity_source) = args;
-        let (input, parsed) = many0(alt((
-            |i| {
-                let (i, decl_sep) =
-                    Self::parse_decl_sep(i, entity_references.clone(), entity_source.clone())?;
-                match decl_sep {
-                    Some(decl_sep) => Ok((i, Some(decl_sep))),
-                    None => Ok((i, None)),
-                }
-            },
-            |i| {
-                let (i, result) = MarkupDeclaration::parse(
-                    i,
-                    (entity_references.clone(), entity_source.clone()),
-                )?;
-                match result {
-                    Some(markup_declaration) => {
-                        Ok((i, Some(Subset::MarkupDecl(markup_declaration))))
-                    }
-                    None => Err(nom::Err::Error(nom::error::make_error(
-                        input,
-                        nom::error::ErrorKind::Verify,
-                    ))),
-                }
-            },
-        )))(input)?;
+        let (entity_references, config, entity_source, budget) = args;
+        let (input, parsed) =
+            Self::parse_declarations(input, entity_references.clone(), entity_source, &budget)?;
         let mut consolidated: Vec<Subset> = vec![];
-        let mut external_subsets: Vec<Subset> = vec![];
-        for mut subset in parsed.into_iter().flatten() {
+        let mut external_subsets: HashMap<Name, Vec<Subset>> = HashMap::new();
+        for mut subset in parsed {
             match &mut subset {
                 Subset::MarkupDecl(markup_declaration) => match markup_declaration {
                     MarkupDeclaration::Entity(entity) => {
-                        match entity {
-                            EntityDecl::Parameter(EntityDeclaration {
-                                name,
-                                entity_def:
-                                    EntityDefinition::External {
-                                        id: ExternalID::System(ext_file),
-                                        ..
-                                    },
-                                ..
-                            }) => {
-                                let Config {
-                                    external_parse_config:
-                                        ExternalEntityParseConfig {
-                                            allow_ext_parse,
-                                            base_directory,
-                                            ..
-                                        },
-                                } = config;
-                                if *allow_ext_parse {
-                                    let file_path = match base_directory {
-                                        Some(base) => format!("{}/{}", base, ext_file),
-                                        None => ext_file.clone(),
-                                    };
-                                    let _processed_external_entity =
-                                        Document::process_external_entity_file(
-                                            file_path,
-                                            name,
-                                            config,
-                                            entity_references.clone(),
-                                        );
-                                    if let Ok(Some(ext_subsets)) =
-                                        Document::get_external_entity_from_declaration(
-                                            entity.clone(),
-                                            entity_references.clone(),
-                                            config,
-                                        )
-                                    {
-                                        external_subsets.extend(ext_subsets.clone())
-                                    }
-                                }
+                        let ext_subsets = match Document::get_external_entity_from_declaration(
+                            entity.clone(),
+                            entity_references.clone(),
+                            config,
+                            &budget,
+                        ) {
+                            Ok(ext_subsets) => ext_subsets,
+                            Err(e @ Error::ExternalEntityNotAllowed { .. }) => {
+                                return Err(nom::Err::Failure(e))
                             }
-                            _ => {
-                                if let Ok(Some(ext_subsets)) =
-                                    Document::get_external_entity_from_declaration(
-                                        entity.clone(),
-                                        entity_references.clone(),
-                                        config,
-                                    )
-                                {
-                                    consolidated.extend(ext_subsets);
+                            // A general entity that is not well-formed is an error where it is referenced
+                            Err(e) if e.is_malformed_entity_file() => match entity {
+                                EntityDecl::General(EntityDeclaration { name, .. }) => {
+                                    budget.declare_malformed_entity(name, true, true);
+                                    None
                                 }
+                                EntityDecl::Parameter(_) => return Err(nom::Err::Failure(e)),
+                            },
+                            // External entities that are denied or cannot be read are left unexpanded
+                            Err(_) => None,
+                        };
+                        if let Some(ext_subsets) = ext_subsets {
+                            if let EntityDecl::Parameter(EntityDeclaration {
+                                name,
+                                entity_def: EntityDefinition::External { .. },
+                            }) = entity
+                            {
+                                external_subsets
+                                    .entry(name.clone())
+                                    .or_default()
+                                    .extend(ext_subsets)
+                            } else {
+                                consolidated.extend(ext_subsets);
                             }
                         }
-
                     }
                     MarkupDeclaration::AttList {
                         name,
@@ -214,10 +180,14 @@ impl<'a> Parse<'a> for Subset {
                             )));
                         },
                         EntityValue::Document(_doc) => {
-                            for external_subset in &external_subsets {
-                                *expansion = Some(Box::new(external_subset.clone()));
+                            // All declarations of the entity are kept, the last one as its expansion
+                            if let Some((last, declarations)) = external_subsets
+                                .get(name)
+                                .and_then(|subsets| subsets.split_last())
+                            {
+                                consolidated.extend(declarations.iter().cloned());
+                                *expansion = Some(Box::new(last.clone()));
                             }
-
                         },
                         EntityValue::ParameterReference(_reference) => {
                             unimplemented!("External EntityValue::ParameterReference encountered, needs implementation")
@@ -259,30 +229,275 @@ impl<'a> Parse<'a> for Subset {
     }
 }
 
+impl Subset {
+    // [28b] intSubset ::= (markupdecl | DeclSep)*
+    // [31] extSubsetDecl ::= ( markupdecl | conditionalSect | DeclSep)*
+    //
+    // Conditional sections are only recognized in the external subset, the declarations of included sections are
+    // returned in place of the section
+    fn parse_declarations<'a>(
+        input: &'a str,
+        entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        entity_source: EntitySource,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Vec<Subset>> {
+        let (remaining, parsed) = many0(alt((
+            |i| {
+                if entity_source != EntitySource::External {
+                    return Err(nom::Err::Error(nom::error::make_error(
+                        i,
+                        nom::error::ErrorKind::Verify,
+                    )));
+                }
+                Self::parse_conditional_sect(
+                    i,
+                    entity_references.clone(),
+                    entity_source.clone(),
+                    budget,
+                )
+            },
+            |i| {
+                let (i, decl_sep) = Self::parse_decl_sep(
+                    i,
+                    entity_references.clone(),
+                    entity_source.clone(),
+                    budget,
+                )?;
+                Ok((i, decl_sep.into_iter().collect()))
+            },
+            |i| {
+                let (i, result) = MarkupDeclaration::parse(
+                    i,
+                    (
+                        entity_references.clone(),
+                        entity_source.clone(),
+                        budget.clone(),
+                    ),
+                )?;
+                match result {
+                    Some(markup_declaration) => {
+                        Self::declare_entity(&markup_declaration, &entity_references, budget);
+                        Ok((i, vec![Subset::MarkupDecl(markup_declaration)]))
+                    }
+                    None => Err(nom::Err::Error(nom::error::make_error(
+                        input,
+                        nom::error::ErrorKind::Verify,
+                    ))),
+                }
+            },
+        )))(input)?;
+        Ok((remaining, parsed.into_iter().flatten().collect()))
+    }
+
+    // Parameter entities are available as soon as they are declared so that they can switch the conditional sections
+    // that follow. The first declaration is binding, as the internal subset is read before the external one. General
+    // entities are recorded as they are declared for the checks of references to them.
+    fn declare_entity(
+        markup_declaration: &MarkupDeclaration,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        budget: &ExpansionBudget,
+    ) {
+        match markup_declaration {
+            MarkupDeclaration::Entity(EntityDecl::Parameter(EntityDeclaration {
+                name,
+                entity_def: EntityDefinition::EntityValue(value @ EntityValue::Value(_)),
+            })) => {
+                entity_references
+                    .borrow_mut()
+                    .entry((name.clone(), EntitySource::Internal))
+                    .or_insert_with(|| value.clone());
+            }
+            MarkupDeclaration::Entity(EntityDecl::General(declaration)) => {
+                budget.declare_entity(declaration);
+            }
+            _ => {}
+        }
+    }
+
+    // [61] conditionalSect ::= includeSect | ignoreSect
+    // [62] includeSect ::= '<![' S? 'INCLUDE' S? '[' extSubsetDecl ']]>'
+    // [63] ignoreSect ::= '<![' S? 'IGNORE' S? '[' ignoreSectContents* ']]>'
+    fn parse_conditional_sect<'a>(
+        input: &'a str,
+        entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        entity_source: EntitySource,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Vec<Subset>> {
+        let (input, _) = pair(tag("<!["), Self::parse_multispace0)(input)?;
+        // A parameter entity that is not a keyword leaves the section unparsed, as parameter entity references
+        // within markup are not supported
+        let (input, include) = alt((
+            value(true, tag("INCLUDE")),
+            value(false, tag("IGNORE")),
+            |i| Self::parse_conditional_keyword_reference(i, &entity_references, budget),
+        ))(input)?;
+        let (input, _) = cut(pair(Self::parse_multispace0, tag("[")))(input)?;
+        if include {
+            cut(terminated(
+                |i| {
+                    Self::parse_declarations(
+                        i,
+                        entity_references.clone(),
+                        entity_source.clone(),
+                        budget,
+                    )
+                },
+                tag("]]>"),
+            ))(input)
+        } else {
+            cut(map(
+                terminated(Self::parse_ignore_sect_contents, tag("]]>")),
+                |_| vec![],
+            ))(input)
+        }
+    }
+
+    // The keyword of a conditional section given by a parameter entity, as in `<![%draft;[`
+    fn parse_conditional_keyword_reference<'a>(
+        input: &'a str,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, bool> {
+        let (remaining, reference) = Reference::parse_parameter_reference(input)?;
+        let failure = |error| nom::Err::Failure(Error::ParseError(error));
+        let Reference::EntityRef(name) = reference else {
+            return Err(nom::Err::Error(nom::error::make_error(
+                input,
+                nom::error::ErrorKind::Verify,
+            )));
+        };
+        budget.record_expansion(&name).map_err(|e| {
+            failure(ParseError::with_cause(
+                nom::error::ErrorKind::Verify,
+                input,
+                e,
+            ))
+        })?;
+        let keyword = {
+            let entities = entity_references.borrow();
+            match entities
+                .get(&(name.clone(), EntitySource::Internal))
+                .or_else(|| entities.get(&(name.clone(), EntitySource::External)))
+            {
+                Some(EntityValue::Value(keyword)) => keyword.trim().to_string(),
+                _ => {
+                    return Err(nom::Err::Error(Error::ParseError(ParseError::with_cause(
+                        nom::error::ErrorKind::Verify,
+                        input,
+                        Error::UndeclaredEntity(name),
+                    ))))
+                }
+            }
+        };
+        match keyword.as_str() {
+            "INCLUDE" => Ok((remaining, true)),
+            "IGNORE" => Ok((remaining, false)),
+            _ => Err(nom::Err::Error(Error::ParseError(ParseError::with_message(
+                nom::error::ErrorKind::Verify,
+                input,
+                format!(
+                    "Parameter entity `{name}` must be INCLUDE or IGNORE to mark a conditional section, found `{keyword}`"
+                ),
+            )))),
+        }
+    }
+
+    // [64] ignoreSectContents ::= Ignore ('<![' ignoreSectContents ']]>' Ignore)*
+    fn parse_ignore_sect_contents(input: &str) -> IResult<&str, ()> {
+        let (input, _) = Self::parse_ignore(input)?;
+        let (input, _) = many0(tuple((
+            tag("<!["),
+            Self::parse_ignore_sect_contents,
+            tag("]]>"),
+            Self::parse_ignore,
+        )))(input)?;
+        Ok((input, ()))
+    }
+
+    // [65] Ignore ::= Char* - (Char* ('<![' | ']]>') Char*)
+    fn parse_ignore(input: &str) -> IResult<&str, &str> {
+        let end = [input.find("<!["), input.find("]]>")]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(input.len());
+        Ok((&input[end..], &input[..end]))
+    }
+}
+
 impl ParseDeclSep for Subset {
     type Output = Option<Subset>;
 
     // [28a] DeclSep ::=  PEReference | S
-    fn parse_decl_sep(
-        input: &str,
+    fn parse_decl_sep<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, Self::Output> {
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Self::Output> {
         let (input, decl_sep) = alt((
-            map(Reference::parse_parameter_reference, |reference| {
-                let expansion =
-                    Self::expand_entity(&reference, &entity_references, entity_source.clone());
+            |i| {
+                let (remaining, reference) = Reference::parse_parameter_reference(i)?;
+                // Entities may be declared by the parameter entity, see [WFC: Entity Declared]
+                budget.require_entity_declarations(false);
+                let expansion = Self::expand_entity(
+                    &reference,
+                    &entity_references,
+                    entity_source.clone(),
+                    budget,
+                )
+                .map_err(|e| {
+                    nom::Err::Failure(Error::ParseError(ParseError::with_cause(
+                        nom::error::ErrorKind::Verify,
+                        i,
+                        e,
+                    )))
+                })?;
+
+                // [WFC: PE Between Declarations] The replacement text must be a sequence of markup declarations
+                let replacement_text = match &reference {
+                    Reference::EntityRef(name) => {
+                        let entities = entity_references.borrow();
+                        match entities
+                            .get(&(name.clone(), EntitySource::Internal))
+                            .or_else(|| entities.get(&(name.clone(), EntitySource::External)))
+                        {
+                            Some(EntityValue::Value(text)) => Some((name.clone(), text.clone())),
+                            _ => None,
+                        }
+                    }
+                    Reference::CharRef(_) => None,
+                };
+                if let Some((name, text)) = replacement_text {
+                    let declarations = Self::parse_declarations(
+                        &text,
+                        entity_references.clone(),
+                        entity_source.clone(),
+                        budget,
+                    );
+                    if !matches!(declarations, Ok((rest, _)) if rest.trim().is_empty()) {
+                        return Err(nom::Err::Failure(Error::ParseError(ParseError::with_message(
+                            nom::error::ErrorKind::Verify,
+                            i,
+                            format!("The replacement text of parameter entity `{name}` is not a sequence of markup declarations"),
+                        ))));
+                    }
+                }
 
                 let expanded_subset = match &expansion {
                     Some(EntityValue::MarkupDecl(elem)) => Some(elem.clone()),
                     _ => None,
                 };
 
-                Some(Subset::DeclSep {
-                    reference,
-                    expansion: expanded_subset.map(|subset| Box::new(Subset::MarkupDecl(*subset))),
-                })
-            }),
+                Ok((
+                    remaining,
+                    Some(Subset::DeclSep {
+                        reference,
+                        expansion: expanded_subset
+                            .map(|subset| Box::new(Subset::MarkupDecl(*subset))),
+                    }),
+                ))
+            },
             map(Self::parse_multispace1, |_| None),
         ))(input)?;
         Ok((input, decl_sep))
@@ -292,24 +507,29 @@ impl ParseDeclSep for Subset {
 pub trait ParseDeclSep {
     type Output;
     // [28a] DeclSep ::=  PEReference | S
-    fn parse_decl_sep(
-        input: &str,
+    fn parse_decl_sep<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, Self::Output>;
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Self::Output>;
+    /// Looks up the replacement of a parameter entity reference, counting it against the
+    /// [`EntityExpansionLimits`](crate::config::EntityExpansionLimits) of the document
     fn expand_entity(
         reference: &Reference,
         entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> Option<EntityValue> {
+        budget: &ExpansionBudget,
+    ) -> Result<Option<EntityValue>, Error> {
         match reference {
             Reference::EntityRef(name) => {
+                budget.record_expansion(name)?;
                 let entities = entity_references.borrow();
-                entities
+                Ok(entities
                     .get(&(name.clone(), entity_source.clone()))
-                    .cloned()
+                    .cloned())
             }
-            Reference::CharRef(_) => None,
+            Reference::CharRef(_) => Ok(None),
         }
     }
 }
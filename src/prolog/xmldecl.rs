@@ -4,6 +4,7 @@ use nom::{
     bytes::complete::tag,
     character::complete::{alpha1, alphanumeric1, digit1},
     combinator::{map, opt},
+    error::context,
     error::ErrorKind,
     multi::many0,
     sequence::{delimited, pair, preceded, tuple},
@@ -39,25 +40,34 @@ impl<'a> Parse<'a> for XmlDecl {
     type Output = IResult<&'a str, Self>;
     // [23] XMLDecl	::=  '<?xml' VersionInfo EncodingDecl? SDDecl? S? '?>'
     fn parse(input: &'a str, _args: Self::Args) -> Self::Output {
-        map(
-            tuple((
-                tag("<?xml"),
-                Self::parse_version_info,
-                opt(Self::parse_encoding_decl),
-                opt(Self::parse_sd_decl),
-                Self::parse_multispace0,
-                tag("?>"),
-            )),
-            |(_start, version, encoding, standalone, _whitespace, _end)| Self {
-                version,
-                encoding,
-                standalone,
-            },
+        context(
+            "[23] XMLDecl",
+            map(
+                tuple((
+                    tag("<?xml"),
+                    Self::parse_version_info,
+                    opt(Self::parse_encoding_decl),
+                    opt(Self::parse_sd_decl),
+                    Self::parse_multispace0,
+                    tag("?>"),
+                )),
+                |(_start, version, encoding, standalone, _whitespace, _end)| Self {
+                    version,
+                    encoding,
+                    standalone,
+                },
+            ),
         )(input)
     }
 }
 
 impl XmlDecl {
+    /// Whether `input` opens an XML or text declaration rather than a processing
+    /// instruction whose target merely starts with `xml`, such as `<?xml-stylesheet?>`.
+    pub(crate) fn starts_declaration(input: &str) -> bool {
+        input.starts_with("<?xml") && input[5..].starts_with([' ', '\t', '\r', '\n', '?'])
+    }
+
     // [24] VersionInfo	::= S 'version' Eq ("'" VersionNum "'" | '"' VersionNum '"')
     fn parse_version_info(input: &str) -> IResult<&str, String> {
         map(
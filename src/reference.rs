@@ -2,8 +2,9 @@
 
 use crate::{
     attribute::AttributeValue,
+    error::{Error, ParseError},
     parse::Parse,
-    prolog::subset::entity::{entity_value::EntityValue, EntitySource},
+    prolog::subset::entity::{entity_value::EntityValue, expansion::ExpansionBudget, EntitySource},
     transcode::Decode,
     Document, IResult, Name,
 };
@@ -12,7 +13,8 @@ use nom::{
     bytes::complete::tag,
     character::complete::{char, digit1, hex_digit1},
     combinator::map,
-    sequence::tuple,
+    error::context,
+    sequence::{delimited, tuple},
 };
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
@@ -28,135 +30,256 @@ impl<'a> Parse<'a> for Reference {
     type Output = IResult<&'a str, Self>;
     //[67] Reference ::= EntityRef | CharRef
     fn parse(input: &'a str, args: Self::Args) -> Self::Output {
-        alt((
-            move |i| Self::parse_entity_ref(i, args.clone()),
-            Self::parse_char_reference,
-        ))(input)
+        context(
+            "[67] Reference",
+            alt((
+                move |i| Self::parse_entity_ref(i, args.clone()),
+                Self::parse_char_reference,
+            )),
+        )(input)
     }
 }
 impl Reference {
+    /// Expands an entity reference to its replacement, following references to other entities
+    ///
+    /// Fails with [`Error::RecursiveEntity`] when an entity references itself, and with
+    /// [`Error::EntityExpansionLimitExceeded`] when the [`EntityExpansionLimits`](crate::config::EntityExpansionLimits)
+    /// of the document are exceeded.
     pub(crate) fn normalize_entity(
         &self,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
-    ) -> EntityValue {
+        budget: &ExpansionBudget,
+    ) -> Result<EntityValue, Error> {
+        self.expand_entity_reference(&entity_references, budget)
+    }
+
+    fn expand_entity_reference(
+        &self,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        budget: &ExpansionBudget,
+    ) -> Result<EntityValue, Error> {
         match self {
             Reference::EntityRef(name) => {
-                let refs_map = entity_references.borrow();
-
-                // Try to find the most appropriate source based on available references
-                let possible_sources = [EntitySource::External, EntitySource::Internal];
-                let entity_value = possible_sources
-                    .iter()
-                    .filter_map(|source| refs_map.get(&(name.clone(), source.clone())).cloned())
-                    .next()
-                    .unwrap_or_else(|| EntityValue::Value(name.local_part.clone())); // Default to just returning the name if no entity is found
-
-                match entity_value {
-                    EntityValue::Value(val) => {
-                        if refs_map.contains_key(&(
-                            Name {
-                                prefix: None,
-                                local_part: val.clone(),
-                            },
-                            EntitySource::Internal,
-                        )) {
-                            // This value is another reference, recurse
-                            let reference_name = Name {
-                                prefix: None,
-                                local_part: val,
-                            };
-                            Reference::EntityRef(reference_name)
-                                .normalize_entity(entity_references.clone())
-                        } else {
-                            EntityValue::Value(val)
-                        }
+                budget.check_reference(name, false)?;
+                let entity_value = {
+                    let refs_map = entity_references.borrow();
+
+                    // Try to find the most appropriate source based on available references
+                    let possible_sources = [EntitySource::External, EntitySource::Internal];
+                    possible_sources
+                        .iter()
+                        .filter_map(|source| refs_map.get(&(name.clone(), source.clone())).cloned())
+                        .next()
+                };
+                // Default to just returning the name if no entity is found
+                let Some(entity_value) = entity_value else {
+                    budget.check_undeclared(name)?;
+                    budget.record_expansion(name)?;
+                    budget.record_expanded_size(name, name.local_part.len())?;
+                    return Ok(EntityValue::Value(name.local_part.clone()));
+                };
+
+                budget.enter(name)?;
+                let expanded = match entity_value {
+                    EntityValue::Value(text) => {
+                        Self::expand_replacement_text(name, &text, entity_references, budget)
                     }
                     EntityValue::Reference(ref next_ref) => {
                         // Recursively resolve the next reference
-                        next_ref.normalize_entity(entity_references.clone())
+                        next_ref.expand_entity_reference(entity_references, budget)
                     }
-                    _ => entity_value,
+                    EntityValue::Document(document) => budget
+                        .record_expanded_size(name, Self::document_size(&document))
+                        .map(|()| EntityValue::Document(document)),
+                    entity_value => Ok(entity_value),
+                };
+                budget.exit();
+                expanded
+            }
+            Reference::CharRef(value) => Ok(EntityValue::Value(value.clone())),
+        }
+    }
+
+    // Expands the references in the replacement text of the entity `name`, which is parsed as content when it
+    // has markup
+    //
+    // The text is counted against the budget before it is added to the expansion, the text of the entities it
+    // references is counted as they are expanded, so an expansion stops growing once the size limit is reached.
+    fn expand_replacement_text(
+        name: &Name,
+        text: &str,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        budget: &ExpansionBudget,
+    ) -> Result<EntityValue, Error> {
+        if text.contains('<') {
+            budget.record_expanded_size(name, text.len())?;
+            return match Document::parse_content(
+                text,
+                entity_references,
+                EntitySource::Internal,
+                budget,
+            ) {
+                Ok(("", Document::Nested(mut documents))) if documents.len() == 1 => {
+                    Ok(EntityValue::Document(documents.remove(0)))
                 }
+                Ok(("", document)) => Ok(EntityValue::Document(document)),
+                Ok(_) | Err(nom::Err::Incomplete(_)) => Err(Error::MalformedEntity(name.clone())),
+                Err(nom::Err::Error(e) | nom::Err::Failure(e)) => Err(e.into_root_cause()),
+            };
+        }
+        let mut documents = vec![];
+        let mut value = String::new();
+        let mut rest = text;
+        while let Some(position) = rest.find('&') {
+            budget.record_expanded_size(name, position)?;
+            value.push_str(&rest[..position]);
+            let (remaining, reference) =
+                Reference::parse(&rest[position..], EntitySource::Internal)
+                    .map_err(|_| Error::MalformedEntity(name.clone()))?;
+            match reference.expand_entity_reference(entity_references, budget)? {
+                EntityValue::Value(text) => value.push_str(&text),
+                EntityValue::Document(document) => {
+                    if !value.is_empty() {
+                        documents.push(Document::Content(Some(std::mem::take(&mut value))));
+                    }
+                    match document {
+                        Document::Nested(nested) => documents.extend(nested),
+                        document => documents.push(document),
+                    }
+                }
+                _ => {}
+            }
+            rest = remaining;
+        }
+        budget.record_expanded_size(name, rest.len())?;
+        value.push_str(rest);
+        if documents.is_empty() {
+            return Ok(EntityValue::Value(value));
+        }
+        if !value.is_empty() {
+            documents.push(Document::Content(Some(value)));
+        }
+        Ok(EntityValue::Document(Document::Nested(documents)))
+    }
+
+    // The length of the text in a parsed external entity
+    fn document_size(document: &Document) -> usize {
+        match document {
+            Document::Element(_, content, _) => Self::document_size(content),
+            Document::Nested(documents) => documents.iter().map(Self::document_size).sum(),
+            Document::Content(Some(text)) | Document::Comment(text) | Document::CDATA(text) => {
+                text.len()
             }
-            Reference::CharRef(value) => EntityValue::Value(value.clone()),
+            _ => 0,
         }
     }
 
+    /// Expands an entity reference in an attribute value, see [`Reference::normalize_entity`] for the errors
     pub(crate) fn normalize_attribute(
         &self,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> AttributeValue {
+        budget: &ExpansionBudget,
+    ) -> Result<AttributeValue, Error> {
+        self.expand_attribute_reference(&entity_references, entity_source, budget)
+    }
+
+    fn expand_attribute_reference(
+        &self,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        entity_source: EntitySource,
+        budget: &ExpansionBudget,
+    ) -> Result<AttributeValue, Error> {
         match self {
             Reference::EntityRef(name) => {
-                let refs_map = entity_references.borrow();
-                match refs_map
+                budget.check_reference(name, true)?;
+                // The entities of the DTD being parsed are known to the budget, for attribute defaults
+                let entity_value = entity_references
+                    .borrow()
                     .get(&(name.clone(), entity_source.clone()))
                     .cloned()
-                {
-                    Some(EntityValue::Value(val))
-                        if refs_map.contains_key(&(
-                            Name {
-                                prefix: None,
-                                local_part: val.clone(),
-                            },
-                            entity_source.clone(),
-                        )) =>
-                    {
-                        let reference_name = Name {
-                            prefix: None,
-                            local_part: val,
-                        };
-                        Reference::EntityRef(reference_name)
-                            .normalize_attribute(entity_references.clone(), entity_source.clone())
+                    .or_else(|| budget.entity_value(name));
+                let Some(entity_value) = entity_value else {
+                    budget.check_undeclared(name)?;
+                    budget.record_expansion(name)?;
+                    budget.record_expanded_size(name, name.local_part.len())?;
+                    return Ok(if entity_source == EntitySource::External {
+                        AttributeValue::Reference(self.clone())
+                    } else {
+                        AttributeValue::Value(name.local_part.clone())
+                    });
+                };
+
+                budget.enter(name)?;
+                let expanded = match entity_value {
+                    EntityValue::Value(text) => {
+                        Self::expand_attribute_text(name, &text, entity_references, budget)
                     }
-                    Some(EntityValue::Reference(Reference::EntityRef(entity))) => {
-                        if let Some(EntityValue::Value(val)) = refs_map
-                            .get(&(entity.clone(), EntitySource::Internal))
-                            .cloned()
-                        {
-                            AttributeValue::Value(val)
+                    EntityValue::Reference(reference) => reference.expand_attribute_reference(
+                        entity_references,
+                        Self::attribute_entity_source(&reference, entity_references),
+                        budget,
+                    ),
+                    EntityValue::Document(doc) => {
+                        if let Document::Empty = doc {
+                            Ok(AttributeValue::EmptyExternalReference)
                         } else {
-                            Reference::EntityRef(entity.clone()).normalize_attribute(
-                                entity_references.clone(),
-                                EntitySource::External,
+                            unimplemented!(
+                                "Unexpected Document variant to convert to AttributeValue"
                             )
                         }
                     }
-                    Some(entity_value) => {
-                        // Convert EntityValue to AttributeValue
-                        match entity_value {
-                            EntityValue::Value(val) => AttributeValue::Value(val),
-                            EntityValue::Reference(reference) => reference.normalize_attribute(
-                                entity_references.clone(),
-                                entity_source.clone(),
-                            ),
-                            EntityValue::Document(doc) => {
-                                if let Document::Empty = doc {
-                                    AttributeValue::EmptyExternalReference
-                                } else {
-                                    unimplemented!(
-                                        "Unexpected Document variant to convert to AttributeValue"
-                                    )
-                                }
-                            }
-                            _ => panic!("Unexpected EntityValue variant"),
-                        }
-                    }
-                    None => {
-                        if entity_source == EntitySource::External {
-                            if let Reference::EntityRef(_name) = &self {
-                                AttributeValue::Reference(self.clone())
-                            } else {
-                                AttributeValue::Value(name.local_part.clone())
-                            }
-                        } else {
-                            AttributeValue::Value(name.local_part.clone())
-                        }
-                    }
-                }
+                    _ => panic!("Unexpected EntityValue variant"),
+                };
+                budget.exit();
+                expanded
+            }
+            Reference::CharRef(value) => Ok(AttributeValue::Value(value.clone())),
+        }
+    }
+
+    // Expands the references in the replacement text of the entity `name` referenced in an attribute value
+    fn expand_attribute_text(
+        name: &Name,
+        text: &str,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+        budget: &ExpansionBudget,
+    ) -> Result<AttributeValue, Error> {
+        let mut value = String::new();
+        let mut rest = text;
+        while let Some(position) = rest.find('&') {
+            budget.record_expanded_size(name, position)?;
+            value.push_str(&rest[..position]);
+            let (remaining, reference) =
+                Reference::parse(&rest[position..], EntitySource::Internal)
+                    .map_err(|_| Error::MalformedEntity(name.clone()))?;
+            let entity_source = Self::attribute_entity_source(&reference, entity_references);
+            if let AttributeValue::Value(text) =
+                reference.expand_attribute_reference(entity_references, entity_source, budget)?
+            {
+                value.push_str(&text);
             }
-            Reference::CharRef(value) => AttributeValue::Value(value.clone()),
+            rest = remaining;
+        }
+        budget.record_expanded_size(name, rest.len())?;
+        value.push_str(rest);
+        Ok(AttributeValue::Value(value))
+    }
+
+    // References in replacement text are to internal entities, or else to external ones
+    fn attribute_entity_source(
+        reference: &Reference,
+        entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
+    ) -> EntitySource {
+        match reference {
+            Reference::EntityRef(name)
+                if !entity_references
+                    .borrow()
+                    .contains_key(&(name.clone(), EntitySource::Internal)) =>
+            {
+                EntitySource::External
+            }
+            _ => EntitySource::Internal,
         }
     }
 }
@@ -192,24 +315,30 @@ pub trait ParseReference<'a>: Parse<'a> + Decode {
 
     //[66] CharRef ::= '&#' [0-9]+ ';' | '&#x' [0-9a-fA-F]+ ';'
     fn parse_char_reference(input: &str) -> IResult<&str, Reference> {
-        //TODO: remove reconstruction if possible
-        alt((
-            map(
-                tuple((tag("&#"), digit1, tag(";"))),
-                |(start, digits, end): (&str, &str, &str)| {
-                    let reconstructed = format!("{}{}{}", start, digits, end);
-                    let decoded = reconstructed.decode().unwrap().into_owned();
-                    Reference::CharRef(decoded)
-                },
-            ),
-            map(
-                tuple((tag("&#x"), hex_digit1, tag(";"))),
-                |(start, hex, end): (&str, &str, &str)| {
-                    let reconstructed = format!("{}{}{}", start, hex, end);
-                    let decoded = reconstructed.decode().unwrap().into_owned();
-                    Reference::CharRef(decoded)
-                },
-            ),
-        ))(input)
+        let (remaining, code_point) = alt((
+            map(delimited(tag("&#x"), hex_digit1, char(';')), |hex| {
+                u32::from_str_radix(hex, 16)
+            }),
+            map(delimited(tag("&#"), digit1, char(';')), |digits: &str| {
+                digits.parse::<u32>()
+            }),
+        ))(input)?;
+        // [WFC: Legal Character] Characters referred to using character references must match the production for Char
+        match code_point
+            .ok()
+            .and_then(char::from_u32)
+            .filter(|c| Self::is_char(*c))
+        {
+            Some(c) => Ok((remaining, Reference::CharRef(c.to_string()))),
+            None => Err(nom::Err::Failure(Error::ParseError(
+                ParseError::with_cause(
+                    nom::error::ErrorKind::Verify,
+                    input,
+                    Error::IllegalCharacterReference(
+                        input[..input.len() - remaining.len()].to_string(),
+                    ),
+                ),
+            ))),
+        }
     }
 }
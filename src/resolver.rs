@@ -0,0 +1,145 @@
+//! Resolvers supply the content of external entities and external DTD subsets
+//!
+//! Besides the resolvers of this module, [`CatalogResolver`](crate::catalog::CatalogResolver) maps identifiers to
+//! files through XML catalogs.
+//!
+//! A resolver is used by setting [`ExternalEntityParseConfig::resolver`](crate::config::ExternalEntityParseConfig::resolver).
+//! The [`ExternalEntityPolicy`](crate::config::ExternalEntityPolicy) still applies to the files it maps identifiers
+//! to, content that the resolver supplies itself is read under any policy other than `Deny`:
+//!
+//! ```rust
+//! use nom_xml::{
+//!     config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+//!     parse::Parse,
+//!     resolver::MemoryResolver,
+//!     Document,
+//! };
+//! use std::sync::Arc;
+//!
+//! let resolver = MemoryResolver::new().with_system_id("greeting.ent", "Hello");
+//! let config = Config {
+//!     external_parse_config: ExternalEntityParseConfig {
+//!         policy: ExternalEntityPolicy::AllowAll,
+//!         resolver: Some(Arc::new(resolver)),
+//!         ..Default::default()
+//!     },
+//!     ..Default::default()
+//! };
+//! let xml = "<!DOCTYPE doc [<!ENTITY greeting SYSTEM \"greeting.ent\">]><doc>&greeting;</doc>";
+//! let (_, doc) = Document::parse(xml, &config).unwrap();
+//! assert!(doc.to_string().ends_with("<doc>Hello</doc>"));
+//! ```
+
+use crate::error::Error;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Supplies the content of an external entity from its identifiers
+pub trait EntityResolver: Send + Sync {
+    /// Returns the file the entity is read from, or its content
+    ///
+    /// `base_uri` is the [`base_directory`](crate::config::ExternalEntityParseConfig::base_directory) that relative
+    /// system identifiers are resolved against. Returning [`Error::ExternalEntityDisabled`] leaves the entity
+    /// unexpanded, [`Error::ExternalEntityNotAllowed`] stops parsing, and other errors are reported as
+    /// [`Error::EntityFile`].
+    fn resolve(
+        &self,
+        public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+    ) -> Result<ResolvedEntity, Error>;
+}
+
+/// What an [`EntityResolver`] maps the identifiers of an entity to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolvedEntity {
+    /// A file, which is read if the [`ExternalEntityPolicy`](crate::config::ExternalEntityPolicy) allows it
+    File(PathBuf),
+    /// The bytes of the entity, which are decoded like a file
+    Content(Vec<u8>),
+}
+
+/// Maps the system identifier to a file path relative to the base URI
+///
+/// This is the resolver used when none is configured.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileSystemResolver;
+
+impl EntityResolver for FileSystemResolver {
+    fn resolve(
+        &self,
+        _public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+    ) -> Result<ResolvedEntity, Error> {
+        let path = match base_uri {
+            Some(base) => Path::new(base).join(system_id),
+            None => Path::new(system_id).to_path_buf(),
+        };
+        Ok(ResolvedEntity::File(path))
+    }
+}
+
+/// Serves entities from memory, looked up by public identifier first and then by system identifier
+///
+/// Entities that are not in the map are left unexpanded.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    public_ids: HashMap<String, Vec<u8>>,
+    system_ids: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_public_id(mut self, public_id: &str, content: impl Into<Vec<u8>>) -> Self {
+        self.public_ids
+            .insert(public_id.to_string(), content.into());
+        self
+    }
+
+    pub fn with_system_id(mut self, system_id: &str, content: impl Into<Vec<u8>>) -> Self {
+        self.system_ids
+            .insert(system_id.to_string(), content.into());
+        self
+    }
+}
+
+impl EntityResolver for MemoryResolver {
+    fn resolve(
+        &self,
+        public_id: Option<&str>,
+        system_id: &str,
+        _base_uri: Option<&str>,
+    ) -> Result<ResolvedEntity, Error> {
+        public_id
+            .and_then(|public_id| self.public_ids.get(public_id))
+            .or_else(|| self.system_ids.get(system_id))
+            .cloned()
+            .map(ResolvedEntity::Content)
+            .ok_or_else(|| Error::ExternalEntityDisabled {
+                system_id: system_id.to_string(),
+            })
+    }
+}
+
+/// Resolves no entity, so all of them are left unexpanded
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DenyAllResolver;
+
+impl EntityResolver for DenyAllResolver {
+    fn resolve(
+        &self,
+        _public_id: Option<&str>,
+        system_id: &str,
+        _base_uri: Option<&str>,
+    ) -> Result<ResolvedEntity, Error> {
+        Err(Error::ExternalEntityDisabled {
+            system_id: system_id.to_string(),
+        })
+    }
+}
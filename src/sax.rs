@@ -0,0 +1,88 @@
+// sax.rs
+
+use crate::{
+    config::Config,
+    error::Error,
+    event::{Event, EventReader},
+    processing_instruction::ProcessingInstruction,
+    prolog::doctype::DocType,
+    tag::Tag,
+    Name,
+};
+use std::io::BufRead;
+
+/// Callbacks for [`parse`] and [`parse_str`], called in document order as the input is read
+///
+/// Every method has an empty default implementation, so a handler only needs to implement the callbacks it uses.
+///
+/// ```rust
+/// use nom_xml::{config::Config, sax::{self, Handler}, tag::Tag};
+///
+/// #[derive(Default)]
+/// struct ElementCounter {
+///     elements: usize,
+/// }
+///
+/// impl Handler for ElementCounter {
+///     fn start_element(&mut self, _tag: &Tag) {
+///         self.elements += 1;
+///     }
+/// }
+///
+/// let mut counter = ElementCounter::default();
+/// sax::parse_str("<root><a/><b><c/></b></root>", &Config::default(), &mut counter).unwrap();
+/// assert_eq!(counter.elements, 4);
+/// ```
+pub trait Handler {
+    /// Called for start-tags and empty-element tags, empty-element tags are followed by [`end_element`](Handler::end_element)
+    fn start_element(&mut self, _tag: &Tag) {}
+
+    fn end_element(&mut self, _name: &Name) {}
+
+    /// Called with character data, with entity and character references expanded
+    fn characters(&mut self, _text: &str) {}
+
+    /// Called with the contents of a CDATA section, which are passed to [`characters`](Handler::characters) by default
+    fn cdata(&mut self, text: &str) {
+        self.characters(text)
+    }
+
+    fn comment(&mut self, _comment: &str) {}
+
+    fn processing_instruction(&mut self, _processing_instruction: &ProcessingInstruction) {}
+
+    fn doctype(&mut self, _doc_type: &DocType) {}
+}
+
+/// Reads the document from a buffered reader, pushing its events into the handler
+///
+/// Parsing stops at the first error; the handler will have received every event before it.
+pub fn parse<R: BufRead, H: Handler + ?Sized>(
+    reader: R,
+    config: &Config,
+    handler: &mut H,
+) -> Result<(), Error> {
+    for event in EventReader::new(reader, config) {
+        match event? {
+            Event::StartTag(tag) => handler.start_element(&tag),
+            Event::EndTag(tag) => handler.end_element(&tag.name),
+            Event::Text(text) => handler.characters(&text),
+            Event::CDATA(text) => handler.cdata(&text),
+            Event::Comment(comment) => handler.comment(&comment),
+            Event::ProcessingInstruction(processing_instruction) => {
+                handler.processing_instruction(&processing_instruction)
+            }
+            Event::Doctype(doc_type) => handler.doctype(&doc_type),
+        }
+    }
+    Ok(())
+}
+
+/// Parses the document in the string, pushing its events into the handler
+pub fn parse_str<H: Handler + ?Sized>(
+    input: &str,
+    config: &Config,
+    handler: &mut H,
+) -> Result<(), Error> {
+    parse(input.as_bytes(), config, handler)
+}
@@ -0,0 +1,576 @@
+// selector.rs
+
+//! Selection of elements with CSS selectors
+//!
+//! The supported selectors are type selectors and `*`, attribute selectors for presence (`[a]`), equality (`[a=v]`),
+//! prefix (`[a^=v]`), suffix (`[a$=v]`) and substring (`[a*=v]`) matching, the `:nth-child()`, `:first-child` and
+//! `:last-child` pseudo-classes, the descendant (` `) and child (`>`) combinators and selector lists (`,`).
+//!
+//! Type selectors match the local part of element names whatever their prefix, `prefix|name` only the elements
+//! written with `prefix`, and `|name` the unprefixed ones. Attribute names are matched as written, `[xml|lang]`
+//! selects `xml:lang`. Namespace declarations are not attributes.
+//!
+//! ```rust
+//! use nom_xml::{config::Config, parse::Parse, Document};
+//!
+//! let xml = "<catalog><book isbn=\"978-0316129084\"><title>Leviathan Wakes</title></book><book isbn=\"0-316-12908-X\"><title>Unknown</title></book></catalog>";
+//! let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+//! let titles: Vec<String> = doc
+//!     .select("catalog > book[isbn^='978'] title")
+//!     .unwrap()
+//!     .map(ToString::to_string)
+//!     .collect();
+//! assert_eq!(titles, ["<title>Leviathan Wakes</title>"]);
+//! ```
+
+use crate::{
+    attribute::Attribute,
+    error::Error,
+    namespaces::{qualify, text},
+    parse::Parse,
+    tag::Tag,
+    Document, IResult,
+};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till, take_until},
+    character::complete::{char, multispace0, multispace1},
+    combinator::{map, opt, value},
+    multi::{many0, separated_list1},
+    sequence::{delimited, pair, preceded, tuple},
+};
+use std::{fmt, str::FromStr};
+
+/// A parsed selector list, for selecting with the same selector in several documents
+///
+/// ```rust
+/// use nom_xml::{config::Config, parse::Parse, selector::Selector, Document};
+///
+/// let odd_rows: Selector = "row:nth-child(odd)".parse().unwrap();
+/// let (_, doc) = Document::parse("<table><row/><row/><row/></table>", &Config::default()).unwrap();
+/// assert_eq!(odd_rows.select(&doc).count(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Selector {
+    source: String,
+    complex_selectors: Vec<ComplexSelector>,
+}
+
+impl Selector {
+    /// Parses `selector`, failing with [`Error::InvalidSelector`] when it is not a supported selector list
+    pub fn parse(selector: &str) -> Result<Self, Error> {
+        let invalid = |message: String| Error::InvalidSelector {
+            selector: selector.to_string(),
+            message,
+        };
+        let complex_selectors = match delimited(
+            multispace0,
+            separated_list1(
+                delimited(multispace0, char(','), multispace0),
+                parse_complex_selector,
+            ),
+            multispace0,
+        )(selector)
+        {
+            Ok(("", complex_selectors)) => complex_selectors,
+            Ok((remaining, _)) => {
+                return Err(invalid(format!(
+                    "Unexpected `{remaining}` at offset {}",
+                    selector.len() - remaining.len()
+                )))
+            }
+            Err(nom::Err::Failure(Error::InvalidSelector { message, .. })) => {
+                return Err(invalid(message))
+            }
+            Err(_) => return Err(invalid("Expected a selector".to_string())),
+        };
+        Ok(Self {
+            source: selector.to_string(),
+            complex_selectors,
+        })
+    }
+
+    /// The elements of `document` that match the selector, in document order
+    pub fn select<'a>(&self, document: &'a Document) -> Selection<'a> {
+        let mut elements = vec![];
+        let mut ancestors = vec![];
+        let roots = child_elements(document);
+        self.collect(&roots, &mut ancestors, &mut elements);
+        Selection {
+            elements: elements.into_iter(),
+        }
+    }
+
+    fn collect<'a>(
+        &self,
+        siblings: &[(&'a Document, &'a Tag)],
+        ancestors: &mut Vec<ElementContext<'a>>,
+        elements: &mut Vec<&'a Document>,
+    ) {
+        for (index, (element, tag)) in siblings.iter().enumerate() {
+            ancestors.push(ElementContext {
+                tag,
+                position: index + 1,
+                siblings: siblings.len(),
+            });
+            if self
+                .complex_selectors
+                .iter()
+                .any(|complex_selector| complex_selector.matches(ancestors))
+            {
+                elements.push(element);
+            }
+            if let Document::Element(_, content, _) = element {
+                self.collect(&child_elements(content), ancestors, elements);
+            }
+            ancestors.pop();
+        }
+    }
+}
+
+impl FromStr for Selector {
+    type Err = Error;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        Self::parse(selector)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// The elements selected by [`Document::select`] or [`Selector::select`], in document order
+#[derive(Clone, Debug)]
+pub struct Selection<'a> {
+    elements: std::vec::IntoIter<&'a Document>,
+}
+
+impl<'a> Iterator for Selection<'a> {
+    type Item = &'a Document;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.elements.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.elements.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Selection<'_> {}
+
+impl Document {
+    /// The [`Document::Element`]s and [`Document::EmptyTag`]s that match a CSS selector, see [`selector`](crate::selector)
+    pub fn select(&self, selector: &str) -> Result<Selection<'_>, Error> {
+        Ok(Selector::parse(selector)?.select(self))
+    }
+}
+
+/// An element being matched, with the elements that it is nested in before it
+struct ElementContext<'a> {
+    tag: &'a Tag,
+    /// The 1-based index of the element among the elements that have the same parent
+    position: usize,
+    siblings: usize,
+}
+
+/// Compound selectors joined by combinators, `combinators[i]` is between `compounds[i]` and `compounds[i + 1]`
+#[derive(Clone, Debug)]
+struct ComplexSelector {
+    compounds: Vec<CompoundSelector>,
+    combinators: Vec<Combinator>,
+}
+
+impl ComplexSelector {
+    /// Whether the last element of `chain` matches, with its ancestors before it
+    fn matches(&self, chain: &[ElementContext]) -> bool {
+        matches_from_right(&self.compounds, &self.combinators, chain)
+    }
+}
+
+fn matches_from_right(
+    compounds: &[CompoundSelector],
+    combinators: &[Combinator],
+    chain: &[ElementContext],
+) -> bool {
+    let (Some((compound, compounds)), Some((element, ancestors))) =
+        (compounds.split_last(), chain.split_last())
+    else {
+        return false;
+    };
+    if !compound.matches(element) {
+        return false;
+    }
+    let Some((combinator, combinators)) = combinators.split_last() else {
+        return true;
+    };
+    match combinator {
+        Combinator::Child => matches_from_right(compounds, combinators, ancestors),
+        Combinator::Descendant => (1..=ancestors.len())
+            .rev()
+            .any(|end| matches_from_right(compounds, combinators, &ancestors[..end])),
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Clone, Debug, Default)]
+struct CompoundSelector {
+    prefix: PrefixSelector,
+    /// The local part, `None` for `*`
+    local_part: Option<String>,
+    attributes: Vec<AttributeSelector>,
+    pseudo_classes: Vec<PseudoClass>,
+}
+
+impl CompoundSelector {
+    fn matches(&self, element: &ElementContext) -> bool {
+        let name = qualify(&element.tag.name);
+        let prefix_matches = match &self.prefix {
+            PrefixSelector::Any => true,
+            PrefixSelector::None => name.prefix.is_none(),
+            PrefixSelector::Prefix(prefix) => name.prefix.as_ref() == Some(prefix),
+        };
+        prefix_matches
+            && self
+                .local_part
+                .as_ref()
+                .is_none_or(|local_part| name.local_part == *local_part)
+            && self
+                .attributes
+                .iter()
+                .all(|attribute| attribute.matches(element.tag))
+            && self
+                .pseudo_classes
+                .iter()
+                .all(|pseudo_class| pseudo_class.matches(element))
+    }
+}
+
+enum SubclassSelector {
+    Attribute(AttributeSelector),
+    PseudoClass(PseudoClass),
+}
+
+#[derive(Clone, Debug, Default)]
+enum PrefixSelector {
+    #[default]
+    Any,
+    None,
+    Prefix(String),
+}
+
+#[derive(Clone, Debug)]
+struct AttributeSelector {
+    /// The qualified name of the attribute
+    name: String,
+    matcher: Option<(AttributeMatcher, String)>,
+}
+
+impl AttributeSelector {
+    fn matches(&self, tag: &Tag) -> bool {
+        let Some(found) = attribute_value(tag, &self.name) else {
+            return false;
+        };
+        match &self.matcher {
+            None => true,
+            Some((AttributeMatcher::Equal, value)) => found == *value,
+            // An empty value never matches with the substring matchers
+            Some((_, value)) if value.is_empty() => false,
+            Some((AttributeMatcher::Prefix, value)) => found.starts_with(value),
+            Some((AttributeMatcher::Suffix, value)) => found.ends_with(value),
+            Some((AttributeMatcher::Substring, value)) => found.contains(value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum AttributeMatcher {
+    Equal,
+    Prefix,
+    Suffix,
+    Substring,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PseudoClass {
+    /// `:nth-child(an+b)`, `:first-child` is `:nth-child(1)`
+    NthChild {
+        a: i64,
+        b: i64,
+    },
+    LastChild,
+}
+
+impl PseudoClass {
+    fn matches(&self, element: &ElementContext) -> bool {
+        match *self {
+            PseudoClass::NthChild { a, b } => {
+                // Whether position = a * n + b for some n >= 0
+                let offset = element.position as i64 - b;
+                match a {
+                    0 => offset == 0,
+                    a => offset % a == 0 && offset / a >= 0,
+                }
+            }
+            PseudoClass::LastChild => element.position == element.siblings,
+        }
+    }
+}
+
+/// The elements of `content` with their tags, skipping the prolog of documents
+fn child_elements(content: &Document) -> Vec<(&Document, &Tag)> {
+    let mut elements = vec![];
+    collect_child_elements(content, &mut elements);
+    elements
+}
+
+fn collect_child_elements<'a>(content: &'a Document, elements: &mut Vec<(&'a Document, &'a Tag)>) {
+    match content {
+        Document::Element(tag, ..) | Document::EmptyTag(tag) => elements.push((content, tag)),
+        Document::Nested(documents) => {
+            for document in documents {
+                collect_child_elements(document, elements);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn attribute_value(tag: &Tag, name: &str) -> Option<String> {
+    tag.attributes
+        .iter()
+        .flatten()
+        .find_map(|attribute| match attribute {
+            Attribute::Instance {
+                name: attribute_name,
+                value,
+            } if attribute_name.to_string() == name => Some(text(value)),
+            _ => None,
+        })
+}
+
+/// Fails the parse with a message for [`Error::InvalidSelector`]
+fn failure<'a, T>(message: String) -> IResult<&'a str, T> {
+    Err(nom::Err::Failure(Error::InvalidSelector {
+        selector: String::new(),
+        message,
+    }))
+}
+
+// complex-selector ::= compound-selector (combinator compound-selector)*
+fn parse_complex_selector(input: &str) -> IResult<&str, ComplexSelector> {
+    let (input, first) = parse_compound_selector(input)?;
+    let (input, rest) = many0(pair(parse_combinator, parse_compound_selector))(input)?;
+    let mut complex_selector = ComplexSelector {
+        compounds: vec![first],
+        combinators: vec![],
+    };
+    for (combinator, compound) in rest {
+        complex_selector.combinators.push(combinator);
+        complex_selector.compounds.push(compound);
+    }
+    Ok((input, complex_selector))
+}
+
+// combinator ::= S* '>' S* | S+
+fn parse_combinator(input: &str) -> IResult<&str, Combinator> {
+    alt((
+        value(
+            Combinator::Child,
+            delimited(multispace0, char('>'), multispace0),
+        ),
+        value(Combinator::Descendant, multispace1),
+    ))(input)
+}
+
+// compound-selector ::= type-selector? (attribute-selector | pseudo-class)*, with at least one of them
+fn parse_compound_selector(input: &str) -> IResult<&str, CompoundSelector> {
+    let (input, type_selector) = opt(parse_type_selector)(input)?;
+    let (input, subclass_selectors) = many0(alt((
+        map(parse_attribute_selector, SubclassSelector::Attribute),
+        map(parse_pseudo_class, SubclassSelector::PseudoClass),
+    )))(input)?;
+    let mut attributes = vec![];
+    let mut pseudo_classes = vec![];
+    for subclass_selector in subclass_selectors {
+        match subclass_selector {
+            SubclassSelector::Attribute(attribute) => attributes.push(attribute),
+            SubclassSelector::PseudoClass(pseudo_class) => pseudo_classes.push(pseudo_class),
+        }
+    }
+    if type_selector.is_none() && attributes.is_empty() && pseudo_classes.is_empty() {
+        return Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
+            input.to_string(),
+            nom::error::ErrorKind::Verify,
+        ))));
+    }
+    let (prefix, local_part) = type_selector.unwrap_or((PrefixSelector::Any, None));
+    Ok((
+        input,
+        CompoundSelector {
+            prefix,
+            local_part,
+            attributes,
+            pseudo_classes,
+        },
+    ))
+}
+
+// type-selector ::= (prefix? '|')? (ident | '*')
+fn parse_type_selector(input: &str) -> IResult<&str, (PrefixSelector, Option<String>)> {
+    let local_part = |input| alt((map(parse_identifier, Some), value(None, char('*'))))(input);
+    alt((
+        map(
+            tuple((
+                alt((
+                    map(parse_identifier, PrefixSelector::Prefix),
+                    value(PrefixSelector::Any, char('*')),
+                )),
+                char('|'),
+                local_part,
+            )),
+            |(prefix, _bar, local_part)| (prefix, local_part),
+        ),
+        map(preceded(char('|'), local_part), |local_part| {
+            (PrefixSelector::None, local_part)
+        }),
+        map(local_part, |local_part| (PrefixSelector::Any, local_part)),
+    ))(input)
+}
+
+// attribute-selector ::= '[' S* attribute-name S* (matcher S* (ident | string) S*)? ']'
+fn parse_attribute_selector(input: &str) -> IResult<&str, AttributeSelector> {
+    let (input, _) = pair(char('['), multispace0)(input)?;
+    let (input, name) = parse_attribute_name(input)?;
+    let (input, matcher) = opt(pair(
+        delimited(
+            multispace0,
+            alt((
+                value(AttributeMatcher::Equal, tag("=")),
+                value(AttributeMatcher::Prefix, tag("^=")),
+                value(AttributeMatcher::Suffix, tag("$=")),
+                value(AttributeMatcher::Substring, tag("*=")),
+            )),
+            multispace0,
+        ),
+        alt((parse_string, parse_identifier)),
+    ))(input)?;
+    let (input, closed) = opt(preceded(multispace0, char(']')))(input)?;
+    if closed.is_none() {
+        return failure(format!("Expected `]` in attribute selector `[{name}`"));
+    }
+    Ok((input, AttributeSelector { name, matcher }))
+}
+
+/// The qualified name of an attribute, `prefix|name` is written `prefix:name` in the document
+fn parse_attribute_name(input: &str) -> IResult<&str, String> {
+    alt((
+        map(
+            tuple((parse_identifier, char('|'), parse_identifier)),
+            |(prefix, _bar, local_part)| format!("{prefix}:{local_part}"),
+        ),
+        preceded(opt(char('|')), parse_identifier),
+    ))(input)
+}
+
+// pseudo-class ::= ':' ident ('(' argument ')')?
+fn parse_pseudo_class(input: &str) -> IResult<&str, PseudoClass> {
+    let (input, name) = preceded(char(':'), parse_identifier)(input)?;
+    let (input, argument) = opt(delimited(char('('), take_until(")"), char(')')))(input)?;
+    let pseudo_class = match (name.as_str(), argument) {
+        ("first-child", None) => PseudoClass::NthChild { a: 0, b: 1 },
+        ("last-child", None) => PseudoClass::LastChild,
+        ("nth-child", Some(argument)) => match parse_nth(argument) {
+            Some((a, b)) => PseudoClass::NthChild { a, b },
+            None => return failure(format!("Invalid argument `{argument}` of `:nth-child()`")),
+        },
+        ("nth-child", None) => return failure("`:nth-child()` needs an argument".to_string()),
+        (name, _) => return failure(format!("Unsupported pseudo-class `:{name}`")),
+    };
+    Ok((input, pseudo_class))
+}
+
+/// Parses the `an+b` argument of `:nth-child()`, including `odd` and `even`
+fn parse_nth(argument: &str) -> Option<(i64, i64)> {
+    let argument: String = argument
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match argument.as_str() {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => {}
+    }
+    let integer = |number: &str| -> Option<i64> {
+        let digits = number.strip_prefix(['+', '-']).unwrap_or(number);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        number.parse().ok()
+    };
+    match argument.split_once('n') {
+        Some((a, b)) => {
+            let a = match a {
+                "" | "+" => 1,
+                "-" => -1,
+                a => integer(a)?,
+            };
+            let b = match b {
+                "" => 0,
+                b if b.starts_with(['+', '-']) => integer(b)?,
+                _ => return None,
+            };
+            Some((a, b))
+        }
+        None => Some((0, integer(&argument)?)),
+    }
+}
+
+/// An identifier, in which any character can be escaped with a backslash, e.g. `a\:b`
+fn parse_identifier(input: &str) -> IResult<&str, String> {
+    let mut identifier = String::new();
+    let mut chars = input.char_indices().peekable();
+    let mut end = 0;
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((escaped_index, escaped)) => {
+                    identifier.push(escaped);
+                    end = escaped_index + escaped.len_utf8();
+                    continue;
+                }
+                None => break,
+            },
+            // The CSS ident characters that are also allowed in XML names
+            c if Document::is_name_char(c) && !matches!(c, ':' | '.') => identifier.push(c),
+            _ => break,
+        }
+        end = index + c.len_utf8();
+    }
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(nom::Err::Error(Error::NomError(nom::error::Error::new(
+            input.to_string(),
+            nom::error::ErrorKind::AlphaNumeric,
+        ))));
+    }
+    Ok((&input[end..], identifier))
+}
+
+fn parse_string(input: &str) -> IResult<&str, String> {
+    map(
+        alt((
+            delimited(char('"'), take_till(|c| c == '"'), char('"')),
+            delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+        )),
+        str::to_string,
+    )(input)
+}
@@ -1,15 +1,16 @@
 use crate::{
-    attribute::{Attribute, AttributeValue, DefaultDecl},
-    error::Error,
+    attribute::{Attribute, AttributeValue, DefaultDecl, Prefix},
+    error::{Error, ParseError},
     namespaces::ParseNamespace,
     parse::Parse,
-    prolog::subset::entity::{entity_value::EntityValue, EntitySource},
+    prolog::subset::entity::{entity_value::EntityValue, expansion::ExpansionBudget, EntitySource},
     IResult, Name,
 };
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    combinator::{map, map_res, opt},
+    combinator::{map, map_res, opt, verify},
+    error::context,
     multi::{many0, many1},
     sequence::{delimited, pair, tuple},
 };
@@ -50,38 +51,49 @@ impl Tag {
 
     // [40] STag ::= '<' Name (S Attribute)* S? '>'
     // Namespaces (Third Edition) [12] STag ::= '<' QName (S Attribute)* S? '>'
-    pub fn parse_start_tag(
-        input: &str,
+    pub fn parse_start_tag<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, Self> {
-        map(
-            tuple((
-                alt((tag("&#60;"), tag("&#x3C;"), tag("<"))),
-                alt((Self::parse_qualified_name, Self::parse_name)),
-                many0(pair(Self::parse_multispace1, |i| {
-                    Attribute::parse_attribute(i, entity_references.clone(), entity_source.clone())
-                })),
-                Self::parse_multispace0,
-                alt((tag("&#62;"), tag("&#x3E;"), tag(">"))),
-            )),
-            |(_open_char, name, attributes, _whitespace, _close_char)| {
-                let attributes: Vec<_> = attributes
-                    .into_iter()
-                    .map(|(_whitespace, attr)| attr)
-                    .collect();
-                Self {
-                    name,
-                    attributes: if attributes.is_empty() {
-                        // check doctype here, if within that, add them to the tag else, None
-                        None
-                    } else {
-                        Some(attributes)
-                    },
-                    state: TagState::Start,
-                }
-            },
-        )(input)
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Self> {
+        let (remaining, start_tag) = context(
+            "[40] STag",
+            map(
+                tuple((
+                    alt((tag("&#60;"), tag("&#x3C;"), tag("<"))),
+                    alt((Self::parse_qualified_name, Self::parse_name)),
+                    many0(pair(Self::parse_multispace1, |i| {
+                        Attribute::parse_attribute(
+                            i,
+                            entity_references.clone(),
+                            entity_source.clone(),
+                            budget,
+                        )
+                    })),
+                    Self::parse_multispace0,
+                    alt((tag("&#62;"), tag("&#x3E;"), tag(">"))),
+                )),
+                |(_open_char, name, attributes, _whitespace, _close_char)| {
+                    let attributes: Vec<_> = attributes
+                        .into_iter()
+                        .map(|(_whitespace, attr)| attr)
+                        .collect();
+                    Self {
+                        name,
+                        attributes: if attributes.is_empty() {
+                            // check doctype here, if within that, add them to the tag else, None
+                            None
+                        } else {
+                            Some(attributes)
+                        },
+                        state: TagState::Start,
+                    }
+                },
+            ),
+        )(input)?;
+        start_tag.check_unique_attributes(input)?;
+        Ok((remaining, start_tag))
     }
 
     pub fn parse_start_tag_by_name<'a>(
@@ -90,6 +102,7 @@ impl Tag {
         attributes: &Option<Vec<Attribute>>,
         entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
+        budget: &ExpansionBudget,
     ) -> IResult<&'a str, Self> {
         let mut current_input = input;
 
@@ -111,7 +124,7 @@ impl Tag {
                         },
                     ),
                     many0(pair(Self::parse_multispace1, |i| {
-                        Attribute::parse_attribute(i, entity_references.clone(), entity_source.clone())
+                        Attribute::parse_attribute(i, entity_references.clone(), entity_source.clone(), budget)
                     })),
                     Self::parse_multispace0,
                     alt((tag("&#62;"), tag("&#x3E;"), tag(">"))),
@@ -171,21 +184,24 @@ impl Tag {
     // [42] ETag ::= '</' Name S? '>'
     // Namespaces (Third Edition) [13] ETag ::= '</' QName S? '>'
     pub fn parse_end_tag(input: &str) -> IResult<&str, Self> {
-        delimited(
-            alt((tag("&#60;/"), tag("&#x3C;/"), tag("</"))),
-            map(
-                tuple((
-                    Self::parse_multispace0,
-                    alt((Self::parse_qualified_name, Self::parse_name)),
-                    Self::parse_multispace0,
-                )),
-                |(_open_tag, name, _close_tag)| Self {
-                    name,
-                    attributes: None, // Attributes are not parsed for end tags
-                    state: TagState::End,
-                },
+        context(
+            "[42] ETag",
+            delimited(
+                alt((tag("&#60;/"), tag("&#x3C;/"), tag("</"))),
+                map(
+                    tuple((
+                        Self::parse_multispace0,
+                        alt((Self::parse_qualified_name, Self::parse_name)),
+                        Self::parse_multispace0,
+                    )),
+                    |(_open_tag, name, _close_tag)| Self {
+                        name,
+                        attributes: None, // Attributes are not parsed for end tags
+                        state: TagState::End,
+                    },
+                ),
+                alt((tag("&#62;"), tag("&#x3E;"), tag(">"))),
             ),
-            alt((tag("&#62;"), tag("&#x3E;"), tag(">"))),
         )(input)
     }
     // [42] ETag ::= '</' Name S? '>'
@@ -223,28 +239,73 @@ impl Tag {
 
     // [44] EmptyElemTag ::= '<' Name (S Attribute)* S? '/>'
     // Namespaces (Third Edition) [14] EmptyElemTag ::= '<' QName (S Attribute)* S? '/>'
-    pub fn parse_empty_element_tag(
-        input: &str,
+    pub fn parse_empty_element_tag<'a>(
+        input: &'a str,
         entity_references: Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
-    ) -> IResult<&str, Self> {
-        map(
-            tuple((
-                alt((tag("&#60;"), tag("&#x3C;"), tag("<"))),
-                alt((Self::parse_qualified_name, Self::parse_name)),
-                opt(many1(pair(Self::parse_multispace1, |i| {
-                    Attribute::parse(i, (entity_references.clone(), entity_source.clone()))
-                }))),
-                Self::parse_multispace0,
-                alt((tag("/&#62;"), tag("/&#x3E;"), tag("/>"))),
-            )),
-            |(_open_tag, name, attributes, _whitespace, _close_tag)| Self {
-                name,
-                attributes: attributes
-                    .map(|attr| attr.into_iter().map(|(_whitespace, attr)| attr).collect()),
-                state: TagState::Empty,
-            },
-        )(input)
+        budget: &ExpansionBudget,
+    ) -> IResult<&'a str, Self> {
+        let (remaining, empty_tag) = context(
+            "[44] EmptyElemTag",
+            map(
+                tuple((
+                    alt((tag("&#60;"), tag("&#x3C;"), tag("<"))),
+                    alt((Self::parse_qualified_name, Self::parse_name)),
+                    opt(many1(pair(Self::parse_multispace1, |i| {
+                        Attribute::parse(
+                            i,
+                            (
+                                entity_references.clone(),
+                                entity_source.clone(),
+                                budget.clone(),
+                            ),
+                        )
+                    }))),
+                    Self::parse_multispace0,
+                    alt((tag("/&#62;"), tag("/&#x3E;"), tag("/>"))),
+                )),
+                |(_open_tag, name, attributes, _whitespace, _close_tag)| Self {
+                    name,
+                    attributes: attributes
+                        .map(|attr| attr.into_iter().map(|(_whitespace, attr)| attr).collect()),
+                    state: TagState::Empty,
+                },
+            ),
+        )(input)?;
+        empty_tag.check_unique_attributes(input)?;
+        Ok((remaining, empty_tag))
+    }
+
+    // [WFC: Unique Att Spec] An attribute name must not appear more than once in the same start-tag or empty-element tag
+    fn check_unique_attributes(&self, input: &str) -> Result<(), nom::Err<Error>> {
+        let mut seen_names = std::collections::HashSet::new();
+        for attribute in self.attributes.iter().flatten() {
+            let name = match attribute {
+                Attribute::Instance { name, .. } => name.clone(),
+                Attribute::Namespace {
+                    prefix: Prefix::Default,
+                    ..
+                } => Name::new(None, "xmlns"),
+                Attribute::Namespace {
+                    prefix: Prefix::Prefix(prefix),
+                    ..
+                } => Name::new(Some("xmlns"), prefix),
+                _ => continue,
+            };
+            if !seen_names.insert(name.to_string()) {
+                return Err(nom::Err::Failure(Error::ParseError(
+                    ParseError::with_cause(
+                        nom::error::ErrorKind::Verify,
+                        input,
+                        Error::DuplicateAttribute {
+                            tag: self.name.clone(),
+                            attribute: name,
+                        },
+                    ),
+                )));
+            }
+        }
+        Ok(())
     }
 
     // [44] EmptyElemTag ::= '<' Name (S Attribute)* S? '/>'
@@ -255,19 +316,30 @@ impl Tag {
         _attributes: &Option<Vec<Attribute>>, //TODO: implement empty tag attribute matching
         entity_references: &Rc<RefCell<HashMap<(Name, EntitySource), EntityValue>>>,
         entity_source: EntitySource,
+        budget: &ExpansionBudget,
     ) -> IResult<&'a str, Self> {
         map(
             tuple((
                 alt((tag("&#60;"), tag("&#x3C;"), tag("<"))),
-                tag(tag_name),
+                verify(
+                    alt((Self::parse_qualified_name, Self::parse_name)),
+                    |name: &Name| name.local_part == tag_name,
+                ),
                 opt(many1(pair(Self::parse_multispace1, |i| {
-                    Attribute::parse(i, (entity_references.clone(), entity_source.clone()))
+                    Attribute::parse(
+                        i,
+                        (
+                            entity_references.clone(),
+                            entity_source.clone(),
+                            budget.clone(),
+                        ),
+                    )
                 }))),
                 Self::parse_multispace0,
                 alt((tag("/&#62;"), tag("/&#x3E;"), tag("/>"))),
             )),
             |(_open_tag, name, attributes, _whitespace, _close_tag)| Self {
-                name: Name::new(None, name),
+                name,
                 attributes: attributes
                     .map(|attr| attr.into_iter().map(|(_whitespace, attr)| attr).collect()),
                 state: TagState::Empty,
@@ -308,6 +380,31 @@ impl Tag {
         }
     }
 
+    /// Normalizes the values of the attributes that are declared with a type other than `CDATA`, see
+    /// [`AttType::normalize_value`](crate::attribute::AttType::normalize_value)
+    pub fn normalize_attributes(&mut self, att_defs: &[Attribute]) {
+        for attribute in self.attributes.iter_mut().flatten() {
+            if let Attribute::Instance {
+                name,
+                value: AttributeValue::Value(value),
+            } = attribute
+            {
+                let name = name.to_string();
+                let att_type = att_defs.iter().find_map(|att_def| match att_def {
+                    Attribute::Definition {
+                        name: def_name,
+                        att_type,
+                        ..
+                    } if def_name.to_string() == name => Some(att_type),
+                    _ => None,
+                });
+                if let Some(att_type) = att_type {
+                    *value = att_type.normalize_value(value);
+                }
+            }
+        }
+    }
+
     pub fn add_attributes(&mut self, new_attributes: Vec<Attribute>) {
         self.attributes = if new_attributes.is_empty() {
             None
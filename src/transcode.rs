@@ -9,7 +9,7 @@ use std::borrow::Cow;
 pub trait Decode {
     fn as_str(&self) -> &str;
 
-    fn decode(&self) -> Result<Cow<str>, Box<dyn std::error::Error + '_>> {
+    fn decode(&self) -> Result<Cow<'_, str>, Box<dyn std::error::Error + '_>> {
         match self.as_str() {
             "amp" => Ok(Cow::Borrowed("&")),
             "lt" => Ok(Cow::Borrowed("<")),
@@ -30,14 +30,11 @@ pub trait Decode {
                     Err(e) => Err(Box::new(e)),
                 }
             }
-            _ => Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to decode",
-            ))),
+            _ => Err(Box::new(std::io::Error::other("Failed to decode"))),
         }
     }
 
-    fn decode_hex(&self, code: &str) -> IResult<&str, Cow<str>, Error<&str>> {
+    fn decode_hex(&self, code: &str) -> IResult<&str, Cow<'_, str>, Error<&str>> {
         match u32::from_str_radix(code, 16) {
             Ok(n) => match char::from_u32(n) {
                 Some(c) => Ok((self.as_str(), Cow::Owned(c.to_string()))),
@@ -53,7 +50,7 @@ pub trait Decode {
         }
     }
 
-    fn decode_digit(&self, code: &str) -> IResult<&str, Cow<str>, Error<&str>> {
+    fn decode_digit(&self, code: &str) -> IResult<&str, Cow<'_, str>, Error<&str>> {
         match code.parse::<u32>() {
             Ok(n) => match char::from_u32(n) {
                 Some(c) => Ok((self.as_str(), Cow::Owned(c.to_string()))),
@@ -92,8 +89,7 @@ pub trait Encode {
             ">" => Ok("gt".to_string()),
             "\"" => Ok("quot".to_string()),
             "'" => Ok("apos".to_string()),
-            _ => Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            _ => Err(Box::new(std::io::Error::other(
                 "Unsupported character for encoding",
             ))),
         }
@@ -106,6 +102,47 @@ pub trait Encode {
     fn encode_digit(&self, ch: char) -> String {
         format!("&#{};", ch as u32)
     }
+
+    /// Replaces a character with its predefined entity reference, falling back to a hexadecimal character reference
+    fn encode_reference(&self, ch: char) -> String {
+        let ch_string = ch.to_string();
+        let encoded = match ch_string.encode() {
+            Ok(entity_name) => format!("&{entity_name};"),
+            Err(_) => self.encode_hex(ch),
+        };
+        encoded
+    }
+
+    /// Escapes the value so that it can be written as [14] CharData
+    ///
+    /// `>` is only disallowed as part of `]]>`, but it is always escaped for simplicity.
+    /// Carriage returns are written as character references so they survive end-of-line handling.
+    fn encode_char_data(&self) -> String {
+        let mut encoded = String::with_capacity(self.as_str().len());
+        for ch in self.as_str().chars() {
+            match ch {
+                '&' | '<' | '>' => encoded.push_str(&self.encode_reference(ch)),
+                '\r' => encoded.push_str(&self.encode_hex(ch)),
+                _ => encoded.push(ch),
+            }
+        }
+        encoded
+    }
+
+    /// Escapes the value so that it can be written as a double quoted [10] AttValue
+    ///
+    /// Whitespace other than spaces is written as character references so it survives attribute-value normalization.
+    fn encode_attribute_value(&self) -> String {
+        let mut encoded = String::with_capacity(self.as_str().len());
+        for ch in self.as_str().chars() {
+            match ch {
+                '&' | '<' | '"' => encoded.push_str(&self.encode_reference(ch)),
+                '\t' | '\n' | '\r' => encoded.push_str(&self.encode_hex(ch)),
+                _ => encoded.push(ch),
+            }
+        }
+        encoded
+    }
 }
 
 impl Encode for String {
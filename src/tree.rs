@@ -0,0 +1,398 @@
+// tree.rs
+
+//! Navigation of a document in every direction through an arena of its nodes
+//!
+//! A [`Tree`] borrows a [`Document`] and stores its nodes in document order, each with the [`NodeId`] of its parent,
+//! first and last children and previous and next siblings, so that moving up or sideways takes constant time. The
+//! nodes follow the XPath data model: the root node, elements, attributes, text, comments and processing
+//! instructions. The attributes of an element come right after it and are neither children nor siblings. Namespace
+//! declarations are not attributes.
+//!
+//! ```rust
+//! use nom_xml::{config::Config, parse::Parse, tree::Node, Document};
+//!
+//! let xml = "<catalog><book isbn=\"978-0316129084\"><title>Leviathan Wakes</title></book><book/></catalog>";
+//! let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+//! let tree = doc.tree();
+//!
+//! let text = tree.descendants(tree.root()).find(|&id| tree.node(id) == Node::Text("Leviathan Wakes")).unwrap();
+//! let book = tree.ancestors(text).nth(1).unwrap();
+//! assert_eq!(tree.path(book), "/catalog/book[1]");
+//! assert_eq!(tree.path(tree.next_sibling(book).unwrap()), "/catalog/book[2]");
+//! let isbn = tree.attributes(book).next().unwrap();
+//! assert_eq!(tree.path(isbn), "/catalog/book[1]/@isbn");
+//! assert_eq!(tree.parent(isbn), Some(book));
+//! ```
+
+use crate::{
+    attribute::{Attribute, AttributeValue},
+    namespaces::{namespace_declaration, qualify, text, XML_NAMESPACE},
+    processing_instruction::ProcessingInstruction,
+    Document, Name,
+};
+use std::iter;
+
+/// A node of a [`Tree`], following the XPath data model
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Node<'a> {
+    /// The root node, whose children are the root element and the comments and processing instructions outside of it
+    Root(&'a Document),
+    /// A [`Document::Element`] or a [`Document::EmptyTag`]
+    Element(&'a Document),
+    /// An attribute of an element, namespace declarations are not attributes
+    Attribute {
+        name: &'a Name,
+        value: &'a AttributeValue,
+    },
+    /// The text of a [`Document::Content`] or a [`Document::CDATA`]
+    Text(&'a str),
+    Comment(&'a str),
+    ProcessingInstruction(&'a ProcessingInstruction),
+}
+
+impl<'a> Node<'a> {
+    /// The string-value of the node, which for the root and for elements is the text of all of their descendants
+    pub fn string_value(&self) -> String {
+        match self {
+            Node::Root(document) | Node::Element(document) => {
+                let mut text = String::new();
+                descendant_text(document, &mut text);
+                text
+            }
+            Node::Attribute { value, .. } => text(value),
+            Node::Text(text) | Node::Comment(text) => text.to_string(),
+            Node::ProcessingInstruction(ProcessingInstruction { data, .. }) => {
+                data.clone().unwrap_or_default()
+            }
+        }
+    }
+
+    /// The name of elements and attributes and the target of processing instructions
+    pub fn name(&self) -> Option<&'a Name> {
+        match self {
+            Node::Element(Document::Element(tag, ..) | Document::EmptyTag(tag)) => Some(&tag.name),
+            Node::Attribute { name, .. } => Some(name),
+            Node::ProcessingInstruction(ProcessingInstruction { target, .. }) => Some(target),
+            _ => None,
+        }
+    }
+
+    /// The `Document` of the root node and of elements
+    pub fn document(&self) -> Option<&'a Document> {
+        match self {
+            Node::Root(document) | Node::Element(document) => Some(document),
+            _ => None,
+        }
+    }
+}
+
+/// The identifier of a node in a [`Tree`], identifiers are ordered in document order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    /// The position of the node in document order, the root node is 0
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// The nodes of a document in an arena, see [`tree`](crate::tree)
+///
+/// The [`NodeId`]s of a tree index it only, methods given the identifier of a node of another tree may panic.
+#[derive(Clone, Debug)]
+pub struct Tree<'a> {
+    nodes: Vec<NodeData<'a>>,
+}
+
+#[derive(Clone, Debug)]
+struct NodeData<'a> {
+    node: Node<'a>,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous_sibling: Option<NodeId>,
+    next_sibling: Option<NodeId>,
+    /// The number of attributes, which are the nodes right after an element
+    attributes: usize,
+}
+
+impl<'a> Tree<'a> {
+    /// Builds the tree of `document`, whose root node contains the root element and the comments and processing
+    /// instructions of the prolog and after the root element
+    pub fn new(document: &'a Document) -> Self {
+        let mut tree = Self {
+            nodes: vec![NodeData {
+                node: Node::Root(document),
+                parent: None,
+                first_child: None,
+                last_child: None,
+                previous_sibling: None,
+                next_sibling: None,
+                attributes: 0,
+            }],
+        };
+        tree.add_top_level(document);
+        tree
+    }
+
+    /// The root node, whose children are the root element and the comments and processing instructions outside of it
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn node(&self, id: NodeId) -> Node<'a> {
+        self.nodes[id.0].node
+    }
+
+    /// The identifiers of all of the nodes, in document order
+    pub fn node_ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    /// The parent of a node, the element of an attribute, and `None` for the root node
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].first_child
+    }
+
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].last_child
+    }
+
+    pub fn previous_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].previous_sibling
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].next_sibling
+    }
+
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        iter::successors(self.first_child(id), |&child| self.next_sibling(child))
+    }
+
+    /// The attributes of an element, in the order they are written in its start tag
+    pub fn attributes(&self, id: NodeId) -> impl Iterator<Item = NodeId> {
+        (id.0 + 1..id.0 + 1 + self.nodes[id.0].attributes).map(NodeId)
+    }
+
+    /// The parent of a node, its parent and so on up to the root node
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        iter::successors(self.parent(id), |&ancestor| self.parent(ancestor))
+    }
+
+    /// The descendants of a node in document order, without attributes
+    pub fn descendants(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        // The descendants are the nodes from the first child up to the next node that follows the subtree of `id`
+        let range = match self.first_child(id) {
+            Some(first_child) => {
+                let end = iter::once(id)
+                    .chain(self.ancestors(id))
+                    .find_map(|node| self.next_sibling(node))
+                    .map_or(self.nodes.len(), |next| next.0);
+                first_child.0..end
+            }
+            None => 0..0,
+        };
+        range
+            .map(NodeId)
+            .filter(|&node| !matches!(self.node(node), Node::Attribute { .. }))
+    }
+
+    /// The siblings after a node, in document order
+    pub fn following_siblings(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        iter::successors(self.next_sibling(id), |&sibling| self.next_sibling(sibling))
+    }
+
+    /// The siblings before a node, nearest first
+    pub fn preceding_siblings(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        iter::successors(self.previous_sibling(id), |&sibling| {
+            self.previous_sibling(sibling)
+        })
+    }
+
+    /// The namespace URI bound to `prefix`, or the default namespace for `None`, in the scope of a node
+    ///
+    /// The declarations of the nearest element that is the node or one of its ancestors are in scope, and so on up to
+    /// the root element. Undeclared prefixes are not checked, see [`Document::resolve_namespaces`].
+    pub fn namespace_uri(&self, id: NodeId, prefix: Option<&str>) -> Option<String> {
+        let declared = iter::once(id)
+            .chain(self.ancestors(id))
+            .filter_map(|node| match self.node(node) {
+                Node::Element(Document::Element(tag, ..) | Document::EmptyTag(tag)) => Some(tag),
+                _ => None,
+            })
+            .find_map(|tag| {
+                tag.attributes
+                    .iter()
+                    .flatten()
+                    .filter_map(namespace_declaration)
+                    .find(|(declared, _)| declared.as_deref() == prefix)
+            });
+        match declared {
+            // `xmlns=""` undeclares the default namespace
+            Some((_, uri)) => (!uri.is_empty()).then_some(uri),
+            None => (prefix == Some("xml")).then(|| XML_NAMESPACE.to_string()),
+        }
+    }
+
+    /// The path of a node from the root, such as `/catalog/book[2]/@isbn`
+    ///
+    /// Steps have a position when the parent has other children with the same name or of the same kind, e.g.
+    /// `/doc/text()[2]`, as in the paths of [`ValidationError`](crate::error::ValidationError)s.
+    pub fn path(&self, id: NodeId) -> String {
+        let Some(parent) = self.parent(id) else {
+            return "/".to_string();
+        };
+        let parent_path = if parent == self.root() {
+            String::new()
+        } else {
+            self.path(parent)
+        };
+        let step = self.step(id);
+        if let Node::Attribute { .. } = self.node(id) {
+            return format!("{parent_path}/@{step}");
+        }
+        let same_step: Vec<NodeId> = self
+            .children(parent)
+            .filter(|&sibling| self.step(sibling) == step)
+            .collect();
+        match same_step.iter().position(|&sibling| sibling == id) {
+            Some(position) if same_step.len() > 1 => {
+                format!("{parent_path}/{step}[{}]", position + 1)
+            }
+            _ => format!("{parent_path}/{step}"),
+        }
+    }
+
+    fn step(&self, id: NodeId) -> String {
+        match self.node(id) {
+            Node::Root(_) => String::new(),
+            Node::Element(_) | Node::Attribute { .. } => self
+                .node(id)
+                .name()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            Node::Text(_) => "text()".to_string(),
+            Node::Comment(_) => "comment()".to_string(),
+            Node::ProcessingInstruction(processing_instruction) => {
+                format!(
+                    "processing-instruction('{}')",
+                    processing_instruction.target
+                )
+            }
+        }
+    }
+
+    fn add(&mut self, node: Node<'a>, parent: NodeId) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        let mut data = NodeData {
+            node,
+            parent: Some(parent),
+            first_child: None,
+            last_child: None,
+            previous_sibling: None,
+            next_sibling: None,
+            attributes: 0,
+        };
+        if let Node::Attribute { .. } = node {
+            self.nodes[parent.0].attributes += 1;
+        } else {
+            data.previous_sibling = self.nodes[parent.0].last_child;
+            match data.previous_sibling {
+                Some(previous) => self.nodes[previous.0].next_sibling = Some(id),
+                None => self.nodes[parent.0].first_child = Some(id),
+            }
+            self.nodes[parent.0].last_child = Some(id);
+        }
+        self.nodes.push(data);
+        id
+    }
+
+    /// Adds the root element and the comments and processing instructions of the prolog and after the root element
+    fn add_top_level(&mut self, document: &'a Document) {
+        match document {
+            Document::Nested(documents) => {
+                for document in documents {
+                    self.add_top_level(document);
+                }
+            }
+            Document::Prolog {
+                misc: Some(misc), ..
+            } => {
+                for misc in misc {
+                    self.add_content(&misc.content, self.root());
+                }
+            }
+            Document::Prolog { .. } => {}
+            document => self.add_content(document, self.root()),
+        }
+    }
+
+    fn add_content(&mut self, document: &'a Document, parent: NodeId) {
+        match document {
+            Document::Element(tag, content, _) => {
+                let element = self.add(Node::Element(document), parent);
+                self.add_attributes(tag.attributes.iter().flatten(), element);
+                self.add_content(content, element);
+            }
+            Document::EmptyTag(tag) => {
+                let element = self.add(Node::Element(document), parent);
+                self.add_attributes(tag.attributes.iter().flatten(), element);
+            }
+            Document::Content(Some(text)) | Document::CDATA(text) => {
+                self.add(Node::Text(text), parent);
+            }
+            Document::Comment(comment) => {
+                self.add(Node::Comment(comment), parent);
+            }
+            Document::ProcessingInstruction(processing_instruction) => {
+                self.add(Node::ProcessingInstruction(processing_instruction), parent);
+            }
+            Document::Nested(documents) => {
+                for document in documents {
+                    self.add_content(document, parent);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn add_attributes(&mut self, attributes: impl Iterator<Item = &'a Attribute>, element: NodeId) {
+        for attribute in attributes {
+            // Namespace declarations of empty-element tags are parsed as ordinary attributes
+            if let Attribute::Instance { name, value } = attribute {
+                let qualified_name = qualify(name);
+                if qualified_name.prefix.as_deref() != Some("xmlns")
+                    && qualified_name.to_string() != "xmlns"
+                {
+                    self.add(Node::Attribute { name, value }, element);
+                }
+            }
+        }
+    }
+}
+
+impl Document {
+    /// The [`Tree`] of the document, for navigating to the parents and siblings of its nodes
+    pub fn tree(&self) -> Tree<'_> {
+        Tree::new(self)
+    }
+}
+
+fn descendant_text(document: &Document, text: &mut String) {
+    match document {
+        Document::Element(_, content, _) => descendant_text(content, text),
+        Document::Content(Some(content)) | Document::CDATA(content) => text.push_str(content),
+        Document::Nested(documents) => {
+            for document in documents {
+                descendant_text(document, text);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,739 @@
+// validation.rs
+
+//! Validation of documents against their DTD, and lookup of elements by ID
+
+use crate::{
+    attribute::{AttType, Attribute, DefaultDecl, Prefix, TokenizedType},
+    error::{Error, ValidationError, ValidationErrorKind},
+    namespaces::text,
+    parse::Parse,
+    prolog::{
+        content_particle::ContentParticle,
+        declaration_content::{DeclarationContent, Mixed},
+        doctype::DocType,
+        subset::{markup_declaration::MarkupDeclaration, Subset},
+    },
+    tag::Tag,
+    ConditionalState, Document, Name,
+};
+use std::collections::HashMap;
+
+impl Document {
+    /// Checks the document against the element type and attribute-list declarations of its DTD
+    ///
+    /// Every element must be declared, the root element must be the one named by the document type declaration and
+    /// the content of every element must match its declared content model. Every attribute must be declared for its
+    /// element with a value that matches its type, `#REQUIRED` attributes must be specified and `#FIXED` attributes
+    /// must have their default value. ID values must be unique, element types can be declared with only one ID
+    /// attribute and every `IDREF` or `IDREFS` value must name an ID of the document. All violations are returned
+    /// together in [`Error::Validation`], each with the path of the element or attribute it was found at.
+    ///
+    /// Set [`Config::validate`](crate::config::Config::validate) to validate documents as they are parsed.
+    ///
+    /// ```rust
+    /// use nom_xml::{config::Config, error::{Error, ValidationErrorKind}, parse::Parse, Document};
+    ///
+    /// let xml = "<!DOCTYPE list [\n<!ELEMENT list (item+)>\n<!ELEMENT item (#PCDATA)>\n]>\n<list><item>a</item><list/></list>";
+    /// let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+    /// let Err(Error::Validation(errors)) = doc.validate() else {
+    ///     unreachable!("`list` is not allowed in `list`")
+    /// };
+    /// assert_eq!(errors[0].path, "/list");
+    /// assert!(matches!(errors[0].kind, ValidationErrorKind::InvalidContent { .. }));
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        let (doc_type, root) = self.doc_type_and_root();
+        let Some(doc_type) = doc_type else {
+            return Err(Error::Validation(vec![ValidationError {
+                path: "/".to_string(),
+                kind: ValidationErrorKind::MissingDocType,
+            }]));
+        };
+        let mut validator = Validator::new(doc_type);
+        if let Some(root) = root {
+            validator.validate_root(root, &doc_type.name);
+        }
+        validator.validate_id_references();
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(validator.errors))
+        }
+    }
+
+    /// The element whose ID attribute has the value `id`
+    ///
+    /// ID attributes are the attributes declared with the `ID` type in the DTD, and `xml:id`. The document is searched
+    /// on every call, use an [`IdIndex`] to look up several elements.
+    ///
+    /// ```rust
+    /// use nom_xml::{config::Config, parse::Parse, Document};
+    ///
+    /// let xml = "<!DOCTYPE manual [\n<!ATTLIST section key ID #IMPLIED>\n]>\n<manual><section key=\"intro\">Hello</section></manual>";
+    /// let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+    /// assert_eq!(
+    ///     doc.get_element_by_id("intro").unwrap().to_string(),
+    ///     "<section key=\"intro\">Hello</section>"
+    /// );
+    /// assert!(doc.get_element_by_id("missing").is_none());
+    /// ```
+    pub fn get_element_by_id(&self, id: &str) -> Option<&Document> {
+        self.id_index().get(id)
+    }
+
+    /// Indexes the elements of the document by the value of their ID attribute
+    ///
+    /// See [`get_element_by_id`](Self::get_element_by_id) for the attributes that are IDs.
+    pub fn id_index(&self) -> IdIndex<'_> {
+        let (doc_type, root) = self.doc_type_and_root();
+        let declarations = doc_type.map(Declarations::new).unwrap_or_default();
+        let mut index = IdIndex::default();
+        if let Some(root) = root {
+            index.insert_element(root, &declarations);
+        }
+        index
+    }
+
+    fn doc_type_and_root(&self) -> (Option<&DocType>, Option<&Document>) {
+        let top_level = self.top_level();
+
+        let doc_type = top_level.iter().find_map(|document| match document {
+            Document::Prolog {
+                doc_type: Some(doc_type),
+                ..
+            } => Some(doc_type),
+            _ => None,
+        });
+        let root = top_level
+            .into_iter()
+            .find(|document| matches!(document, Document::Element(..) | Document::EmptyTag(_)));
+        (doc_type, root)
+    }
+}
+
+/// Elements looked up by the value of their ID attribute
+///
+/// When several elements have the same ID, the first one in document order is indexed.
+#[derive(Clone, Debug, Default)]
+pub struct IdIndex<'a> {
+    elements: HashMap<String, &'a Document>,
+}
+
+impl<'a> IdIndex<'a> {
+    pub fn get(&self, id: &str) -> Option<&'a Document> {
+        self.elements.get(id).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    fn insert_element(&mut self, element: &'a Document, declarations: &Declarations) {
+        let Some((tag, content)) = element_parts(element) else {
+            return;
+        };
+        for attribute in tag.attributes.iter().flatten() {
+            if let Attribute::Instance { name, value } = attribute {
+                if declarations.is_id(&tag.name, name) {
+                    self.elements
+                        .entry(normalize_id(&text(value)))
+                        .or_insert(element);
+                }
+            }
+        }
+        let mut children = vec![];
+        if let Some(content) = content {
+            collect_children(content, &mut children);
+        }
+        for child in children {
+            if let Child::Element(_, document) = child {
+                self.insert_element(document, declarations);
+            }
+        }
+    }
+}
+
+/// The element types and attributes declared in a DTD
+///
+/// Declarations are looked up by qualified name, as names are not always split at their prefix.
+#[derive(Default)]
+struct Declarations<'a> {
+    elements: HashMap<String, (&'a DeclarationContent, ContentModel)>,
+    attributes: HashMap<String, Vec<&'a Attribute>>,
+    // The element types declared with more than one ID attribute, in the order of their second one
+    multiple_ids: Vec<&'a Name>,
+}
+
+impl<'a> Declarations<'a> {
+    fn new(doc_type: &'a DocType) -> Self {
+        let mut declarations = Self::default();
+        for subset in doc_type.subset.iter().flatten() {
+            declarations.declare(subset);
+        }
+        declarations
+    }
+
+    // The first declaration of an element type or of an attribute is the one used
+    fn declare(&mut self, subset: &'a Subset) {
+        match subset {
+            Subset::MarkupDecl(MarkupDeclaration::Element {
+                name,
+                content_spec: Some(content_spec),
+            }) => {
+                self.elements
+                    .entry(name.to_string())
+                    .or_insert_with(|| (content_spec, ContentModel::new(content_spec)));
+            }
+            Subset::MarkupDecl(MarkupDeclaration::AttList {
+                name,
+                att_defs: Some(att_defs),
+            }) => {
+                let element = name;
+                let declared = self.attributes.entry(name.to_string()).or_default();
+                let ids = id_definitions(declared).count();
+                for att_def in att_defs {
+                    if let Attribute::Definition { name, .. } = att_def {
+                        if !declared
+                            .iter()
+                            .any(|declared| definition_name(declared) == Some(name))
+                        {
+                            declared.push(att_def);
+                        }
+                    }
+                }
+                if ids < 2 && id_definitions(declared).count() >= 2 {
+                    self.multiple_ids.push(element);
+                }
+            }
+            Subset::DeclSep {
+                expansion: Some(expansion),
+                ..
+            } => self.declare(expansion),
+            _ => {}
+        }
+    }
+
+    fn attributes(&self, element: &Name) -> &[&'a Attribute] {
+        self.attributes
+            .get(&element.to_string())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn is_id(&self, element: &Name, attribute: &Name) -> bool {
+        let attribute = attribute.to_string();
+        attribute == "xml:id"
+            || self.attributes(element).iter().any(|definition| {
+                is_id_definition(definition)
+                    && definition_name(definition).is_some_and(|name| name.to_string() == attribute)
+            })
+    }
+
+    // Errors of the declarations themselves are reported at the document, as no element has to be of the type
+    fn errors(&self) -> Vec<ValidationError> {
+        self.multiple_ids
+            .iter()
+            .map(|&element| ValidationError {
+                path: "/".to_string(),
+                kind: ValidationErrorKind::MultipleIdAttributes {
+                    element: element.clone(),
+                    attributes: id_definitions(self.attributes(element))
+                        .filter_map(|definition| definition_name(definition).cloned())
+                        .collect(),
+                },
+            })
+            .collect()
+    }
+}
+
+fn id_definitions<'a, 'b>(
+    declared: &'b [&'a Attribute],
+) -> impl Iterator<Item = &'a Attribute> + 'b {
+    declared
+        .iter()
+        .copied()
+        .filter(|definition| is_id_definition(definition))
+}
+
+fn is_id_definition(definition: &Attribute) -> bool {
+    matches!(
+        definition,
+        Attribute::Definition {
+            att_type: AttType::Tokenized(TokenizedType::ID),
+            ..
+        }
+    )
+}
+
+struct Validator<'a> {
+    declarations: Declarations<'a>,
+    // The path of the attribute each ID is first given by
+    ids: HashMap<String, String>,
+    // The path, name and value of IDREF and IDREFS attributes, checked once all IDs are known
+    id_references: Vec<(String, Name, String)>,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(doc_type: &'a DocType) -> Self {
+        let declarations = Declarations::new(doc_type);
+        let errors = declarations.errors();
+        Self {
+            declarations,
+            ids: HashMap::new(),
+            id_references: vec![],
+            errors,
+        }
+    }
+
+    fn validate_root(&mut self, root: &Document, doc_type_name: &Name) {
+        let Some((tag, _)) = element_parts(root) else {
+            return;
+        };
+        let path = format!("/{}", tag.name);
+        if tag.name.to_string() != doc_type_name.to_string() {
+            self.errors.push(ValidationError {
+                path: path.clone(),
+                kind: ValidationErrorKind::RootElementMismatch {
+                    expected: doc_type_name.clone(),
+                    found: tag.name.clone(),
+                },
+            });
+        }
+        self.validate_element(root, &path);
+    }
+
+    fn validate_element(&mut self, element: &Document, path: &str) {
+        let Some((tag, content)) = element_parts(element) else {
+            return;
+        };
+        let mut children = vec![];
+        if let Some(content) = content {
+            collect_children(content, &mut children);
+        }
+
+        match self.declarations.elements.get(&tag.name.to_string()) {
+            Some((declaration, model)) if !model.matches(&children) => {
+                self.errors.push(ValidationError {
+                    path: path.to_string(),
+                    kind: ValidationErrorKind::InvalidContent {
+                        element: tag.name.clone(),
+                        model: (*declaration).clone(),
+                        content: describe(&children),
+                    },
+                })
+            }
+            Some(_) => {}
+            None => self.errors.push(ValidationError {
+                path: path.to_string(),
+                kind: ValidationErrorKind::UndeclaredElement(tag.name.clone()),
+            }),
+        }
+        self.validate_attributes(tag, path);
+
+        let elements: Vec<(String, &Document)> = children
+            .iter()
+            .filter_map(|child| match child {
+                Child::Element(name, document) => Some((name.to_string(), *document)),
+                _ => None,
+            })
+            .collect();
+        // Elements are numbered among the siblings with the same name, counted up front to number them in one pass
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (name, _) in &elements {
+            *counts.entry(name).or_default() += 1;
+        }
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for (name, document) in &elements {
+            let child_path = if counts[name.as_str()] > 1 {
+                let position = positions.entry(name).or_default();
+                *position += 1;
+                format!("{path}/{name}[{position}]")
+            } else {
+                format!("{path}/{name}")
+            };
+            self.validate_element(document, &child_path);
+        }
+    }
+
+    fn validate_attributes(&mut self, tag: &Tag, path: &str) {
+        let declared = self.declarations.attributes(&tag.name).to_vec();
+        let specified: Vec<(Name, String)> = tag
+            .attributes
+            .iter()
+            .flatten()
+            .filter_map(|attribute| match attribute {
+                Attribute::Instance { name, value } => Some((name.clone(), text(value))),
+                // Namespace declarations are attributes like any other for the DTD
+                Attribute::Namespace { prefix, uri } => {
+                    let name = match prefix {
+                        Prefix::Default => Name::new(None, "xmlns"),
+                        Prefix::Prefix(prefix) => Name::new(Some("xmlns"), prefix),
+                    };
+                    Some((name, text(uri)))
+                }
+                _ => None,
+            })
+            .collect();
+
+        for (name, value) in &specified {
+            let attribute_path = format!("{path}/@{name}");
+            let definition = declared.iter().find_map(|definition| match definition {
+                Attribute::Definition {
+                    name: declared_name,
+                    att_type,
+                    default_decl,
+                    ..
+                } if declared_name.to_string() == name.to_string() => {
+                    Some((att_type, default_decl))
+                }
+                _ => None,
+            });
+            let Some((att_type, default_decl)) = definition else {
+                self.errors.push(ValidationError {
+                    path: attribute_path,
+                    kind: ValidationErrorKind::UndeclaredAttribute(name.clone()),
+                });
+                continue;
+            };
+            let value = att_type.normalize_value(value);
+            if !is_valid_value(&value, att_type) {
+                self.errors.push(ValidationError {
+                    path: attribute_path.clone(),
+                    kind: ValidationErrorKind::InvalidAttributeValue {
+                        attribute: name.clone(),
+                        att_type: att_type.clone(),
+                        value: value.clone(),
+                    },
+                });
+            }
+            if let DefaultDecl::Fixed(fixed) = default_decl {
+                let fixed = att_type.normalize_value(fixed);
+                if value != fixed {
+                    self.errors.push(ValidationError {
+                        path: attribute_path.clone(),
+                        kind: ValidationErrorKind::FixedAttributeMismatch {
+                            attribute: name.clone(),
+                            expected: fixed,
+                            found: value.clone(),
+                        },
+                    });
+                }
+            }
+            match att_type {
+                AttType::Tokenized(TokenizedType::ID) if is_name(&value) => {
+                    match self.ids.get(&value) {
+                        Some(first) => self.errors.push(ValidationError {
+                            path: attribute_path,
+                            kind: ValidationErrorKind::DuplicateId {
+                                id: value,
+                                first: first.clone(),
+                            },
+                        }),
+                        None => {
+                            self.ids.insert(value, attribute_path);
+                        }
+                    }
+                }
+                AttType::Tokenized(TokenizedType::IDREF | TokenizedType::IDREFS) => {
+                    for id in value.split(' ').filter(|id| is_name(id)) {
+                        self.id_references.push((
+                            attribute_path.clone(),
+                            name.clone(),
+                            id.to_string(),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for definition in declared {
+            if let Attribute::Definition {
+                name,
+                default_decl: DefaultDecl::Required,
+                ..
+            } = definition
+            {
+                if !specified
+                    .iter()
+                    .any(|(specified, _)| specified.to_string() == name.to_string())
+                {
+                    self.errors.push(ValidationError {
+                        path: format!("{path}/@{name}"),
+                        kind: ValidationErrorKind::MissingRequiredAttribute(name.clone()),
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Validator<'_> {
+    fn validate_id_references(&mut self) {
+        for (path, attribute, id) in std::mem::take(&mut self.id_references) {
+            if !self.ids.contains_key(&id) {
+                self.errors.push(ValidationError {
+                    path,
+                    kind: ValidationErrorKind::UndefinedIdReference { attribute, id },
+                });
+            }
+        }
+    }
+}
+
+fn definition_name(attribute: &Attribute) -> Option<&Name> {
+    match attribute {
+        Attribute::Definition { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+// Values of every type other than CDATA are compared with leading and trailing spaces removed and runs of spaces
+// collapsed
+fn normalize_id(value: &str) -> String {
+    AttType::Tokenized(TokenizedType::ID).normalize_value(value)
+}
+
+fn is_valid_value(value: &str, att_type: &AttType) -> bool {
+    let tokens = || value.split(' ');
+    match att_type {
+        AttType::CDATA => true,
+        AttType::Tokenized(TokenizedType::ID | TokenizedType::IDREF | TokenizedType::ENTITY) => {
+            is_name(value)
+        }
+        AttType::Tokenized(TokenizedType::IDREFS | TokenizedType::ENTITIES) => {
+            tokens().all(is_name)
+        }
+        AttType::Tokenized(TokenizedType::NMTOKEN) => is_nmtoken(value),
+        AttType::Tokenized(TokenizedType::NMTOKENS) => tokens().all(is_nmtoken),
+        AttType::Enumerated {
+            notation: Some(notations),
+            ..
+        } => notations
+            .iter()
+            .any(|notation| notation.to_string() == value),
+        AttType::Enumerated {
+            enumeration: Some(enumeration),
+            ..
+        } => enumeration.iter().any(|token| token == value),
+        AttType::Enumerated { .. } => false,
+    }
+}
+
+// [5] Name ::= NameStartChar (NameChar)*
+fn is_name(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars.next().is_some_and(Document::is_name_start_char) && chars.all(Document::is_name_char)
+}
+
+// [7] Nmtoken ::= (NameChar)+
+fn is_nmtoken(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(Document::is_name_char)
+}
+
+/// The tag of an element and its content, which is `None` for empty-element tags
+fn element_parts(document: &Document) -> Option<(&Tag, Option<&Document>)> {
+    match document {
+        Document::Element(tag, content, _) => Some((tag, Some(content))),
+        Document::EmptyTag(tag) => Some((tag, None)),
+        _ => None,
+    }
+}
+
+/// The content of an element as it is seen by its content model
+enum Child<'a> {
+    Element(&'a Name, &'a Document),
+    Text,
+    // Character data that is only white space, which is allowed in element content
+    WhiteSpace,
+    Markup,
+}
+
+fn collect_children<'a>(content: &'a Document, children: &mut Vec<Child<'a>>) {
+    match content {
+        Document::Nested(documents) => {
+            for document in documents {
+                collect_children(document, children);
+            }
+        }
+        Document::Element(tag, ..) | Document::EmptyTag(tag) => {
+            children.push(Child::Element(&tag.name, content))
+        }
+        Document::Content(Some(text)) if text.is_empty() => {}
+        Document::Content(Some(text)) if text.chars().all(|c| c.is_ascii_whitespace()) => {
+            children.push(Child::WhiteSpace)
+        }
+        Document::Content(Some(_)) | Document::CDATA(_) => children.push(Child::Text),
+        Document::Comment(_) | Document::ProcessingInstruction(_) => children.push(Child::Markup),
+        Document::Content(None) | Document::Empty | Document::Prolog { .. } => {}
+    }
+}
+
+/// Writes the content of an element like a content model, e.g. `(a, #PCDATA, b)`
+fn describe(children: &[Child]) -> String {
+    let items: Vec<String> = children
+        .iter()
+        .filter_map(|child| match child {
+            Child::Element(name, _) => Some(name.to_string()),
+            Child::Text => Some("#PCDATA".to_string()),
+            Child::WhiteSpace | Child::Markup => None,
+        })
+        .collect();
+    format!("({})", items.join(", "))
+}
+
+/// A content specification compiled for checking the children of elements
+enum ContentModel {
+    Empty,
+    Any,
+    // The element types allowed among character data, none for `(#PCDATA)`
+    Mixed(Vec<String>),
+    Children(Automaton),
+}
+
+impl ContentModel {
+    fn new(content_spec: &DeclarationContent) -> Self {
+        match content_spec {
+            DeclarationContent::Empty => ContentModel::Empty,
+            DeclarationContent::Any => ContentModel::Any,
+            DeclarationContent::Mixed(Mixed::PCDATA) => ContentModel::Mixed(vec![]),
+            DeclarationContent::Mixed(Mixed::Names(names)) => {
+                ContentModel::Mixed(names.iter().map(ToString::to_string).collect())
+            }
+            DeclarationContent::Children(particle) => {
+                ContentModel::Children(Automaton::new(particle))
+            }
+        }
+    }
+
+    fn matches(&self, children: &[Child]) -> bool {
+        match self {
+            // Comments and processing instructions are content too
+            ContentModel::Empty => children.is_empty(),
+            ContentModel::Any => true,
+            ContentModel::Mixed(names) => children.iter().all(|child| match child {
+                Child::Element(name, _) => names.contains(&name.to_string()),
+                _ => true,
+            }),
+            ContentModel::Children(automaton) => {
+                let mut names = vec![];
+                for child in children {
+                    match child {
+                        Child::Element(name, _) => names.push(name.to_string()),
+                        Child::Text => return false,
+                        Child::WhiteSpace | Child::Markup => {}
+                    }
+                }
+                automaton.matches(&names)
+            }
+        }
+    }
+}
+
+/// A nondeterministic finite automaton over element names, built from a content particle by Thompson's construction
+struct Automaton {
+    states: Vec<State>,
+    accept: usize,
+}
+
+#[derive(Default)]
+struct State {
+    transitions: Vec<(String, usize)>,
+    epsilon: Vec<usize>,
+}
+
+impl Automaton {
+    const START: usize = 0;
+
+    fn new(particle: &ContentParticle) -> Self {
+        let mut automaton = Self {
+            states: vec![State::default()],
+            accept: Self::START,
+        };
+        automaton.accept = automaton.compile(particle, Self::START);
+        automaton
+    }
+
+    fn add_state(&mut self) -> usize {
+        self.states.push(State::default());
+        self.states.len() - 1
+    }
+
+    // Adds the states matching `particle` after `from`, returning the state reached once it is matched
+    fn compile(&mut self, particle: &ContentParticle, from: usize) -> usize {
+        // Each particle gets its own entry and exit states so the loops of its quantifier stay local to it
+        let entry = self.add_state();
+        self.states[from].epsilon.push(entry);
+        let (matched, conditional_state) = match particle {
+            ContentParticle::Name(name, conditional_state) => {
+                let matched = self.add_state();
+                self.states[entry]
+                    .transitions
+                    .push((name.to_string(), matched));
+                (matched, conditional_state)
+            }
+            ContentParticle::Sequence(particles, conditional_state) => (
+                particles
+                    .iter()
+                    .fold(entry, |from, particle| self.compile(particle, from)),
+                conditional_state,
+            ),
+            ContentParticle::Choice(particles, conditional_state) => {
+                let matched = self.add_state();
+                for particle in particles {
+                    let end = self.compile(particle, entry);
+                    self.states[end].epsilon.push(matched);
+                }
+                (matched, conditional_state)
+            }
+        };
+        let exit = self.add_state();
+        self.states[matched].epsilon.push(exit);
+        match conditional_state {
+            ConditionalState::None => {}
+            ConditionalState::Optional => self.states[entry].epsilon.push(exit),
+            ConditionalState::ZeroOrMore => {
+                self.states[entry].epsilon.push(exit);
+                self.states[matched].epsilon.push(entry);
+            }
+            ConditionalState::OneOrMore => self.states[matched].epsilon.push(entry),
+        }
+        exit
+    }
+
+    fn matches(&self, names: &[String]) -> bool {
+        let mut current = self.closure([Self::START]);
+        for name in names {
+            let next: Vec<usize> = current
+                .iter()
+                .enumerate()
+                .filter(|(_, active)| **active)
+                .flat_map(|(state, _)| &self.states[state].transitions)
+                .filter(|(transition, _)| transition == name)
+                .map(|(_, to)| *to)
+                .collect();
+            if next.is_empty() {
+                return false;
+            }
+            current = self.closure(next);
+        }
+        current[self.accept]
+    }
+
+    // The states reachable from `states` without matching an element
+    fn closure(&self, states: impl IntoIterator<Item = usize>) -> Vec<bool> {
+        let mut reached = vec![false; self.states.len()];
+        let mut pending: Vec<usize> = states.into_iter().collect();
+        while let Some(state) = pending.pop() {
+            if !reached[state] {
+                reached[state] = true;
+                pending.extend(&self.states[state].epsilon);
+            }
+        }
+        reached
+    }
+}
@@ -0,0 +1,1083 @@
+// xpath.rs
+
+//! Evaluation of XPath 1.0 expressions over a parsed [`Document`]
+//!
+//! The subset of XPath 1.0 that is supported covers location paths with the `child`, `descendant`,
+//! `descendant-or-self`, `self`, `parent`, `ancestor`, `ancestor-or-self`, `following-sibling`,
+//! `preceding-sibling` and `attribute` axes and their abbreviations (`//`, `.`, `..` and `@`), predicates, the
+//! `node()`, `text()`, `comment()` and `processing-instruction()` node tests, the operators and the core function
+//! library except for `id`, `lang` and `namespace-uri`. Variables and the `following`, `preceding` and `namespace`
+//! axes are not supported.
+//!
+//! Name tests compare qualified names as they are written in the document, e.g. `atom:entry` selects the elements
+//! written with the `atom` prefix, and `entry` the unprefixed ones whatever their default namespace.
+//!
+//! ```rust
+//! use nom_xml::{config::Config, parse::Parse, xpath::Value, Document};
+//!
+//! let xml = "<library><book year=\"2011\"><title>Leviathan Wakes</title></book><book year=\"2012\"><title>Caliban's War</title></book></library>";
+//! let (_, doc) = Document::parse(xml, &Config::default()).unwrap();
+//!
+//! let titles = doc.select_xpath("//book[@year > 2011]/title/text()").unwrap();
+//! assert_eq!(titles[0].string_value(), "Caliban's War");
+//!
+//! assert_eq!(doc.xpath("count(//book)").unwrap(), Value::Number(2.0));
+//! assert_eq!(
+//!     doc.xpath("string(/library/book[1]/@year)").unwrap(),
+//!     Value::String("2011".to_string())
+//! );
+//! ```
+
+pub use crate::tree::Node;
+
+use crate::{
+    error::Error,
+    namespaces::{qualify, ParseNamespace},
+    parse::Parse,
+    tree::{NodeId, Tree},
+    Document, IResult,
+};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_till},
+    character::complete::{char, digit0, digit1, multispace0, satisfy},
+    combinator::{map, not, opt, peek, recognize, value, verify},
+    multi::{many0, separated_list0},
+    sequence::{delimited, pair, preceded, terminated, tuple},
+};
+use std::{fmt, iter, str::FromStr};
+
+/// The result of an XPath expression
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value<'a> {
+    /// The selected nodes in document order
+    Nodes(Vec<Node<'a>>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+impl Value<'_> {
+    /// The value converted with the XPath `string()` function
+    pub fn string(&self) -> String {
+        match self {
+            Value::Nodes(nodes) => nodes.first().map(Node::string_value).unwrap_or_default(),
+            Value::String(string) => string.clone(),
+            Value::Number(number) => number_to_string(*number),
+            Value::Boolean(boolean) => boolean.to_string(),
+        }
+    }
+
+    /// The value converted with the XPath `number()` function
+    pub fn number(&self) -> f64 {
+        match self {
+            Value::Number(number) => *number,
+            Value::Boolean(boolean) => f64::from(u8::from(*boolean)),
+            value => string_to_number(&value.string()),
+        }
+    }
+
+    /// The value converted with the XPath `boolean()` function
+    pub fn boolean(&self) -> bool {
+        match self {
+            Value::Nodes(nodes) => !nodes.is_empty(),
+            Value::String(string) => !string.is_empty(),
+            Value::Number(number) => *number != 0.0 && !number.is_nan(),
+            Value::Boolean(boolean) => *boolean,
+        }
+    }
+}
+
+/// A compiled XPath expression, for evaluating the same expression on several documents
+///
+/// ```rust
+/// use nom_xml::{config::Config, parse::Parse, xpath::XPath, Document};
+///
+/// let titles = XPath::compile("/book/title").unwrap();
+/// let (_, doc) = Document::parse("<book><title>Abaddon's Gate</title></book>", &Config::default()).unwrap();
+/// assert_eq!(titles.evaluate(&doc).unwrap().string(), "Abaddon's Gate");
+/// ```
+#[derive(Clone, Debug)]
+pub struct XPath {
+    expression: String,
+    expr: Expr,
+}
+
+impl XPath {
+    /// Parses `expression`, failing with [`Error::InvalidXPath`] when it is not a supported XPath expression
+    pub fn compile(expression: &str) -> Result<Self, Error> {
+        let invalid = |message: String| Error::InvalidXPath {
+            expression: expression.to_string(),
+            message,
+        };
+        let expr = match delimited(multispace0, Self::parse_expr, multispace0)(expression) {
+            Ok(("", expr)) => expr,
+            Ok((remaining, _)) => {
+                return Err(invalid(format!(
+                    "Unexpected `{remaining}` at offset {}",
+                    expression.len() - remaining.len()
+                )))
+            }
+            Err(nom::Err::Failure(Error::InvalidXPath { message, .. })) => {
+                return Err(invalid(message))
+            }
+            Err(_) => return Err(invalid("Not a valid expression".to_string())),
+        };
+        Ok(Self {
+            expression: expression.to_string(),
+            expr,
+        })
+    }
+
+    /// Evaluates the expression with the root node of `document` as the context node
+    pub fn evaluate<'a>(&self, document: &'a Document) -> Result<Value<'a>, Error> {
+        let tree = Tree::new(document);
+        let evaluator = Evaluator {
+            tree: &tree,
+            expression: &self.expression,
+        };
+        let context = Context {
+            node: tree.root(),
+            position: 1,
+            size: 1,
+        };
+        Ok(match evaluator.evaluate(&self.expr, &context)? {
+            Object::Nodes(nodes) => {
+                Value::Nodes(nodes.into_iter().map(|node| tree.node(node)).collect())
+            }
+            Object::String(string) => Value::String(string),
+            Object::Number(number) => Value::Number(number),
+            Object::Boolean(boolean) => Value::Boolean(boolean),
+        })
+    }
+
+    /// Evaluates an expression that selects nodes, failing with [`Error::InvalidXPath`] for other values
+    pub fn select<'a>(&self, document: &'a Document) -> Result<Vec<Node<'a>>, Error> {
+        match self.evaluate(document)? {
+            Value::Nodes(nodes) => Ok(nodes),
+            _ => Err(Error::InvalidXPath {
+                expression: self.expression.clone(),
+                message: "The expression does not select nodes".to_string(),
+            }),
+        }
+    }
+}
+
+impl FromStr for XPath {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::compile(expression)
+    }
+}
+
+impl fmt::Display for XPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl Document {
+    /// Evaluates an XPath expression with the root node of the document as the context node, see [`xpath`](crate::xpath)
+    pub fn xpath(&self, expression: &str) -> Result<Value<'_>, Error> {
+        XPath::compile(expression)?.evaluate(self)
+    }
+
+    /// The nodes selected by an XPath expression in document order, see [`xpath`](crate::xpath)
+    pub fn select_xpath(&self, expression: &str) -> Result<Vec<Node<'_>>, Error> {
+        XPath::compile(expression)?.select(self)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(Comparison, Box<Expr>, Box<Expr>),
+    Arithmetic(Arithmetic, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Function(String, Vec<Expr>),
+    Path {
+        absolute: bool,
+        steps: Vec<Step>,
+    },
+    /// A primary expression filtered by predicates and followed by a relative location path
+    Filter {
+        primary: Box<Expr>,
+        predicates: Vec<Expr>,
+        steps: Vec<Step>,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Arithmetic {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+#[derive(Clone, Debug)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Axis {
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    SelfNode,
+    Parent,
+    Ancestor,
+    AncestorOrSelf,
+    FollowingSibling,
+    PrecedingSibling,
+    Attribute,
+}
+
+#[derive(Clone, Debug)]
+enum NodeTest {
+    /// A qualified name, as it is written in the document
+    Name(String),
+    /// `*`
+    Any,
+    /// `prefix:*`
+    Prefix(String),
+    Node,
+    Text,
+    Comment,
+    ProcessingInstruction(Option<String>),
+}
+
+/// The arity of the supported functions as (minimum, maximum)
+fn function_arity(name: &str) -> Option<(usize, usize)> {
+    Some(match name {
+        "last" | "position" | "true" | "false" => (0, 0),
+        "count" | "sum" | "boolean" | "not" | "floor" | "ceiling" | "round" => (1, 1),
+        "local-name" | "name" | "string" | "string-length" | "normalize-space" | "number" => (0, 1),
+        "starts-with" | "contains" | "substring-before" | "substring-after" => (2, 2),
+        "substring" => (2, 3),
+        "translate" => (3, 3),
+        "concat" => (2, usize::MAX),
+        _ => return None,
+    })
+}
+
+impl<'a> Parse<'a> for XPath {
+    type Args = ();
+    type Output = IResult<&'a str, Self>;
+}
+impl<'a> ParseNamespace<'a> for XPath {}
+
+/// Fails the parse with a message for [`Error::InvalidXPath`]
+fn failure<'a, T>(message: String) -> IResult<&'a str, T> {
+    Err(nom::Err::Failure(Error::InvalidXPath {
+        expression: String::new(),
+        message,
+    }))
+}
+
+fn token<'a, O>(
+    parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    delimited(multispace0, parser, multispace0)
+}
+
+/// An operator name such as `and` or `div`, which must not be the start of a longer name
+fn keyword<'a>(name: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    token(terminated(tag(name), not(satisfy(XPath::is_name_char))))
+}
+
+impl XPath {
+    // [14] Expr ::= OrExpr
+    // [21] OrExpr ::= AndExpr | OrExpr 'or' AndExpr
+    fn parse_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, first) = Self::parse_and_expr(input)?;
+        let (input, rest) = many0(preceded(keyword("or"), Self::parse_and_expr))(input)?;
+        Ok((input, fold(first, rest, Expr::Or)))
+    }
+
+    // [22] AndExpr ::= EqualityExpr | AndExpr 'and' EqualityExpr
+    fn parse_and_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, first) = Self::parse_equality_expr(input)?;
+        let (input, rest) = many0(preceded(keyword("and"), Self::parse_equality_expr))(input)?;
+        Ok((input, fold(first, rest, Expr::And)))
+    }
+
+    // [23] EqualityExpr ::= RelationalExpr | EqualityExpr '=' RelationalExpr | EqualityExpr '!=' RelationalExpr
+    fn parse_equality_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, first) = Self::parse_relational_expr(input)?;
+        let (input, rest) = many0(pair(
+            token(alt((
+                value(Comparison::NotEqual, tag("!=")),
+                value(Comparison::Equal, tag("=")),
+            ))),
+            Self::parse_relational_expr,
+        ))(input)?;
+        Ok((input, fold_operators(first, rest, Expr::Compare)))
+    }
+
+    // [24] RelationalExpr ::= AdditiveExpr | RelationalExpr ('<' | '>' | '<=' | '>=') AdditiveExpr
+    fn parse_relational_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, first) = Self::parse_additive_expr(input)?;
+        let (input, rest) = many0(pair(
+            token(alt((
+                value(Comparison::LessOrEqual, tag("<=")),
+                value(Comparison::GreaterOrEqual, tag(">=")),
+                value(Comparison::Less, tag("<")),
+                value(Comparison::Greater, tag(">")),
+            ))),
+            Self::parse_additive_expr,
+        ))(input)?;
+        Ok((input, fold_operators(first, rest, Expr::Compare)))
+    }
+
+    // [25] AdditiveExpr ::= MultiplicativeExpr | AdditiveExpr ('+' | '-') MultiplicativeExpr
+    fn parse_additive_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, first) = Self::parse_multiplicative_expr(input)?;
+        let (input, rest) = many0(pair(
+            token(alt((
+                value(Arithmetic::Add, char('+')),
+                value(Arithmetic::Subtract, char('-')),
+            ))),
+            Self::parse_multiplicative_expr,
+        ))(input)?;
+        Ok((input, fold_operators(first, rest, Expr::Arithmetic)))
+    }
+
+    // [26] MultiplicativeExpr ::= UnaryExpr | MultiplicativeExpr ('*' | 'div' | 'mod') UnaryExpr
+    fn parse_multiplicative_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, first) = Self::parse_unary_expr(input)?;
+        let (input, rest) = many0(pair(
+            alt((
+                value(Arithmetic::Multiply, token(char('*'))),
+                value(Arithmetic::Divide, keyword("div")),
+                value(Arithmetic::Modulo, keyword("mod")),
+            )),
+            Self::parse_unary_expr,
+        ))(input)?;
+        Ok((input, fold_operators(first, rest, Expr::Arithmetic)))
+    }
+
+    // [27] UnaryExpr ::= UnionExpr | '-' UnaryExpr
+    fn parse_unary_expr(input: &str) -> IResult<&str, Expr> {
+        alt((
+            map(preceded(token(char('-')), Self::parse_unary_expr), |expr| {
+                Expr::Negate(Box::new(expr))
+            }),
+            Self::parse_union_expr,
+        ))(input)
+    }
+
+    // [18] UnionExpr ::= PathExpr | UnionExpr '|' PathExpr
+    fn parse_union_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, first) = Self::parse_path_expr(input)?;
+        let (input, rest) = many0(preceded(token(char('|')), Self::parse_path_expr))(input)?;
+        Ok((input, fold(first, rest, Expr::Union)))
+    }
+
+    // [19] PathExpr ::= LocationPath | FilterExpr | FilterExpr '/' RelativeLocationPath
+    //                   | FilterExpr '//' RelativeLocationPath
+    fn parse_path_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, _) = multispace0(input)?;
+        let (input, expr) = alt((Self::parse_filter_expr, Self::parse_location_path))(input)?;
+        let (input, _) = multispace0(input)?;
+        Ok((input, expr))
+    }
+
+    // [20] FilterExpr ::= PrimaryExpr | FilterExpr Predicate
+    fn parse_filter_expr(input: &str) -> IResult<&str, Expr> {
+        let (input, primary) = Self::parse_primary_expr(input)?;
+        let (input, predicates) = many0(Self::parse_predicate)(input)?;
+        let (input, steps) = opt(Self::parse_following_steps)(input)?;
+        let steps = steps.unwrap_or_default();
+        if predicates.is_empty() && steps.is_empty() {
+            return Ok((input, primary));
+        }
+        Ok((
+            input,
+            Expr::Filter {
+                primary: Box::new(primary),
+                predicates,
+                steps,
+            },
+        ))
+    }
+
+    // [15] PrimaryExpr ::= VariableReference | '(' Expr ')' | Literal | Number | FunctionCall
+    fn parse_primary_expr(input: &str) -> IResult<&str, Expr> {
+        alt((
+            delimited(token(char('(')), Self::parse_expr, token(char(')'))),
+            map(Self::parse_literal, Expr::Literal),
+            map(Self::parse_number, Expr::Number),
+            Self::parse_function_call,
+        ))(input)
+    }
+
+    // [16] FunctionCall ::= FunctionName '(' ( Argument ( ',' Argument )* )? ')'
+    // [35] FunctionName ::= QName - NodeType
+    fn parse_function_call(input: &str) -> IResult<&str, Expr> {
+        let (input, name) = terminated(
+            verify(recognize(Self::parse_qualified_name), |name: &str| {
+                !is_node_type(name)
+            }),
+            peek(token(char('('))),
+        )(input)?;
+        let (input, arguments) = delimited(
+            token(char('(')),
+            separated_list0(token(char(',')), Self::parse_expr),
+            token(char(')')),
+        )(input)?;
+        match function_arity(name) {
+            None => failure(format!("Unknown function `{name}()`")),
+            Some((min, max)) if arguments.len() < min || arguments.len() > max => failure(format!(
+                "Function `{name}()` does not take {} arguments",
+                arguments.len()
+            )),
+            Some(_) => Ok((input, Expr::Function(name.to_string(), arguments))),
+        }
+    }
+
+    // [29] Literal ::= '"' [^"]* '"' | "'" [^']* "'"
+    fn parse_literal(input: &str) -> IResult<&str, String> {
+        map(
+            alt((
+                delimited(char('"'), take_till(|c| c == '"'), char('"')),
+                delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+            )),
+            str::to_string,
+        )(input)
+    }
+
+    // [30] Number ::= Digits ('.' Digits?)? | '.' Digits
+    fn parse_number(input: &str) -> IResult<&str, f64> {
+        map(
+            recognize(alt((
+                recognize(pair(digit1, opt(pair(char('.'), digit0)))),
+                recognize(pair(char('.'), digit1)),
+            ))),
+            |number: &str| number.parse().unwrap_or(f64::NAN),
+        )(input)
+    }
+
+    // [1] LocationPath ::= RelativeLocationPath | AbsoluteLocationPath
+    // [2] AbsoluteLocationPath ::= '/' RelativeLocationPath? | AbbreviatedAbsoluteLocationPath
+    fn parse_location_path(input: &str) -> IResult<&str, Expr> {
+        alt((
+            map(Self::parse_following_steps, |steps| Expr::Path {
+                absolute: true,
+                steps,
+            }),
+            map(char('/'), |_| Expr::Path {
+                absolute: true,
+                steps: vec![],
+            }),
+            map(Self::parse_relative_location_path, |steps| Expr::Path {
+                absolute: false,
+                steps,
+            }),
+        ))(input)
+    }
+
+    /// The steps after a `/` or a `//`, which is short for `/descendant-or-self::node()/`
+    fn parse_following_steps(input: &str) -> IResult<&str, Vec<Step>> {
+        let (input, descendants) =
+            token(alt((value(true, tag("//")), value(false, tag("/")))))(input)?;
+        let (input, mut steps) = Self::parse_relative_location_path(input)?;
+        if descendants {
+            steps.insert(0, descendant_or_self_step());
+        }
+        Ok((input, steps))
+    }
+
+    // [3] RelativeLocationPath ::= Step | RelativeLocationPath '/' Step | AbbreviatedRelativeLocationPath
+    // [11] AbbreviatedRelativeLocationPath ::= RelativeLocationPath '//' Step
+    fn parse_relative_location_path(input: &str) -> IResult<&str, Vec<Step>> {
+        let (mut input, first) = Self::parse_step(input)?;
+        let mut steps = vec![first];
+        loop {
+            let Ok((remaining, descendants)) =
+                token(alt((value(true, tag("//")), value(false, tag("/")))))(input)
+            else {
+                return Ok((input, steps));
+            };
+            let (remaining, step) = Self::parse_step(remaining)?;
+            if descendants {
+                steps.push(descendant_or_self_step());
+            }
+            steps.push(step);
+            input = remaining;
+        }
+    }
+
+    // [4] Step ::= AxisSpecifier NodeTest Predicate* | AbbreviatedStep
+    // [12] AbbreviatedStep ::= '.' | '..'
+    fn parse_step(input: &str) -> IResult<&str, Step> {
+        alt((
+            value(
+                Step {
+                    axis: Axis::Parent,
+                    test: NodeTest::Node,
+                    predicates: vec![],
+                },
+                tag(".."),
+            ),
+            value(
+                Step {
+                    axis: Axis::SelfNode,
+                    test: NodeTest::Node,
+                    predicates: vec![],
+                },
+                char('.'),
+            ),
+            map(
+                tuple((
+                    Self::parse_axis_specifier,
+                    Self::parse_node_test,
+                    many0(Self::parse_predicate),
+                )),
+                |(axis, test, predicates)| Step {
+                    axis,
+                    test,
+                    predicates,
+                },
+            ),
+        ))(input)
+    }
+
+    // [5] AxisSpecifier ::= AxisName '::' | AbbreviatedAxisSpecifier
+    // [13] AbbreviatedAxisSpecifier ::= '@'?
+    fn parse_axis_specifier(input: &str) -> IResult<&str, Axis> {
+        if let Ok((input, _)) = token(char('@'))(input) {
+            return Ok((input, Axis::Attribute));
+        }
+        let Ok((remaining, name)) =
+            terminated(recognize(Self::parse_non_colonized_name), token(tag("::")))(input)
+        else {
+            return Ok((input, Axis::Child));
+        };
+        let axis = match name {
+            "child" => Axis::Child,
+            "descendant" => Axis::Descendant,
+            "descendant-or-self" => Axis::DescendantOrSelf,
+            "self" => Axis::SelfNode,
+            "parent" => Axis::Parent,
+            "ancestor" => Axis::Ancestor,
+            "ancestor-or-self" => Axis::AncestorOrSelf,
+            "following-sibling" => Axis::FollowingSibling,
+            "preceding-sibling" => Axis::PrecedingSibling,
+            "attribute" => Axis::Attribute,
+            "following" | "preceding" | "namespace" => {
+                return failure(format!("The `{name}` axis is not supported"))
+            }
+            _ => return failure(format!("Unknown axis `{name}`")),
+        };
+        Ok((remaining, axis))
+    }
+
+    // [7] NodeTest ::= NameTest | NodeType '(' ')' | 'processing-instruction' '(' Literal ')'
+    // [37] NameTest ::= '*' | NCName ':' '*' | QName
+    fn parse_node_test(input: &str) -> IResult<&str, NodeTest> {
+        alt((
+            Self::parse_node_type_test,
+            value(NodeTest::Any, char('*')),
+            map(
+                terminated(recognize(Self::parse_non_colonized_name), tag(":*")),
+                |prefix: &str| NodeTest::Prefix(prefix.to_string()),
+            ),
+            map(recognize(Self::parse_qualified_name), |name: &str| {
+                NodeTest::Name(name.to_string())
+            }),
+        ))(input)
+    }
+
+    // [38] NodeType ::= 'comment' | 'text' | 'processing-instruction' | 'node'
+    fn parse_node_type_test(input: &str) -> IResult<&str, NodeTest> {
+        let (input, node_type) = terminated(
+            verify(recognize(Self::parse_non_colonized_name), is_node_type),
+            token(char('(')),
+        )(input)?;
+        let (input, target) = match node_type {
+            "processing-instruction" => opt(token(Self::parse_literal))(input)?,
+            _ => (input, None),
+        };
+        let (input, _) = char(')')(input)?;
+        let test = match node_type {
+            "node" => NodeTest::Node,
+            "text" => NodeTest::Text,
+            "comment" => NodeTest::Comment,
+            _ => NodeTest::ProcessingInstruction(target),
+        };
+        Ok((input, test))
+    }
+
+    // [8] Predicate ::= '[' PredicateExpr ']'
+    fn parse_predicate(input: &str) -> IResult<&str, Expr> {
+        delimited(token(char('[')), Self::parse_expr, token(char(']')))(input)
+    }
+}
+
+fn is_node_type(name: &str) -> bool {
+    matches!(name, "node" | "text" | "comment" | "processing-instruction")
+}
+
+fn descendant_or_self_step() -> Step {
+    Step {
+        axis: Axis::DescendantOrSelf,
+        test: NodeTest::Node,
+        predicates: vec![],
+    }
+}
+
+fn fold(first: Expr, rest: Vec<Expr>, combine: impl Fn(Box<Expr>, Box<Expr>) -> Expr) -> Expr {
+    rest.into_iter().fold(first, |left, right| {
+        combine(Box::new(left), Box::new(right))
+    })
+}
+
+fn fold_operators<O>(
+    first: Expr,
+    rest: Vec<(O, Expr)>,
+    combine: impl Fn(O, Box<Expr>, Box<Expr>) -> Expr,
+) -> Expr {
+    rest.into_iter().fold(first, |left, (operator, right)| {
+        combine(operator, Box::new(left), Box::new(right))
+    })
+}
+
+/// The nodes on `axis` from `node`, in document order for forward axes and in reverse for reverse axes
+fn axis(tree: &Tree, axis: Axis, node: NodeId) -> Vec<NodeId> {
+    match axis {
+        Axis::Child => tree.children(node).collect(),
+        Axis::Descendant => tree.descendants(node).collect(),
+        Axis::DescendantOrSelf => iter::once(node).chain(tree.descendants(node)).collect(),
+        Axis::SelfNode => vec![node],
+        Axis::Parent => tree.parent(node).into_iter().collect(),
+        Axis::Ancestor => tree.ancestors(node).collect(),
+        Axis::AncestorOrSelf => iter::once(node).chain(tree.ancestors(node)).collect(),
+        // Attributes are not children, so they have no siblings
+        Axis::FollowingSibling => tree.following_siblings(node).collect(),
+        Axis::PrecedingSibling => tree.preceding_siblings(node).collect(),
+        Axis::Attribute => tree.attributes(node).collect(),
+    }
+}
+
+fn matches(tree: &Tree, test: &NodeTest, axis: Axis, node: NodeId) -> bool {
+    let node = &tree.node(node);
+    // The principal node type of the attribute axis is attribute, of the other axes element
+    let principal = match (axis, node) {
+        (Axis::Attribute, Node::Attribute { .. }) => true,
+        (Axis::Attribute, _) => false,
+        (_, node) => matches!(node, Node::Element(_)),
+    };
+    match test {
+        NodeTest::Name(name) => {
+            principal && node.name().is_some_and(|found| found.to_string() == *name)
+        }
+        NodeTest::Any => principal,
+        NodeTest::Prefix(prefix) => {
+            principal
+                && node
+                    .name()
+                    .is_some_and(|found| qualify(found).prefix.as_ref() == Some(prefix))
+        }
+        NodeTest::Node => true,
+        NodeTest::Text => matches!(node, Node::Text(_)),
+        NodeTest::Comment => matches!(node, Node::Comment(_)),
+        NodeTest::ProcessingInstruction(target) => match node {
+            Node::ProcessingInstruction(processing_instruction) => target
+                .as_ref()
+                .is_none_or(|target| processing_instruction.target.to_string() == *target),
+            _ => false,
+        },
+    }
+}
+
+/// A value during evaluation, with node-sets as sorted indices of the [`Tree`]
+#[derive(Clone, Debug)]
+enum Object {
+    Nodes(Vec<NodeId>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+struct Context {
+    node: NodeId,
+    position: usize,
+    size: usize,
+}
+
+struct Evaluator<'t, 'a> {
+    tree: &'t Tree<'a>,
+    expression: &'t str,
+}
+
+impl Evaluator<'_, '_> {
+    fn error(&self, message: String) -> Error {
+        Error::InvalidXPath {
+            expression: self.expression.to_string(),
+            message,
+        }
+    }
+
+    fn evaluate(&self, expr: &Expr, context: &Context) -> Result<Object, Error> {
+        Ok(match expr {
+            Expr::Or(left, right) => Object::Boolean(
+                self.boolean(&self.evaluate(left, context)?)
+                    || self.boolean(&self.evaluate(right, context)?),
+            ),
+            Expr::And(left, right) => Object::Boolean(
+                self.boolean(&self.evaluate(left, context)?)
+                    && self.boolean(&self.evaluate(right, context)?),
+            ),
+            Expr::Compare(comparison, left, right) => {
+                let left = self.evaluate(left, context)?;
+                let right = self.evaluate(right, context)?;
+                Object::Boolean(self.compare(*comparison, &left, &right))
+            }
+            Expr::Arithmetic(operator, left, right) => {
+                let left = self.number(&self.evaluate(left, context)?);
+                let right = self.number(&self.evaluate(right, context)?);
+                Object::Number(match operator {
+                    Arithmetic::Add => left + right,
+                    Arithmetic::Subtract => left - right,
+                    Arithmetic::Multiply => left * right,
+                    Arithmetic::Divide => left / right,
+                    Arithmetic::Modulo => left % right,
+                })
+            }
+            Expr::Negate(expr) => Object::Number(-self.number(&self.evaluate(expr, context)?)),
+            Expr::Union(left, right) => {
+                let mut nodes = self.nodes(self.evaluate(left, context)?)?;
+                nodes.extend(self.nodes(self.evaluate(right, context)?)?);
+                nodes.sort_unstable();
+                nodes.dedup();
+                Object::Nodes(nodes)
+            }
+            Expr::Literal(literal) => Object::String(literal.clone()),
+            Expr::Number(number) => Object::Number(*number),
+            Expr::Function(name, arguments) => self.call(name, arguments, context)?,
+            Expr::Path { absolute, steps } => {
+                let start = if *absolute {
+                    self.tree.root()
+                } else {
+                    context.node
+                };
+                Object::Nodes(self.steps(vec![start], steps)?)
+            }
+            Expr::Filter {
+                primary,
+                predicates,
+                steps,
+            } => {
+                let mut nodes = self.nodes(self.evaluate(primary, context)?)?;
+                for predicate in predicates {
+                    nodes = self.filter(nodes, predicate)?;
+                }
+                Object::Nodes(self.steps(nodes, steps)?)
+            }
+        })
+    }
+
+    fn nodes(&self, object: Object) -> Result<Vec<NodeId>, Error> {
+        match object {
+            Object::Nodes(nodes) => Ok(nodes),
+            object => Err(self.error(format!("Expected a node-set, found {object:?}"))),
+        }
+    }
+
+    fn steps(&self, mut nodes: Vec<NodeId>, steps: &[Step]) -> Result<Vec<NodeId>, Error> {
+        for step in steps {
+            let mut selected = vec![];
+            for node in nodes {
+                let mut candidates: Vec<NodeId> = axis(self.tree, step.axis, node)
+                    .into_iter()
+                    .filter(|candidate| matches(self.tree, &step.test, step.axis, *candidate))
+                    .collect();
+                for predicate in &step.predicates {
+                    candidates = self.filter(candidates, predicate)?;
+                }
+                selected.extend(candidates);
+            }
+            selected.sort_unstable();
+            selected.dedup();
+            nodes = selected;
+        }
+        Ok(nodes)
+    }
+
+    /// The nodes for which `predicate` is true, numbers are compared with the position of the node in `nodes`
+    fn filter(&self, nodes: Vec<NodeId>, predicate: &Expr) -> Result<Vec<NodeId>, Error> {
+        let size = nodes.len();
+        let mut filtered = vec![];
+        for (index, node) in nodes.into_iter().enumerate() {
+            let context = Context {
+                node,
+                position: index + 1,
+                size,
+            };
+            let keep = match self.evaluate(predicate, &context)? {
+                Object::Number(number) => number == context.position as f64,
+                object => self.boolean(&object),
+            };
+            if keep {
+                filtered.push(node);
+            }
+        }
+        Ok(filtered)
+    }
+
+    fn string_value(&self, node: NodeId) -> String {
+        self.tree.node(node).string_value()
+    }
+
+    fn string(&self, object: &Object) -> String {
+        match object {
+            Object::Nodes(nodes) => nodes
+                .first()
+                .map(|node| self.string_value(*node))
+                .unwrap_or_default(),
+            Object::String(string) => string.clone(),
+            Object::Number(number) => number_to_string(*number),
+            Object::Boolean(boolean) => boolean.to_string(),
+        }
+    }
+
+    fn number(&self, object: &Object) -> f64 {
+        match object {
+            Object::Number(number) => *number,
+            Object::Boolean(boolean) => f64::from(u8::from(*boolean)),
+            object => string_to_number(&self.string(object)),
+        }
+    }
+
+    fn boolean(&self, object: &Object) -> bool {
+        match object {
+            Object::Nodes(nodes) => !nodes.is_empty(),
+            Object::String(string) => !string.is_empty(),
+            Object::Number(number) => *number != 0.0 && !number.is_nan(),
+            Object::Boolean(boolean) => *boolean,
+        }
+    }
+
+    // XPath 1.0 §3.4 Booleans, node-sets are compared through the string-values of their nodes
+    fn compare(&self, comparison: Comparison, left: &Object, right: &Object) -> bool {
+        match (left, right) {
+            (Object::Nodes(left), Object::Nodes(right)) => left.iter().any(|left| {
+                let left = Object::String(self.string_value(*left));
+                right.iter().any(|right| {
+                    self.compare_values(
+                        comparison,
+                        &left,
+                        &Object::String(self.string_value(*right)),
+                    )
+                })
+            }),
+            (Object::Nodes(nodes), Object::Boolean(_))
+            | (Object::Boolean(_), Object::Nodes(nodes)) => {
+                let nodes = Object::Boolean(!nodes.is_empty());
+                match left {
+                    Object::Nodes(_) => self.compare_values(comparison, &nodes, right),
+                    _ => self.compare_values(comparison, left, &nodes),
+                }
+            }
+            (Object::Nodes(nodes), value) => nodes.iter().any(|node| {
+                self.compare_values(comparison, &Object::String(self.string_value(*node)), value)
+            }),
+            (value, Object::Nodes(nodes)) => nodes.iter().any(|node| {
+                self.compare_values(comparison, value, &Object::String(self.string_value(*node)))
+            }),
+            (left, right) => self.compare_values(comparison, left, right),
+        }
+    }
+
+    fn compare_values(&self, comparison: Comparison, left: &Object, right: &Object) -> bool {
+        let equal = || match (left, right) {
+            (Object::Boolean(_), _) | (_, Object::Boolean(_)) => {
+                self.boolean(left) == self.boolean(right)
+            }
+            (Object::Number(_), _) | (_, Object::Number(_)) => {
+                self.number(left) == self.number(right)
+            }
+            _ => self.string(left) == self.string(right),
+        };
+        let (left, right) = (self.number(left), self.number(right));
+        match comparison {
+            Comparison::Equal => equal(),
+            Comparison::NotEqual => !equal(),
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+
+    // XPath 1.0 §4 Core Function Library
+    fn call(&self, name: &str, arguments: &[Expr], context: &Context) -> Result<Object, Error> {
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.evaluate(argument, context))
+            .collect::<Result<Vec<_>, Error>>()?;
+        // The functions whose argument defaults to the context node
+        let context_node = || Object::Nodes(vec![context.node]);
+        let argument = |index: usize| arguments.get(index).cloned().unwrap_or_else(context_node);
+        let string = |index: usize| self.string(&argument(index));
+        Ok(match name {
+            "last" => Object::Number(context.size as f64),
+            "position" => Object::Number(context.position as f64),
+            "count" => Object::Number(self.nodes(argument(0))?.len() as f64),
+            "local-name" | "name" => {
+                let nodes = self.nodes(argument(0))?;
+                let name = nodes
+                    .first()
+                    .and_then(|node| self.tree.node(*node).name())
+                    .map(|found| match name {
+                        "local-name" => qualify(found).local_part,
+                        _ => found.to_string(),
+                    });
+                Object::String(name.unwrap_or_default())
+            }
+            "string" => Object::String(string(0)),
+            "concat" => Object::String(
+                arguments
+                    .iter()
+                    .map(|argument| self.string(argument))
+                    .collect(),
+            ),
+            "starts-with" => Object::Boolean(string(0).starts_with(&string(1))),
+            "contains" => Object::Boolean(string(0).contains(&string(1))),
+            "substring-before" => {
+                let (haystack, needle) = (string(0), string(1));
+                Object::String(
+                    haystack
+                        .split_once(&needle)
+                        .map(|(before, _)| before.to_string())
+                        .unwrap_or_default(),
+                )
+            }
+            "substring-after" => {
+                let (haystack, needle) = (string(0), string(1));
+                Object::String(
+                    haystack
+                        .split_once(&needle)
+                        .map(|(_, after)| after.to_string())
+                        .unwrap_or_default(),
+                )
+            }
+            "substring" => {
+                let start = round(self.number(&arguments[1]));
+                let end = match arguments.get(2) {
+                    Some(length) => start + round(self.number(length)),
+                    None => f64::INFINITY,
+                };
+                Object::String(
+                    string(0)
+                        .chars()
+                        .enumerate()
+                        .filter(|(index, _)| {
+                            let position = (index + 1) as f64;
+                            position >= start && position < end
+                        })
+                        .map(|(_, c)| c)
+                        .collect(),
+                )
+            }
+            "string-length" => Object::Number(string(0).chars().count() as f64),
+            "normalize-space" => Object::String(
+                string(0)
+                    .split([' ', '\t', '\r', '\n'])
+                    .filter(|word| !word.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            "translate" => {
+                let from: Vec<char> = string(1).chars().collect();
+                let to: Vec<char> = string(2).chars().collect();
+                Object::String(
+                    string(0)
+                        .chars()
+                        .filter_map(|c| match from.iter().position(|from| *from == c) {
+                            Some(index) => to.get(index).copied(),
+                            None => Some(c),
+                        })
+                        .collect(),
+                )
+            }
+            "boolean" => Object::Boolean(self.boolean(&arguments[0])),
+            "not" => Object::Boolean(!self.boolean(&arguments[0])),
+            "true" => Object::Boolean(true),
+            "false" => Object::Boolean(false),
+            "number" => Object::Number(self.number(&argument(0))),
+            "sum" => Object::Number(
+                self.nodes(argument(0))?
+                    .into_iter()
+                    .map(|node| string_to_number(&self.string_value(node)))
+                    .sum(),
+            ),
+            "floor" => Object::Number(self.number(&arguments[0]).floor()),
+            "ceiling" => Object::Number(self.number(&arguments[0]).ceil()),
+            "round" => Object::Number(round(self.number(&arguments[0]))),
+            _ => unreachable!("unknown functions are rejected by XPath::compile"),
+        })
+    }
+}
+
+/// Rounds to the closest integer, and halfway values towards positive infinity
+fn round(number: f64) -> f64 {
+    if number.is_finite() {
+        (number + 0.5).floor()
+    } else {
+        number
+    }
+}
+
+// XPath 1.0 §4.2 String Functions, numbers are written without exponent and integers without decimal point
+fn number_to_string(number: f64) -> String {
+    if number.is_nan() {
+        "NaN".to_string()
+    } else if number.is_infinite() {
+        if number > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        }
+        .to_string()
+    } else if number == 0.0 {
+        "0".to_string()
+    } else {
+        number.to_string()
+    }
+}
+
+// XPath 1.0 §4.4 Number Functions, strings that are not a Number with an optional minus sign are NaN
+fn string_to_number(string: &str) -> f64 {
+    let string = string.trim_matches(|c| matches!(c, ' ' | '\t' | '\r' | '\n'));
+    let digits = string.strip_prefix('-').unwrap_or(string);
+    let valid = !digits.is_empty()
+        && digits != "."
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && digits.matches('.').count() <= 1;
+    if valid {
+        string.parse().unwrap_or(f64::NAN)
+    } else {
+        f64::NAN
+    }
+}
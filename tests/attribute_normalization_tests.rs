@@ -0,0 +1,127 @@
+use nom_xml::{
+    attribute::{Attribute, AttributeValue},
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+    event::{Event, EventReader},
+    parse::Parse,
+    resolver::MemoryResolver,
+    tag::Tag,
+    Document,
+};
+use std::{error::Error, sync::Arc};
+
+const DTD: &str = "<!ELEMENT doc (item*)>\n<!ELEMENT item EMPTY>\n<!ENTITY nl \"\n\">\n<!ATTLIST doc id ID #IMPLIED title CDATA #IMPLIED>\n<!ATTLIST item refs IDREFS #IMPLIED size (small|large) 'small' note CDATA #IMPLIED>";
+
+fn attribute_values(tag: &Tag) -> Vec<(String, String)> {
+    tag.attributes
+        .iter()
+        .flatten()
+        .filter_map(|attribute| match attribute {
+            Attribute::Instance {
+                name,
+                value: AttributeValue::Value(value),
+            } => Some((name.to_string(), value.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+fn tags(document: &Document, found: &mut Vec<Tag>) {
+    match document {
+        Document::Element(tag, content, _) => {
+            found.push(tag.clone());
+            tags(content, found);
+        }
+        Document::EmptyTag(tag) => found.push(tag.clone()),
+        Document::Nested(documents) => documents.iter().for_each(|document| tags(document, found)),
+        _ => {}
+    }
+}
+
+fn parsed_attribute_values(input: &str, config: &Config) -> Vec<Vec<(String, String)>> {
+    let (_, document) = Document::parse(input, config).unwrap();
+    let mut found = vec![];
+    tags(&document, &mut found);
+    found.iter().map(attribute_values).collect()
+}
+
+fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+    values
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_white_space_is_replaced_in_cdata_values() {
+    let input =
+        format!("<!DOCTYPE doc [\n{DTD}\n]>\n<doc title=\"a\tb\r\nc\nd&nl;e&#9;f&#10;g\"/>");
+    assert_eq!(
+        parsed_attribute_values(&input, &Config::default()),
+        vec![pairs(&[("title", "a b c d e\tf\ng")])]
+    );
+
+    // Without a declaration, values are normalized as CDATA
+    assert_eq!(
+        parsed_attribute_values("<doc a=\"  x\ty  \"/>", &Config::default()),
+        vec![pairs(&[("a", "  x y  ")])]
+    );
+}
+
+#[test]
+fn test_tokenized_values_are_collapsed() {
+    let input = format!(
+        "<!DOCTYPE doc [\n{DTD}\n]>\n<doc id=\"  d1 \"><item refs=\"\n  d1\t\td1  \" note=\"  kept  \"/><item size=\" large \" refs=\"&#32;d1&#9;\"/></doc>"
+    );
+    assert_eq!(
+        parsed_attribute_values(&input, &Config::default()),
+        vec![
+            pairs(&[("id", "d1")]),
+            pairs(&[("refs", "d1 d1"), ("note", "  kept  ")]),
+            pairs(&[("size", "large"), ("refs", "d1\t")]),
+        ]
+    );
+}
+
+#[test]
+fn test_external_subset_declarations() {
+    let resolver = MemoryResolver::new().with_system_id("doc.dtd", DTD);
+    let config = Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy: ExternalEntityPolicy::AllowAll,
+            resolver: Some(Arc::new(resolver)),
+            base_directory: None,
+        },
+        ..Default::default()
+    };
+    let input =
+        "<!DOCTYPE doc SYSTEM \"doc.dtd\">\n<doc id=\" d1\n\"><item refs=\" d1   d1 \" size=\"large \"/></doc>";
+    assert_eq!(
+        parsed_attribute_values(input, &config),
+        vec![
+            pairs(&[("id", "d1")]),
+            pairs(&[("refs", "d1 d1"), ("size", "large")])
+        ]
+    );
+}
+
+#[test]
+fn test_events_are_normalized() -> Result<(), Box<dyn Error>> {
+    let input = format!("<!DOCTYPE doc [\n{DTD}\n]>\n<doc id=\" d1 \" title=\" a\tb \"><item refs=\"d1  d1\"/></doc>");
+    let config = Config::default();
+    let start_tags: Vec<_> = EventReader::from_str(&input, &config)
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::StartTag(tag) => Some(attribute_values(&tag)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        start_tags,
+        vec![
+            pairs(&[("id", "d1"), ("title", " a b ")]),
+            pairs(&[("refs", "d1 d1"), ("size", "small")]),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use nom_xml::{
+    attribute::{Attribute, Prefix},
+    config::Config,
+    parse::Parse,
+    Document, Name,
+};
+use std::error::Error;
+
+#[test]
+fn test_prefixed_attributes() -> Result<(), Box<dyn Error>> {
+    // Only the `xmlns:` prefix declares namespaces, other prefixes such as `xml:` name attributes
+    let input = "<root xml:lang=\"en\" xmlns:ns=\"urn:ns\">text</root>";
+    let (_, document) = Document::parse(input, &Config::default())?;
+    let Document::Element(tag, _, _) = document else {
+        panic!("Expected an element, got {document:?}");
+    };
+    let attributes = tag.attributes.unwrap_or_default();
+
+    assert!(matches!(
+        &attributes[..],
+        [
+            Attribute::Instance { name, .. },
+            Attribute::Namespace { prefix: Prefix::Prefix(prefix), .. },
+        ] if *name == Name::new(Some("xml"), "lang") && prefix == "ns"
+    ));
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use nom_xml::{
+    borrowed::{BorrowedAttribute, BorrowedDocument, BorrowedName, BorrowedTag},
+    config::Config,
+    io::read_file,
+    parse::Parse,
+    Document,
+};
+use std::{borrow::Cow, fs::File};
+
+fn parse(input: &str) -> BorrowedDocument<'_> {
+    let (_, document) = BorrowedDocument::parse(input, &Config::default()).unwrap();
+    document
+}
+
+fn is_borrowed_from(text: Cow<str>, input: &str) -> bool {
+    matches!(text, Cow::Borrowed(text) if input.as_bytes().as_ptr_range().contains(&text.as_ptr()))
+}
+
+fn children<'a, 'b>(document: &'b BorrowedDocument<'a>) -> &'b [BorrowedDocument<'a>] {
+    match document {
+        BorrowedDocument::Element(_, content, _) => match &**content {
+            BorrowedDocument::Nested(children) => children,
+            BorrowedDocument::Empty => &[],
+            child => std::slice::from_ref(child),
+        },
+        _ => panic!("not an element: {document:?}"),
+    }
+}
+
+#[test]
+fn test_text_is_borrowed() {
+    let xml = "<dc:book xmlns:dc=\"urn:dc\" isbn='978-0316129084'>Leviathan Wakes<!-- first --><![CDATA[<James S. A. Corey>]]><cover format=\"jpg\"/></dc:book>";
+    let document = parse(xml);
+    let BorrowedDocument::Element(start_tag, _, end_tag) = &document else {
+        panic!("not an element: {document:?}");
+    };
+    assert!(is_borrowed_from(
+        start_tag.name.prefix.as_ref().unwrap().clone(),
+        xml
+    ));
+    assert!(is_borrowed_from(start_tag.name.local_part.clone(), xml));
+    assert!(is_borrowed_from(end_tag.name.local_part.clone(), xml));
+    match start_tag.attributes.as_deref() {
+        Some(
+            [BorrowedAttribute::Namespace {
+                prefix: Some(prefix),
+                uri,
+            }, BorrowedAttribute::Instance { name, value }],
+        ) => {
+            assert_eq!((prefix.as_ref(), uri.as_ref()), ("dc", "urn:dc"));
+            assert!(is_borrowed_from(uri.clone(), xml));
+            assert_eq!(name.local_part, "isbn");
+            assert!(is_borrowed_from(value.clone(), xml));
+        }
+        attributes => panic!("unexpected attributes {attributes:?}"),
+    }
+
+    match children(&document) {
+        [BorrowedDocument::Content(content), BorrowedDocument::Comment(comment), BorrowedDocument::CDATA(cdata), BorrowedDocument::EmptyTag(cover)] =>
+        {
+            assert_eq!(content, "Leviathan Wakes");
+            assert_eq!(comment, " first ");
+            assert_eq!(cdata, "<James S. A. Corey>");
+            for text in [content, comment, cdata] {
+                assert!(is_borrowed_from(text.clone(), xml));
+            }
+            let Some([BorrowedAttribute::Instance { value, .. }]) = cover.attributes.as_deref()
+            else {
+                panic!("unexpected tag {cover:?}");
+            };
+            assert!(is_borrowed_from(value.clone(), xml));
+        }
+        children => panic!("unexpected children {children:?}"),
+    }
+}
+
+#[test]
+fn test_references_are_owned() {
+    let xml = "<!DOCTYPE book [<!ENTITY author \"James S. A. Corey\">]><book note=\"a&lt;b\" tabs=\"a\tb\">Corey &amp; Corey, &author;&#x21;</book>";
+    let document = parse(xml);
+    let BorrowedDocument::Nested(documents) = &document else {
+        panic!("no prolog: {document:?}");
+    };
+    let book = &documents[1];
+    let BorrowedDocument::Element(
+        BorrowedTag {
+            attributes: Some(attributes),
+            ..
+        },
+        ..,
+    ) = book
+    else {
+        panic!("not an element: {book:?}");
+    };
+    let values: Vec<_> = attributes
+        .iter()
+        .map(|attribute| match attribute {
+            BorrowedAttribute::Instance { value, .. } => value,
+            BorrowedAttribute::Namespace { uri, .. } => uri,
+        })
+        .collect();
+    assert!(
+        matches!(values[..], [Cow::Owned(note), Cow::Owned(tabs)] if note == "a<b" && tabs == "a b")
+    );
+
+    let texts: Vec<_> = children(book)
+        .iter()
+        .map(|child| match child {
+            BorrowedDocument::Content(text) => text,
+            child => panic!("not text: {child:?}"),
+        })
+        .collect();
+    assert_eq!(texts, ["Corey ", "&", "Corey, ", "James S. A. Corey!"]);
+    assert!(is_borrowed_from(texts[0].clone(), xml));
+    assert!(matches!(texts[1], Cow::Owned(_)));
+    assert!(is_borrowed_from(texts[2].clone(), xml));
+    assert!(matches!(texts[3], Cow::Owned(_)));
+}
+
+#[test]
+fn test_into_owned_equals_parsed_document() {
+    let config = Config::default();
+    for xml in [
+        "<catalog/>",
+        "<catalog></catalog>",
+        "<catalog><book/></catalog>",
+        "<catalog>\n  <book>Leviathan Wakes</book>\n  <book xml:lang=\"en\">Caliban's War</book>\n</catalog>",
+        "<?xml version=\"1.0\"?>\n<!-- The Expanse -->\n<catalog a='1' b=\"&#34;\"/>\n<?sort by=\"year\"?>\n",
+        "<!DOCTYPE doc [<!ENTITY e \"<b>bold</b>\"><!ATTLIST doc id ID #IMPLIED kind CDATA \"novel\">]><doc id='  x  '>a &e; b</doc>",
+    ] {
+        let (_, owned) = Document::parse(xml, &config).unwrap();
+        assert_eq!(parse(xml).into_owned(), owned, "{xml}");
+    }
+
+    let mut paths: Vec<_> = std::fs::read_dir("tests/xmltest/valid/sa")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "xml"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let xml = read_file(&mut File::open(&path).unwrap()).unwrap();
+        let (_, owned) = Document::parse(&xml, &config).unwrap();
+        let (_, borrowed) = BorrowedDocument::parse(&xml, &config).unwrap();
+        assert_eq!(Document::from(borrowed), owned, "{}", path.display());
+    }
+}
+
+#[test]
+fn test_errors_and_owned_documents() {
+    let config = Config::default();
+    for xml in [
+        "<a><b>text</c></a>",
+        "<a b=\"1\" b=\"2\"/>",
+        "<a><!-- -- --></a>",
+        "<a>]]></a>",
+        "<a>&undeclared;</a>",
+        "<a/><b/>",
+        "<a>\u{1}</a>",
+    ] {
+        let owned = Document::parse(xml, &config).unwrap_err();
+        let borrowed = BorrowedDocument::parse(xml, &config).unwrap_err();
+        assert_eq!(borrowed.to_string(), owned.to_string(), "{xml}");
+    }
+
+    // Documents converted from owned documents own their text
+    let (_, owned) = Document::parse("<a b=\"c\">d</a>", &config).unwrap();
+    let document = BorrowedDocument::from(owned.clone());
+    let BorrowedDocument::Element(start_tag, content, _) = &document else {
+        panic!("not an element: {document:?}");
+    };
+    assert_eq!(
+        start_tag.name,
+        BorrowedName {
+            prefix: None,
+            local_part: Cow::Owned("a".to_string())
+        }
+    );
+    assert!(matches!(&**content, BorrowedDocument::Content(Cow::Owned(text)) if text == "d"));
+    assert_eq!(document.into_owned(), owned);
+}
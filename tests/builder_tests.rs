@@ -0,0 +1,107 @@
+use nom_xml::{
+    builder::Element,
+    prolog::xmldecl::{Standalone, XmlDecl},
+    Document,
+};
+
+mod common;
+
+use common::parse;
+
+#[test]
+fn test_tags() {
+    assert_eq!(Element::new("br").build(), parse("<br/>"));
+    assert_eq!(Element::new("p").with_end_tag().build(), parse("<p></p>"));
+    assert_eq!(
+        Element::new("p").with_end_tag().build().to_string(),
+        "<p></p>"
+    );
+    assert_eq!(Element::new("p").text("text").build(), parse("<p>text</p>"));
+    assert_eq!(
+        Element::new("p").child(Element::new("br")).build(),
+        parse("<p><br/></p>")
+    );
+    assert_eq!(
+        Element::new("p")
+            .child(Element::new("b").text("bold"))
+            .build(),
+        parse("<p><b>bold</b></p>")
+    );
+    assert_eq!(
+        Element::new("p")
+            .text("a ")
+            .child(Element::new("b").text("bold"))
+            .text(" text")
+            .build()
+            .to_string(),
+        "<p>a <b>bold</b> text</p>"
+    );
+}
+
+#[test]
+fn test_attributes() {
+    let xml =
+        "<book isbn=\"978-0316129084\" note=\"a &lt; b\"><title>Leviathan Wakes</title></book>";
+    let book = Element::new("book")
+        .attr("isbn", "0")
+        .attr("note", "a < b")
+        .attr("isbn", "978-0316129084")
+        .child(Element::new("title").text("Leviathan Wakes"));
+    assert_eq!(book.clone().build(), parse(xml));
+    assert_eq!(Document::from(book).to_string(), xml);
+
+    // Namespace declarations and prefixed names are represented as the parser represents them
+    for xml in [
+        "<dc:book xmlns=\"urn:default\" xmlns:dc=\"urn:dc\" dc:id=\"1\" lang=\"en\"><dc:title>T</dc:title></dc:book>",
+        "<dc:book xmlns=\"urn:default\" xmlns:dc=\"urn:dc\" dc:id=\"1\" lang=\"en\"/>",
+    ] {
+        let title = Element::new("dc:title").text("T");
+        let book = Element::new("dc:book")
+            .attr("xmlns", "urn:default")
+            .attr("xmlns:dc", "urn:dc")
+            .attr("dc:id", "1")
+            .attr("lang", "en");
+        let book = if xml.ends_with("/>") {
+            book
+        } else {
+            book.child(title)
+        };
+        assert_eq!(book.build(), parse(xml));
+    }
+}
+
+#[test]
+fn test_children() {
+    let xml = "<catalog><!-- The Expanse --><?sort by=\"year\"?><book><![CDATA[<Leviathan Wakes>]]></book>text<book/></catalog>";
+    let catalog = Element::new("catalog")
+        .comment(" The Expanse ")
+        .processing_instruction("sort", Some("by=\"year\""))
+        .children([
+            Element::new("book").cdata("<Leviathan Wakes>").build(),
+            Document::Content(Some("te".to_string())),
+        ])
+        .text("")
+        .text("xt")
+        .child(Element::new("book"));
+    let document = catalog.build();
+    assert_eq!(document, parse(xml));
+    assert_eq!(document.to_string(), xml);
+
+    // Parsed documents can be children
+    let nested = Element::new("shelf").child(parse(xml)).build();
+    assert_eq!(nested, parse(&format!("<shelf>{xml}</shelf>")));
+}
+
+#[test]
+fn test_build_document() {
+    let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<catalog><book>Leviathan Wakes</book></catalog>";
+    let document = Element::new("catalog")
+        .child(Element::new("book").text("Leviathan Wakes"))
+        .build_document(XmlDecl {
+            version: "1.0".to_string(),
+            encoding: Some("UTF-8".to_string()),
+            standalone: Some(Standalone::Yes),
+        });
+    assert_eq!(document, parse(xml));
+    assert_eq!(document.to_string(), xml);
+}
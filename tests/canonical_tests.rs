@@ -0,0 +1,153 @@
+use nom_xml::{
+    config::{
+        CanonicalConfig, CanonicalMethod, Config, ExternalEntityParseConfig, ExternalEntityPolicy,
+    },
+    io::parse_entire_file,
+    parse::Parse,
+    Document,
+};
+use std::{error::Error, fs, fs::File, path::Path};
+
+fn c14n(with_comments: bool) -> CanonicalConfig {
+    CanonicalConfig {
+        method: CanonicalMethod::C14n,
+        with_comments,
+    }
+}
+
+fn exclusive_c14n(inclusive_prefixes: &[&str]) -> CanonicalConfig {
+    CanonicalConfig {
+        method: CanonicalMethod::ExclusiveC14n {
+            inclusive_prefixes: inclusive_prefixes.iter().map(|p| p.to_string()).collect(),
+        },
+        with_comments: false,
+    }
+}
+
+/// Compares the xmltest canonical form of every file in `directory` with its expectation in `directory/out`
+fn check_xmltest_directory(directory: &str, known_failures: &[&str]) -> Result<(), Box<dyn Error>> {
+    let config = Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy: ExternalEntityPolicy::AllowAll,
+            base_directory: Some(directory.to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let canonical_config = CanonicalConfig {
+        method: CanonicalMethod::XmlTest,
+        with_comments: false,
+    };
+
+    let mut entries: Vec<_> = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries {
+        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+        if path.extension().is_none_or(|extension| extension != "xml")
+            || known_failures.contains(&file_name.as_str())
+        {
+            continue;
+        }
+        let expected = fs::read_to_string(Path::new(directory).join("out").join(&file_name))?;
+        let document = parse_entire_file(&mut File::open(&path)?, &config)?;
+        assert_eq!(
+            document.to_canonical_string(&canonical_config),
+            expected,
+            "Canonical form of {} does not match",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_canonical_valid_sa_out() -> Result<(), Box<dyn Error>> {
+    // These files need behaviour the parser does not have yet: whitespace-only text between elements, default
+    // attributes declared more than once, and the notation declarations of the second canonical form
+    check_xmltest_directory(
+        "tests/xmltest/valid/sa",
+        &[
+            "044.xml", "059.xml", "069.xml", "076.xml", "080.xml", "085.xml", "090.xml", "091.xml",
+            "092.xml", "093.xml",
+        ],
+    )
+}
+
+#[test]
+fn test_canonical_valid_ext_sa_out() -> Result<(), Box<dyn Error>> {
+    check_xmltest_directory(
+        "tests/xmltest/valid/ext-sa",
+        &["006.xml", "009.xml", "014.xml"],
+    )
+}
+
+#[test]
+fn test_c14n_drops_prolog_and_expands_markup() -> Result<(), Box<dyn Error>> {
+    let input = "<?xml version=\"1.0\"?>\n<!DOCTYPE doc [\n<!ENTITY ent \"expanded\">\n]>\n<?pi-before data?>\n<!-- before -->\n<doc z='1' a=\"&quot;\t&amp;\"><e/><![CDATA[<cdata> & text]]>&ent;</doc>\n<!-- after -->";
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_canonical_string(&c14n(false)),
+        "<?pi-before data?>\n<doc a=\"&quot; &amp;\" z=\"1\"><e></e>&lt;cdata&gt; &amp; textexpanded</doc>"
+    );
+    assert_eq!(
+        document.to_canonical_string(&c14n(true)),
+        "<?pi-before data?>\n<!-- before -->\n<doc a=\"&quot; &amp;\" z=\"1\"><e></e>&lt;cdata&gt; &amp; textexpanded</doc>\n<!-- after -->"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_c14n_line_endings() -> Result<(), Box<dyn Error>> {
+    let input = "<doc>first\r\nsecond&#13;</doc>";
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_canonical_string(&c14n(false)),
+        "<doc>first\nsecond&#xD;</doc>"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_c14n_sorts_namespaces_and_attributes() -> Result<(), Box<dyn Error>> {
+    let input = r#"<doc xmlns:zz="urn:a" xmlns:aa="urn:b" xmlns="urn:default" zz:attr="1" aa:attr="2" b="3" a="4"><child xmlns:aa="urn:b" xmlns:new="urn:new"/></doc>"#;
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    // Attributes are ordered by namespace URI, unqualified ones first, and the repeated declaration of `aa` is superfluous
+    assert_eq!(
+        document.to_canonical_string(&c14n(false)),
+        r#"<doc xmlns="urn:default" xmlns:aa="urn:b" xmlns:zz="urn:a" a="4" b="3" zz:attr="1" aa:attr="2"><child xmlns:new="urn:new"></child></doc>"#
+    );
+    Ok(())
+}
+
+#[test]
+fn test_exclusive_c14n_renders_visibly_utilized_namespaces() -> Result<(), Box<dyn Error>> {
+    let input = r#"<ns:doc xmlns:ns="urn:ns" xmlns:unused="urn:unused" xmlns:other="urn:other"><other:child/><ns:child/></ns:doc>"#;
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_canonical_string(&exclusive_c14n(&[])),
+        r#"<ns:doc xmlns:ns="urn:ns"><other:child xmlns:other="urn:other"></other:child><ns:child></ns:child></ns:doc>"#
+    );
+    assert_eq!(
+        document.to_canonical_string(&exclusive_c14n(&["unused"])),
+        r#"<ns:doc xmlns:ns="urn:ns" xmlns:unused="urn:unused"><other:child xmlns:other="urn:other"></other:child><ns:child></ns:child></ns:doc>"#
+    );
+    Ok(())
+}
+
+#[test]
+fn test_c14n_default_namespace_undeclaration() -> Result<(), Box<dyn Error>> {
+    let input = r#"<doc xmlns="urn:default"><inner xmlns=""><leaf xmlns=""/></inner></doc>"#;
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_canonical_string(&c14n(false)),
+        r#"<doc xmlns="urn:default"><inner xmlns=""><leaf></leaf></inner></doc>"#
+    );
+    Ok(())
+}
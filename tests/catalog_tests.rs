@@ -0,0 +1,172 @@
+use nom_xml::{
+    catalog::{Catalog, CatalogResolver},
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+    error::Error,
+    parse::Parse,
+    Document,
+};
+use std::{path::PathBuf, sync::Arc};
+
+const DIRECTORY: &str = "tests/catalog";
+
+fn catalog() -> Catalog {
+    Catalog::from_file(format!("{DIRECTORY}/catalog.xml")).unwrap()
+}
+
+fn path(path: &str) -> Option<PathBuf> {
+    Some(PathBuf::from(DIRECTORY).join(path))
+}
+
+fn config(resolver: CatalogResolver) -> Config {
+    Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy: ExternalEntityPolicy::AllowAll,
+            resolver: Some(Arc::new(resolver)),
+            base_directory: None,
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_catalog_public_and_system_entries() {
+    let catalog = catalog();
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Example//DTD Doc V1.0//EN"), None),
+        path("dtd/doc.dtd")
+    );
+    // Public identifiers are compared with normalized whitespace
+    assert_eq!(
+        catalog.resolve_external_id(Some("  -//Example//DTD\n Doc V1.0//EN "), None),
+        path("dtd/doc.dtd")
+    );
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("http://example.com/doc.dtd")),
+        path("dtd/doc.dtd")
+    );
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Unknown//EN"), Some("unknown.dtd")),
+        None
+    );
+}
+
+#[test]
+fn test_catalog_rewrite_and_suffix_entries() {
+    let catalog = catalog();
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("http://example.com/dtd/greeting.ent")),
+        path("dtd/greeting.ent")
+    );
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("https://mirror.example.org/greeting.ent")),
+        path("dtd/greeting.ent")
+    );
+}
+
+#[test]
+fn test_catalog_group_prefer_system_and_base() {
+    let catalog = catalog();
+    let public_id = Some("-//Example//ENTITIES Greeting//EN");
+    assert_eq!(
+        catalog.resolve_external_id(public_id, None),
+        path("dtd/greeting.ent")
+    );
+    // With `prefer="system"` the public entry is not used when there is a system identifier
+    assert_eq!(
+        catalog.resolve_external_id(public_id, Some("greeting")),
+        None
+    );
+}
+
+#[test]
+fn test_catalog_delegate_and_next_catalog() {
+    let catalog = catalog();
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Delegated//TEXT Delegated//EN"), None),
+        path("delegated/delegated.ent")
+    );
+    // Delegation only consults the delegated catalogs
+    assert_eq!(
+        catalog.resolve_external_id(Some("-//Delegated//TEXT Other//EN"), None),
+        None
+    );
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("urn:example:next")),
+        path("dtd/next.ent")
+    );
+}
+
+#[test]
+fn test_catalog_referred_to_twice() {
+    let catalog = Catalog::from_file(format!("{DIRECTORY}/shared.xml")).unwrap();
+    assert_eq!(
+        catalog.resolve_external_id(None, Some("urn:example:next")),
+        path("dtd/next.ent")
+    );
+}
+
+#[test]
+fn test_catalog_resolves_public_doctype() -> Result<(), Error> {
+    let input = "<!DOCTYPE doc PUBLIC \"-//Example//DTD Doc V1.0//EN\" \"http://unreachable.example.com/doc.dtd\">\n<doc>&e;</doc>";
+    let (_, document) = Document::parse(input, &config(CatalogResolver::new(catalog())))?;
+    assert!(document
+        .to_string()
+        .ends_with("<doc>declared in doc.dtd</doc>"));
+    Ok(())
+}
+
+#[test]
+fn test_catalog_files_are_checked_by_the_policy() {
+    // The catalog maps the DTD to a file outside of the allowed directory
+    let input = "<!DOCTYPE doc PUBLIC \"-//Example//DTD Doc V1.0//EN\" \"http://unreachable.example.com/doc.dtd\">\n<doc/>";
+    let mut config = config(CatalogResolver::new(catalog()));
+    config.external_parse_config.policy =
+        ExternalEntityPolicy::AllowDirectories(vec![PathBuf::from(format!(
+            "{DIRECTORY}/delegated"
+        ))]);
+    assert!(matches!(
+        Document::parse(input, &config),
+        Err(nom::Err::Failure(Error::ExternalEntityNotAllowed { path, .. }))
+            if path == PathBuf::from(DIRECTORY).join("dtd/doc.dtd")
+    ));
+
+    config.external_parse_config.policy = ExternalEntityPolicy::Deny;
+    assert!(Document::parse(input, &config).is_ok());
+}
+
+#[test]
+fn test_catalog_resolves_entities() -> Result<(), Error> {
+    let input = "<!DOCTYPE doc [\n<!ENTITY a PUBLIC \"-//Example//ENTITIES Greeting//EN\" \"greeting\">\n<!ENTITY b SYSTEM \"urn:example:next\">\n<!ENTITY c SYSTEM \"unlisted.ent\">\n]>\n<doc><a>&a;</a><b>&b;</b><c>&c;</c></doc>";
+    let resolver = CatalogResolver::new(catalog());
+    let (_, document) = Document::parse(input, &config(resolver))?;
+    // `a` is not resolved because of `prefer="system"`, and `c` is not in the catalog
+    assert!(document
+        .to_string()
+        .ends_with("<doc><a>a</a><b>next</b><c>c</c></doc>"));
+    Ok(())
+}
+
+#[test]
+fn test_catalog_resolver_does_not_read_unlisted_files() -> Result<(), Box<dyn std::error::Error>> {
+    let file = std::fs::canonicalize("tests/xmltest/valid/ext-sa/001.ent")?;
+    let input = format!(
+        "<!DOCTYPE doc [<!ENTITY e SYSTEM \"{}\">]><doc>&e;</doc>",
+        file.display()
+    );
+    let (_, document) = Document::parse(&input, &config(CatalogResolver::new(catalog())))?;
+    assert!(document.to_string().ends_with("<doc>e</doc>"));
+    Ok(())
+}
+
+#[test]
+fn test_catalog_file_errors() {
+    assert!(matches!(
+        Catalog::from_file(format!("{DIRECTORY}/missing.xml")),
+        Err(Error::CatalogFile { path, source })
+            if path == format!("{DIRECTORY}/missing.xml") && matches!(*source, Error::IoError(_))
+    ));
+    assert!(matches!(
+        Catalog::parse_str("<catalog><public", DIRECTORY),
+        Err(Error::ParseError(_))
+    ));
+}
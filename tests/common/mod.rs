@@ -0,0 +1,55 @@
+//! Documents and helpers shared by the integration tests
+
+// Each test crate only uses some of them
+#![allow(dead_code)]
+
+use nom_xml::{config::Config, parse::Parse, Document};
+
+pub fn parse(input: &str) -> Document {
+    let (_, document) = Document::parse(input, &Config::default()).unwrap();
+    document
+}
+
+/// Books and a magazine in a catalog without namespaces, one of the books on a shelf
+pub fn catalog() -> Document {
+    parse(CATALOG)
+}
+
+/// Books in a catalog with a default namespace, with comments and a processing instruction around them
+pub fn namespaced_catalog() -> Document {
+    parse(NAMESPACED_CATALOG)
+}
+
+/// Books in a library, with a comment, a processing instruction and a CDATA section among them
+pub fn library() -> Document {
+    parse(LIBRARY)
+}
+
+const CATALOG: &str = r#"<?xml version="1.0"?>
+<catalog>
+  <book isbn="978-0316129084" lang="en-US"><title>Leviathan Wakes</title><author>James S. A. Corey</author></book>
+  <book isbn="0-316-12906-0" lang="en-GB"><title>Caliban's War</title></book>
+  <magazine issn="1234-5678"><title>Expanse Monthly</title></magazine>
+  <book isbn="978-0316129077"><title>Abaddon's Gate</title><series><title>The Expanse</title></series></book>
+  <shelf><book isbn="978-0316217620" lang="en"><title>Cibola Burn</title></book></shelf>
+</catalog>"#;
+
+const NAMESPACED_CATALOG: &str = r#"<?xml version="1.0"?>
+<!-- The Expanse -->
+<catalog xmlns="urn:catalog" xmlns:dc="urn:dc">
+  <book isbn="978-0316129084" dc:year="2011"><title>Leviathan Wakes</title>first<!-- note -->second</book>
+  <book isbn="978-0316129060"><title>Caliban's War</title></book>
+  <?sort by="year"?>
+  <shelf xmlns=""><book><title>Cibola Burn</title></book></shelf>
+</catalog>"#;
+
+const LIBRARY: &str = r#"<?xml version="1.0"?>
+<!-- The Expanse -->
+<library name="Expanse">
+  <book isbn="978-0316129084" year="2011"><title>Leviathan Wakes</title><price>9.99</price></book>
+  <book isbn="978-0316129060" year="2012"><title>Caliban's War</title><price>10.50</price></book>
+  <?sort by="year"?>
+  <book isbn="978-0316129077" year="2013"><title><![CDATA[Abaddon's Gate]]></title><price>12</price></book>
+  <novella year="2012"><title>Gods of Risk</title></novella>
+  <shelf><book year="2014"><title>Cibola Burn</title></book></shelf>
+</library>"#;
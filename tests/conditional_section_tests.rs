@@ -0,0 +1,89 @@
+use nom_xml::{
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+    error::Error,
+    io::parse_entire_file,
+    parse::Parse,
+    resolver::MemoryResolver,
+    Document,
+};
+use std::{fs::File, sync::Arc};
+
+fn parse_with_dtd(input: &str, dtd: &str) -> Result<String, Error> {
+    let config = Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy: ExternalEntityPolicy::AllowAll,
+            resolver: Some(Arc::new(
+                MemoryResolver::new().with_system_id("doc.dtd", dtd),
+            )),
+            base_directory: None,
+        },
+        ..Default::default()
+    };
+    let (_, document) = Document::parse(input, &config)?;
+    Ok(document.to_string())
+}
+
+#[test]
+fn test_conditional_sections_compliance() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy: ExternalEntityPolicy::AllowAll,
+            base_directory: Some("tests/xmltest/valid/not-sa".into()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    for file_number in ["013", "014", "015", "016", "028", "029", "030"] {
+        let mut file = File::open(format!("tests/xmltest/valid/not-sa/{file_number}.xml"))?;
+        let document = parse_entire_file(&mut file, &config)?;
+        let expected =
+            std::fs::read_to_string(format!("tests/xmltest/valid/not-sa/out/{file_number}.xml"))?;
+        assert!(
+            document.to_string().ends_with(&expected),
+            "{file_number}: {document}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_nested_conditional_sections() -> Result<(), Error> {
+    let dtd = r#"<!ELEMENT doc (#PCDATA)>
+<!ENTITY % draft "INCLUDE">
+<![IGNORE[
+    <![INCLUDE[ <!ATTLIST doc a CDATA "ignored"> ]]>
+    <![ ]]> <!-- "]]" and "<!" alone do not end the section -->
+]]>
+<![INCLUDE[
+    <![ %draft; [ <!ATTLIST doc b CDATA "draft"> ]]>
+    <![IGNORE[ <!ATTLIST doc c CDATA "ignored"> ]]>
+]]>"#;
+    let document = parse_with_dtd("<!DOCTYPE doc SYSTEM \"doc.dtd\"><doc/>", dtd)?;
+    assert!(document.ends_with("<doc b=\"draft\"/>"), "{document}");
+    Ok(())
+}
+
+#[test]
+fn test_internal_subset_switches_conditional_sections() -> Result<(), Error> {
+    let dtd = r#"<!ENTITY % draft "INCLUDE">
+<![%draft;[<!ATTLIST doc status CDATA "draft">]]>
+<!ATTLIST doc status CDATA "final">"#;
+    let document = parse_with_dtd("<!DOCTYPE doc SYSTEM \"doc.dtd\"><doc/>", dtd)?;
+    assert!(document.ends_with("<doc status=\"draft\"/>"), "{document}");
+
+    let document = parse_with_dtd(
+        "<!DOCTYPE doc SYSTEM \"doc.dtd\" [<!ENTITY % draft \"IGNORE\">]><doc/>",
+        dtd,
+    )?;
+    assert!(document.ends_with("<doc status=\"final\"/>"), "{document}");
+    Ok(())
+}
+
+#[test]
+fn test_conditional_section_in_internal_subset() {
+    assert!(Document::parse(
+        "<!DOCTYPE doc [<![INCLUDE[<!ELEMENT doc EMPTY>]]>]><doc/>",
+        &Config::default()
+    )
+    .is_err());
+}
@@ -0,0 +1,257 @@
+use nom_xml::{
+    attribute::{Attribute, AttributeValue},
+    error::Error,
+    tag::{Tag, TagState},
+    Document, Name,
+};
+
+mod common;
+
+use common::parse;
+
+fn element(name: &str, text: &str) -> Document {
+    parse(&format!("<{name}>{text}</{name}>"))
+}
+
+#[test]
+fn test_children() {
+    let document = parse("<?xml version=\"1.0\"?><a>x<b/><!--c--></a>");
+    let root = document.root_element().unwrap();
+    assert_eq!(root.children().len(), 3);
+    assert_eq!(root.children()[1].to_string(), "<b/>");
+    assert!(root.children()[1].children().is_empty());
+    assert_eq!(parse("<a>x</a>").children().len(), 1);
+    assert!(parse("<a></a>").children().is_empty());
+    assert!(document.children().is_empty());
+}
+
+#[test]
+fn test_append_insert_and_remove_children() {
+    let mut document = parse("<?xml version=\"1.0\"?><catalog></catalog>");
+    let catalog = document.root_element_mut().unwrap();
+    catalog
+        .append_child(element("book", "Leviathan Wakes"))
+        .unwrap();
+    assert!(matches!(catalog.children_mut()[0], Document::Element(..)));
+    catalog
+        .append_child(element("book", "Abaddon's Gate"))
+        .unwrap();
+    catalog
+        .insert_child(1, element("book", "Caliban's War"))
+        .unwrap();
+    catalog
+        .insert_child(0, Document::Comment(" The Expanse ".to_string()))
+        .unwrap();
+    assert_eq!(
+        catalog.to_string(),
+        "<catalog><!-- The Expanse --><book>Leviathan Wakes</book><book>Caliban's War</book><book>Abaddon's Gate</book></catalog>"
+    );
+
+    let removed = catalog.remove_child(2).unwrap();
+    assert_eq!(removed.to_string(), "<book>Caliban's War</book>");
+    let replaced = catalog
+        .replace_child(2, element("novella", "Gods of Risk"))
+        .unwrap();
+    assert_eq!(replaced.to_string(), "<book>Abaddon's Gate</book>");
+    assert_eq!(
+        catalog.to_string(),
+        "<catalog><!-- The Expanse --><book>Leviathan Wakes</book><novella>Gods of Risk</novella></catalog>"
+    );
+
+    // The content keeps the representation of parsed documents
+    catalog.remove_child(0).unwrap();
+    catalog.remove_child(0).unwrap();
+    assert_eq!(
+        catalog,
+        &parse("<catalog><novella>Gods of Risk</novella></catalog>")
+    );
+    catalog.remove_child(0).unwrap();
+    assert_eq!(catalog, &parse("<catalog></catalog>"));
+    assert_eq!(
+        document.to_string(),
+        "<?xml version=\"1.0\"?>\n<catalog></catalog>"
+    );
+}
+
+#[test]
+fn test_empty_tags_become_elements() {
+    let mut book = parse("<book isbn=\"978-0316129084\"/>");
+    book.append_child(element("title", "Leviathan Wakes"))
+        .unwrap();
+    assert_eq!(
+        book,
+        parse("<book isbn=\"978-0316129084\"><title>Leviathan Wakes</title></book>")
+    );
+
+    let mut book = parse("<book/>");
+    book.set_text("").unwrap();
+    assert_eq!(book.to_string(), "<book/>");
+    book.set_text("Cibola Burn").unwrap();
+    assert_eq!(book, parse("<book>Cibola Burn</book>"));
+
+    // The children of nested documents are added one by one
+    let mut shelf = parse("<shelf/>");
+    shelf
+        .append_child(Document::Nested(vec![
+            element("book", "a"),
+            Document::Content(Some("text".to_string())),
+        ]))
+        .unwrap();
+    assert_eq!(shelf.children().len(), 2);
+    assert_eq!(shelf.to_string(), "<shelf><book>a</book>text</shelf>");
+}
+
+#[test]
+fn test_attributes() {
+    let mut book = parse("<book isbn=\"0\" lang=\"en\"><title>Leviathan Wakes</title></book>");
+    book.set_attribute(Name::new(None, "isbn"), "978-0316129084")
+        .unwrap();
+    book.set_attribute(Name::new(None, "note"), "a < b & \"c\"")
+        .unwrap();
+    book.set_attribute(
+        Name::new(Some("xmlns"), "dc"),
+        "http://purl.org/dc/elements/1.1/",
+    )
+    .unwrap();
+    book.set_attribute(Name::new(Some("dc"), "date"), "2011")
+        .unwrap();
+    assert_eq!(
+        book.to_string(),
+        "<book isbn=\"978-0316129084\" lang=\"en\" note=\"a &lt; b &amp; &quot;c&quot;\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" dc:date=\"2011\"><title>Leviathan Wakes</title></book>"
+    );
+    let reparsed = parse(&book.to_string());
+    let namespaced = reparsed.resolve_namespaces().unwrap().unwrap();
+    assert_eq!(
+        namespaced.attribute(Some("http://purl.org/dc/elements/1.1/"), "date"),
+        Some("2011")
+    );
+
+    assert!(matches!(
+        book.remove_attribute(&Name::new(None, "lang")).unwrap(),
+        Some(Attribute::Instance { value: AttributeValue::Value(value), .. }) if value == "en"
+    ));
+    assert!(book
+        .remove_attribute(&Name::new(None, "lang"))
+        .unwrap()
+        .is_none());
+    for name in [
+        Name::new(None, "isbn"),
+        Name::new(None, "note"),
+        Name::new(Some("xmlns"), "dc"),
+        Name::new(Some("dc"), "date"),
+    ] {
+        assert!(book.remove_attribute(&name).unwrap().is_some());
+    }
+    assert_eq!(book, parse("<book><title>Leviathan Wakes</title></book>"));
+
+    // Prefixed attributes of empty-element tags are matched by their qualified name
+    let mut entry = parse("<entry xml:lang=\"en\"/>");
+    entry
+        .set_attribute(Name::new(Some("xml"), "lang"), "fr")
+        .unwrap();
+    assert_eq!(entry.to_string(), "<entry xml:lang=\"fr\"/>");
+}
+
+#[test]
+fn test_set_text_and_rename() {
+    let mut book = parse("<book><title>Leviathan Wakes</title><!-- first --></book>");
+    book.children_mut()[0].set_text("Caliban's War").unwrap();
+    book.rename(Name::new(Some("ex"), "novel")).unwrap();
+    assert_eq!(
+        book.to_string(),
+        "<ex:novel><title>Caliban's War</title><!-- first --></ex:novel>"
+    );
+    book.set_text("1 < 2").unwrap();
+    assert_eq!(book.to_string(), "<ex:novel>1 &lt; 2</ex:novel>");
+    book.children_mut()[0].set_text("3 > 2").unwrap();
+    assert_eq!(book.to_string(), "<ex:novel>3 &gt; 2</ex:novel>");
+    book.set_text("").unwrap();
+    assert_eq!(book.to_string(), "<ex:novel></ex:novel>");
+
+    let mut empty = parse("<book/>");
+    empty.rename(Name::new(None, "novel")).unwrap();
+    assert_eq!(
+        empty,
+        Document::EmptyTag(Tag::new(Name::new(None, "novel"), None, TagState::Empty))
+    );
+}
+
+#[test]
+fn test_wrap() {
+    let mut catalog = parse("<catalog><book>Leviathan Wakes</book>text</catalog>");
+    catalog.children_mut()[0]
+        .wrap(Name::new(None, "shelf"))
+        .unwrap();
+    assert_eq!(
+        catalog.to_string(),
+        "<catalog><shelf><book>Leviathan Wakes</book></shelf>text</catalog>"
+    );
+    catalog.wrap(Name::new(None, "library")).unwrap();
+    assert_eq!(
+        catalog,
+        parse(
+            "<library><catalog><shelf><book>Leviathan Wakes</book></shelf>text</catalog></library>"
+        )
+    );
+}
+
+#[test]
+fn test_invalid_edits() {
+    let mut document = parse("<?xml version=\"1.0\"?><catalog><book/></catalog>");
+    assert_eq!(
+        document
+            .append_child(element("book", "a"))
+            .unwrap_err()
+            .to_string(),
+        "Cannot append a child to nested documents"
+    );
+    let mut comment = Document::Comment("c".to_string());
+    assert_eq!(
+        comment
+            .set_attribute(Name::new(None, "a"), "b")
+            .unwrap_err()
+            .to_string(),
+        "Cannot set an attribute on a comment"
+    );
+    assert!(matches!(
+        comment.rename(Name::new(None, "a")),
+        Err(Error::UnsupportedEdit { .. })
+    ));
+
+    let catalog = document.root_element_mut().unwrap();
+    let prolog = parse("<?xml version=\"1.0\"?><a/>");
+    assert_eq!(
+        catalog
+            .append_child(prolog.clone())
+            .unwrap_err()
+            .to_string(),
+        "Cannot add the prolog to the content of an element"
+    );
+    assert!(matches!(
+        catalog.wrap(Name::new(None, "1st")),
+        Err(Error::InvalidName(_))
+    ));
+    assert!(matches!(
+        catalog.rename(Name::new(Some("a:b"), "c")),
+        Err(Error::InvalidName(_))
+    ));
+    assert!(matches!(
+        catalog.set_text("\u{0}"),
+        Err(Error::IllegalCharacter('\u{0}'))
+    ));
+    assert_eq!(
+        catalog.remove_child(1).unwrap_err().to_string(),
+        "Child index 1 is out of bounds for an element with 1 children"
+    );
+    assert!(matches!(
+        catalog.insert_child(2, element("book", "b")),
+        Err(Error::ChildIndexOutOfBounds { index: 2, len: 1 })
+    ));
+    assert!(catalog.insert_child(1, element("book", "b")).is_ok());
+
+    // Failed edits leave the document unchanged
+    assert_eq!(
+        document.to_string(),
+        "<?xml version=\"1.0\"?>\n<catalog><book/><book>b</book></catalog>"
+    );
+}
@@ -0,0 +1,237 @@
+use nom_xml::{
+    config::{Config, EntityExpansionLimits},
+    error::{Error, ExpansionLimit},
+    event::EventReader,
+    parse::Parse,
+    Document, Name,
+};
+
+fn expansion_error(input: &str, config: &Config) -> Error {
+    match Document::parse(input, config) {
+        Err(nom::Err::Error(error) | nom::Err::Failure(error)) => error,
+        result => panic!("Expected an error, got {result:?}"),
+    }
+}
+
+fn names(names: &[&str]) -> Vec<Name> {
+    names.iter().map(|name| Name::new(None, name)).collect()
+}
+
+// A chain of entities `e0` -> `e1` -> ... -> `e{depth}`, where the last one is "end"
+fn nested_entities(depth: usize) -> String {
+    let mut input = String::from("<!DOCTYPE doc [\n");
+    for i in 0..depth {
+        input.push_str(&format!("<!ENTITY e{i} \"&e{};\">\n", i + 1));
+    }
+    input.push_str(&format!("<!ENTITY e{depth} \"end\">\n]>\n<doc>&e0;</doc>"));
+    input
+}
+
+#[test]
+fn test_self_referencing_entity() {
+    let error = expansion_error(
+        "<!DOCTYPE doc [<!ENTITY a \"&a;\">]><doc>&a;</doc>",
+        &Config::default(),
+    );
+    assert!(matches!(
+        error.root_cause(),
+        Error::RecursiveEntity { chain } if *chain == names(&["a", "a"])
+    ));
+}
+
+#[test]
+fn test_mutually_recursive_entities() {
+    let input = "<!DOCTYPE doc [\n<!ENTITY a \"&b;\">\n<!ENTITY b \"&a;\">\n]>\n<doc>&a;</doc>";
+    let error = expansion_error(input, &Config::default());
+    assert!(matches!(
+        error.root_cause(),
+        Error::RecursiveEntity { chain } if *chain == names(&["a", "b", "a"])
+    ));
+    assert_eq!(
+        error.to_string(),
+        "ParseError: 5:6: Entity `a` references itself: a -> b -> a\n<doc>&a;</doc>\n     ^"
+    );
+
+    let config = Config::default();
+    let error = EventReader::from_str(input, &config)
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(error.root_cause(), Error::RecursiveEntity { .. }));
+}
+
+#[test]
+fn test_entity_value_naming_another_entity() {
+    let (_, doc) = Document::parse(
+        "<!DOCTYPE doc [<!ENTITY a \"b\"><!ENTITY b \"x\">]><doc a=\"&a;\">&a;</doc>",
+        &Config::default(),
+    )
+    .unwrap();
+    assert!(doc.to_string().ends_with("<doc a=\"b\">b</doc>"));
+}
+
+#[test]
+fn test_entity_depth_limit() {
+    let input = nested_entities(40);
+    let error = expansion_error(&input, &Config::default());
+    assert!(matches!(
+        error.root_cause(),
+        Error::EntityExpansionLimitExceeded {
+            limit: ExpansionLimit::Depth(32),
+            ..
+        }
+    ));
+
+    let config = Config {
+        entity_limits: EntityExpansionLimits {
+            max_depth: 64,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (_, doc) = Document::parse(&input, &config).unwrap();
+    assert!(doc.to_string().ends_with("<doc>end</doc>"));
+}
+
+#[test]
+fn test_entity_expanded_size_limit() {
+    let input = format!(
+        "<!DOCTYPE doc [<!ENTITY a \"{}\">]><doc>{}</doc>",
+        "x".repeat(1000),
+        "&a;".repeat(100)
+    );
+    assert!(Document::parse(&input, &Config::default()).is_ok());
+
+    let config = Config {
+        entity_limits: EntityExpansionLimits {
+            max_expanded_size: 50_000,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let error = expansion_error(&input, &config);
+    assert!(matches!(
+        error.root_cause(),
+        Error::EntityExpansionLimitExceeded {
+            entity,
+            limit: ExpansionLimit::ExpandedSize(50_000),
+        } if *entity == Name::new(None, "a")
+    ));
+}
+
+#[test]
+fn test_entity_expansion_count_limit() {
+    let input = format!(
+        "<!DOCTYPE doc [<!ENTITY a \"x\">]><doc>{}<b x=\"&a;\"/></doc>",
+        "&a;".repeat(9)
+    );
+    let config = Config {
+        entity_limits: EntityExpansionLimits {
+            max_expansions: 10,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    assert!(Document::parse(&input, &config).is_ok());
+
+    // The attribute value is the 11th reference
+    let input = input.replacen("&a;", "&a;&a;", 1);
+    let error = expansion_error(&input, &config);
+    assert!(matches!(
+        error.root_cause(),
+        Error::EntityExpansionLimitExceeded {
+            limit: ExpansionLimit::Expansions(10),
+            ..
+        }
+    ));
+
+    let error = EventReader::from_str(&input, &config)
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(
+        error.root_cause(),
+        Error::EntityExpansionLimitExceeded {
+            limit: ExpansionLimit::Expansions(10),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_billion_laughs() {
+    let mut input = String::from("<!DOCTYPE lolz [\n<!ENTITY lol \"lol\">\n");
+    input.push_str(&format!("<!ENTITY lol1 \"{}\">\n", "&lol;".repeat(10)));
+    for i in 2..10 {
+        input.push_str(&format!(
+            "<!ENTITY lol{i} \"{}\">\n",
+            format!("&lol{};", i - 1).repeat(10)
+        ));
+    }
+    input.push_str("]>\n<lolz>&lol9;</lolz>");
+
+    let error = expansion_error(&input, &Config::default());
+    assert!(matches!(
+        error.root_cause(),
+        Error::EntityExpansionLimitExceeded {
+            limit: ExpansionLimit::Expansions(100_000),
+            ..
+        }
+    ));
+
+    let error = EventReader::from_str(&input, &Config::default())
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(
+        error.root_cause(),
+        Error::EntityExpansionLimitExceeded { .. }
+    ));
+}
+
+#[test]
+fn test_quadratic_blowup() {
+    // Few expansions of one large entity, nested so that each reference in the document expands it many times
+    let input = format!(
+        "<!DOCTYPE doc [<!ENTITY a \"{}\"><!ENTITY b \"{}\">]><doc>{}</doc>",
+        "x".repeat(50_000),
+        "&a;".repeat(100),
+        "&b;".repeat(10)
+    );
+    let error = expansion_error(&input, &Config::default());
+    assert!(matches!(
+        error.root_cause(),
+        Error::EntityExpansionLimitExceeded {
+            limit: ExpansionLimit::ExpandedSize(10_485_760),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_nested_expansion_stops_at_size_limit() {
+    // Expanding `c` in full would take 9 GB, the expansion has to stop once it reaches the limit
+    let entities = format!(
+        "<!ENTITY a \"{}\"><!ENTITY b \"{}\"><!ENTITY c \"{}\">",
+        "x".repeat(100_000),
+        "&a;".repeat(300),
+        "&b;".repeat(300)
+    );
+    for body in ["<doc>&c;</doc>", "<doc x=\"&c;\"/>"] {
+        let input = format!("<!DOCTYPE doc [{entities}]>{body}");
+        let error = expansion_error(&input, &Config::default());
+        assert!(matches!(
+            error.root_cause(),
+            Error::EntityExpansionLimitExceeded {
+                entity,
+                limit: ExpansionLimit::ExpandedSize(10_485_760),
+            } if *entity == Name::new(None, "a")
+        ));
+    }
+}
+
+#[test]
+fn test_nested_references_in_replacement_text() {
+    let input = "<!DOCTYPE doc [<!ENTITY a \"a\"><!ENTITY b \"&a; and &#38;a;\"><!ENTITY c \"<i>&b;</i>\">]><doc x=\"&b;\">&c; &b;</doc>";
+    let (_, doc) = Document::parse(input, &Config::default()).unwrap();
+    assert!(doc
+        .to_string()
+        .ends_with("<doc x=\"a and a\"><i>a and a</i>a and a</doc>"));
+}
@@ -0,0 +1,153 @@
+use nom_xml::{
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+    error::Error,
+    parse::Parse,
+    resolver::{
+        DenyAllResolver, EntityResolver, FileSystemResolver, MemoryResolver, ResolvedEntity,
+    },
+    Document,
+};
+use std::sync::{Arc, Mutex};
+
+fn config(resolver: impl EntityResolver + 'static, base_directory: Option<&str>) -> Config {
+    Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy: ExternalEntityPolicy::AllowAll,
+            resolver: Some(Arc::new(resolver)),
+            base_directory: base_directory.map(str::to_string),
+        },
+        ..Default::default()
+    }
+}
+
+fn parse(input: &str, config: &Config) -> Result<String, Error> {
+    let (_, document) = Document::parse(input, config)?;
+    Ok(document.to_string())
+}
+
+#[test]
+fn test_memory_resolver_external_subset() -> Result<(), Error> {
+    let resolver = MemoryResolver::new().with_system_id("doc.dtd", "<!ENTITY e \"from the dtd\">");
+    let document = parse(
+        "<!DOCTYPE doc SYSTEM \"doc.dtd\"><doc>&e;</doc>",
+        &config(resolver, None),
+    )?;
+    assert!(document.ends_with("<doc>from the dtd</doc>"));
+    Ok(())
+}
+
+#[test]
+fn test_memory_resolver_prefers_public_id() -> Result<(), Error> {
+    let resolver = MemoryResolver::new()
+        .with_public_id("-//Example//ENTITIES Greeting//EN", "public")
+        .with_system_id("greeting.ent", "system");
+    let input = "<!DOCTYPE doc [\n<!ENTITY a PUBLIC \"-//Example//ENTITIES Greeting//EN\" \"greeting.ent\">\n<!ENTITY b SYSTEM \"greeting.ent\">\n]>\n<doc><a>&a;</a><b>&b;</b></doc>";
+    let document = parse(input, &config(resolver, None))?;
+    assert!(document.ends_with("<doc><a>public</a><b>system</b></doc>"));
+    Ok(())
+}
+
+#[test]
+fn test_deny_all_resolver_leaves_entities_unexpanded() -> Result<(), Error> {
+    let input = "<!DOCTYPE doc [<!ENTITY e SYSTEM \"e.ent\">]><doc>&e;</doc>";
+    let document = parse(input, &config(DenyAllResolver, None))?;
+    assert_eq!(document, parse(input, &Config::default())?);
+
+    // Entities missing from a memory resolver are skipped the same way
+    let document = parse(input, &config(MemoryResolver::new(), None))?;
+    assert_eq!(document, parse(input, &Config::default())?);
+    Ok(())
+}
+
+#[test]
+fn test_file_system_resolver() -> Result<(), Error> {
+    let input = "<!DOCTYPE doc [<!ENTITY e SYSTEM \"001.ent\">]><doc>&e;</doc>";
+    let directory = Some("tests/xmltest/valid/ext-sa");
+    let document = parse(input, &config(FileSystemResolver, directory))?;
+    assert!(document.ends_with("<doc>Data\n</doc>"));
+
+    // Files that cannot be read are skipped like denied entities
+    let document = parse(input, &config(FileSystemResolver, Some("tests")))?;
+    assert_eq!(document, parse(input, &Config::default())?);
+    Ok(())
+}
+
+// The public identifier, system identifier and base URI of a request
+type Request = (Option<String>, String, Option<String>);
+
+#[derive(Default)]
+struct RecordingResolver {
+    requests: Mutex<Vec<Request>>,
+}
+
+impl EntityResolver for RecordingResolver {
+    fn resolve(
+        &self,
+        public_id: Option<&str>,
+        system_id: &str,
+        base_uri: Option<&str>,
+    ) -> Result<ResolvedEntity, Error> {
+        self.requests.lock().unwrap().push((
+            public_id.map(str::to_string),
+            system_id.to_string(),
+            base_uri.map(str::to_string),
+        ));
+        Ok(ResolvedEntity::Content(b"resolved".to_vec()))
+    }
+}
+
+#[test]
+fn test_custom_resolver_receives_identifiers() -> Result<(), Error> {
+    let resolver = Arc::new(RecordingResolver::default());
+    let config = Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy: ExternalEntityPolicy::AllowAll,
+            resolver: Some(resolver.clone()),
+            base_directory: Some("entities".to_string()),
+        },
+        ..Default::default()
+    };
+    let input =
+        "<!DOCTYPE doc [<!ENTITY e PUBLIC \"-//Example//TEXT E//EN\" \"e.ent\">]><doc>&e;</doc>";
+    let document = parse(input, &config)?;
+    assert!(document.ends_with("<doc>resolved</doc>"));
+    assert_eq!(
+        *resolver.requests.lock().unwrap(),
+        [(
+            Some("-//Example//TEXT E//EN".to_string()),
+            "e.ent".to_string(),
+            Some("entities".to_string())
+        )]
+    );
+    Ok(())
+}
+
+struct MalformedResolver;
+
+impl EntityResolver for MalformedResolver {
+    fn resolve(
+        &self,
+        _public_id: Option<&str>,
+        _system_id: &str,
+        _base_uri: Option<&str>,
+    ) -> Result<ResolvedEntity, Error> {
+        Err(Error::EntityFile {
+            path: "doc.dtd".to_string(),
+            source: Box::new(std::io::Error::from(std::io::ErrorKind::InvalidData).into()),
+        })
+    }
+}
+
+#[test]
+fn test_resolver_entity_file_errors_are_kept() {
+    // An entity that the resolver reports as not well-formed stops parsing, instead of being skipped
+    let result = parse(
+        "<!DOCTYPE doc SYSTEM \"doc.dtd\"><doc/>",
+        &config(MalformedResolver, None),
+    );
+    assert!(matches!(
+        result,
+        Err(Error::EntityFile { path, source })
+            if path == "doc.dtd" && matches!(*source, Error::IoError(_))
+    ));
+}
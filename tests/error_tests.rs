@@ -0,0 +1,263 @@
+use nom_xml::{
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+    error::{Error, Location, ParseError},
+    event::EventReader,
+    io::parse_entire_file,
+    parse::Parse,
+    prolog::{external_id::ExternalID, subset::entity::expansion::ExpansionBudget},
+    Document, Name,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    io::BufReader,
+    rc::Rc,
+};
+
+fn parse_error(input: &str) -> ParseError {
+    match Document::parse(input, &Config::default()) {
+        Err(
+            nom::Err::Error(Error::ParseError(error)) | nom::Err::Failure(Error::ParseError(error)),
+        ) => error,
+        result => panic!("Expected a parse error, got {result:?}"),
+    }
+}
+
+#[test]
+fn test_error_location() {
+    let error = parse_error("<root>\n  <a x=\"1\" <b/>\n</root>");
+
+    assert_eq!(error.expected, Some("[44] EmptyElemTag"));
+    assert_eq!(
+        error.location,
+        Some(Location {
+            offset: 18,
+            line: 2,
+            column: 12,
+            snippet: "  <a x=\"1\" <b/>".to_string(),
+        })
+    );
+    assert_eq!(
+        error.to_string(),
+        "2:12: expected [44] EmptyElemTag\n  <a x=\"1\" <b/>\n           ^"
+    );
+}
+
+#[test]
+fn test_error_message() {
+    let error = parse_error("<root><!-- a -- b --></root>");
+
+    assert_eq!(error.expected, Some("[15] Comment"));
+    assert_eq!(
+        error.message.as_deref(),
+        Some("Comment ` a -- b ` contains '--'")
+    );
+    let location = error.location.unwrap();
+    assert_eq!((location.line, location.column), (1, 7));
+}
+
+#[test]
+fn test_error_column_counts_characters() {
+    let error = parse_error("<root>\u{e9}\u{e9}<a x=\"1\" <b/></root>");
+
+    let location = error.location.unwrap();
+    assert_eq!(location.offset, 19);
+    assert_eq!(location.column, 18);
+}
+
+#[test]
+fn test_error_unexpected_end_of_input() {
+    let error = parse_error("<root>\n<a>");
+
+    let location = error.location.unwrap();
+    assert_eq!((location.line, location.column), (2, 4));
+}
+
+#[test]
+fn test_error_from_file() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::temp_dir().join("nom_xml_error_from_file.xml");
+    fs::write(&path, "<root>\n<a>\n<b x=></b>\n</a>\n</root>")?;
+    let result = parse_entire_file(&mut File::open(&path)?, &Config::default());
+    fs::remove_file(&path)?;
+
+    match result {
+        Err(Error::ParseError(error)) => {
+            let location = error.location.as_ref().unwrap();
+            assert_eq!((location.line, location.column), (3, 4));
+            assert_eq!(location.snippet, "<b x=></b>");
+        }
+        result => panic!("Expected a parse error, got {result:?}"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_error_location_from_read() {
+    // The reader's buffer is drained as events are produced, which must not shift the reported location
+    let input = "<root>\n  <a>text\n  </b>\n</root>";
+    let config = Config::default();
+    let from_str = EventReader::from_str(input, &config).find_map(Result::err);
+    let from_read = EventReader::new(BufReader::with_capacity(1, input.as_bytes()), &config)
+        .find_map(Result::err);
+
+    match (from_str, from_read) {
+        (Some(Error::ParseError(from_str)), Some(Error::ParseError(from_read))) => {
+            let location = from_str.location.as_ref().unwrap();
+            assert_eq!((location.line, location.column), (3, 3));
+            assert_eq!(location.snippet, "  </b>");
+            assert_eq!(from_str.location, from_read.location);
+        }
+        errors => panic!("Expected parse errors, got {errors:?}"),
+    }
+}
+
+#[test]
+fn test_error_mismatched_end_tag() {
+    let error = parse_error("<a>text</b>");
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::MismatchedEndTag { start, end } if *start == Name::new(None, "a") && *end == Name::new(None, "b")
+    ));
+
+    let config = Config::default();
+    let error = EventReader::from_str("<root><a>text</b></root>", &config)
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(
+        error.root_cause(),
+        Error::MismatchedEndTag { start, end } if *start == Name::new(None, "a") && *end == Name::new(None, "b")
+    ));
+    assert_eq!(
+        error.to_string(),
+        "ParseError: 1:14: End tag `b` does not match start tag `a`\n<root><a>text</b></root>\n             ^"
+    );
+}
+
+#[test]
+fn test_error_nested_mismatched_end_tag() {
+    let error = parse_error("<a><b></c></a>");
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::MismatchedEndTag { start, end } if *start == Name::new(None, "b") && *end == Name::new(None, "c")
+    ));
+}
+
+#[test]
+fn test_error_duplicate_attribute() {
+    let error = parse_error("<a x='1' y='2' x='3'/>");
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::DuplicateAttribute { tag, attribute } if *tag == Name::new(None, "a") && *attribute == Name::new(None, "x")
+    ));
+}
+
+#[test]
+fn test_error_illegal_characters() {
+    let error = parse_error("<a>\u{1}</a>");
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::IllegalCharacter('\u{1}')
+    ));
+
+    let error = parse_error("<a>&#xFFFE;</a>");
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::IllegalCharacterReference(reference) if reference == "&#xFFFE;"
+    ));
+}
+
+#[test]
+fn test_error_entity_references() {
+    let error = parse_error("<a>&e;</a>");
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::UndeclaredEntity(name) if *name == Name::new(None, "e")
+    ));
+
+    let error = parse_error("<!DOCTYPE a [<!ENTITY e '<b>'>]><a>&e;</a>");
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::MalformedEntity(name) if *name == Name::new(None, "e")
+    ));
+
+    let error = parse_error("<!DOCTYPE a [<!ENTITY e SYSTEM 'e.xml'>]><a x='&e;'/>");
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::ExternalEntityInAttributeValue(name) if *name == Name::new(None, "e")
+    ));
+
+    let error = parse_error(
+        "<!DOCTYPE a [<!NOTATION n SYSTEM 'n'><!ENTITY e SYSTEM 'e.gif' NDATA n>]><a>&e;</a>",
+    );
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::UnparsedEntityReference(name) if *name == Name::new(None, "e")
+    ));
+
+    // Entities are not checked until they are referenced
+    assert!(Document::parse("<!DOCTYPE a [<!ENTITY e '<b>'>]><a/>", &Config::default()).is_ok());
+
+    // References in the replacement text are resolved where the entity is referenced, after the entities they
+    // reference are declared
+    let (_, document) = Document::parse(
+        "<!DOCTYPE d [<!ENTITY a \"<x>&b;</x>\"><!ENTITY b \"y\">]><d>&a;</d>",
+        &Config::default(),
+    )
+    .unwrap();
+    assert!(document.to_string().ends_with("<d><x>y</x></d>"));
+
+    // [WFC: No Recursion] applies to the default values of attributes too
+    let error = parse_error(
+        "<!DOCTYPE d [<!ENTITY a \"&b;\"><!ENTITY b \"&a;\"><!ATTLIST d x CDATA \"&a;\">]><d/>",
+    );
+    assert!(matches!(
+        Error::ParseError(error).root_cause(),
+        Error::RecursiveEntity { .. }
+    ));
+}
+
+#[test]
+fn test_error_content_after_root() {
+    let error = parse_error("<a/>\n<!-- end -->\n<b/>");
+    assert_eq!(
+        error.message.as_deref(),
+        Some("Only comments, processing instructions and white space may follow the root element")
+    );
+    assert_eq!(error.location.map(|location| location.line), Some(3));
+}
+
+#[test]
+fn test_error_external_entity() {
+    let external_id = ExternalID::System("missing.dtd".to_string());
+    let entity_references = Rc::new(RefCell::new(HashMap::new()));
+    let budget = ExpansionBudget::default();
+
+    let result = external_id.get_external_entity_from_id(
+        ">",
+        entity_references.clone(),
+        &Config::default(),
+        &budget,
+    );
+    assert!(matches!(
+        result,
+        Err(Error::ExternalEntityDisabled { system_id }) if system_id == "missing.dtd"
+    ));
+
+    let config = Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy: ExternalEntityPolicy::AllowAll,
+            base_directory: Some("tests".to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    match external_id.get_external_entity_from_id(">", entity_references, &config, &budget) {
+        Err(Error::EntityFile { path, source }) => {
+            assert_eq!(path, "tests/missing.dtd");
+            assert!(matches!(*source, Error::IoError(_)));
+        }
+        result => panic!("Expected an entity file error, got {result:?}"),
+    }
+}
@@ -0,0 +1,194 @@
+use nom_xml::{
+    attribute::Attribute,
+    config::Config,
+    event::{Event, EventReader},
+    io::read_file,
+    processing_instruction::ProcessingInstruction,
+    tag::{Tag, TagState},
+    Name,
+};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+};
+
+fn start_tag(name: &str, attributes: Option<Vec<Attribute>>, state: TagState) -> Event {
+    Event::StartTag(Tag::new(Name::new(None, name), attributes, state))
+}
+
+fn end_tag(name: &str) -> Event {
+    Event::EndTag(Tag::new(Name::new(None, name), None, TagState::End))
+}
+
+#[test]
+fn test_events_from_str() -> Result<(), Box<dyn Error>> {
+    let input = "<root a=\"1\"><!-- note --><child>Text &amp; more</child><empty/><![CDATA[<raw>]]><?pi data?></root>";
+    let config = Config::default();
+    let events = EventReader::from_str(input, &config).collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(
+        events,
+        vec![
+            start_tag(
+                "root",
+                Some(vec![Attribute::new("a", "1")]),
+                TagState::Start
+            ),
+            Event::Comment(" note ".to_string()),
+            start_tag("child", None, TagState::Start),
+            Event::Text("Text & more".to_string()),
+            end_tag("child"),
+            start_tag("empty", None, TagState::Empty),
+            end_tag("empty"),
+            Event::CDATA("<raw>".to_string()),
+            Event::ProcessingInstruction(ProcessingInstruction {
+                target: Name::new(None, "pi"),
+                data: Some("data".to_string()),
+            }),
+            end_tag("root"),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_events_prolog_and_entities() -> Result<(), Box<dyn Error>> {
+    let input = "<?xml version=\"1.0\"?>\n<!-- before -->\n<!DOCTYPE doc [\n<!ENTITY e \"replacement\">\n<!ATTLIST doc a CDATA \"default\">\n]>\n<doc>&e;&#65;\n</doc>\n<?after?>\n";
+    let config = Config::default();
+    let events = EventReader::from_str(input, &config).collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(events.len(), 6);
+    assert_eq!(events[0], Event::Comment(" before ".to_string()));
+    assert!(
+        matches!(&events[1], Event::Doctype(doc_type) if doc_type.name == Name::new(None, "doc"))
+    );
+    assert_eq!(
+        events[2],
+        start_tag(
+            "doc",
+            Some(vec![Attribute::new("a", "default")]),
+            TagState::Start
+        )
+    );
+    assert_eq!(events[3], Event::Text("replacementA\n".to_string()));
+    assert_eq!(events[4], end_tag("doc"));
+    assert!(
+        matches!(&events[5], Event::ProcessingInstruction(pi) if pi.target == Name::new(None, "after"))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_events_xml_prefixed_processing_instruction() -> Result<(), Box<dyn Error>> {
+    let input = "<?xml-stylesheet href=\"a\"?><root/>";
+    let config = Config::default();
+    let events = EventReader::from_str(input, &config).collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(
+        events,
+        vec![
+            Event::ProcessingInstruction(ProcessingInstruction {
+                target: Name::new(None, "xml-stylesheet"),
+                data: Some("href=\"a\"".to_string()),
+            }),
+            start_tag("root", None, TagState::Empty),
+            end_tag("root"),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_events_normalize_line_endings() -> Result<(), Box<dyn Error>> {
+    let input = "<doc>a\r\nb\rc</doc>";
+    let config = Config::default();
+    let events = EventReader::new(BufReader::with_capacity(1, input.as_bytes()), &config)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(events[1], Event::Text("a\nb\nc".to_string()));
+    Ok(())
+}
+
+#[test]
+fn test_events_mismatched_end_tag() {
+    let config = Config::default();
+    let result = EventReader::from_str("<a><b></a></b>", &config).collect::<Result<Vec<_>, _>>();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_events_unexpected_end_of_input() {
+    let config = Config::default();
+    let mut events = EventReader::from_str("<a><b>text</b>", &config);
+    assert!(matches!(events.next(), Some(Ok(Event::StartTag(_)))));
+    assert!(events.any(|event| event.is_err()));
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn test_events_malformed_markup_before_end_of_input() {
+    // The input never ends, so the error has to be reported without reading more of it
+    let input = "<root><child a=1>".as_bytes().chain(io::repeat(b' '));
+    let config = Config::default();
+    let mut events = EventReader::new(BufReader::new(input), &config);
+    assert!(matches!(events.next(), Some(Ok(Event::StartTag(_)))));
+    assert!(matches!(events.next(), Some(Err(_))));
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn test_events_content_after_root() {
+    let config = Config::default();
+    let result = EventReader::from_str("<a/><b/>", &config).collect::<Result<Vec<_>, _>>();
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_events_valid_sa_from_read() -> Result<(), Box<dyn Error>> {
+    // Reading through a tiny buffer splits every token across reads, which must not change the events
+    let config = Config::default();
+    let mut entries: Vec<_> = fs::read_dir("tests/xmltest/valid/sa")?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries
+        .iter()
+        .filter(|path| path.extension().is_some_and(|extension| extension == "xml"))
+    {
+        let data = read_file(&mut File::open(path)?)?;
+        let from_str = EventReader::from_str(&data, &config).collect::<Result<Vec<_>, _>>()?;
+        let from_read = EventReader::new(BufReader::with_capacity(3, File::open(path)?), &config)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(from_str, from_read, "Events of {} differ", path.display());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_events_doctype_with_processing_instruction() -> Result<(), Box<dyn Error>> {
+    // The quote inside the processing instruction does not start a literal
+    let input = "<!DOCTYPE doc [<?pi \"?><!ELEMENT doc (#PCDATA)>]><doc>a</doc>";
+    let config = Config::default();
+    let from_str = EventReader::from_str(input, &config).collect::<Result<Vec<_>, _>>()?;
+    let from_read = EventReader::new(BufReader::with_capacity(1, input.as_bytes()), &config)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    assert!(
+        matches!(&from_str[0], Event::Doctype(doc_type) if doc_type.name == Name::new(None, "doc"))
+    );
+    assert_eq!(from_str[2], Event::Text("a".to_string()));
+    assert_eq!(from_str, from_read);
+    Ok(())
+}
+
+#[test]
+fn test_events_invalid_utf8() {
+    let input: &[u8] = b"<doc>a\xFFb</doc>";
+    let config = Config::default();
+    let result = EventReader::new(BufReader::new(input), &config).collect::<Result<Vec<_>, _>>();
+    assert!(matches!(
+        result,
+        Err(nom_xml::error::Error::IoError(error)) if error.kind() == io::ErrorKind::InvalidData
+    ));
+}
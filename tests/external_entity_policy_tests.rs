@@ -0,0 +1,105 @@
+use nom_xml::{
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+    error::Error,
+    io::parse_entire_file,
+    parse::Parse,
+    Document,
+};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+const DIRECTORY: &str = "tests/xmltest/valid/ext-sa";
+
+fn config(policy: ExternalEntityPolicy) -> Config {
+    Config {
+        external_parse_config: ExternalEntityParseConfig {
+            policy,
+            base_directory: Some(DIRECTORY.to_string()),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+fn parse(policy: ExternalEntityPolicy) -> Result<Document, Error> {
+    parse_entire_file(
+        &mut File::open(Path::new(DIRECTORY).join("001.xml"))?,
+        &config(policy),
+    )
+}
+
+#[test]
+fn test_policy_allow_all() -> Result<(), Box<dyn std::error::Error>> {
+    let document = parse(ExternalEntityPolicy::AllowAll)?;
+    assert!(document.to_string().contains("<doc>Data\n</doc>"));
+    Ok(())
+}
+
+#[test]
+fn test_policy_deny_leaves_entities_unexpanded() -> Result<(), Box<dyn std::error::Error>> {
+    let document = parse(ExternalEntityPolicy::Deny)?;
+    assert!(!document.to_string().contains("Data"));
+    Ok(())
+}
+
+#[test]
+fn test_policy_allow_directories() -> Result<(), Box<dyn std::error::Error>> {
+    let document = parse(ExternalEntityPolicy::AllowDirectories(vec![PathBuf::from(
+        "tests/xmltest/valid",
+    )]))?;
+    assert!(document.to_string().contains("<doc>Data\n</doc>"));
+
+    let result = parse(ExternalEntityPolicy::AllowDirectories(vec![PathBuf::from(
+        "tests/xmltest/valid/not-sa",
+    )]));
+    assert!(matches!(
+        result,
+        Err(Error::ExternalEntityNotAllowed { system_id, path })
+            if system_id == "001.ent" && path == Path::new(DIRECTORY).join("001.ent")
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_policy_allow_directories_resolves_parent_components() {
+    let input = "<!DOCTYPE doc [\n<!ENTITY e SYSTEM \"../not-sa/001.ent\">\n]>\n<doc>&e;</doc>";
+    let config = config(ExternalEntityPolicy::AllowDirectories(vec![PathBuf::from(
+        DIRECTORY,
+    )]));
+    let result = Document::parse(input, &config);
+
+    assert!(matches!(
+        result,
+        Err(nom::Err::Failure(Error::ExternalEntityNotAllowed { .. }))
+    ));
+}
+
+#[test]
+fn test_policy_callback() -> Result<(), Box<dyn std::error::Error>> {
+    let requests = Arc::new(Mutex::new(vec![]));
+    let recorded = requests.clone();
+    let policy = |allow: bool| {
+        let recorded = recorded.clone();
+        ExternalEntityPolicy::Callback(Arc::new(move |system_id: &str, path: &Path| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((system_id.to_string(), path.to_path_buf()));
+            allow
+        }))
+    };
+
+    parse(policy(true))?;
+    assert!(matches!(
+        parse(policy(false)),
+        Err(Error::ExternalEntityNotAllowed { .. })
+    ));
+    assert_eq!(
+        requests.lock().unwrap()[0],
+        ("001.ent".to_string(), Path::new(DIRECTORY).join("001.ent"))
+    );
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use nom_xml::{
+    attribute::{Attribute, AttributeValue},
+    config::{Config, FormatConfig},
+    parse::Parse,
+    Document, Name,
+};
+use std::error::Error;
+
+fn format_config(indent: &str, line_width: usize, collapse_empty_elements: bool) -> FormatConfig {
+    FormatConfig {
+        indent: indent.to_string(),
+        line_width,
+        collapse_empty_elements,
+    }
+}
+
+#[test]
+fn test_format_indentation() -> Result<(), Box<dyn Error>> {
+    let input = "<root><a><b>text</b><c/></a><!-- note --><d/></root>";
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 0, false)),
+        "<root>\n  <a>\n    <b>text</b>\n    <c/>\n  </a>\n  <!-- note -->\n  <d/>\n</root>\n"
+    );
+    assert_eq!(
+        document.to_string_pretty(&format_config("\t", 0, false)),
+        "<root>\n\t<a>\n\t\t<b>text</b>\n\t\t<c/>\n\t</a>\n\t<!-- note -->\n\t<d/>\n</root>\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_format_line_width() -> Result<(), Box<dyn Error>> {
+    let input = "<root><a><b>text</b></a><c/></root>";
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 80, false)),
+        "<root><a><b>text</b></a><c/></root>\n"
+    );
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 20, false)),
+        "<root>\n  <a><b>text</b></a>\n  <c/>\n</root>\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_format_wraps_long_start_tags() -> Result<(), Box<dyn Error>> {
+    let input = r#"<root><item first="1" second="2"><x/></item></root>"#;
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 20, false)),
+        "<root>\n  <item\n    first=\"1\"\n    second=\"2\">\n    <x/>\n  </item>\n</root>\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_format_collapse_empty_elements() -> Result<(), Box<dyn Error>> {
+    let input = r#"<root><empty a="1"></empty><full>x</full></root>"#;
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 0, true)),
+        "<root>\n  <empty a=\"1\"/>\n  <full>x</full>\n</root>\n"
+    );
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 0, false)),
+        "<root>\n  <empty a=\"1\"></empty>\n  <full>x</full>\n</root>\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_format_mixed_content_is_not_reindented() -> Result<(), Box<dyn Error>> {
+    let input = "<root><p>Some <b>bold</b>, text</p></root>";
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 0, false)),
+        "<root>\n  <p>Some <b>bold</b>, text</p>\n</root>\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_format_respects_xml_space() -> Result<(), Box<dyn Error>> {
+    let input = r#"<root><pre xml:space="preserve"><a><b/></a></pre><div xml:space="preserve"><p xml:space="default"><a><b/></a></p></div></root>"#;
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 0, false)),
+        "<root>\n  <pre xml:space=\"preserve\"><a><b/></a></pre>\n  <div xml:space=\"preserve\"><p xml:space=\"default\"><a><b/></a></p></div>\n</root>\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_format_prolog() -> Result<(), Box<dyn Error>> {
+    let input = "<?xml version=\"1.0\"?>\n<!DOCTYPE root>\n<root><a/></root>";
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    assert_eq!(
+        document.to_string_pretty(&format_config("  ", 0, false)),
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE root>\n<root>\n  <a/>\n</root>\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_prefixed_attribute_is_not_namespace_declaration() -> Result<(), Box<dyn Error>> {
+    let input = r#"<root xmlns:ns="urn:ns" xml:lang="en">text</root>"#;
+    let (_, document) = Document::parse(input, &Config::default())?;
+
+    if let Document::Element(start_tag, _, _) = &document {
+        let attributes = start_tag.attributes.as_ref().unwrap();
+        assert!(matches!(attributes[0], Attribute::Namespace { .. }));
+        assert_eq!(
+            attributes[1],
+            Attribute::Instance {
+                name: Name::new(Some("xml"), "lang"),
+                value: AttributeValue::Value("en".to_string()),
+            }
+        );
+    } else {
+        panic!("Expected an element");
+    }
+    assert_eq!(document.to_string(), input);
+    Ok(())
+}
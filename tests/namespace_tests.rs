@@ -0,0 +1,257 @@
+use nom_xml::{
+    config::Config,
+    error::Error,
+    namespaces::{ExpandedName, NamespacedElement, XML_NAMESPACE},
+    parse::Parse,
+    Document, Name,
+};
+
+mod common;
+
+use common::parse;
+
+const SOAP: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+const ATOM: &str = "http://www.w3.org/2005/Atom";
+
+fn namespace_error(input: &str) -> Error {
+    match parse(input).resolve_namespaces() {
+        Err(error) => error,
+        Ok(root) => panic!("Expected a namespace error, got {root:?}"),
+    }
+}
+
+fn expanded_names(element: &NamespacedElement) -> Vec<String> {
+    let mut names = vec![element.expanded_name.to_string()];
+    for child in &element.children {
+        names.extend(expanded_names(child));
+    }
+    names
+}
+
+#[test]
+fn test_prefixes_bound_to_the_same_uri() {
+    let document = parse(&format!(
+        "<soap:Envelope xmlns:soap=\"{SOAP}\"><soap:Header/><env:Body xmlns:env=\"{SOAP}\"><s:Fault xmlns:s=\"{SOAP}\">x</s:Fault></env:Body></soap:Envelope>"
+    ));
+    let envelope = document.resolve_namespaces().unwrap().unwrap();
+    assert_eq!(
+        expanded_names(&envelope),
+        [
+            format!("{{{SOAP}}}Envelope"),
+            format!("{{{SOAP}}}Header"),
+            format!("{{{SOAP}}}Body"),
+            format!("{{{SOAP}}}Fault"),
+        ]
+    );
+    let body = &envelope.children[1];
+    assert_eq!(body.name, Name::new(Some("env"), "Body"));
+    assert_eq!(body.namespace_uri(Some("soap")), Some(SOAP));
+    assert_eq!(body.namespace_uri(Some("s")), None);
+    assert_eq!(
+        body.in_scope_namespaces(),
+        [
+            (Some("env"), SOAP),
+            (Some("soap"), SOAP),
+            (Some("xml"), XML_NAMESPACE)
+        ]
+    );
+    let fault = envelope.descendants_by_name(Some(SOAP), "Fault");
+    assert_eq!(fault.len(), 1);
+    assert_eq!(
+        fault[0].element.to_string(),
+        format!("<s:Fault xmlns:s=\"{SOAP}\">x</s:Fault>")
+    );
+}
+
+#[test]
+fn test_default_namespace_scoping() {
+    let document = parse(&format!(
+        "<feed xmlns=\"{ATOM}\"><entry><title xmlns=\"\">a</title><x:title xmlns:x=\"{ATOM}\">b</x:title></entry><id/></feed>"
+    ));
+    let feed = document.resolve_namespaces().unwrap().unwrap();
+    assert_eq!(
+        expanded_names(&feed),
+        [
+            format!("{{{ATOM}}}feed"),
+            format!("{{{ATOM}}}entry"),
+            "title".to_string(),
+            format!("{{{ATOM}}}title"),
+            format!("{{{ATOM}}}id"),
+        ]
+    );
+    assert_eq!(feed.children[0].children[0].namespace_uri(None), None);
+    assert_eq!(feed.descendants_by_name(Some(ATOM), "title").len(), 1);
+    assert_eq!(feed.descendants_by_name(None, "title").len(), 1);
+}
+
+#[test]
+fn test_attribute_namespaces() {
+    let document = parse(&format!(
+        "<a:doc xmlns=\"urn:default\" xmlns:a=\"{ATOM}\" a:x=\"1\" y=\"2\" xml:lang=\"en\"><e a:x=\"3\" b:y=\"4\" xmlns:b=\"urn:b\"/></a:doc>"
+    ));
+    let doc = document.resolve_namespaces().unwrap().unwrap();
+    let attributes: Vec<_> = doc
+        .attributes
+        .iter()
+        .map(|attribute| (attribute.expanded_name.clone(), attribute.value.as_str()))
+        .collect();
+    assert_eq!(
+        attributes,
+        [
+            (ExpandedName::new(Some(ATOM), "x"), "1"),
+            (ExpandedName::new(None, "y"), "2"),
+            (ExpandedName::new(Some(XML_NAMESPACE), "lang"), "en"),
+        ]
+    );
+    // Attributes of empty-element tags are resolved the same way
+    let e = &doc.children[0];
+    assert_eq!(e.expanded_name, ExpandedName::new(Some("urn:default"), "e"));
+    assert_eq!(e.attribute(Some(ATOM), "x"), Some("3"));
+    assert_eq!(e.attribute(Some("urn:b"), "y"), Some("4"));
+    assert_eq!(e.attribute(None, "y"), None);
+}
+
+#[test]
+fn test_undeclared_prefixes() {
+    assert!(matches!(
+        namespace_error("<a:doc/>"),
+        Error::UndeclaredNamespacePrefix(name) if name == Name::new(Some("a"), "doc")
+    ));
+    assert!(matches!(
+        namespace_error("<doc a:x=\"1\"/>"),
+        Error::UndeclaredNamespacePrefix(name) if name == Name::new(Some("a"), "x")
+    ));
+    // A declaration is in scope in the content of its element only
+    assert!(matches!(
+        namespace_error("<doc><e xmlns:a=\"urn:a\"/><a:e/></doc>"),
+        Error::UndeclaredNamespacePrefix(name) if name == Name::new(Some("a"), "e")
+    ));
+    assert_eq!(
+        namespace_error("<xmlns:doc/>").to_string(),
+        "Prefix `xmlns` of `xmlns:doc` is not bound to a namespace"
+    );
+}
+
+#[test]
+fn test_reserved_bindings() {
+    let document = parse(&format!("<doc xmlns:xml=\"{XML_NAMESPACE}\"/>"));
+    assert!(document.resolve_namespaces().is_ok());
+
+    for input in [
+        "<doc xmlns:xml=\"urn:other\"/>".to_string(),
+        "<doc xmlns:xmlns=\"urn:other\"/>".to_string(),
+        format!("<doc xmlns:x=\"{XML_NAMESPACE}\"/>"),
+        format!("<doc xmlns=\"{XML_NAMESPACE}\"/>"),
+        "<doc xmlns:x=\"http://www.w3.org/2000/xmlns/\"/>".to_string(),
+    ] {
+        assert!(
+            matches!(
+                namespace_error(&input),
+                Error::ReservedNamespaceBinding { .. }
+            ),
+            "{input}"
+        );
+    }
+    assert_eq!(
+        namespace_error("<doc xmlns:xml=\"urn:other\"/>").to_string(),
+        "Namespace declaration `xmlns:xml=\"urn:other\"` violates the reserved bindings of `xml` and `xmlns`"
+    );
+    assert!(matches!(
+        namespace_error("<doc xmlns:a=\"urn:a\"><e xmlns:a=\"\"/></doc>"),
+        Error::EmptyNamespaceUri { prefix } if prefix == "a"
+    ));
+}
+
+#[test]
+fn test_duplicate_expanded_attributes() {
+    let error = namespace_error(&format!(
+        "<doc xmlns:a=\"{ATOM}\" xmlns:b=\"{ATOM}\" a:x=\"1\" b:x=\"2\"></doc>"
+    ));
+    assert_eq!(
+        error.to_string(),
+        format!("Attribute `{{{ATOM}}}x` is specified more than once in tag `doc`")
+    );
+    // An unprefixed attribute is in no namespace, even when there is a default namespace
+    let document = parse(&format!(
+        "<doc xmlns=\"{ATOM}\" xmlns:a=\"{ATOM}\" x=\"1\" a:x=\"2\"/>"
+    ));
+    assert!(document.resolve_namespaces().is_ok());
+}
+
+#[test]
+fn test_resolve_namespaces_while_parsing() {
+    let config = Config {
+        resolve_namespaces: true,
+        ..Default::default()
+    };
+    assert!(Document::parse("<a:doc xmlns:a=\"urn:a\"><a:e/></a:doc>", &config).is_ok());
+    assert!(matches!(
+        Document::parse("<doc><a:e/></doc>", &config),
+        Err(nom::Err::Failure(Error::UndeclaredNamespacePrefix(_)))
+    ));
+    assert!(Document::parse("<doc><a:e/></doc>", &Config::default()).is_ok());
+}
+
+const FEED: &str = "<?xml version=\"1.0\"?>
+<!DOCTYPE feed [<!ENTITY copy \"<entry>not an entry</entry>\">]>
+<feed xmlns=\"http://www.w3.org/2005/Atom\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">
+  <!-- <entry>commented out</entry> -->
+  <title>Feed</title>
+  <entry><title>First</title><dc:creator>A</dc:creator></entry>
+  <ext:entry xmlns:ext=\"urn:extension\">Not Atom</ext:entry>
+  <a:entry xmlns:a=\"http://www.w3.org/2005/Atom\" a:id=\"2\"><a:title>Second</a:title></a:entry>
+  <x:container xmlns:x=\"http://www.w3.org/2005/Atom\"><x:entry>Third</x:entry></x:container>
+  <entry/>
+</feed>";
+
+#[test]
+fn test_parse_elements_by_expanded_name() {
+    let atom_entry = ExpandedName::new(Some(ATOM), "entry");
+    let (_, entries) = Document::parse_elements_by_expanded_name(FEED, &atom_entry, &None).unwrap();
+    let entries: Vec<String> = entries.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        entries,
+        [
+            format!("<entry xmlns=\"{ATOM}\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"><title>First</title><dc:creator>A</dc:creator></entry>"),
+            format!("<a:entry xmlns:a=\"{ATOM}\" a:id=\"2\" xmlns=\"{ATOM}\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"><a:title>Second</a:title></a:entry>"),
+            format!("<x:entry xmlns=\"{ATOM}\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:x=\"{ATOM}\">Third</x:entry>"),
+            format!("<entry xmlns=\"{ATOM}\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\"/>"),
+        ]
+    );
+
+    // The extracted elements keep their namespaces
+    let (_, entries) =
+        Document::parse_elements_by_tag_name(FEED, "{http://www.w3.org/2005/Atom}entry", &None)
+            .unwrap();
+    for entry in &entries {
+        let entry = entry.resolve_namespaces().unwrap().unwrap();
+        assert_eq!(entry.expanded_name, atom_entry);
+    }
+
+    let (_, extension) =
+        Document::parse_element_by_tag_name(FEED, "{urn:extension}entry", &None).unwrap();
+    assert_eq!(
+        extension.to_string(),
+        "<ext:entry xmlns:ext=\"urn:extension\" xmlns=\"http://www.w3.org/2005/Atom\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">Not Atom</ext:entry>"
+    );
+    assert!(Document::parse_element_by_tag_name(FEED, "{urn:other}entry", &None).is_err());
+
+    // A malformed start tag does not open a scope for its end tag to close
+    let entry = ExpandedName::new(Some("urn:a"), "entry");
+    let input =
+        "<root xmlns:a=\"urn:a\"><x xmlns:a=\"urn:b\"><y><b c=></b></y><a:entry/></x></root>";
+    assert!(Document::parse_elements_by_expanded_name(input, &entry, &None).is_err());
+}
+
+#[test]
+fn test_parse_elements_by_tag_name_with_prefixes() {
+    // Local parts select elements with any prefix, qualified names only the ones written with that prefix
+    let (_, entries) = Document::parse_elements_by_tag_name(FEED, "entry", &None).unwrap();
+    assert_eq!(entries.len(), 5);
+    let (_, entry) = Document::parse_element_by_tag_name(FEED, "a:entry", &None).unwrap();
+    assert!(entry.to_string().starts_with("<a:entry xmlns:a="));
+    let (_, creator) = Document::parse_element_by_tag_name(FEED, "creator", &None).unwrap();
+    assert_eq!(creator.to_string(), "<dc:creator>A</dc:creator>");
+    let (_, empty) = Document::parse_element_by_tag_name("<f:a><f:b/></f:a>", "b", &None).unwrap();
+    assert_eq!(empty.to_string(), "<f:b/>");
+}
@@ -0,0 +1,136 @@
+use nom_xml::{
+    config::Config,
+    io::parse_entire_file,
+    processing_instruction::ProcessingInstruction,
+    prolog::doctype::DocType,
+    sax::{self, Handler},
+    tag::Tag,
+    Document, Name,
+};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::BufReader,
+};
+
+/// Records every callback as a line of text
+#[derive(Default)]
+struct Recorder {
+    calls: Vec<String>,
+}
+
+impl Handler for Recorder {
+    fn start_element(&mut self, tag: &Tag) {
+        self.calls.push(format!("start {}", tag.name));
+    }
+
+    fn end_element(&mut self, name: &Name) {
+        self.calls.push(format!("end {name}"));
+    }
+
+    fn characters(&mut self, text: &str) {
+        self.calls.push(format!("characters {text}"));
+    }
+
+    fn comment(&mut self, comment: &str) {
+        self.calls.push(format!("comment {comment}"));
+    }
+
+    fn processing_instruction(&mut self, processing_instruction: &ProcessingInstruction) {
+        self.calls
+            .push(format!("pi {}", processing_instruction.target));
+    }
+
+    fn doctype(&mut self, doc_type: &DocType) {
+        self.calls.push(format!("doctype {}", doc_type.name));
+    }
+}
+
+#[test]
+fn test_handler_callbacks() -> Result<(), Box<dyn Error>> {
+    let input = "<!DOCTYPE doc [\n<!ENTITY e \"entity\">\n]>\n<doc><!--c--><?target?><a>&e;</a><b/><![CDATA[cdata]]></doc>";
+    let mut recorder = Recorder::default();
+    sax::parse_str(input, &Config::default(), &mut recorder)?;
+
+    assert_eq!(
+        recorder.calls,
+        [
+            "doctype doc",
+            "start doc",
+            "comment c",
+            "pi target",
+            "start a",
+            "characters entity",
+            "end a",
+            "start b",
+            "end b",
+            "characters cdata",
+            "end doc",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_handler_stops_at_error() {
+    let mut recorder = Recorder::default();
+    let result = sax::parse_str("<doc><a></b></doc>", &Config::default(), &mut recorder);
+
+    assert!(result.is_err());
+    assert_eq!(recorder.calls, ["start doc", "start a"]);
+}
+
+/// Counts elements without keeping any of the document
+#[derive(Default)]
+struct ElementCounter {
+    depth: usize,
+    max_depth: usize,
+    elements: usize,
+}
+
+impl Handler for ElementCounter {
+    fn start_element(&mut self, _tag: &Tag) {
+        self.elements += 1;
+        self.depth += 1;
+        self.max_depth = self.max_depth.max(self.depth);
+    }
+
+    fn end_element(&mut self, _name: &Name) {
+        self.depth -= 1;
+    }
+}
+
+fn count_elements(document: &Document) -> usize {
+    match document {
+        Document::Element(_, content, _) => 1 + count_elements(content),
+        Document::EmptyTag(_) => 1,
+        Document::Nested(documents) => documents.iter().map(count_elements).sum(),
+        _ => 0,
+    }
+}
+
+#[test]
+fn test_handler_counts_match_document() -> Result<(), Box<dyn Error>> {
+    let config = Config::default();
+    let mut entries: Vec<_> = fs::read_dir("tests/xmltest/valid/sa")?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries
+        .iter()
+        .filter(|path| path.extension().is_some_and(|extension| extension == "xml"))
+    {
+        let mut counter = ElementCounter::default();
+        sax::parse(BufReader::new(File::open(path)?), &config, &mut counter)?;
+        let document = parse_entire_file(&mut File::open(path)?, &config)?;
+
+        assert_eq!(counter.depth, 0);
+        assert_eq!(
+            counter.elements,
+            count_elements(&document),
+            "Element count of {} differs",
+            path.display()
+        );
+    }
+    Ok(())
+}
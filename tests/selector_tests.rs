@@ -0,0 +1,172 @@
+use nom_xml::{error::Error, selector::Selector, Document};
+
+mod common;
+
+use common::{catalog, parse};
+
+fn select(document: &Document, selector: &str) -> Vec<String> {
+    document
+        .select(selector)
+        .unwrap()
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn titles(document: &Document, selector: &str) -> Vec<String> {
+    document
+        .select(selector)
+        .unwrap()
+        .map(|element| match element {
+            Document::Element(_, content, _) => content.to_string(),
+            _ => panic!("`{selector}` selected {element:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_combinators() {
+    let document = catalog();
+    assert_eq!(
+        titles(&document, "catalog > book[isbn^='978'] title"),
+        ["Leviathan Wakes", "Abaddon's Gate", "The Expanse"]
+    );
+    assert_eq!(
+        titles(&document, "catalog > book > title"),
+        ["Leviathan Wakes", "Caliban's War", "Abaddon's Gate"]
+    );
+    assert_eq!(
+        titles(&document, "catalog book title"),
+        [
+            "Leviathan Wakes",
+            "Caliban's War",
+            "Abaddon's Gate",
+            "The Expanse",
+            "Cibola Burn"
+        ]
+    );
+    assert_eq!(titles(&document, "shelf>book>title"), ["Cibola Burn"]);
+    // Descendant combinators backtrack over the ancestors
+    assert_eq!(titles(&document, "book series > title"), ["The Expanse"]);
+    assert_eq!(titles(&document, "catalog > title").len(), 0);
+    assert_eq!(select(&document, "catalog").len(), 1);
+    assert_eq!(select(&document, "*").len(), 15);
+    assert_eq!(select(&document, "catalog > *").len(), 5);
+}
+
+#[test]
+fn test_attribute_selectors() {
+    let document = catalog();
+    assert_eq!(select(&document, "[isbn]").len(), 4);
+    assert_eq!(
+        titles(&document, "book[isbn='0-316-12906-0'] > title"),
+        ["Caliban's War"]
+    );
+    assert_eq!(
+        titles(&document, "[lang^=en-] > title"),
+        ["Leviathan Wakes", "Caliban's War"]
+    );
+    assert_eq!(
+        titles(&document, "book[isbn$=\"77\"] > title"),
+        ["Abaddon's Gate"]
+    );
+    assert_eq!(
+        titles(&document, "[issn*='34-5'] title"),
+        ["Expanse Monthly"]
+    );
+    assert_eq!(
+        titles(&document, "book[isbn^='978'][lang] > title"),
+        ["Leviathan Wakes", "Cibola Burn"]
+    );
+    assert_eq!(
+        titles(&document, "book[ lang = en ] > title"),
+        ["Cibola Burn"]
+    );
+    // Empty values never match with the substring matchers
+    assert!(select(&document, "book[isbn^='']").is_empty());
+    assert!(select(&document, "book[isbn='978']").is_empty());
+}
+
+#[test]
+fn test_nth_child() {
+    let document = parse(
+        "<table><row>1</row><row>2</row><row>3</row><row>4</row><row>5</row><footer/></table>",
+    );
+    assert_eq!(titles(&document, "row:nth-child(odd)"), ["1", "3", "5"]);
+    assert_eq!(titles(&document, "row:nth-child(even)"), ["2", "4"]);
+    assert_eq!(titles(&document, "row:nth-child(2)"), ["2"]);
+    assert_eq!(titles(&document, "row:nth-child(3n)"), ["3"]);
+    assert_eq!(titles(&document, "row:nth-child(2n+3)"), ["3", "5"]);
+    assert_eq!(titles(&document, "row:nth-child(-n + 2)"), ["1", "2"]);
+    assert_eq!(titles(&document, "row:nth-child(n+4)"), ["4", "5"]);
+    assert_eq!(titles(&document, "row:first-child"), ["1"]);
+    assert!(titles(&document, "row:last-child").is_empty());
+    // The root element is the only child of the document
+    assert_eq!(select(&document, ":last-child").len(), 2);
+    assert_eq!(select(&document, "table:first-child:last-child").len(), 1);
+}
+
+#[test]
+fn test_prefixed_names() {
+    let document = parse("<atom:feed xmlns:atom=\"http://www.w3.org/2005/Atom\"><atom:entry xml:lang=\"en\">a</atom:entry><entry>b</entry><dc:entry>c</dc:entry></atom:feed>");
+    assert_eq!(titles(&document, "feed > entry"), ["a", "b", "c"]);
+    assert_eq!(titles(&document, "atom|feed > atom|entry"), ["a"]);
+    assert_eq!(titles(&document, "|entry"), ["b"]);
+    assert_eq!(titles(&document, "*|entry:nth-child(3)"), ["c"]);
+    assert_eq!(titles(&document, "[xml|lang=en]"), ["a"]);
+    // Namespace declarations are not attributes
+    assert!(select(&document, "[xmlns|atom]").is_empty());
+}
+
+#[test]
+fn test_selector_lists() {
+    let document = catalog();
+    // Elements are selected once, in document order
+    assert_eq!(
+        titles(
+            &document,
+            "magazine > title, book:first-child > title, shelf title"
+        ),
+        ["Leviathan Wakes", "Expanse Monthly", "Cibola Burn"]
+    );
+    let selector: Selector = "book > title".parse().unwrap();
+    assert_eq!(selector.to_string(), "book > title");
+    assert_eq!(selector.select(&document).len(), 4);
+    let other = parse("<book><title/></book>");
+    assert_eq!(selector.select(&other).len(), 1);
+}
+
+#[test]
+fn test_invalid_selectors() {
+    let document = catalog();
+    for selector in [
+        "",
+        "book >",
+        "> book",
+        "book,",
+        "book[isbn",
+        "book[isbn~=978]",
+        "book:hover",
+        "book:nth-child(x)",
+        "book:nth-child",
+        "book.title",
+        "#isbn",
+    ] {
+        assert!(
+            matches!(
+                document.select(selector),
+                Err(Error::InvalidSelector { .. })
+            ),
+            "{selector}"
+        );
+    }
+    assert_eq!(
+        Selector::parse("book:hover").unwrap_err().to_string(),
+        "Selector `book:hover`: Unsupported pseudo-class `:hover`"
+    );
+    assert_eq!(
+        Selector::parse("catalog book.title")
+            .unwrap_err()
+            .to_string(),
+        "Selector `catalog book.title`: Unexpected `.title` at offset 12"
+    );
+}
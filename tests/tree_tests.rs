@@ -0,0 +1,181 @@
+use nom_xml::{
+    namespaces::XML_NAMESPACE,
+    tree::{Node, NodeId, Tree},
+};
+
+mod common;
+
+use common::namespaced_catalog;
+
+fn names(tree: &Tree, ids: impl Iterator<Item = NodeId>) -> Vec<String> {
+    ids.map(|id| match tree.node(id) {
+        Node::Root(_) => "/".to_string(),
+        Node::Text(text) => format!("'{text}'"),
+        Node::Comment(_) => "comment()".to_string(),
+        node => node.name().unwrap().to_string(),
+    })
+    .collect()
+}
+
+fn find(tree: &Tree, path: &str) -> NodeId {
+    tree.node_ids()
+        .find(|&id| tree.path(id) == path)
+        .unwrap_or_else(|| panic!("no node at {path}"))
+}
+
+#[test]
+fn test_child_and_sibling_links() {
+    let document = namespaced_catalog();
+    let tree = document.tree();
+    let root = tree.root();
+    assert_eq!(names(&tree, tree.children(root)), ["comment()", "catalog"]);
+    let catalog = tree.last_child(root).unwrap();
+    assert!(
+        matches!(tree.node(catalog), Node::Element(element) if element == document.root_element().unwrap())
+    );
+    assert_eq!(
+        names(&tree, tree.children(catalog)),
+        ["book", "book", "sort", "shelf"]
+    );
+
+    let book = tree.first_child(catalog).unwrap();
+    assert_eq!(
+        names(&tree, tree.children(book)),
+        ["title", "'first'", "comment()", "'second'"]
+    );
+    let second_book = tree.next_sibling(book).unwrap();
+    assert_eq!(tree.previous_sibling(second_book), Some(book));
+    assert_eq!(tree.previous_sibling(book), None);
+    assert_eq!(
+        names(&tree, tree.following_siblings(book)),
+        ["book", "sort", "shelf"]
+    );
+    let shelf = tree.last_child(catalog).unwrap();
+    assert_eq!(
+        names(&tree, tree.preceding_siblings(shelf)),
+        ["sort", "book", "book"]
+    );
+    assert_eq!(tree.next_sibling(shelf), None);
+    assert_eq!(tree.first_child(tree.first_child(root).unwrap()), None);
+}
+
+#[test]
+fn test_upward_navigation() {
+    let document = namespaced_catalog();
+    let tree = document.tree();
+    let title = find(&tree, "/catalog/shelf/book/title");
+    assert_eq!(tree.node(title).string_value(), "Cibola Burn");
+    assert_eq!(
+        names(&tree, tree.ancestors(title)),
+        ["book", "shelf", "catalog", "/"]
+    );
+    assert_eq!(tree.parent(tree.root()), None);
+
+    // Attributes have their element as parent, but are neither children nor siblings
+    let book = find(&tree, "/catalog/book[1]");
+    assert_eq!(names(&tree, tree.attributes(book)), ["isbn", "dc:year"]);
+    let year = tree.attributes(book).nth(1).unwrap();
+    assert_eq!(tree.node(year).string_value(), "2011");
+    assert_eq!(tree.parent(year), Some(book));
+    assert_eq!(tree.next_sibling(year), None);
+    assert_eq!(tree.children(year).count(), 0);
+    assert_eq!(tree.descendants(year).count(), 0);
+    assert!(tree.children(book).all(|child| child != year));
+    // Namespace declarations are not attributes
+    assert_eq!(tree.attributes(tree.parent(book).unwrap()).count(), 0);
+}
+
+#[test]
+fn test_descendants_in_document_order() {
+    let document = namespaced_catalog();
+    let tree = document.tree();
+    let catalog = find(&tree, "/catalog");
+    assert_eq!(
+        names(&tree, tree.descendants(catalog)),
+        [
+            "book",
+            "title",
+            "'Leviathan Wakes'",
+            "'first'",
+            "comment()",
+            "'second'",
+            "book",
+            "title",
+            "'Caliban's War'",
+            "sort",
+            "shelf",
+            "book",
+            "title",
+            "'Cibola Burn'"
+        ]
+    );
+    let second_book = find(&tree, "/catalog/book[2]");
+    assert_eq!(
+        names(&tree, tree.descendants(second_book)),
+        ["title", "'Caliban's War'"]
+    );
+    assert_eq!(tree.descendants(tree.root()).count(), 16);
+    let ids: Vec<NodeId> = tree.node_ids().collect();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(ids[0].index(), 0);
+}
+
+#[test]
+fn test_paths() {
+    let document = namespaced_catalog();
+    let tree = document.tree();
+    let paths: Vec<String> = tree.node_ids().map(|id| tree.path(id)).collect();
+    assert_eq!(
+        paths,
+        [
+            "/",
+            "/comment()",
+            "/catalog",
+            "/catalog/book[1]",
+            "/catalog/book[1]/@isbn",
+            "/catalog/book[1]/@dc:year",
+            "/catalog/book[1]/title",
+            "/catalog/book[1]/title/text()",
+            "/catalog/book[1]/text()[1]",
+            "/catalog/book[1]/comment()",
+            "/catalog/book[1]/text()[2]",
+            "/catalog/book[2]",
+            "/catalog/book[2]/@isbn",
+            "/catalog/book[2]/title",
+            "/catalog/book[2]/title/text()",
+            "/catalog/processing-instruction('sort')",
+            "/catalog/shelf",
+            "/catalog/shelf/book",
+            "/catalog/shelf/book/title",
+            "/catalog/shelf/book/title/text()",
+        ]
+    );
+}
+
+#[test]
+fn test_namespace_scope() {
+    let document = namespaced_catalog();
+    let tree = document.tree();
+    let title = find(&tree, "/catalog/book[1]/title");
+    assert_eq!(
+        tree.namespace_uri(title, None).as_deref(),
+        Some("urn:catalog")
+    );
+    assert_eq!(
+        tree.namespace_uri(title, Some("dc")).as_deref(),
+        Some("urn:dc")
+    );
+    assert_eq!(
+        tree.namespace_uri(title, Some("xml")).as_deref(),
+        Some(XML_NAMESPACE)
+    );
+    assert_eq!(tree.namespace_uri(title, Some("other")), None);
+    // The default namespace is undeclared in the shelf
+    let shelved = find(&tree, "/catalog/shelf/book/title/text()");
+    assert_eq!(tree.namespace_uri(shelved, None), None);
+    assert_eq!(
+        tree.namespace_uri(shelved, Some("dc")).as_deref(),
+        Some("urn:dc")
+    );
+    assert_eq!(tree.namespace_uri(tree.root(), None), None);
+}
@@ -1,6 +1,6 @@
 use nom_xml::{
     attribute::{AttType, Attribute, AttributeValue, DefaultDecl, TokenizedType},
-    config::{Config, ExternalEntityParseConfig},
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
     io::parse_entire_file,
     prolog::{
         content_particle::ContentParticle,
@@ -36,10 +36,11 @@ fn test_valid_ext_sa_001() -> Result<(), Box<dyn Error>> {
         "001",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
     assert_eq!(
@@ -94,10 +95,11 @@ fn test_valid_ext_sa_002() -> Result<(), Box<dyn Error>> {
         "002",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
     assert_eq!(
@@ -151,10 +153,11 @@ fn test_valid_ext_sa_003() -> Result<(), Box<dyn Error>> {
         "003",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
     assert_eq!(
@@ -208,10 +211,11 @@ fn test_valid_ext_sa_004() -> Result<(), Box<dyn Error>> {
         "004",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -267,10 +271,11 @@ fn test_valid_ext_sa_005() -> Result<(), Box<dyn Error>> {
         "005",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
     assert_eq!(
@@ -353,10 +358,11 @@ fn test_valid_ext_sa_006() -> Result<(), Box<dyn Error>> {
         "006",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
     assert_eq!(
@@ -430,10 +436,11 @@ fn test_valid_ext_sa_007() -> Result<(), Box<dyn Error>> {
         "007",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -493,10 +500,11 @@ fn test_valid_ext_sa_008() -> Result<(), Box<dyn Error>> {
         "008",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -557,10 +565,11 @@ fn test_valid_ext_sa_009() -> Result<(), Box<dyn Error>> {
         "009",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -617,10 +626,11 @@ fn test_valid_ext_sa_010() -> Result<(), Box<dyn Error>> {
         "010",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -677,10 +687,11 @@ fn test_valid_ext_sa_011() -> Result<(), Box<dyn Error>> {
         "011",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -742,10 +753,11 @@ fn test_valid_ext_sa_012() -> Result<(), Box<dyn Error>> {
         "012",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -833,10 +845,11 @@ fn test_valid_ext_sa_013() -> Result<(), Box<dyn Error>> {
         "013",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -934,10 +947,11 @@ fn test_valid_ext_sa_014() -> Result<(), Box<dyn Error>> {
         "014",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/ext-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
     assert_eq!(
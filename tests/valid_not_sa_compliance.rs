@@ -1,6 +1,6 @@
 use nom_xml::{
     attribute::{AttType, Attribute, AttributeValue, DefaultDecl},
-    config::{Config, ExternalEntityParseConfig},
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
     io::parse_entire_file,
     prolog::{
         declaration_content::DeclarationContent,
@@ -33,10 +33,11 @@ fn test_valid_not_sa_001() -> Result<(), Box<dyn Error>> {
         "001",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/not-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -79,10 +80,11 @@ fn test_valid_not_sa_002() -> Result<(), Box<dyn Error>> {
         "002",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/not-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -126,10 +128,11 @@ fn test_valid_not_sa_003() -> Result<(), Box<dyn Error>> {
         "003",
         &Config {
             external_parse_config: ExternalEntityParseConfig {
-                allow_ext_parse: true,
-                ignore_ext_parse_warning: true,
+                policy: ExternalEntityPolicy::AllowAll,
                 base_directory: Some("tests/xmltest/valid/not-sa".into()),
+                ..Default::default()
             },
+            ..Default::default()
         },
     )?;
 
@@ -0,0 +1,358 @@
+use nom_xml::{
+    attribute::{AttType, TokenizedType},
+    config::{Config, ExternalEntityParseConfig, ExternalEntityPolicy},
+    error::{Error, ValidationError, ValidationErrorKind},
+    io::parse_entire_file,
+    parse::Parse,
+    prolog::declaration_content::DeclarationContent,
+    Document, Name,
+};
+use std::fs::{self, File};
+
+fn validation_errors(input: &str) -> Vec<ValidationError> {
+    let (_, document) = Document::parse(input, &Config::default()).unwrap();
+    match document.validate() {
+        Err(Error::Validation(errors)) => errors,
+        result => panic!("Expected validation errors, got {result:?}"),
+    }
+}
+
+fn is_valid(dtd: &str, element: &str) -> bool {
+    let input = format!("<!DOCTYPE doc [\n{dtd}\n]>\n{element}");
+    let (_, document) = Document::parse(&input, &Config::default()).unwrap();
+    document.validate().is_ok()
+}
+
+#[test]
+fn test_valid_sa_documents_are_valid() -> Result<(), Box<dyn std::error::Error>> {
+    for directory in ["valid/sa", "valid/ext-sa"] {
+        let config = Config {
+            external_parse_config: ExternalEntityParseConfig {
+                policy: ExternalEntityPolicy::AllowAll,
+                base_directory: Some(format!("tests/xmltest/{directory}")),
+                ..Default::default()
+            },
+            validate: true,
+            ..Default::default()
+        };
+        for entry in fs::read_dir(format!("tests/xmltest/{directory}"))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "xml") {
+                let result = parse_entire_file(&mut File::open(&path)?, &config);
+                assert!(result.is_ok(), "{}: {result:?}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_invalid_documents_are_rejected() -> Result<(), Box<dyn std::error::Error>> {
+    for directory in ["invalid", "invalid/not-sa"] {
+        let config = Config {
+            external_parse_config: ExternalEntityParseConfig {
+                policy: ExternalEntityPolicy::AllowAll,
+                base_directory: Some(format!("tests/xmltest/{directory}")),
+                ..Default::default()
+            },
+            validate: true,
+            ..Default::default()
+        };
+        for entry in fs::read_dir(format!("tests/xmltest/{directory}"))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "xml") {
+                let result = parse_entire_file(&mut File::open(&path)?, &config);
+                assert!(
+                    matches!(result, Err(Error::Validation(_))),
+                    "{}: {result:?}",
+                    path.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_children_content_models() {
+    let dtd = "<!ELEMENT doc ((a | b)*, c, (d, e)?)+>\n<!ELEMENT a EMPTY>\n<!ELEMENT b EMPTY>\n<!ELEMENT c EMPTY>\n<!ELEMENT d EMPTY>\n<!ELEMENT e EMPTY>";
+    assert!(is_valid(dtd, "<doc><c/></doc>"));
+    assert!(is_valid(dtd, "<doc><a/><b/><a/><c/><d/><e/><c/></doc>"));
+    assert!(is_valid(
+        dtd,
+        "<doc>\n  <b/>\n  <c/>\n  <!-- comment -->\n</doc>"
+    ));
+    assert!(!is_valid(dtd, "<doc></doc>"));
+    assert!(!is_valid(dtd, "<doc><a/></doc>"));
+    assert!(!is_valid(dtd, "<doc><c/><d/></doc>"));
+    assert!(!is_valid(dtd, "<doc><c/><e/></doc>"));
+    assert!(!is_valid(dtd, "<doc><c/>text</doc>"));
+    assert!(!is_valid(dtd, "<doc><c/><![CDATA[ ]]></doc>"));
+}
+
+#[test]
+fn test_empty_any_and_mixed_content() {
+    let dtd = "<!ELEMENT doc ANY>\n<!ELEMENT e EMPTY>\n<!ELEMENT p (#PCDATA)>\n<!ELEMENT m (#PCDATA | e | p)*>";
+    assert!(is_valid(dtd, "<doc>text<e/><p>x</p><m/></doc>"));
+    assert!(is_valid(dtd, "<doc><e></e><m>a<e/>b<p>c</p></m></doc>"));
+    assert!(!is_valid(dtd, "<doc><e><!-- comment --></e></doc>"));
+    assert!(!is_valid(dtd, "<doc><p>a<e/></p></doc>"));
+    assert!(!is_valid(dtd, "<doc><m><doc/></m></doc>"));
+}
+
+#[test]
+fn test_validation_errors_have_element_paths() {
+    let input = "<!DOCTYPE list [\n<!ELEMENT list (item+)>\n<!ELEMENT item (name, price?)>\n<!ELEMENT name (#PCDATA)>\n<!ELEMENT price (#PCDATA)>\n]>\n<list><item><name>a</name></item><item><price>1</price></item><item><name>c</name><size/></item></list>";
+    let errors = validation_errors(input);
+    assert_eq!(errors.len(), 3);
+    assert!(matches!(
+        &errors[0],
+        ValidationError {
+            path,
+            kind: ValidationErrorKind::InvalidContent { element, model: DeclarationContent::Children(_), content },
+        } if path == "/list/item[2]" && *element == Name::new(None, "item") && content == "(price)"
+    ));
+    assert!(matches!(
+        &errors[1],
+        ValidationError {
+            path,
+            kind: ValidationErrorKind::InvalidContent { content, .. },
+        } if path == "/list/item[3]" && content == "(name, size)"
+    ));
+    assert_eq!(
+        errors[2],
+        ValidationError {
+            path: "/list/item[3]/size".to_string(),
+            kind: ValidationErrorKind::UndeclaredElement(Name::new(None, "size")),
+        }
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "/list/item[2]: Content (price) of element `item` does not match (name, price?)"
+    );
+}
+
+#[test]
+fn test_root_element_and_doctype() {
+    assert_eq!(
+        validation_errors("<!DOCTYPE doc [<!ELEMENT other EMPTY>]><other/>"),
+        [ValidationError {
+            path: "/other".to_string(),
+            kind: ValidationErrorKind::RootElementMismatch {
+                expected: Name::new(None, "doc"),
+                found: Name::new(None, "other"),
+            },
+        }]
+    );
+    assert_eq!(
+        validation_errors("<doc/>"),
+        [ValidationError {
+            path: "/".to_string(),
+            kind: ValidationErrorKind::MissingDocType,
+        }]
+    );
+}
+
+#[test]
+fn test_validating_config() {
+    let input = "<!DOCTYPE doc [<!ELEMENT doc EMPTY>]><doc>text</doc>";
+    assert!(Document::parse(input, &Config::default()).is_ok());
+
+    let config = Config {
+        validate: true,
+        ..Default::default()
+    };
+    match Document::parse(input, &config) {
+        Err(nom::Err::Failure(Error::Validation(errors))) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(
+                errors[0].to_string(),
+                "/doc: Content (#PCDATA) of element `doc` does not match EMPTY"
+            );
+        }
+        result => panic!("Expected validation errors, got {result:?}"),
+    }
+}
+
+const ATTRIBUTES_DTD: &str = "<!ELEMENT doc EMPTY>\n<!ATTLIST doc\n    id ID #REQUIRED\n    version CDATA #FIXED \"1.0\"\n    size (small | medium | large) \"medium\"\n    tokens NMTOKENS #IMPLIED\n    token NMTOKEN #IMPLIED>";
+
+#[test]
+fn test_attribute_values() {
+    assert!(is_valid(ATTRIBUTES_DTD, "<doc id=\"d1\"/>"));
+    assert!(is_valid(
+        ATTRIBUTES_DTD,
+        "<doc id=\" d1 \" version=\"1.0\" size=\" large\" tokens=\" a.1  -b \" token=\"2024\"/>"
+    ));
+    assert!(!is_valid(ATTRIBUTES_DTD, "<doc id=\"1d\"/>"));
+    assert!(!is_valid(ATTRIBUTES_DTD, "<doc id=\"d1\" size=\"huge\"/>"));
+    assert!(!is_valid(ATTRIBUTES_DTD, "<doc id=\"d1\" token=\"a b\"/>"));
+    assert!(!is_valid(ATTRIBUTES_DTD, "<doc id=\"d1\" tokens=\"a,b\"/>"));
+    assert!(!is_valid(ATTRIBUTES_DTD, "<doc id=\"d1\" tokens=\"\"/>"));
+}
+
+#[test]
+fn test_attribute_errors() {
+    let input = format!(
+        "<!DOCTYPE doc [\n{ATTRIBUTES_DTD}\n]>\n<doc version=\"2.0\" size=\"huge\" color=\"red\" xmlns=\"urn:example\"/>"
+    );
+    let errors = validation_errors(&input);
+    assert_eq!(
+        errors,
+        [
+            ValidationError {
+                path: "/doc/@version".to_string(),
+                kind: ValidationErrorKind::FixedAttributeMismatch {
+                    attribute: Name::new(None, "version"),
+                    expected: "1.0".to_string(),
+                    found: "2.0".to_string(),
+                },
+            },
+            ValidationError {
+                path: "/doc/@size".to_string(),
+                kind: ValidationErrorKind::InvalidAttributeValue {
+                    attribute: Name::new(None, "size"),
+                    att_type: AttType::Enumerated {
+                        notation: None,
+                        enumeration: Some(vec![
+                            "small".to_string(),
+                            "medium".to_string(),
+                            "large".to_string()
+                        ]),
+                    },
+                    value: "huge".to_string(),
+                },
+            },
+            ValidationError {
+                path: "/doc/@color".to_string(),
+                kind: ValidationErrorKind::UndeclaredAttribute(Name::new(None, "color")),
+            },
+            ValidationError {
+                path: "/doc/@xmlns".to_string(),
+                kind: ValidationErrorKind::UndeclaredAttribute(Name::new(None, "xmlns")),
+            },
+            ValidationError {
+                path: "/doc/@id".to_string(),
+                kind: ValidationErrorKind::MissingRequiredAttribute(Name::new(None, "id")),
+            },
+        ]
+    );
+    assert_eq!(
+        errors[1].to_string(),
+        "/doc/@size: Value \"huge\" of attribute `size` is not a valid (small | medium | large)"
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "/doc/@version: Attribute `version` has the value \"2.0\" but is #FIXED to \"1.0\""
+    );
+}
+
+#[test]
+fn test_tokenized_attribute_types() {
+    let dtd = "<!ELEMENT doc EMPTY>\n<!ATTLIST doc id ID #IMPLIED refs IDREFS #IMPLIED ref IDREF #IMPLIED>";
+    assert!(is_valid(dtd, "<doc id=\"a\" refs=\"a a\" ref=\"a\"/>"));
+    let input = format!("<!DOCTYPE doc [\n{dtd}\n]>\n<doc id=\"a\" refs=\"a 1b\"/>");
+    assert!(matches!(
+        &validation_errors(&input)[..],
+        [ValidationError {
+            kind: ValidationErrorKind::InvalidAttributeValue {
+                att_type: AttType::Tokenized(TokenizedType::IDREFS),
+                value,
+                ..
+            },
+            ..
+        }] if value == "a 1b"
+    ));
+}
+
+#[test]
+fn test_id_and_idref_integrity() {
+    let dtd = "<!ELEMENT doc (a*)>\n<!ELEMENT a EMPTY>\n<!ATTLIST a id ID #IMPLIED ref IDREF #IMPLIED refs IDREFS #IMPLIED>";
+    assert!(is_valid(
+        dtd,
+        "<doc><a id=\"x\"/><a id=\"y\" ref=\"x\"/><a refs=\" x  y \"/></doc>"
+    ));
+
+    let input = format!(
+        "<!DOCTYPE doc [\n{dtd}\n]>\n<doc><a id=\"x\"/><a id=\"x\" ref=\"z\"/><a refs=\"x w\"/></doc>"
+    );
+    let errors = validation_errors(&input);
+    assert_eq!(
+        errors,
+        vec![
+            ValidationError {
+                path: "/doc/a[2]/@id".to_string(),
+                kind: ValidationErrorKind::DuplicateId {
+                    id: "x".to_string(),
+                    first: "/doc/a[1]/@id".to_string(),
+                },
+            },
+            ValidationError {
+                path: "/doc/a[2]/@ref".to_string(),
+                kind: ValidationErrorKind::UndefinedIdReference {
+                    attribute: Name::new(None, "ref"),
+                    id: "z".to_string(),
+                },
+            },
+            ValidationError {
+                path: "/doc/a[3]/@refs".to_string(),
+                kind: ValidationErrorKind::UndefinedIdReference {
+                    attribute: Name::new(None, "refs"),
+                    id: "w".to_string(),
+                },
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "/doc/a[2]/@id: ID \"x\" is already given by /doc/a[1]/@id"
+    );
+}
+
+#[test]
+fn test_multiple_id_attributes() {
+    let input = "<!DOCTYPE doc [\n<!ELEMENT doc EMPTY>\n<!ATTLIST doc one ID #IMPLIED two ID #IMPLIED>\n]>\n<doc/>";
+    assert!(matches!(
+        &validation_errors(input)[..],
+        [ValidationError {
+            path,
+            kind: ValidationErrorKind::MultipleIdAttributes { element, attributes },
+        }] if path == "/"
+            && *element == Name::new(None, "doc")
+            && *attributes == vec![Name::new(None, "one"), Name::new(None, "two")]
+    ));
+
+    // The declarations are checked once, however many elements of the type there are
+    let input = "<!DOCTYPE doc [\n<!ELEMENT doc (a*)>\n<!ELEMENT a EMPTY>\n<!ATTLIST a one ID #IMPLIED>\n<!ATTLIST a two ID #IMPLIED three ID #IMPLIED>\n]>\n<doc><a/><a/><a/></doc>";
+    assert!(matches!(
+        &validation_errors(input)[..],
+        [ValidationError {
+            path,
+            kind: ValidationErrorKind::MultipleIdAttributes { element, attributes },
+        }] if path == "/"
+            && *element == Name::new(None, "a")
+            && *attributes == vec![Name::new(None, "one"), Name::new(None, "two"), Name::new(None, "three")]
+    ));
+}
+
+#[test]
+fn test_get_element_by_id() {
+    let input = "<!DOCTYPE doc [\n<!ATTLIST item key ID #IMPLIED>\n]>\n<doc><list><item key=\"first\">One</item><item key=\"second\">Two</item></list><note xml:id=\"n1\">Note</note></doc>";
+    let (_, document) = Document::parse(input, &Config::default()).unwrap();
+    assert_eq!(
+        document.get_element_by_id("second").unwrap().to_string(),
+        "<item key=\"second\">Two</item>"
+    );
+    assert_eq!(
+        document.get_element_by_id("n1").unwrap().to_string(),
+        "<note xml:id=\"n1\">Note</note>"
+    );
+    assert!(document.get_element_by_id("Two").is_none());
+
+    let index = document.id_index();
+    assert_eq!(index.len(), 3);
+    assert!(index.get("first").is_some());
+
+    let (_, document) =
+        Document::parse("<doc><a xml:id=\"only\"/></doc>", &Config::default()).unwrap();
+    assert!(document.get_element_by_id("only").is_some());
+}
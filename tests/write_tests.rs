@@ -0,0 +1,92 @@
+use nom_xml::{
+    attribute::Attribute,
+    config::Config,
+    io::parse_entire_file,
+    parse::Parse,
+    processing_instruction::ProcessingInstruction,
+    tag::{Tag, TagState},
+    Document, Name,
+};
+use std::{error::Error, fs::File};
+
+#[test]
+fn test_write_escapes_content_and_attributes() {
+    let document = Document::Element(
+        Tag::new(
+            Name::new(None, "root"),
+            Some(vec![Attribute::new("attr", "\"a\" & <b>\n")]),
+            TagState::Start,
+        ),
+        Box::new(Document::Content(Some("1 < 2 & 3 > 2".to_string()))),
+        Tag::new(Name::new(None, "root"), None, TagState::End),
+    );
+
+    assert_eq!(
+        document.to_string(),
+        "<root attr=\"&quot;a&quot; &amp; &lt;b>&#xA;\">1 &lt; 2 &amp; 3 &gt; 2</root>"
+    );
+}
+
+#[test]
+fn test_write_markup_variants() {
+    let document = Document::Element(
+        Tag::new(Name::new(Some("ns"), "root"), None, TagState::Start),
+        Box::new(Document::Nested(vec![
+            Document::EmptyTag(Tag::new(
+                Name::new(None, "empty"),
+                Some(vec![Attribute::new("a", "1")]),
+                TagState::Empty,
+            )),
+            Document::Comment(" comment ".to_string()),
+            Document::ProcessingInstruction(ProcessingInstruction {
+                target: Name::new(None, "target"),
+                data: Some("data".to_string()),
+            }),
+            Document::CDATA("<raw> ]]> data".to_string()),
+        ])),
+        Tag::new(Name::new(Some("ns"), "root"), None, TagState::End),
+    );
+
+    assert_eq!(
+        document.to_string(),
+        "<ns:root><empty a=\"1\"/><!-- comment --><?target data?><![CDATA[<raw> ]]]]><![CDATA[> data]]></ns:root>"
+    );
+}
+
+#[test]
+fn test_write_whitespace_only_content_as_references() -> Result<(), Box<dyn Error>> {
+    let (_, document) = Document::parse("<doc>&#32;&#10;</doc>", &Config::default())?;
+
+    assert_eq!(document.to_string(), "<doc>&#x20;&#xA;</doc>");
+    Ok(())
+}
+
+#[test]
+fn test_write_prolog() -> Result<(), Box<dyn Error>> {
+    let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<!--before-->\n<!DOCTYPE doc [\n<!ELEMENT doc (a | b)*>\n<!ATTLIST doc\n    id ID #REQUIRED\n    kind (x | y) \"x\">\n<!ENTITY e \"text\">\n]>\n<doc id=\"d1\" kind=\"x\">text</doc>";
+    let (_, document) = Document::parse(xml, &Config::default())?;
+
+    assert_eq!(document.to_string(), xml);
+    Ok(())
+}
+
+#[test]
+fn test_write_valid_sa_round_trip() -> Result<(), Box<dyn Error>> {
+    for entry in std::fs::read_dir("tests/xmltest/valid/sa")? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "xml") {
+            continue;
+        }
+        let document = match parse_entire_file(&mut File::open(&path)?, &Config::default()) {
+            Ok(document) => document,
+            Err(_) => continue,
+        };
+        let written = document.to_string();
+        let (_, reparsed) = Document::parse(&written, &Config::default())
+            .map_err(|e| format!("{}: failed to reparse {written:?}: {e:?}", path.display()))?;
+
+        // The parser may split text into differently shaped Content nodes, so compare the written output
+        assert_eq!(written, reparsed.to_string(), "{}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,335 @@
+use nom_xml::{
+    error::Error,
+    xpath::{Node, Value, XPath},
+    Document,
+};
+
+mod common;
+
+use common::{library, parse};
+
+fn strings(document: &Document, expression: &str) -> Vec<String> {
+    document
+        .select_xpath(expression)
+        .unwrap()
+        .iter()
+        .map(Node::string_value)
+        .collect()
+}
+
+fn evaluate(document: &Document, expression: &str) -> Value<'static> {
+    match document.xpath(expression).unwrap() {
+        Value::Nodes(_) => panic!("`{expression}` selected nodes"),
+        Value::String(string) => Value::String(string),
+        Value::Number(number) => Value::Number(number),
+        Value::Boolean(boolean) => Value::Boolean(boolean),
+    }
+}
+
+#[test]
+fn test_child_and_descendant_axes() {
+    let document = library();
+    assert_eq!(
+        strings(&document, "/library/book/title"),
+        ["Leviathan Wakes", "Caliban's War", "Abaddon's Gate"]
+    );
+    assert_eq!(
+        strings(&document, "//book/title"),
+        [
+            "Leviathan Wakes",
+            "Caliban's War",
+            "Abaddon's Gate",
+            "Cibola Burn"
+        ]
+    );
+    assert_eq!(
+        strings(&document, "/descendant::title[../@year = 2012]"),
+        ["Caliban's War", "Gods of Risk"]
+    );
+    assert_eq!(strings(&document, "/library/*/title").len(), 4);
+    assert_eq!(
+        strings(&document, "/library/shelf/book/title/text()"),
+        ["Cibola Burn"]
+    );
+    assert!(document.select_xpath("/book").unwrap().is_empty());
+
+    let library = document.select_xpath("/library").unwrap();
+    assert!(matches!(
+        library[0],
+        Node::Element(Document::Element(tag, ..)) if tag.name.local_part == "library"
+    ));
+}
+
+#[test]
+fn test_attribute_and_parent_axes() {
+    let document = library();
+    assert_eq!(
+        strings(&document, "//book/@year"),
+        ["2011", "2012", "2013", "2014"]
+    );
+    assert_eq!(strings(&document, "/library/attribute::name"), ["Expanse"]);
+    assert_eq!(
+        document.select_xpath("/library/book[1]/@*").unwrap().len(),
+        2
+    );
+    assert_eq!(
+        strings(&document, "//title[. = 'Cibola Burn']/../../@name"),
+        Vec::<String>::new()
+    );
+    let shelf = document
+        .select_xpath("//title[. = 'Cibola Burn']/ancestor::shelf")
+        .unwrap();
+    assert_eq!(shelf[0].name().unwrap().local_part, "shelf");
+    assert_eq!(
+        strings(&document, "//price/parent::book/@isbn"),
+        ["978-0316129084", "978-0316129060", "978-0316129077"]
+    );
+    assert_eq!(
+        strings(&document, "/library/book[2]/following-sibling::*/title"),
+        ["Abaddon's Gate", "Gods of Risk"]
+    );
+    assert_eq!(
+        strings(
+            &document,
+            "/library/novella/preceding-sibling::book[1]/title"
+        ),
+        ["Abaddon's Gate"]
+    );
+}
+
+#[test]
+fn test_predicates() {
+    let document = library();
+    assert_eq!(
+        strings(&document, "/library/book[2]/title"),
+        ["Caliban's War"]
+    );
+    assert_eq!(
+        strings(&document, "/library/book[last()]/title"),
+        ["Abaddon's Gate"]
+    );
+    assert_eq!(
+        strings(&document, "/library/book[position() > 1][1]/title"),
+        ["Caliban's War"]
+    );
+    assert_eq!(strings(&document, "(//book)[4]/title"), ["Cibola Burn"]);
+    // Every book is the first one of its parent
+    assert_eq!(strings(&document, "//book[1]/title").len(), 2);
+    assert_eq!(
+        strings(&document, "//book[price > 10]/title"),
+        ["Caliban's War", "Abaddon's Gate"]
+    );
+    assert_eq!(
+        strings(&document, "//book[@year >= 2012 and not(price)]/title"),
+        ["Cibola Burn"]
+    );
+    assert_eq!(
+        strings(
+            &document,
+            "//*[@year = '2012' or @isbn = '978-0316129084']/title"
+        ),
+        ["Leviathan Wakes", "Caliban's War", "Gods of Risk"]
+    );
+    assert_eq!(
+        strings(&document, "//novella/title | //book[1]/title"),
+        ["Leviathan Wakes", "Gods of Risk", "Cibola Burn"]
+    );
+}
+
+#[test]
+fn test_text_comment_and_processing_instruction_nodes() {
+    let document = library();
+    assert_eq!(
+        strings(&document, "/library/book/title/text()"),
+        ["Leviathan Wakes", "Caliban's War", "Abaddon's Gate"]
+    );
+    assert_eq!(strings(&document, "/comment()"), [" The Expanse "]);
+    let processing_instructions = document
+        .select_xpath("//processing-instruction('sort')")
+        .unwrap();
+    assert_eq!(processing_instructions.len(), 1);
+    assert_eq!(processing_instructions[0].string_value(), "by=\"year\"");
+    assert!(document
+        .select_xpath("//processing-instruction('other')")
+        .unwrap()
+        .is_empty());
+    assert_eq!(document.select_xpath("/node()").unwrap().len(), 2);
+}
+
+#[test]
+fn test_string_functions() {
+    let document = library();
+    let string = |value: &str| Value::String(value.to_string());
+    assert_eq!(
+        evaluate(&document, "string(//book[2]/title)"),
+        string("Caliban's War")
+    );
+    assert_eq!(evaluate(&document, "name(/*)"), string("library"));
+    assert_eq!(
+        evaluate(&document, "concat(//book[1]/@year, '-', //book[3]/@year)"),
+        string("2011-2013")
+    );
+    assert_eq!(
+        evaluate(&document, "substring-before(//book[1]/@isbn, '-')"),
+        string("978")
+    );
+    assert_eq!(
+        evaluate(&document, "substring-after(//book[1]/@isbn, '-')"),
+        string("0316129084")
+    );
+    assert_eq!(
+        evaluate(&document, "substring('12345', 2, 3)"),
+        string("234")
+    );
+    assert_eq!(
+        evaluate(&document, "substring('12345', 1.5, 2.6)"),
+        string("234")
+    );
+    assert_eq!(
+        evaluate(&document, "substring('12345', 0 div 0, 3)"),
+        string("")
+    );
+    assert_eq!(
+        evaluate(&document, "normalize-space('  a \n b  ')"),
+        string("a b")
+    );
+    assert_eq!(
+        evaluate(&document, "translate('bar', 'abc', 'ABC')"),
+        string("BAr")
+    );
+    assert_eq!(
+        evaluate(&document, "translate('--aaa--', 'a-', 'A')"),
+        string("AAA")
+    );
+    assert_eq!(
+        evaluate(&document, "string-length(//book[3]/title)"),
+        Value::Number(14.0)
+    );
+    assert_eq!(
+        evaluate(&document, "starts-with(//book[1]/title, 'Levi')"),
+        Value::Boolean(true)
+    );
+    assert_eq!(
+        evaluate(&document, "contains(//book[1]/title, 'Wake')"),
+        Value::Boolean(true)
+    );
+    assert_eq!(evaluate(&document, "string(1 div 0)"), string("Infinity"));
+    assert_eq!(evaluate(&document, "string(0.5 * 3)"), string("1.5"));
+    assert_eq!(evaluate(&document, "string(-0)"), string("0"));
+}
+
+#[test]
+fn test_number_and_boolean_functions() {
+    let document = library();
+    assert_eq!(evaluate(&document, "count(//book)"), Value::Number(4.0));
+    assert_eq!(evaluate(&document, "sum(//price)"), Value::Number(32.49));
+    assert_eq!(
+        evaluate(&document, "number(//book[1]/@year) + 1"),
+        Value::Number(2012.0)
+    );
+    assert_eq!(evaluate(&document, "7 mod 3 - -2"), Value::Number(3.0));
+    assert_eq!(
+        evaluate(&document, "floor(2.5) + ceiling(2.5) + round(2.5)"),
+        Value::Number(8.0)
+    );
+    assert_eq!(evaluate(&document, "round(-2.5)"), Value::Number(-2.0));
+    assert!(
+        matches!(evaluate(&document, "number('1e3')"), Value::Number(number) if number.is_nan())
+    );
+    assert_eq!(
+        evaluate(&document, "boolean(//shelf)"),
+        Value::Boolean(true)
+    );
+    assert_eq!(
+        evaluate(&document, "boolean(//magazine)"),
+        Value::Boolean(false)
+    );
+    assert_eq!(
+        evaluate(&document, "not('') and true() and not(false())"),
+        Value::Boolean(true)
+    );
+    assert_eq!(evaluate(&document, "//price = 12"), Value::Boolean(true));
+    assert_eq!(evaluate(&document, "//price != 12"), Value::Boolean(true));
+    assert_eq!(
+        evaluate(&document, "//book/@year < 2012"),
+        Value::Boolean(true)
+    );
+    assert_eq!(
+        evaluate(&document, "2012 < //book/@year"),
+        Value::Boolean(true)
+    );
+    assert_eq!(
+        evaluate(&document, "//magazine = false()"),
+        Value::Boolean(true)
+    );
+}
+
+#[test]
+fn test_value_conversions() {
+    let document = library();
+    let years = document.xpath("//book/@year").unwrap();
+    assert_eq!(years.string(), "2011");
+    assert_eq!(years.number(), 2011.0);
+    assert!(years.boolean());
+
+    let xpath: XPath = "/library/@name".parse().unwrap();
+    assert_eq!(xpath.to_string(), "/library/@name");
+    let other = parse("<library name=\"Other\"/>");
+    assert_eq!(xpath.evaluate(&other).unwrap().string(), "Other");
+}
+
+#[test]
+fn test_prefixed_names() {
+    let document = parse("<atom:feed xmlns:atom=\"http://www.w3.org/2005/Atom\"><atom:entry xml:lang=\"en\"/><entry/><atom:id>1</atom:id></atom:feed>");
+    assert_eq!(
+        document
+            .select_xpath("/atom:feed/atom:entry")
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(document.select_xpath("/atom:feed/entry").unwrap().len(), 1);
+    assert_eq!(document.select_xpath("/atom:feed/atom:*").unwrap().len(), 2);
+    assert_eq!(strings(&document, "//@xml:lang"), ["en"]);
+    assert_eq!(
+        evaluate(&document, "local-name(/*)"),
+        Value::String("feed".to_string())
+    );
+    // Namespace declarations are not attributes
+    assert!(document.select_xpath("/*/@*").unwrap().is_empty());
+}
+
+#[test]
+fn test_invalid_expressions() {
+    let document = library();
+    for expression in [
+        "",
+        "//book[",
+        "/library/book]",
+        "unknown()",
+        "count()",
+        "following::book",
+        "1 + ",
+        "'unterminated",
+    ] {
+        assert!(
+            matches!(document.xpath(expression), Err(Error::InvalidXPath { .. })),
+            "{expression}"
+        );
+    }
+    assert_eq!(
+        document.xpath("count(1)").unwrap_err().to_string(),
+        "XPath expression `count(1)`: Expected a node-set, found Number(1.0)"
+    );
+    assert_eq!(
+        document
+            .select_xpath("count(//book)")
+            .unwrap_err()
+            .to_string(),
+        "XPath expression `count(//book)`: The expression does not select nodes"
+    );
+    assert_eq!(
+        XPath::compile("//book[1]]").unwrap_err().to_string(),
+        "XPath expression `//book[1]]`: Unexpected `]` at offset 9"
+    );
+}